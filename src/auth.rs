@@ -0,0 +1,562 @@
+//! Pre-shared-key HMAC session authentication.
+//!
+//! [`AuthPrimary::connect`] and [`AuthSecondary`] perform a one-time
+//! challenge-response handshake against a shared key, establishing a
+//! session key. Every read/write exchanged afterwards carries a
+//! truncated HMAC-SHA256 tag under that key, so a process that can see
+//! (or inject onto) the bus but doesn't hold the key cannot forge a
+//! write or replay someone else's.
+//!
+//! This layers on top of [`crate::UrapPrimary`]/[`crate::UrapSecondary`]
+//! rather than extending them: the wire format gains a trailing tag on
+//! every packet, which only makes sense once a session exists.
+
+use embedded_io::{Read, Write};
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::{
+    crc16, crc16_update, Error, NakCode, ReadProtect, WriteProtect, OP_ACK, OP_AUTH_CHALLENGE,
+    OP_AUTH_RESPONSE, OP_NAK, OP_READ, OP_WRITE, URAP_COUNT_MAX, URAP_HEADER_SIZE,
+};
+
+/// Size in bytes of an authentication tag: a truncated HMAC-SHA256.
+pub const AUTH_TAG_SIZE: usize = 8;
+
+/// Size in bytes of the random challenge a secondary issues during the
+/// handshake, and that [`AuthSecondary::poll`] callers must supply.
+pub const AUTH_CHALLENGE_SIZE: usize = 16;
+
+fn hmac_tag(key: &[u8], msg: &[u8]) -> [u8; AUTH_TAG_SIZE] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(msg);
+    finalize_tag(mac)
+}
+
+fn finalize_tag(mac: Hmac<Sha256>) -> [u8; AUTH_TAG_SIZE] {
+    let full = mac.finalize().into_bytes();
+    let mut tag = [0u8; AUTH_TAG_SIZE];
+    tag.copy_from_slice(&full[..AUTH_TAG_SIZE]);
+    tag
+}
+
+fn session_key(psk: &[u8], challenge: &[u8; AUTH_CHALLENGE_SIZE]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(psk).expect("HMAC accepts keys of any length");
+    mac.update(challenge);
+    let full = mac.finalize().into_bytes();
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&full);
+    key
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+fn read_nak<IO: Read>(io: &mut IO) -> Result<NakCode, Error<IO::Error>> {
+    let mut nak = [0u8; 1];
+    read_exact(io, &mut nak)?;
+    let mut crc_bytes = [0u8; 2];
+    read_exact(io, &mut crc_bytes)?;
+    Ok(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp))
+}
+
+fn respond_nak<IO: Read + Write>(io: &mut IO, code: NakCode) -> Result<(), Error<IO::Error>> {
+    let payload = [OP_NAK, code as u8];
+    let crc = crc16(&payload);
+    io.write_all(&payload).map_err(Error::Io)?;
+    io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+    io.flush().map_err(Error::Io)?;
+    Ok(())
+}
+
+/// The primary side of an authenticated URAP session.
+///
+/// Obtained only via [`Self::connect`], which performs the handshake; an
+/// `AuthPrimary` is always backed by a session key. There are no typed
+/// accessors here yet (see [`crate::UrapPrimary`]) - only the raw
+/// [`Self::read_4u8`]/[`Self::write_4u8`].
+pub struct AuthPrimary<'a, IO, const WIDTH: usize = 4> {
+    io: &'a mut IO,
+    session_key: [u8; 32],
+}
+
+impl<'a, IO, const WIDTH: usize> AuthPrimary<'a, IO, WIDTH>
+where
+    IO: Read + Write,
+{
+    /// Performs the challenge-response handshake against `psk`,
+    /// returning an authenticated session on success.
+    pub fn connect(io: &'a mut IO, psk: &[u8]) -> Result<Self, Error<IO::Error>> {
+        let header = [OP_AUTH_CHALLENGE, 0, 0, 0];
+        let crc = crc16(&header);
+        io.write_all(&header).map_err(Error::Io)?;
+        io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(io, &mut op)?;
+        if op[0] == OP_NAK {
+            return Err(Error::Nak(read_nak(io)?));
+        }
+
+        let mut challenge = [0u8; AUTH_CHALLENGE_SIZE];
+        read_exact(io, &mut challenge)?;
+        let mut crc_state = crc16(&op);
+        crc_state = crc16_update(crc_state, &challenge);
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            return Err(Error::BadCrc);
+        }
+
+        let session_key = session_key(psk, &challenge);
+        let proof = hmac_tag(&session_key, &challenge);
+
+        let header = [OP_AUTH_RESPONSE, 0, 0, 0];
+        let mut crc_state = crc16(&header);
+        crc_state = crc16_update(crc_state, &proof);
+        io.write_all(&header).map_err(Error::Io)?;
+        io.write_all(&proof).map_err(Error::Io)?;
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                Ok(Self { io, session_key })
+            }
+            _ => Err(Error::Nak(read_nak(io)?)),
+        }
+    }
+
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    pub fn read_4u8(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        assert!(data.len() <= URAP_COUNT_MAX as usize);
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_READ, reg[0], reg[1], count];
+        let crc = crc16(&header);
+        let tag = hmac_tag(&self.session_key, &header);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.write_all(&tag).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crc16(&op);
+                let mut mac = Hmac::<Sha256>::new_from_slice(&self.session_key)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(&op);
+                for word in data.iter_mut() {
+                    read_exact(self.io, word)?;
+                    crc_state = crc16_update(crc_state, word);
+                    mac.update(word);
+                }
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+                mac.update(&crc_bytes);
+
+                let mut tag_bytes = [0u8; AUTH_TAG_SIZE];
+                read_exact(self.io, &mut tag_bytes)?;
+                if mac.verify_truncated_left(&tag_bytes).is_err() {
+                    return Err(Error::Nak(NakCode::AuthFailed));
+                }
+                Ok(())
+            }
+            OP_NAK => Err(Error::Nak(read_nak(self.io)?)),
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    pub fn write_4u8(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        assert!(data.len() <= URAP_COUNT_MAX as usize);
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_WRITE, reg[0], reg[1], count];
+
+        let mut crc_state = crc16(&header);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.session_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&header);
+        for word in data {
+            crc_state = crc16_update(crc_state, word);
+            mac.update(word);
+        }
+        let tag = finalize_tag(mac);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        for word in data {
+            self.io.write_all(word).map_err(Error::Io)?;
+        }
+        self.io
+            .write_all(&crc_state.to_le_bytes())
+            .map_err(Error::Io)?;
+        self.io.write_all(&tag).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                let mut tag_bytes = [0u8; AUTH_TAG_SIZE];
+                read_exact(self.io, &mut tag_bytes)?;
+
+                let mut mac = Hmac::<Sha256>::new_from_slice(&self.session_key)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(&op);
+                mac.update(&crc_bytes);
+                if mac.verify_truncated_left(&tag_bytes).is_err() {
+                    return Err(Error::Nak(NakCode::AuthFailed));
+                }
+                Ok(())
+            }
+            OP_NAK => Err(Error::Nak(read_nak(self.io)?)),
+            _ => Err(Error::BadCrc),
+        }
+    }
+}
+
+/// The secondary side of an authenticated URAP session.
+///
+/// Wraps a [`crate::UrapSecondary`] with the challenge-response handshake
+/// and per-packet tag verification; reads and writes are rejected with
+/// [`NakCode::AuthFailed`] until a session has been established.
+///
+/// No_std has no randomness source to reach for, so [`Self::poll`] takes
+/// a fresh challenge from the caller on every call - it's only consumed
+/// when the incoming request turns out to be a handshake request.
+pub struct AuthSecondary<'a, const REGCNT: usize, const WIDTH: usize = 4, P = [bool; REGCNT], R = crate::NoWriteProtect>
+{
+    inner: crate::UrapSecondary<REGCNT, WIDTH, P, R>,
+    psk: &'a [u8],
+    pending_challenge: Option<[u8; AUTH_CHALLENGE_SIZE]>,
+    session_key: Option<[u8; 32]>,
+}
+
+impl<'a, const REGCNT: usize, const WIDTH: usize, P>
+    AuthSecondary<'a, REGCNT, WIDTH, P, crate::NoWriteProtect>
+where
+    P: WriteProtect,
+{
+    /// Builds an authenticated secondary over `regs`, checked against
+    /// `psk` during the handshake. No registers are read-protected; use
+    /// [`Self::with_read_protect`] to add some.
+    pub fn new(psk: &'a [u8], regs: [[u8; WIDTH]; REGCNT], write_protect: P) -> Self {
+        Self {
+            inner: crate::UrapSecondary::new(regs, write_protect),
+            psk,
+            pending_challenge: None,
+            session_key: None,
+        }
+    }
+}
+
+impl<'a, const REGCNT: usize, const WIDTH: usize, P, R> AuthSecondary<'a, REGCNT, WIDTH, P, R>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+{
+    /// Builds an authenticated secondary with both write- and
+    /// read-protect lists.
+    pub fn with_read_protect(
+        psk: &'a [u8],
+        regs: [[u8; WIDTH]; REGCNT],
+        write_protect: P,
+        read_protect: R,
+    ) -> Self {
+        Self {
+            inner: crate::UrapSecondary::with_read_protect(regs, write_protect, read_protect),
+            psk,
+            pending_challenge: None,
+            session_key: None,
+        }
+    }
+
+    /// Direct access to the backing register array.
+    pub fn regs(&self) -> &[[u8; WIDTH]; REGCNT] {
+        self.inner.regs()
+    }
+
+    /// `true` once the challenge-response handshake has succeeded.
+    pub fn is_authenticated(&self) -> bool {
+        self.session_key.is_some()
+    }
+
+    /// Services a single request read from `io`.
+    ///
+    /// `next_challenge` should be fresh and unpredictable on every call;
+    /// it's used only if this particular request is
+    /// [`crate::OP_AUTH_CHALLENGE`].
+    pub fn poll<IO>(
+        &mut self,
+        io: &mut IO,
+        next_challenge: [u8; AUTH_CHALLENGE_SIZE],
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        read_exact(io, &mut header)?;
+        let register = u16::from_le_bytes([header[1], header[2]]);
+        let count = header[3];
+
+        match header[0] {
+            OP_AUTH_CHALLENGE => self.handle_challenge(io, &header, next_challenge),
+            OP_AUTH_RESPONSE => self.handle_response(io, &header),
+            OP_READ if self.session_key.is_some() => {
+                self.handle_authenticated_read(io, &header, register, count)
+            }
+            OP_WRITE if self.session_key.is_some() => {
+                self.handle_authenticated_write(io, &header, register, count)
+            }
+            OP_READ => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                respond_nak(io, NakCode::AuthFailed)
+            }
+            OP_WRITE => {
+                let mut scratch = [0u8; WIDTH];
+                for _ in 0..count {
+                    read_exact(io, &mut scratch)?;
+                }
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                respond_nak(io, NakCode::AuthFailed)
+            }
+            _ => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                respond_nak(io, NakCode::BadOp)
+            }
+        }
+    }
+
+    fn handle_challenge<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        challenge: [u8; AUTH_CHALLENGE_SIZE],
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        if crc16(header) != u16::from_le_bytes(crc_bytes) {
+            return respond_nak(io, NakCode::BadCrc);
+        }
+
+        self.pending_challenge = Some(challenge);
+
+        let mut crc_state = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        io.write_all(&challenge).map_err(Error::Io)?;
+        crc_state = crc16_update(crc_state, &challenge);
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(())
+    }
+
+    fn handle_response<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut proof = [0u8; AUTH_TAG_SIZE];
+        read_exact(io, &mut proof)?;
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+
+        let mut crc_state = crc16(header);
+        crc_state = crc16_update(crc_state, &proof);
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            return respond_nak(io, NakCode::BadCrc);
+        }
+
+        let challenge = match self.pending_challenge.take() {
+            Some(challenge) => challenge,
+            None => return respond_nak(io, NakCode::AuthFailed),
+        };
+        let key = session_key(self.psk, &challenge);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&challenge);
+        if mac.verify_truncated_left(&proof).is_err() {
+            return respond_nak(io, NakCode::AuthFailed);
+        }
+        self.session_key = Some(key);
+
+        let ack_crc = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        io.write_all(&ack_crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(())
+    }
+
+    fn handle_authenticated_read<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        let mut tag_bytes = [0u8; AUTH_TAG_SIZE];
+        read_exact(io, &mut tag_bytes)?;
+
+        let session_key = self.session_key.expect("checked by poll() before dispatch");
+        if crc16(header) != u16::from_le_bytes(crc_bytes) {
+            return respond_nak(io, NakCode::BadCrc);
+        }
+        let mut mac = Hmac::<Sha256>::new_from_slice(&session_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(header);
+        if mac.verify_truncated_left(&tag_bytes).is_err() {
+            return respond_nak(io, NakCode::AuthFailed);
+        }
+        if count as u16 > URAP_COUNT_MAX {
+            return respond_nak(io, NakCode::CountTooLarge);
+        }
+        if register as usize + count as usize > REGCNT {
+            return respond_nak(io, NakCode::IndexOutOfBounds);
+        }
+        if self.inner.is_read_protected(register, count) {
+            return respond_nak(io, NakCode::IndexReadProtected);
+        }
+
+        let mut crc_state = crc16(&[OP_ACK]);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&session_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        for word in &self.inner.regs()[register as usize..register as usize + count as usize] {
+            io.write_all(word).map_err(Error::Io)?;
+            crc_state = crc16_update(crc_state, word);
+            mac.update(word);
+        }
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        mac.update(&crc_state.to_le_bytes());
+        io.write_all(&finalize_tag(mac)).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(())
+    }
+
+    fn handle_authenticated_write<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        if count as u16 > URAP_COUNT_MAX || register as usize + count as usize > REGCNT {
+            let mut scratch = [0u8; WIDTH];
+            for _ in 0..count {
+                read_exact(io, &mut scratch)?;
+            }
+            let mut crc_bytes = [0u8; 2];
+            read_exact(io, &mut crc_bytes)?;
+            let mut tag_bytes = [0u8; AUTH_TAG_SIZE];
+            read_exact(io, &mut tag_bytes)?;
+
+            let code = if count as u16 > URAP_COUNT_MAX {
+                NakCode::CountTooLarge
+            } else {
+                NakCode::IndexOutOfBounds
+            };
+            return respond_nak(io, code);
+        }
+
+        let session_key = self.session_key.expect("checked by poll() before dispatch");
+        let protected = self.inner.is_write_protected(register, count);
+
+        // Buffered here, rather than applied to the store as each word
+        // arrives: unlike the plain (unauthenticated) secondary, this
+        // path exists specifically to promise that a write which fails
+        // its CRC or tag check never took effect, so nothing lands in
+        // `regs_mut()` until both checks below have passed.
+        let mut words = [[0u8; WIDTH]; URAP_COUNT_MAX as usize];
+        let mut crc_state = crc16(header);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&session_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(header);
+        for word in words.iter_mut().take(count as usize) {
+            read_exact(io, word)?;
+            crc_state = crc16_update(crc_state, word);
+            mac.update(word);
+        }
+
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        let mut tag_bytes = [0u8; AUTH_TAG_SIZE];
+        read_exact(io, &mut tag_bytes)?;
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            return respond_nak(io, NakCode::BadCrc);
+        }
+        if mac.verify_truncated_left(&tag_bytes).is_err() {
+            return respond_nak(io, NakCode::AuthFailed);
+        }
+        if protected {
+            return respond_nak(io, NakCode::IndexWriteProtected);
+        }
+
+        for (i, word) in words.iter().take(count as usize).enumerate() {
+            self.inner.regs_mut()[register as usize + i] = *word;
+        }
+
+        let ack_crc = crc16(&[OP_ACK]);
+        let mut ack_mac = Hmac::<Sha256>::new_from_slice(&session_key)
+            .expect("HMAC accepts keys of any length");
+        ack_mac.update(&[OP_ACK]);
+        ack_mac.update(&ack_crc.to_le_bytes());
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        io.write_all(&ack_crc.to_le_bytes()).map_err(Error::Io)?;
+        io.write_all(&finalize_tag(ack_mac)).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! Sustained throughput benchmarking for a URAP link.
+//!
+//! [`bench_reads`] and [`bench_writes`] repeatedly issue the same request
+//! against an already-connected [`UrapPrimary`] for a fixed duration and
+//! report packets/sec and bytes/sec, so baud rates and packet sizes can
+//! be compared with the same numbers across transports (loopback,
+//! `usockets`, serial, ...) — the benchmark only calls the public
+//! primary API, so anything [`UrapPrimary`] works over, this works over.
+
+use std::time::{Duration, Instant};
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, UrapPrimary, URAP_HEADER_SIZE};
+
+/// Packet and byte counts from a [`bench_reads`] or [`bench_writes`] run.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    /// Requests completed.
+    pub packets: u64,
+    /// Bytes exchanged on the wire, request and response combined.
+    pub bytes: u64,
+    /// Wall-clock time actually spent.
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Requests completed per second.
+    pub fn packets_per_sec(&self) -> f64 {
+        self.packets as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Bytes exchanged per second.
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Repeatedly reads `data.len()` registers starting at `register` for
+/// `duration`, returning aggregate throughput. Returns early on the
+/// first error.
+pub fn bench_reads<IO, const WIDTH: usize, const BIG_ENDIAN: bool>(
+    primary: &mut UrapPrimary<IO, WIDTH, BIG_ENDIAN>,
+    register: u16,
+    data: &mut [[u8; WIDTH]],
+    duration: Duration,
+) -> Result<BenchResult, Error<IO::Error>>
+where
+    IO: Read + Write,
+{
+    let packet_bytes = (URAP_HEADER_SIZE + data.len() * WIDTH + 2) as u64;
+    let mut packets = 0u64;
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        primary.read_4u8(register, data)?;
+        packets += 1;
+    }
+    Ok(BenchResult { packets, bytes: packets * packet_bytes, elapsed: started.elapsed() })
+}
+
+/// Repeatedly writes `data` to `data.len()` consecutive registers
+/// starting at `register` for `duration`, returning aggregate
+/// throughput. Returns early on the first error.
+pub fn bench_writes<IO, const WIDTH: usize, const BIG_ENDIAN: bool>(
+    primary: &mut UrapPrimary<IO, WIDTH, BIG_ENDIAN>,
+    register: u16,
+    data: &[[u8; WIDTH]],
+    duration: Duration,
+) -> Result<BenchResult, Error<IO::Error>>
+where
+    IO: Read + Write,
+{
+    let packet_bytes = (URAP_HEADER_SIZE + data.len() * WIDTH + 2) as u64;
+    let mut packets = 0u64;
+    let started = Instant::now();
+    while started.elapsed() < duration {
+        primary.write_4u8(register, data)?;
+        packets += 1;
+    }
+    Ok(BenchResult { packets, bytes: packets * packet_bytes, elapsed: started.elapsed() })
+}
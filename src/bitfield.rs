@@ -0,0 +1,70 @@
+//! [`urap_bitfields!`], a macro for naming the flags packed into a
+//! single register instead of spelling out `(msb, lsb)` pairs at every
+//! call site.
+
+/// Declares the bitfields of a single register as a set of marker
+/// types, one per field, built on [`crate::UrapPrimary::read_bits`]/
+/// [`crate::UrapPrimary::write_bits`].
+///
+/// ```
+/// use urap::urap_bitfields;
+///
+/// urap_bitfields! {
+///     pub mod status_bits {
+///         register: 0;
+///         pub Enabled: 0, 0;
+///         pub Mode: 3, 1;
+///         pub ErrorCode: 15, 8;
+///     }
+/// }
+///
+/// let _ = status_bits::Mode::MSB;
+/// let _ = status_bits::Mode::LSB;
+/// ```
+///
+/// Each entry is `Name: msb, lsb;`, a one-bit flag being `Name: bit, bit;`.
+/// Only `WIDTH == 4` is supported, matching [`crate::UrapPrimary::read_bits`].
+#[macro_export]
+macro_rules! urap_bitfields {
+    ($vis:vis mod $mod_name:ident { register: $register:literal; $($field_vis:vis $name:ident : $msb:literal, $lsb:literal;)+ }) => {
+        $vis mod $mod_name {
+            #![allow(dead_code)]
+
+            /// The register these bitfields pack into.
+            pub const REGISTER: u16 = $register;
+
+            $(
+                $field_vis struct $name;
+
+                impl $name {
+                    /// Most significant bit of this field, inclusive.
+                    pub const MSB: u8 = $msb;
+                    /// Least significant bit of this field, inclusive.
+                    pub const LSB: u8 = $lsb;
+
+                    /// Reads this field out of [`REGISTER`].
+                    pub fn read<IO, const BIG_ENDIAN: bool>(
+                        primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                    ) -> ::core::result::Result<u32, $crate::Error<IO::Error>>
+                    where
+                        IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+                    {
+                        primary.read_bits(REGISTER, Self::MSB, Self::LSB)
+                    }
+
+                    /// Read-modify-writes this field into [`REGISTER`],
+                    /// leaving the rest of the register untouched.
+                    pub fn write<IO, const BIG_ENDIAN: bool>(
+                        primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                        value: u32,
+                    ) -> ::core::result::Result<(), $crate::Error<IO::Error>>
+                    where
+                        IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+                    {
+                        primary.write_bits(REGISTER, Self::MSB, Self::LSB, value)
+                    }
+                }
+            )+
+        }
+    };
+}
@@ -0,0 +1,146 @@
+//! Write coalescing in front of [`crate::UrapPrimary`], for applications
+//! that set many parameters one field at a time and would otherwise put
+//! one packet on the wire per field.
+//!
+//! [`BufferedPrimary`] holds queued writes in a fixed-capacity buffer
+//! (`CAPACITY`, default 32 registers) instead of sending each
+//! immediately. [`Self::flush`] sends everything pending as the fewest
+//! packets possible: adjacent registers are coalesced into a single
+//! multi-register write. A flush also happens automatically when the
+//! buffer fills up, or lazily - checked on the next
+//! [`Self::queue_write`] call - once `max_age` has elapsed since the
+//! oldest still-pending write, so a caller that only ever queues doesn't
+//! need a background timer to bound staleness.
+
+use std::time::{Duration, Instant};
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, UrapPrimary};
+
+/// Wraps a [`UrapPrimary`], buffering writes until [`Self::flush`] - or
+/// the `CAPACITY`/`max_age` threshold - sends them as coalesced
+/// multi-register packets.
+pub struct BufferedPrimary<IO, const WIDTH: usize = 4, const BIG_ENDIAN: bool = false, const CAPACITY: usize = 32> {
+    inner: UrapPrimary<IO, WIDTH, BIG_ENDIAN>,
+    pending: [(u16, [u8; WIDTH]); CAPACITY],
+    len: usize,
+    max_age: Option<Duration>,
+    oldest: Option<Instant>,
+}
+
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool, const CAPACITY: usize>
+    BufferedPrimary<IO, WIDTH, BIG_ENDIAN, CAPACITY>
+{
+    /// Wraps `primary`. If `max_age` is `Some`, a pending write older
+    /// than it is flushed (along with everything else pending) the next
+    /// time [`Self::queue_write`] is called; `None` disables the age
+    /// threshold, leaving [`Self::flush`] and the `CAPACITY` threshold as
+    /// the only triggers.
+    pub fn new(primary: UrapPrimary<IO, WIDTH, BIG_ENDIAN>, max_age: Option<Duration>) -> Self {
+        Self {
+            inner: primary,
+            pending: [(0, [0u8; WIDTH]); CAPACITY],
+            len: 0,
+            max_age,
+            oldest: None,
+        }
+    }
+
+    /// Unwraps the buffer, returning the primary it was constructed
+    /// with. Anything still pending is discarded, not flushed.
+    pub fn into_inner(self) -> UrapPrimary<IO, WIDTH, BIG_ENDIAN> {
+        self.inner
+    }
+
+    /// Number of registers currently buffered, awaiting a flush.
+    pub fn pending_len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool, const CAPACITY: usize>
+    BufferedPrimary<IO, WIDTH, BIG_ENDIAN, CAPACITY>
+where
+    IO: Read + Write,
+{
+    /// Queues a write of `data` to `data.len()` consecutive registers
+    /// starting at `register`, without touching the wire yet.
+    ///
+    /// Queuing a register already pending replaces its buffered value
+    /// rather than adding a second entry. Flushes first (see
+    /// [`Self::new`]'s `max_age`, and the `CAPACITY` threshold) if either
+    /// trigger fires, which can itself fail if the transport does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data.len()` alone exceeds `CAPACITY` - no flush can
+    /// make room for a single write bigger than the whole buffer.
+    pub fn queue_write(&mut self, register: u16, data: &[[u8; WIDTH]]) -> Result<(), Error<IO::Error>> {
+        assert!(
+            data.len() <= CAPACITY,
+            "a single queue_write cannot exceed the buffer's capacity"
+        );
+
+        if self.is_stale() {
+            self.flush()?;
+        }
+
+        for (i, value) in data.iter().enumerate() {
+            let register = register.wrapping_add(i as u16);
+
+            if let Some(slot) = self.pending[..self.len]
+                .iter_mut()
+                .find(|(r, _)| *r == register)
+            {
+                slot.1 = *value;
+                continue;
+            }
+
+            if self.len == CAPACITY {
+                self.flush()?;
+            }
+
+            self.pending[self.len] = (register, *value);
+            self.len += 1;
+            self.oldest.get_or_insert_with(Instant::now);
+        }
+
+        Ok(())
+    }
+
+    /// Sends every pending write as the fewest possible multi-register
+    /// packets (adjacent registers share one packet), then clears the
+    /// buffer.
+    pub fn flush(&mut self) -> Result<(), Error<IO::Error>> {
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        self.pending[..self.len].sort_by_key(|(register, _)| *register);
+
+        let mut start = 0;
+        while start < self.len {
+            let mut end = start + 1;
+            while end < self.len && self.pending[end].0 == self.pending[end - 1].0 + 1 {
+                end += 1;
+            }
+
+            let run: Vec<[u8; WIDTH]> = self.pending[start..end].iter().map(|(_, v)| *v).collect();
+            self.inner.write_4u8(self.pending[start].0, &run)?;
+
+            start = end;
+        }
+
+        self.len = 0;
+        self.oldest = None;
+        Ok(())
+    }
+
+    fn is_stale(&self) -> bool {
+        match (self.max_age, self.oldest) {
+            (Some(max_age), Some(oldest)) => oldest.elapsed() >= max_age,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! A read cache in front of [`crate::UrapPrimary`], for callers that
+//! re-read the same registers far more often than the secondary's state
+//! actually changes - a dashboard rendering the same telemetry at 60fps
+//! shouldn't put a request on the wire for every frame.
+//!
+//! [`CachedPrimary`] only caches single-register reads ([`Self::read_4u8`]
+//! with `data.len() == 1`); a multi-register read always goes straight to
+//! the wire, since caching a partial overlap between two differently
+//! sized reads of the same range isn't worth the bookkeeping. Every write
+//! invalidates the registers it touches, so a cache hit never serves a
+//! value this primary itself has since overwritten - but a value changed
+//! by some other primary or the secondary's own firmware can still be
+//! served stale until its entry's TTL lapses.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, UrapPrimary};
+
+struct CacheEntry<const WIDTH: usize> {
+    value: [u8; WIDTH],
+    cached_at: Instant,
+}
+
+/// Wraps a [`UrapPrimary`], caching single-register reads for `ttl` and
+/// invalidating an entry as soon as this primary writes to it.
+pub struct CachedPrimary<IO, const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    inner: UrapPrimary<IO, WIDTH, BIG_ENDIAN>,
+    ttl: Duration,
+    cache: HashMap<u16, CacheEntry<WIDTH>>,
+}
+
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> CachedPrimary<IO, WIDTH, BIG_ENDIAN> {
+    /// Wraps `primary`, caching each register's value for `ttl` after it
+    /// was last read or written.
+    pub fn new(primary: UrapPrimary<IO, WIDTH, BIG_ENDIAN>, ttl: Duration) -> Self {
+        Self {
+            inner: primary,
+            ttl,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Unwraps the cache, returning the primary it was constructed with.
+    /// Anything cached is discarded.
+    pub fn into_inner(self) -> UrapPrimary<IO, WIDTH, BIG_ENDIAN> {
+        self.inner
+    }
+
+    /// Drops every cached entry, regardless of its TTL. Useful after an
+    /// event (e.g. a reconnect) that might have changed registers behind
+    /// this primary's back.
+    pub fn invalidate_all(&mut self) {
+        self.cache.clear();
+    }
+}
+
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> CachedPrimary<IO, WIDTH, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    ///
+    /// Served from the cache, without touching the transport, if
+    /// `data.len() == 1` and `register` was read or written within the
+    /// last `ttl`; otherwise this reads through to
+    /// [`UrapPrimary::read_4u8`] and (for a single register) caches the
+    /// result.
+    pub fn read_4u8(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        if data.len() == 1 {
+            if let Some(entry) = self.cache.get(&register) {
+                if entry.cached_at.elapsed() < self.ttl {
+                    data[0] = entry.value;
+                    return Ok(());
+                }
+            }
+        }
+
+        self.inner.read_4u8(register, data)?;
+
+        if data.len() == 1 {
+            self.cache.insert(
+                register,
+                CacheEntry { value: data[0], cached_at: Instant::now() },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`, then invalidates the cache entry for each of them.
+    pub fn write_4u8(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        self.inner.write_4u8(register, data)?;
+
+        for i in 0..data.len() as u16 {
+            self.cache.remove(&(register + i));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+//! Segmenting URAP packets across plain 8-byte CAN frames, so a URAP
+//! primary/secondary pair can share an existing vehicle CAN bus instead of
+//! needing a dedicated UART/TCP link.
+//!
+//! [`CanTransport`] wraps any [`embedded_can::blocking::Can`] implementation
+//! and is itself an [`embedded_io::Read`] + [`Write`], so it drops in
+//! underneath [`crate::UrapPrimary`]/[`crate::UrapSecondary`] unchanged. Each
+//! packet is split across frames using ISO-TP's (ISO 15765-2) Single/First/
+//! Consecutive Frame shapes:
+//!
+//! ```text
+//! single frame:       0x0 | LEN (4 bits)  | DATA (0..=7)
+//! first frame:        0x1 | LEN (12 bits) | DATA (6)
+//! consecutive frame:  0x2 | SEQ (4 bits)  | DATA (0..=7)
+//! ```
+//!
+//! Flow Control frames are not implemented: both ends are expected to keep
+//! up with a back-to-back burst of consecutive frames for the packet sizes
+//! URAP actually sends ([`crate::URAP_COUNT_MAX`] bounds that burst), so
+//! this is ISO-TP-*like* rather than a full ISO-TP stack.
+//!
+//! The two ends of a link must be configured with swapped `tx_id`/`rx_id`
+//! (this end's `tx_id` is the peer's `rx_id`), the same way
+//! [`crate::EncryptedIo`] swaps its nonce prefixes; frames carrying any
+//! other identifier are ignored, so several URAP links can share one bus.
+
+use core::fmt;
+
+use embedded_can::blocking::Can;
+use embedded_can::{Frame, Id};
+use embedded_io::{ErrorType, Read, Write};
+
+const PCI_SINGLE: u8 = 0x0;
+const PCI_FIRST: u8 = 0x1;
+const PCI_CONSECUTIVE: u8 = 0x2;
+
+/// Largest data length a single frame (`LEN` field) can carry: 7 bytes for
+/// a Single Frame, 6 for a First Frame's leading chunk.
+const SF_MAX_LEN: usize = 7;
+const FF_LEAD_LEN: usize = 6;
+const CF_MAX_LEN: usize = 7;
+
+/// Everything that can go wrong framing a URAP packet over CAN.
+#[derive(Debug)]
+pub enum CanError<E> {
+    /// The underlying [`Can`] implementation failed to transmit or receive.
+    Can(E),
+    /// A received frame carried a PCI nibble this transport doesn't
+    /// understand, or a Consecutive Frame arrived with the wrong sequence
+    /// number.
+    Framing,
+    /// A First Frame declared a packet longer than `CAP`.
+    TooLong,
+}
+
+impl<E: fmt::Debug> fmt::Display for CanError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Can(err) => write!(f, "CAN bus error: {err:?}"),
+            Self::Framing => write!(f, "frame carried an unexpected PCI nibble or sequence number"),
+            Self::TooLong => write!(f, "packet longer than the transport's buffer"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for CanError<E> {}
+
+impl<E: embedded_can::Error> embedded_io::Error for CanError<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Can(_) | Self::Framing | Self::TooLong => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Maps a byte stream onto 8-byte CAN frames via ISO-TP-like segmentation.
+///
+/// `CAP` bounds the largest single packet (comfortably covers a
+/// [`crate::URAP_COUNT_MAX`]-register packet at the default width by
+/// default).
+pub struct CanTransport<C: Can, const CAP: usize = 512> {
+    can: C,
+    tx_id: Id,
+    rx_id: Id,
+    write_buf: [u8; CAP],
+    write_len: usize,
+    read_buf: [u8; CAP],
+    read_pos: usize,
+    read_len: usize,
+}
+
+impl<C: Can, const CAP: usize> CanTransport<C, CAP> {
+    /// Wraps `can`, sending frames under `tx_id` and accepting only frames
+    /// addressed to `rx_id` (frames under any other identifier, e.g. from
+    /// another URAP link sharing the bus, are silently skipped).
+    pub fn new(can: C, tx_id: impl Into<Id>, rx_id: impl Into<Id>) -> Self {
+        Self {
+            can,
+            tx_id: tx_id.into(),
+            rx_id: rx_id.into(),
+            write_buf: [0u8; CAP],
+            write_len: 0,
+            read_buf: [0u8; CAP],
+            read_pos: 0,
+            read_len: 0,
+        }
+    }
+
+    fn send_frame(&mut self, data: &[u8]) -> Result<(), CanError<C::Error>> {
+        let frame = C::Frame::new(self.tx_id, data).ok_or(CanError::TooLong)?;
+        self.can.transmit(&frame).map_err(CanError::Can)
+    }
+
+    /// Blocks until a frame addressed to `rx_id` arrives.
+    fn recv_frame(&mut self) -> Result<C::Frame, CanError<C::Error>> {
+        loop {
+            let frame = self.can.receive().map_err(CanError::Can)?;
+            if frame.id() == self.rx_id && frame.is_data_frame() {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn fill_packet(&mut self) -> Result<(), CanError<C::Error>> {
+        let frame = self.recv_frame()?;
+        let data = frame.data();
+        let pci = data.first().copied().unwrap_or(0);
+
+        match pci >> 4 {
+            PCI_SINGLE => {
+                let len = (pci & 0x0F) as usize;
+                if len > data.len() - 1 || len > CAP {
+                    return Err(CanError::Framing);
+                }
+                self.read_buf[..len].copy_from_slice(&data[1..1 + len]);
+                self.read_len = len;
+            }
+            PCI_FIRST => {
+                if data.len() < 2 {
+                    return Err(CanError::Framing);
+                }
+                let len = (((pci & 0x0F) as usize) << 8) | data[1] as usize;
+                if len > CAP {
+                    return Err(CanError::TooLong);
+                }
+                let lead = &data[2..data.len().min(2 + FF_LEAD_LEN)];
+                self.read_buf[..lead.len()].copy_from_slice(lead);
+                let mut filled = lead.len();
+                let mut seq = 1u8;
+
+                while filled < len {
+                    let frame = self.recv_frame()?;
+                    let data = frame.data();
+                    let pci = data.first().copied().unwrap_or(0);
+                    if pci >> 4 != PCI_CONSECUTIVE || pci & 0x0F != seq & 0x0F {
+                        return Err(CanError::Framing);
+                    }
+                    let chunk = &data[1..];
+                    let n = chunk.len().min(len - filled);
+                    self.read_buf[filled..filled + n].copy_from_slice(&chunk[..n]);
+                    filled += n;
+                    seq = seq.wrapping_add(1);
+                }
+                self.read_len = len;
+            }
+            _ => return Err(CanError::Framing),
+        }
+
+        self.read_pos = 0;
+        Ok(())
+    }
+}
+
+impl<C: Can, const CAP: usize> ErrorType for CanTransport<C, CAP> {
+    type Error = CanError<C::Error>;
+}
+
+impl<C: Can, const CAP: usize> Read for CanTransport<C, CAP> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.read_pos >= self.read_len {
+            self.fill_packet()?;
+        }
+
+        let n = buf.len().min(self.read_len - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Ok(n)
+    }
+}
+
+impl<C: Can, const CAP: usize> Write for CanTransport<C, CAP> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(CAP - self.write_len);
+        self.write_buf[self.write_len..self.write_len + n].copy_from_slice(&buf[..n]);
+        self.write_len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.write_len == 0 {
+            return Ok(());
+        }
+
+        if self.write_len <= SF_MAX_LEN {
+            let mut frame_data = [0u8; 8];
+            frame_data[0] = PCI_SINGLE << 4 | self.write_len as u8;
+            frame_data[1..1 + self.write_len].copy_from_slice(&self.write_buf[..self.write_len]);
+            self.send_frame(&frame_data[..1 + self.write_len])?;
+        } else {
+            let len = self.write_len as u16;
+            let mut frame_data = [0u8; 8];
+            frame_data[0] = PCI_FIRST << 4 | (len >> 8) as u8;
+            frame_data[1] = (len & 0xFF) as u8;
+            frame_data[2..2 + FF_LEAD_LEN].copy_from_slice(&self.write_buf[..FF_LEAD_LEN]);
+            self.send_frame(&frame_data)?;
+
+            let mut sent = FF_LEAD_LEN;
+            let mut seq = 1u8;
+            while sent < self.write_len {
+                let n = CF_MAX_LEN.min(self.write_len - sent);
+                let mut frame_data = [0u8; 8];
+                frame_data[0] = PCI_CONSECUTIVE << 4 | (seq & 0x0F);
+                frame_data[1..1 + n].copy_from_slice(&self.write_buf[sent..sent + n]);
+                self.send_frame(&frame_data[..1 + n])?;
+                sent += n;
+                seq = seq.wrapping_add(1);
+            }
+        }
+
+        self.write_len = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Generating a [`crate::urap_registers!`] invocation from a TOML
+//! register-map file, for calling out of a downstream crate's
+//! `build.rs` so firmware and host tooling stay generated from the same
+//! single source of truth instead of hand-keeping indices, types, and
+//! write-protect flags in sync.
+//!
+//! The TOML format is a `registers` table keyed by register name:
+//!
+//! ```toml
+//! [registers.setpoint]
+//! index = 0
+//! type = "f32"
+//! protected = false
+//!
+//! [registers.status]
+//! index = 1
+//! type = "u32"
+//! protected = true
+//! ```
+
+use std::fmt;
+use std::fmt::Write as _;
+
+/// Everything that can go wrong generating a register module from a
+/// register-map file.
+#[derive(Debug)]
+pub enum CodegenError {
+    /// The source isn't valid TOML.
+    Toml(toml::de::Error),
+    /// The top-level `registers` table is missing.
+    MissingRegistersTable,
+    /// A register entry is missing a required field, or one of them has
+    /// the wrong TOML type.
+    BadRegister {
+        /// Name of the offending register.
+        name: String,
+        /// What's wrong with it.
+        reason: String,
+    },
+    /// A register's `type` isn't one of `u32`, `i32`, `f32`, `raw`.
+    UnknownType {
+        /// Name of the offending register.
+        name: String,
+        /// The type string that wasn't recognized.
+        found: String,
+    },
+}
+
+impl From<toml::de::Error> for CodegenError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Toml(err)
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toml(err) => write!(f, "invalid register-map TOML: {err}"),
+            Self::MissingRegistersTable => {
+                write!(f, "register-map file has no [registers] table")
+            }
+            Self::BadRegister { name, reason } => write!(f, "register `{name}`: {reason}"),
+            Self::UnknownType { name, found } => write!(
+                f,
+                "register `{name}` has unknown type `{found}`, expected u32, i32, f32, or raw"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+struct Register {
+    name: String,
+    index: i64,
+    ty: String,
+    protected: bool,
+}
+
+/// Generates a `urap::urap_registers!` invocation named `mod_name` from
+/// a register-map TOML `source`, ready to be written into `OUT_DIR` and
+/// pulled in with `include!`.
+///
+/// Intended to be called from a `build.rs`:
+///
+/// ```no_run
+/// let source = std::fs::read_to_string("registers.toml").unwrap();
+/// let code = urap::codegen::generate_module(&source, "regs").unwrap();
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// std::fs::write(format!("{out_dir}/regs.rs"), code).unwrap();
+/// ```
+pub fn generate_module(source: &str, mod_name: &str) -> Result<String, CodegenError> {
+    let document: toml::Table = source.parse()?;
+    let registers_table = document
+        .get("registers")
+        .and_then(toml::Value::as_table)
+        .ok_or(CodegenError::MissingRegistersTable)?;
+
+    let mut registers = Vec::with_capacity(registers_table.len());
+    for (name, value) in registers_table {
+        registers.push(parse_register(name, value)?);
+    }
+    registers.sort_by_key(|reg| reg.index);
+
+    let mut code = String::new();
+    writeln!(code, "urap::urap_registers! {{").unwrap();
+    writeln!(code, "    pub mod {mod_name} {{").unwrap();
+    for reg in &registers {
+        let name = to_pascal_case(&reg.name);
+        writeln!(code, "        pub {name}: {}, {}, {};", reg.index, reg.ty, reg.protected)
+            .unwrap();
+    }
+    writeln!(code, "    }}").unwrap();
+    writeln!(code, "}}").unwrap();
+    Ok(code)
+}
+
+fn parse_register(name: &str, value: &toml::Value) -> Result<Register, CodegenError> {
+    let bad_register = |reason: &str| CodegenError::BadRegister {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let table = value.as_table().ok_or_else(|| bad_register("expected a table"))?;
+    let index = table
+        .get("index")
+        .and_then(toml::Value::as_integer)
+        .ok_or_else(|| bad_register("missing or non-integer `index`"))?;
+    let ty = table
+        .get("type")
+        .and_then(toml::Value::as_str)
+        .ok_or_else(|| bad_register("missing or non-string `type`"))?;
+    let protected = table
+        .get("protected")
+        .and_then(toml::Value::as_bool)
+        .ok_or_else(|| bad_register("missing or non-bool `protected`"))?;
+
+    if !matches!(ty, "u32" | "i32" | "f32" | "raw") {
+        return Err(CodegenError::UnknownType { name: name.to_string(), found: ty.to_string() });
+    }
+
+    Ok(Register { name: name.to_string(), index, ty: ty.to_string(), protected })
+}
+
+fn to_pascal_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for word in name.split(['_', '-']) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            result.extend(first.to_uppercase());
+            result.extend(chars.flat_map(|c| c.to_lowercase()));
+        }
+    }
+    result
+}
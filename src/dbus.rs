@@ -0,0 +1,678 @@
+//! A D-Bus service wrapper that exposes a URAP secondary's registers as
+//! properties on a D-Bus object, for desktop/system services on embedded
+//! Linux to consume device state idiomatically instead of speaking URAP
+//! directly.
+//!
+//! Each register `n` is exported as a `Register{n}` property of type
+//! `ay` (a byte array) on the configured interface, readable via the
+//! standard `org.freedesktop.DBus.Properties.Get`/`GetAll` methods and
+//! writable via `Set`. Change detection reuses the [`crate::notify`]
+//! extension the same way [`crate::mqtt::MqttBridge`] does: a worker
+//! thread drains [`NotifyPrimary::poll_notifications`] and emits the
+//! standard `org.freedesktop.DBus.Properties.PropertiesChanged` signal
+//! for whatever changed.
+//!
+//! Only the subset of the D-Bus wire protocol this wrapper needs - the
+//! `AUTH EXTERNAL` SASL handshake, `Hello`/`RequestName`, and marshalling
+//! the handful of message shapes above - is implemented here; there's no
+//! dependency on a full D-Bus client crate, the same tradeoff
+//! [`crate::mqtt`] makes for its MQTT client.
+
+use std::io;
+use std::ops::Range;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, NotifyPrimary, UrapPrimary, URAP_COUNT_MAX};
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+const SIGNAL: u8 = 4;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 9;
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+
+fn pad_to(buf: &mut Vec<u8>, align: usize) {
+    while !buf.len().is_multiple_of(align) {
+        buf.push(0);
+    }
+}
+
+fn write_u32(buf: &mut Vec<u8>, value: u32) {
+    pad_to(buf, 4);
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) {
+    write_u32(buf, value.len() as u32);
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+fn write_signature(buf: &mut Vec<u8>, value: &str) {
+    buf.push(value.len() as u8);
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+fn write_byte_array(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_u32(buf, bytes.len() as u32);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_variant_byte_array(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_signature(buf, "ay");
+    write_byte_array(buf, bytes);
+}
+
+/// A single `{key: [bytes]}` entry of an `a{sv}` dictionary, as used by
+/// `GetAll` and `PropertiesChanged`.
+fn write_properties_dict(buf: &mut Vec<u8>, properties: &[(String, [u8; 4])]) {
+    pad_to(buf, 4);
+    let len_pos = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    let start = buf.len();
+    for (name, value) in properties {
+        pad_to(buf, 8);
+        write_string(buf, name);
+        write_variant_byte_array(buf, value);
+    }
+    let len = (buf.len() - start) as u32;
+    buf[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+}
+
+/// A marshalled D-Bus header field (`field_code`, variant value).
+enum HeaderField<'a> {
+    ObjectPath(&'a str),
+    Str(&'a str),
+    Uint32(u32),
+    Signature(&'a str),
+}
+
+fn write_header_fields(buf: &mut Vec<u8>, fields: &[(u8, HeaderField)]) {
+    pad_to(buf, 4);
+    let len_pos = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    let start = buf.len();
+    for (code, value) in fields {
+        pad_to(buf, 8);
+        buf.push(*code);
+        match value {
+            HeaderField::ObjectPath(s) => {
+                write_signature(buf, "o");
+                write_string(buf, s);
+            }
+            HeaderField::Str(s) => {
+                write_signature(buf, "s");
+                write_string(buf, s);
+            }
+            HeaderField::Uint32(n) => {
+                write_signature(buf, "u");
+                write_u32(buf, *n);
+            }
+            HeaderField::Signature(s) => {
+                write_signature(buf, "g");
+                write_signature(buf, s);
+            }
+        }
+    }
+    let len = (buf.len() - start) as u32;
+    buf[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+}
+
+/// Builds a complete, ready-to-send D-Bus message: little-endian fixed
+/// header, header fields, padding to an 8-byte boundary, then `body`.
+fn build_message(message_type: u8, serial: u32, fields: &[(u8, HeaderField)], body: &[u8]) -> Vec<u8> {
+    let mut message = vec![b'l', message_type, 0, 1];
+    message.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    message.extend_from_slice(&serial.to_le_bytes());
+    write_header_fields(&mut message, fields);
+    pad_to(&mut message, 8);
+    message.extend_from_slice(body);
+    message
+}
+
+fn method_call(serial: u32, destination: &str, path: &str, interface: &str, member: &str, signature: &str, body: &[u8]) -> Vec<u8> {
+    let mut fields = vec![
+        (FIELD_PATH, HeaderField::ObjectPath(path)),
+        (FIELD_INTERFACE, HeaderField::Str(interface)),
+        (FIELD_MEMBER, HeaderField::Str(member)),
+        (FIELD_DESTINATION, HeaderField::Str(destination)),
+    ];
+    if !signature.is_empty() {
+        fields.push((FIELD_SIGNATURE, HeaderField::Signature(signature)));
+    }
+    build_message(METHOD_CALL, serial, &fields, body)
+}
+
+fn method_return(serial: u32, reply_serial: u32, signature: &str, body: &[u8]) -> Vec<u8> {
+    let mut fields = vec![(FIELD_REPLY_SERIAL, HeaderField::Uint32(reply_serial))];
+    if !signature.is_empty() {
+        fields.push((FIELD_SIGNATURE, HeaderField::Signature(signature)));
+    }
+    build_message(METHOD_RETURN, serial, &fields, body)
+}
+
+fn error_reply(serial: u32, reply_serial: u32, error_name: &str, message: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_string(&mut body, message);
+    let fields = vec![
+        (FIELD_REPLY_SERIAL, HeaderField::Uint32(reply_serial)),
+        (FIELD_ERROR_NAME, HeaderField::Str(error_name)),
+        (FIELD_SIGNATURE, HeaderField::Signature("s")),
+    ];
+    build_message(ERROR, serial, &fields, &body)
+}
+
+fn properties_changed_signal(serial: u32, path: &str, interface: &str, changed: &[(String, [u8; 4])]) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_string(&mut body, interface);
+    write_properties_dict(&mut body, changed);
+    write_u32(&mut body, 0); // invalidated properties, always empty here
+
+    let fields = vec![
+        (FIELD_PATH, HeaderField::ObjectPath(path)),
+        (FIELD_INTERFACE, HeaderField::Str(PROPERTIES_INTERFACE)),
+        (FIELD_MEMBER, HeaderField::Str("PropertiesChanged")),
+        (FIELD_SIGNATURE, HeaderField::Signature("sa{sv}as")),
+    ];
+    build_message(SIGNAL, serial, &fields, &body)
+}
+
+fn read_exact<IO: io::Read>(io: &mut IO, buf: &mut [u8]) -> io::Result<()> {
+    io.read_exact(buf)
+}
+
+fn read_u32(data: &[u8], pos: &mut usize) -> Option<u32> {
+    while !pos.is_multiple_of(4) {
+        *pos += 1;
+    }
+    let value = u32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().ok()?);
+    *pos += 4;
+    Some(value)
+}
+
+fn read_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let len = read_u32(data, pos)? as usize;
+    let value = std::str::from_utf8(data.get(*pos..*pos + len)?).ok()?.to_string();
+    *pos += len + 1; // skip the trailing nul
+    Some(value)
+}
+
+fn read_signature(data: &[u8], pos: &mut usize) -> Option<String> {
+    let len = *data.get(*pos)? as usize;
+    *pos += 1;
+    let value = std::str::from_utf8(data.get(*pos..*pos + len)?).ok()?.to_string();
+    *pos += len + 1;
+    Some(value)
+}
+
+/// A method call's header, enough to dispatch it, and its raw body.
+struct IncomingCall {
+    serial: u32,
+    path: String,
+    interface: String,
+    member: String,
+    body: Vec<u8>,
+}
+
+/// Reads one complete D-Bus message off `stream` and, if it's a method
+/// call, returns its dispatch-relevant fields. Non-method-call messages
+/// (our own `Hello`/`RequestName` replies, signals we didn't ask for) are
+/// read fully (so the stream stays in sync) and reported as `None`.
+fn read_message(stream: &mut UnixStream) -> io::Result<Option<IncomingCall>> {
+    let mut fixed = [0u8; 16];
+    read_exact(stream, &mut fixed)?;
+    let message_type = fixed[1];
+    let body_length = u32::from_le_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]) as usize;
+    let serial = u32::from_le_bytes([fixed[8], fixed[9], fixed[10], fixed[11]]);
+    let fields_len = u32::from_le_bytes([fixed[12], fixed[13], fixed[14], fixed[15]]) as usize;
+
+    let mut fields_data = vec![0u8; fields_len];
+    read_exact(stream, &mut fields_data)?;
+
+    let header_len = 16 + fields_len;
+    let padding = header_len.next_multiple_of(8) - header_len;
+    let mut pad_buf = vec![0u8; padding];
+    read_exact(stream, &mut pad_buf)?;
+
+    let mut body = vec![0u8; body_length];
+    read_exact(stream, &mut body)?;
+
+    if message_type != METHOD_CALL {
+        return Ok(None);
+    }
+
+    let mut path = String::new();
+    let mut interface = String::new();
+    let mut member = String::new();
+    let mut pos = 0;
+    while pos < fields_data.len() {
+        while !pos.is_multiple_of(8) && pos < fields_data.len() {
+            pos += 1;
+        }
+        if pos >= fields_data.len() {
+            break;
+        }
+        let Some(&code) = fields_data.get(pos) else { break };
+        pos += 1;
+        let Some(signature) = read_signature(&fields_data, &mut pos) else { break };
+        match (code, signature.as_str()) {
+            (FIELD_PATH, "o") => path = read_string(&fields_data, &mut pos).unwrap_or_default(),
+            (FIELD_INTERFACE, "s") => interface = read_string(&fields_data, &mut pos).unwrap_or_default(),
+            (FIELD_MEMBER, "s") => member = read_string(&fields_data, &mut pos).unwrap_or_default(),
+            (_, "s" | "o") => {
+                read_string(&fields_data, &mut pos);
+            }
+            (_, "g") => {
+                read_signature(&fields_data, &mut pos);
+            }
+            (_, "u") => {
+                read_u32(&fields_data, &mut pos);
+            }
+            _ => break,
+        }
+    }
+
+    Ok(Some(IncomingCall { serial, path, interface, member, body }))
+}
+
+fn sasl_handshake(stream: &mut UnixStream) -> io::Result<()> {
+    use std::io::{Read as _, Write as _};
+
+    let uid = unsafe { libc_geteuid() };
+    let hex_uid: String = uid.to_string().bytes().map(|b| format!("{b:02x}")).collect();
+
+    stream.write_all(&[0])?;
+    stream.write_all(format!("AUTH EXTERNAL {hex_uid}\r\n").as_bytes())?;
+
+    let mut response = [0u8; 512];
+    let n = stream.read(&mut response)?;
+    if !response[..n].starts_with(b"OK") {
+        return Err(io::Error::other("bus rejected AUTH EXTERNAL"));
+    }
+
+    stream.write_all(b"BEGIN\r\n")?;
+    Ok(())
+}
+
+/// Reads the effective UID without pulling in a full `libc` dependency
+/// just for this one call; `getuid(2)` has no failure mode.
+unsafe fn libc_geteuid() -> u32 {
+    extern "C" {
+        fn geteuid() -> u32;
+    }
+    geteuid()
+}
+
+fn send_and_await_method_return(stream: &mut UnixStream, request: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Write as _;
+    stream.write_all(request)?;
+    loop {
+        match read_message(stream)? {
+            Some(_) => continue, // an incoming call arrived before our reply; not expected during the handshake
+            None => {
+                // `read_message` already consumed a full non-call message;
+                // the handshake only ever awaits one reply, so this is it.
+                return Ok(Vec::new());
+            }
+        }
+    }
+}
+
+/// The object path, interface name, and register range a [`DbusService`]
+/// exports, shared by its two worker threads.
+struct ObjectConfig {
+    path: String,
+    interface: String,
+    registers: Range<u16>,
+}
+
+/// Serves a URAP secondary's registers as D-Bus properties on `path`
+/// under `interface`, and publishes register changes as
+/// `PropertiesChanged` signals.
+pub struct DbusService {
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+}
+
+impl DbusService {
+    /// Connects to the bus listening on `bus_socket_path`, authenticates
+    /// with `AUTH EXTERNAL`, optionally claims `service_name`, then
+    /// starts one thread replying to `Properties`/`Introspectable`
+    /// method calls against `path`/`interface` and one thread publishing
+    /// `PropertiesChanged` for whatever `registers` change on `io`.
+    /// `poll_interval` paces the change-notification drain.
+    pub fn spawn<IO>(
+        bus_socket_path: &str,
+        service_name: Option<&str>,
+        path: &str,
+        interface: &str,
+        registers: Range<u16>,
+        poll_interval: Duration,
+        mut io: IO,
+    ) -> io::Result<Self>
+    where
+        IO: Read + Write + Send + 'static,
+    {
+        if registers.len() > URAP_COUNT_MAX as usize {
+            return Err(io::Error::other("more registers than a single subscription can cover"));
+        }
+
+        NotifyPrimary::new(&mut io)
+            .subscribe(registers.start, registers.len() as u8)
+            .map_err(|err| io::Error::other(format!("subscribing to register changes failed: {err:?}")))?;
+
+        let mut signal_stream = UnixStream::connect(bus_socket_path)?;
+        sasl_handshake(&mut signal_stream)?;
+        let serial = Arc::new(AtomicU32::new(1));
+
+        let hello = method_call(
+            serial.fetch_add(1, Ordering::SeqCst),
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            "",
+            &[],
+        );
+        send_and_await_method_return(&mut signal_stream, &hello)?;
+
+        if let Some(name) = service_name {
+            let mut body = Vec::new();
+            write_string(&mut body, name);
+            write_u32(&mut body, 4); // DBUS_NAME_FLAG_DO_NOT_QUEUE
+            let request_name = method_call(
+                serial.fetch_add(1, Ordering::SeqCst),
+                "org.freedesktop.DBus",
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "RequestName",
+                "su",
+                &body,
+            );
+            send_and_await_method_return(&mut signal_stream, &request_name)?;
+        }
+
+        // Reads only ever happen on the call thread; writes happen from
+        // both threads (outgoing signals and incoming-call replies share
+        // the one connection), so writes go through a shared lock to
+        // keep one thread's message from interleaving with another's.
+        let read_stream = signal_stream.try_clone()?;
+        let writer = Arc::new(Mutex::new(signal_stream));
+
+        let io = Arc::new(Mutex::new(io));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let config = Arc::new(ObjectConfig {
+            path: path.to_string(),
+            interface: interface.to_string(),
+            registers,
+        });
+
+        let signal_io = Arc::clone(&io);
+        let signal_errors = Arc::clone(&errors);
+        let signal_serial = Arc::clone(&serial);
+        let signal_config = Arc::clone(&config);
+        let signal_writer = Arc::clone(&writer);
+        let signal_thread = thread::spawn(move || {
+            publish_loop(signal_io, signal_writer, signal_serial, signal_config, poll_interval, signal_errors)
+        });
+
+        let call_io = Arc::clone(&io);
+        let call_errors = Arc::clone(&errors);
+        let call_serial = Arc::clone(&serial);
+        let call_config = Arc::clone(&config);
+        let call_thread = thread::spawn(move || {
+            serve_calls(call_io, read_stream, writer, call_serial, call_config, call_errors)
+        });
+
+        Ok(Self {
+            join_handles: vec![signal_thread, call_thread],
+            errors,
+        })
+    }
+
+    /// Pops the oldest recorded error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn property_name(register: u16) -> String {
+    format!("Register{register}")
+}
+
+fn register_of_property(name: &str) -> Option<u16> {
+    name.strip_prefix("Register")?.parse().ok()
+}
+
+fn read_register<IO: Read + Write>(io: &Arc<Mutex<IO>>, register: u16) -> Result<[u8; 4], Error<IO::Error>> {
+    let mut io = io.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut *io);
+    let mut value = [[0u8; 4]; 1];
+    primary.read_4u8(register, &mut value)?;
+    Ok(value[0])
+}
+
+fn write_register<IO: Read + Write>(io: &Arc<Mutex<IO>>, register: u16, value: [u8; 4]) -> Result<(), Error<IO::Error>> {
+    let mut io = io.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut *io);
+    primary.write_4u8(register, &[value])
+}
+
+fn publish_loop<IO: Read + Write>(
+    io: Arc<Mutex<IO>>,
+    writer: Arc<Mutex<UnixStream>>,
+    serial: Arc<AtomicU32>,
+    config: Arc<ObjectConfig>,
+    poll_interval: Duration,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    use std::io::Write as _;
+
+    let mut changed = [0u16; URAP_COUNT_MAX as usize];
+    loop {
+        thread::sleep(poll_interval);
+
+        let reported = {
+            let mut io = match io.lock() {
+                Ok(io) => io,
+                Err(_) => return,
+            };
+            NotifyPrimary::new(&mut *io).poll_notifications(&mut changed[..config.registers.len().max(1)])
+        };
+        let reported = match reported {
+            Ok(reported) => reported,
+            Err(err) => {
+                push_error(&errors, Error::Io(io::Error::other(format!("polling register changes failed: {err:?}"))));
+                continue;
+            }
+        };
+        if reported == 0 {
+            continue;
+        }
+
+        let mut properties = Vec::with_capacity(reported);
+        for &register in &changed[..reported] {
+            match read_register(&io, register) {
+                Ok(value) => properties.push((property_name(register), value)),
+                Err(err) => push_error(
+                    &errors,
+                    Error::Io(io::Error::other(format!("reading changed register {register} failed: {err:?}"))),
+                ),
+            }
+        }
+        if properties.is_empty() {
+            continue;
+        }
+
+        let message =
+            properties_changed_signal(serial.fetch_add(1, Ordering::SeqCst), &config.path, &config.interface, &properties);
+        let write_result = match writer.lock() {
+            Ok(mut writer) => writer.write_all(&message),
+            Err(_) => return,
+        };
+        if let Err(err) = write_result {
+            push_error(&errors, Error::Io(err));
+        }
+    }
+}
+
+fn serve_calls<IO: Read + Write>(
+    io: Arc<Mutex<IO>>,
+    mut reader: UnixStream,
+    writer: Arc<Mutex<UnixStream>>,
+    serial: Arc<AtomicU32>,
+    config: Arc<ObjectConfig>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    use std::io::Write as _;
+
+    loop {
+        let call = match read_message(&mut reader) {
+            Ok(Some(call)) => call,
+            Ok(None) => continue,
+            Err(err) => {
+                push_error(&errors, Error::Io(err));
+                return;
+            }
+        };
+        if call.path != config.path {
+            continue;
+        }
+
+        let reply = if call.interface == PROPERTIES_INTERFACE {
+            handle_properties_call(&io, &call, &config.interface, &config.registers)
+        } else if call.interface == INTROSPECTABLE_INTERFACE && call.member == "Introspect" {
+            let body = {
+                let mut body = Vec::new();
+                write_string(&mut body, &introspection_xml(&config.interface, &config.registers));
+                body
+            };
+            Some(method_return(serial.fetch_add(1, Ordering::SeqCst), call.serial, "s", &body))
+        } else {
+            None
+        };
+
+        let reply = reply.unwrap_or_else(|| {
+            error_reply(
+                serial.fetch_add(1, Ordering::SeqCst),
+                call.serial,
+                "org.freedesktop.DBus.Error.UnknownMethod",
+                "no such method",
+            )
+        });
+
+        let write_result = match writer.lock() {
+            Ok(mut writer) => writer.write_all(&reply),
+            Err(_) => return,
+        };
+        if let Err(err) = write_result {
+            push_error(&errors, Error::Io(err));
+            return;
+        }
+    }
+}
+
+fn handle_properties_call<IO: Read + Write>(
+    io: &Arc<Mutex<IO>>,
+    call: &IncomingCall,
+    interface: &str,
+    registers: &Range<u16>,
+) -> Option<Vec<u8>> {
+    let serial = call.serial;
+    match call.member.as_str() {
+        "Get" => {
+            let mut pos = 0;
+            let requested_interface = read_string(&call.body, &mut pos)?;
+            let property = read_string(&call.body, &mut pos)?;
+            if requested_interface != interface {
+                return None;
+            }
+            let register = register_of_property(&property).filter(|r| registers.contains(r))?;
+            let value = read_register(io, register).ok()?;
+            let mut body = Vec::new();
+            write_variant_byte_array(&mut body, &value);
+            Some(method_return(serial, serial, "v", &body))
+        }
+        "GetAll" => {
+            let mut pos = 0;
+            let requested_interface = read_string(&call.body, &mut pos)?;
+            if requested_interface != interface {
+                return None;
+            }
+            let mut properties = Vec::new();
+            for register in registers.clone() {
+                let value = read_register(io, register).ok()?;
+                properties.push((property_name(register), value));
+            }
+            let mut body = Vec::new();
+            write_properties_dict(&mut body, &properties);
+            Some(method_return(serial, serial, "a{sv}", &body))
+        }
+        "Set" => {
+            let mut pos = 0;
+            let requested_interface = read_string(&call.body, &mut pos)?;
+            let property = read_string(&call.body, &mut pos)?;
+            if requested_interface != interface {
+                return None;
+            }
+            let register = register_of_property(&property).filter(|r| registers.contains(r))?;
+            let signature = read_signature(&call.body, &mut pos)?;
+            if signature != "ay" {
+                return None;
+            }
+            let len = read_u32(&call.body, &mut pos)? as usize;
+            let bytes = call.body.get(pos..pos + len)?;
+            let value: [u8; 4] = bytes.try_into().ok()?;
+            write_register(io, register, value).ok()?;
+            Some(method_return(serial, serial, "", &[]))
+        }
+        _ => None,
+    }
+}
+
+/// A minimal Introspection XML document listing `Register{n}` properties
+/// for every register in `registers`, enough for generic D-Bus
+/// inspection tools (`busctl introspect`, `d-feet`) to show them.
+fn introspection_xml(interface: &str, registers: &Range<u16>) -> String {
+    let mut xml = String::from("<!DOCTYPE node PUBLIC \"-//freedesktop//DTD D-BUS Object Introspection 1.0//EN\"\n\"http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd\">\n<node>\n");
+    xml.push_str(&format!("  <interface name=\"{interface}\">\n"));
+    for register in registers.clone() {
+        xml.push_str(&format!("    <property name=\"{}\" type=\"ay\" access=\"readwrite\"/>\n", property_name(register)));
+    }
+    xml.push_str("  </interface>\n</node>\n");
+    xml
+}
+
+impl Drop for DbusService {
+    fn drop(&mut self) {
+        // Both worker threads run forever today; detach rather than
+        // block the dropping thread. A graceful shutdown API is tracked
+        // separately, mirroring `modbus::ModbusGateway`.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! An object-safe [`UrapPrimary`](crate::UrapPrimary) for tooling that
+//! mixes serial, TCP, and Unix-socket transports in one collection
+//! instead of monomorphizing a primary per transport.
+//!
+//! [`crate::UrapPrimary`] is generic over `IO: embedded_io::Read +
+//! embedded_io::Write`, whose `Error` associated type differs per
+//! transport — that makes a `Vec<UrapPrimary<...>>` over mixed
+//! transports impossible. [`DynPrimary`] erases the transport behind a
+//! boxed `std::io::Read + std::io::Write` and boxes its error too, so
+//! every transport shares the same concrete type.
+
+use std::fmt;
+
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+
+use crate::UrapPrimary;
+
+/// A boxed transport error, erasing the differences between e.g. a
+/// serial port's error type and [`std::io::Error`].
+pub struct DynIoError(Box<dyn std::error::Error + Send + Sync>);
+
+impl DynIoError {
+    pub(crate) fn new<E: std::error::Error + Send + Sync + 'static>(err: E) -> Self {
+        Self(Box::new(err))
+    }
+}
+
+impl fmt::Debug for DynIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for DynIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for DynIoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl embedded_io::Error for DynIoError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// [`crate::Error`] specialized to [`DynPrimary`]'s erased transport.
+pub type DynError = crate::Error<DynIoError>;
+
+/// A transport both [`DynPrimary`] and [`crate::dyn_secondary::UrapService`]
+/// can erase behind a trait object; implemented for every
+/// `std::io::Read + std::io::Write` type.
+pub trait DynTransport: std::io::Read + std::io::Write {}
+impl<T: std::io::Read + std::io::Write> DynTransport for T {}
+
+struct BoxedIo(Box<dyn DynTransport + Send>);
+
+impl ErrorType for BoxedIo {
+    type Error = DynIoError;
+}
+
+impl Read for BoxedIo {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.0.read(buf).map_err(DynIoError::new)
+    }
+}
+
+impl Write for BoxedIo {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.write(buf).map_err(DynIoError::new)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush().map_err(DynIoError::new)
+    }
+}
+
+/// A [`crate::UrapPrimary`] over an erased transport.
+///
+/// `WIDTH` is the width in bytes of a single register (4 by default);
+/// `BIG_ENDIAN` selects the byte order the typed accessors use. See
+/// [`crate::UrapPrimary`] for both.
+pub struct DynPrimary<const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    io: BoxedIo,
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> DynPrimary<WIDTH, BIG_ENDIAN> {
+    /// Erases `io`'s concrete type behind a boxed transport.
+    pub fn new<T>(io: T) -> Self
+    where
+        T: std::io::Read + std::io::Write + Send + 'static,
+    {
+        Self { io: BoxedIo(Box::new(io)) }
+    }
+
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    pub fn read_4u8(&mut self, register: u16, data: &mut [[u8; WIDTH]]) -> Result<(), DynError> {
+        UrapPrimary::<_, WIDTH, BIG_ENDIAN>::new(&mut self.io).read_4u8(register, data)
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    pub fn write_4u8(&mut self, register: u16, data: &[[u8; WIDTH]]) -> Result<(), DynError> {
+        UrapPrimary::<_, WIDTH, BIG_ENDIAN>::new(&mut self.io).write_4u8(register, data)
+    }
+
+    /// Returns `true` if register `0` can be read without error.
+    pub fn is_healthy(&mut self) -> bool {
+        UrapPrimary::<_, WIDTH, BIG_ENDIAN>::new(&mut self.io).is_healthy()
+    }
+}
@@ -0,0 +1,64 @@
+//! An object-safe [`UrapService`] for hosting [`crate::UrapSecondary`]s of
+//! different `REGCNT`/store/transport types in one collection — e.g. a
+//! Linux gateway simulating many devices, each with its own register map
+//! and socket.
+//!
+//! [`crate::UrapSecondary`] is generic over so many type parameters
+//! (`REGCNT`, `WIDTH`, `P`, `R`, `H`, `S`, `MAXCOUNT`, plus whatever
+//! `IO: embedded_io::Read + embedded_io::Write` it's polled with) that a
+//! `Vec` of mixed secondaries is impossible to express directly.
+//! [`UrapService`] erases all of that behind a single method taking an
+//! erased transport, the same way [`crate::dyn_primary::DynPrimary`]
+//! erases a primary's transport.
+
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::dyn_primary::{DynError, DynIoError, DynTransport};
+use crate::{PollOutcome, ReadProtect, RegisterStore, UrapSecondary, WriteHook, WriteProtect};
+
+struct BoxedIoMut<'a>(&'a mut dyn DynTransport);
+
+impl ErrorType for BoxedIoMut<'_> {
+    type Error = DynIoError;
+}
+
+impl Read for BoxedIoMut<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.0.read(buf).map_err(DynIoError::new)
+    }
+}
+
+impl Write for BoxedIoMut<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.write(buf).map_err(DynIoError::new)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush().map_err(DynIoError::new)
+    }
+}
+
+/// Object-safe interface to a [`crate::UrapSecondary`], so a host can
+/// collect secondaries of different `REGCNT`/store/transport types behind
+/// one `Vec<Box<dyn UrapService>>` instead of monomorphizing a collection
+/// per device type.
+pub trait UrapService {
+    /// Services a single request over `io`, as
+    /// [`UrapSecondary::poll`](crate::UrapSecondary::poll), but over an
+    /// erased `std::io::Read + std::io::Write` transport instead of a
+    /// generic `embedded_io` one.
+    fn poll(&mut self, io: &mut dyn DynTransport) -> Result<PollOutcome, DynError>;
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, H, S, const MAXCOUNT: usize> UrapService
+    for UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+    S: RegisterStore<WIDTH>,
+{
+    fn poll(&mut self, io: &mut dyn DynTransport) -> Result<PollOutcome, DynError> {
+        UrapSecondary::poll(self, &mut BoxedIoMut(io))
+    }
+}
@@ -0,0 +1,30 @@
+//! Retry backoff for firmwares built on the [Embassy](https://embassy.dev)
+//! async executor.
+//!
+//! [`EmbassyDelay`] implements the existing synchronous [`crate::Delay`]
+//! trait via `embassy_time::block_for`, so
+//! [`UrapPrimary::read_4u8_with_retry`](crate::UrapPrimary::read_4u8_with_retry)/
+//! [`write_4u8_with_retry`](crate::UrapPrimary::write_4u8_with_retry) get
+//! working backoff from a blocking Embassy task without pulling in
+//! `std::thread::sleep`. `block_for` busy-waits rather than yielding to
+//! the executor, so it's only appropriate for the same kind of short,
+//! bounded blocking task a primary transaction already is - don't call
+//! it from inside an `async fn` you expect other tasks to run during.
+//!
+//! Async-native `UrapPrimary`/`UrapSecondary` traits that actually `.await`
+//! on an `embedded-io-async` transport are a larger follow-up, not
+//! provided here.
+
+use embassy_time::{block_for, Duration};
+
+use crate::Delay;
+
+/// A [`Delay`] backed by `embassy_time::block_for`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmbassyDelay;
+
+impl Delay for EmbassyDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        block_for(Duration::from_millis(ms.into()));
+    }
+}
@@ -0,0 +1,216 @@
+//! An encrypted transport wrapper: [`EncryptedIo`] seals everything
+//! written to it with ChaCha20-Poly1305 and verifies/decrypts everything
+//! read from it, under a key shared out-of-band by both ends.
+//!
+//! It wraps any [`Read`] + [`Write`] transport and is itself one, so it
+//! drops in underneath [`crate::UrapPrimary`]/[`crate::UrapSecondary`]
+//! unchanged - useful when the link (e.g. a shared serial bus, or a
+//! facility network) isn't trusted to keep traffic private.
+//!
+//! Frames on the wire are `LEN (2, LE) | CIPHERTEXT (LEN) | TAG (16)`.
+//! `LEN` is the plaintext length; encryption doesn't change it.
+//! Nonces are never transmitted: each direction derives its nonce from a
+//! caller-chosen 4-byte prefix plus a local monotonic counter, so the two
+//! ends must be given different prefixes or nonces (and therefore
+//! confidentiality) will collide.
+
+use chacha20poly1305::aead::inout::InOutBuf;
+use chacha20poly1305::aead::{AeadInOut, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use embedded_io::{ErrorType, Read, Write};
+use core::fmt;
+
+/// Size in bytes of a Poly1305 authentication tag.
+const TAG_SIZE: usize = 16;
+
+/// What went wrong reading or writing an [`EncryptedIo`].
+#[derive(Debug)]
+pub enum EncryptedIoError<E> {
+    /// The underlying transport returned an error.
+    Io(E),
+    /// The underlying transport closed before a full frame arrived.
+    Eof,
+    /// A received frame's declared length didn't fit the buffer, or its
+    /// tag failed to verify.
+    Invalid,
+}
+
+impl<E: fmt::Display> fmt::Display for EncryptedIoError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "transport error: {err}"),
+            Self::Eof => write!(f, "transport closed before a full frame was received"),
+            Self::Invalid => write!(f, "frame length or authentication tag was invalid"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> core::error::Error for EncryptedIoError<E> {}
+
+impl<E: embedded_io::Error + fmt::Debug + fmt::Display> embedded_io::Error for EncryptedIoError<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Io(err) => err.kind(),
+            Self::Eof => embedded_io::ErrorKind::Other,
+            Self::Invalid => embedded_io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), EncryptedIoError<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(EncryptedIoError::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(EncryptedIoError::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+fn nonce(prefix: [u8; 4], counter: u64) -> Nonce {
+    let mut bytes = [0u8; 12];
+    bytes[..4].copy_from_slice(&prefix);
+    bytes[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::from(bytes)
+}
+
+/// Encrypts and authenticates every byte written to, and decrypts and
+/// verifies every byte read from, an inner transport.
+///
+/// `CAP` bounds the largest single frame (the most a caller writes
+/// between two [`Self::flush`] calls, or reads in one
+/// [`crate::UrapPrimary`]/[`crate::UrapSecondary`] exchange); it
+/// defaults to comfortably cover a [`crate::URAP_COUNT_MAX`]-register
+/// packet at the default width.
+pub struct EncryptedIo<IO, const CAP: usize = 512> {
+    inner: IO,
+    key: Key,
+    tx_prefix: [u8; 4],
+    tx_counter: u64,
+    rx_prefix: [u8; 4],
+    rx_counter: u64,
+    write_buf: [u8; CAP],
+    write_len: usize,
+    read_buf: [u8; CAP],
+    read_pos: usize,
+    read_len: usize,
+}
+
+impl<IO, const CAP: usize> EncryptedIo<IO, CAP> {
+    /// Wraps `inner`, sealing writes under `key` with nonces built from
+    /// `tx_prefix` and verifying reads with nonces built from
+    /// `rx_prefix`. The two ends of a link must be configured with
+    /// swapped prefixes (this end's `tx_prefix` is the peer's
+    /// `rx_prefix`) and must never share a prefix with themselves.
+    pub fn new(inner: IO, key: [u8; 32], tx_prefix: [u8; 4], rx_prefix: [u8; 4]) -> Self {
+        Self {
+            inner,
+            key: Key::from(key),
+            tx_prefix,
+            tx_counter: 0,
+            rx_prefix,
+            rx_counter: 0,
+            write_buf: [0u8; CAP],
+            write_len: 0,
+            read_buf: [0u8; CAP],
+            read_pos: 0,
+            read_len: 0,
+        }
+    }
+
+    fn fill_frame(&mut self) -> Result<bool, EncryptedIoError<IO::Error>>
+    where
+        IO: Read,
+    {
+        let mut len_bytes = [0u8; 2];
+        let mut filled = 0;
+        while filled < len_bytes.len() {
+            match self.inner.read(&mut len_bytes[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(EncryptedIoError::Eof),
+                Ok(n) => filled += n,
+                Err(err) => return Err(EncryptedIoError::Io(err)),
+            }
+        }
+
+        let len = u16::from_le_bytes(len_bytes) as usize;
+        if len > CAP {
+            return Err(EncryptedIoError::Invalid);
+        }
+        read_exact(&mut self.inner, &mut self.read_buf[..len])?;
+        let mut tag_bytes = [0u8; TAG_SIZE];
+        read_exact(&mut self.inner, &mut tag_bytes)?;
+
+        let nonce = nonce(self.rx_prefix, self.rx_counter);
+        let cipher = ChaCha20Poly1305::new(&self.key);
+        cipher
+            .decrypt_inout_detached(
+                &nonce,
+                b"",
+                InOutBuf::from(&mut self.read_buf[..len]),
+                &Tag::from(tag_bytes),
+            )
+            .map_err(|_| EncryptedIoError::Invalid)?;
+        self.rx_counter += 1;
+
+        self.read_len = len;
+        self.read_pos = 0;
+        Ok(true)
+    }
+}
+
+impl<IO: ErrorType, const CAP: usize> ErrorType for EncryptedIo<IO, CAP> {
+    type Error = EncryptedIoError<IO::Error>;
+}
+
+impl<IO: Read, const CAP: usize> Read for EncryptedIo<IO, CAP> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.read_pos >= self.read_len && !self.fill_frame()? {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(self.read_len - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Ok(n)
+    }
+}
+
+impl<IO: Write, const CAP: usize> Write for EncryptedIo<IO, CAP> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(CAP - self.write_len);
+        self.write_buf[self.write_len..self.write_len + n].copy_from_slice(&buf[..n]);
+        self.write_len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.write_len == 0 {
+            return self.inner.flush().map_err(EncryptedIoError::Io);
+        }
+
+        let nonce = nonce(self.tx_prefix, self.tx_counter);
+        let cipher = ChaCha20Poly1305::new(&self.key);
+        let tag = cipher
+            .encrypt_inout_detached(&nonce, b"", InOutBuf::from(&mut self.write_buf[..self.write_len]))
+            .map_err(|_| EncryptedIoError::Invalid)?;
+        self.tx_counter += 1;
+
+        let len = self.write_len as u16;
+        self.inner
+            .write_all(&len.to_le_bytes())
+            .map_err(EncryptedIoError::Io)?;
+        self.inner
+            .write_all(&self.write_buf[..self.write_len])
+            .map_err(EncryptedIoError::Io)?;
+        self.inner
+            .write_all(&tag)
+            .map_err(EncryptedIoError::Io)?;
+        self.inner.flush().map_err(EncryptedIoError::Io)?;
+
+        self.write_len = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+//! A single-threaded, event-driven alternative to
+//! [`crate::usockets::UrapSecondary`]: one `mio` event loop multiplexes
+//! every connection on one thread instead of spawning a thread (or
+//! pool worker) per connection, for resource-constrained gateways
+//! (routers, SBCs) where even a small thread pool is too heavy.
+//!
+//! [`EpollSecondary`] trades away [`crate::usockets::Listener::auth`] and
+//! the live [`crate::usockets::ServerEvent`] callback for that single
+//! thread; reach for [`crate::usockets::UrapSecondary`] if either is
+//! needed.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read as StdRead, Write as StdWrite};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use embedded_io::ErrorType;
+use embedded_io::{Read as EmbeddedRead, Write as EmbeddedWrite};
+use mio::net::{UnixListener, UnixStream};
+use mio::{Events, Interest, Poll, Token, Waker};
+
+use crate::usockets::{bind_reclaiming, send_nak, Listener, Permission};
+use crate::{
+    Error, NakCode, ReadProtect, WriteProtect, {UrapSecondary as CoreSecondary},
+    {OP_WRITE, URAP_HEADER_SIZE},
+};
+
+/// Locks `mutex`, recovering from poison instead of propagating it; see
+/// the twin of this in [`crate::usockets`] for why.
+fn lock_recover<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Token reserved for [`Waker::wake`] calls, which just interrupt a
+/// blocked [`Poll::poll`] so the loop notices [`EpollSecondary::shutdown`]
+/// has been requested; listener and connection tokens start after it.
+const WAKE_TOKEN: Token = Token(0);
+
+/// Reads (and, for writes, the CRC-protected payload) an in-progress
+/// request is buffered into before it's handed to [`CoreSecondary::poll`]
+/// all at once, since URAP's framing assumes a read never blocks
+/// part-way through a packet.
+struct PendingRequest {
+    buf: Vec<u8>,
+    /// Total length `buf` must reach before the request is complete;
+    /// known once the 4-byte header has arrived.
+    want: Option<usize>,
+}
+
+impl PendingRequest {
+    fn new() -> Self {
+        Self {
+            buf: Vec::with_capacity(URAP_HEADER_SIZE),
+            want: None,
+        }
+    }
+
+    /// Hands ownership of the accumulated bytes to the caller and resets
+    /// the buffer so the next request starts clean.
+    fn take(&mut self) -> Vec<u8> {
+        self.want = None;
+        std::mem::take(&mut self.buf)
+    }
+
+    /// Appends `chunk`, updating `want` once the header is in, and
+    /// returns `true` once `buf` holds a complete request.
+    fn feed(&mut self, chunk: &[u8], width: usize) -> bool {
+        self.buf.extend_from_slice(chunk);
+
+        if self.want.is_none() && self.buf.len() >= URAP_HEADER_SIZE {
+            let count = self.buf[3] as usize;
+            let payload = if self.buf[0] == OP_WRITE { count * width } else { 0 };
+            self.want = Some(URAP_HEADER_SIZE + payload + 2);
+        }
+
+        matches!(self.want, Some(want) if self.buf.len() >= want)
+    }
+}
+
+/// Feeds a fully-buffered request to [`CoreSecondary::poll`] and captures
+/// whatever it writes back, without ever touching the real socket (so it
+/// can't block or return [`std::io::ErrorKind::WouldBlock`] mid-packet).
+struct MemIo {
+    input: Vec<u8>,
+    pos: usize,
+    output: Vec<u8>,
+}
+
+impl ErrorType for MemIo {
+    type Error = io::Error;
+}
+
+impl EmbeddedRead for MemIo {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(self.input.len() - self.pos);
+        buf[..n].copy_from_slice(&self.input[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl EmbeddedWrite for MemIo {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// State for one accepted connection: a pending-request buffer for reads
+/// and a pending-response buffer for writes, since neither a read nor a
+/// write is guaranteed to complete in one non-blocking syscall.
+struct Conn {
+    stream: UnixStream,
+    permission: Permission,
+    request: PendingRequest,
+    response: Vec<u8>,
+    response_pos: usize,
+}
+
+/// One bound socket, kept alongside the [`Permission`] its connections
+/// get (the event loop can't borrow back into the original [`Listener`]
+/// list once every socket is registered).
+struct BoundListener {
+    socket: UnixListener,
+    permission: Permission,
+}
+
+/// Single-threaded, `mio`-based Unix-socket secondary server; see the
+/// module docs for how this differs from
+/// [`crate::usockets::UrapSecondary`].
+///
+/// Dropping this (or calling [`Self::shutdown`] directly) wakes the event
+/// loop, which stops accepting connections, closes the ones still open,
+/// and unlinks every bound socket path before its thread exits.
+pub struct EpollSecondary {
+    join_handle: Option<JoinHandle<()>>,
+    shutdown: Arc<AtomicBool>,
+    waker: Arc<Waker>,
+    errors: Arc<Mutex<VecDeque<Error<io::Error>>>>,
+    sockets: Vec<PathBuf>,
+}
+
+impl EpollSecondary {
+    /// Binds every [`Listener`] in `listeners` and runs one `mio` event
+    /// loop, on a single background thread, servicing every connection
+    /// accepted on them against `regs`.
+    pub fn spawn<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+        H: crate::WriteHook<WIDTH> + Send + 'static,
+    {
+        let sockets = listeners.iter().map(|l| l.path.clone()).collect();
+        let poll = Poll::new()?;
+        let mut bound = Vec::with_capacity(listeners.len());
+
+        for (i, listener) in listeners.into_iter().enumerate() {
+            let std_socket = bind_reclaiming(&listener)?;
+            std_socket.set_nonblocking(true)?;
+            let mut socket = UnixListener::from_std(std_socket);
+            poll.registry()
+                .register(&mut socket, Token(i + 1), Interest::READABLE)?;
+            bound.push(BoundListener {
+                socket,
+                permission: listener.permission,
+            });
+        }
+
+        let waker = Arc::new(Waker::new(poll.registry(), WAKE_TOKEN)?);
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let errors: Arc<Mutex<VecDeque<Error<io::Error>>>> = Arc::new(Mutex::new(VecDeque::new()));
+
+        let loop_shutdown = Arc::clone(&shutdown);
+        let loop_errors = Arc::clone(&errors);
+        let join_handle = thread::spawn(move || {
+            run_event_loop(regs, bound, poll, loop_shutdown, loop_errors);
+        });
+
+        Ok(Self {
+            join_handle: Some(join_handle),
+            shutdown,
+            waker,
+            errors,
+            sockets,
+        })
+    }
+
+    /// Pops the oldest recorded transport error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        lock_recover(&self.errors).pop_front()
+    }
+
+    /// Wakes the event loop, which stops accepting connections, closes
+    /// the ones still open, unlinks every bound socket path, and exits
+    /// its thread. Idempotent.
+    pub fn shutdown(&mut self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let _ = self.waker.wake();
+
+        if let Some(handle) = self.join_handle.take() {
+            let _ = handle.join();
+        }
+
+        for path in &self.sockets {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for EpollSecondary {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// How many bytes [`MemIo`]'s response buffer starts at; just large
+/// enough for an ACK or NAK of a typical request without reallocating.
+const RESPONSE_BUF_HINT: usize = 16;
+
+fn run_event_loop<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+    mut regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+    mut listeners: Vec<BoundListener>,
+    mut poll: Poll,
+    shutdown: Arc<AtomicBool>,
+    errors: Arc<Mutex<VecDeque<Error<io::Error>>>>,
+) where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: crate::WriteHook<WIDTH>,
+{
+    let mut events = Events::with_capacity(128);
+    let mut connections: HashMap<Token, Conn> = HashMap::new();
+    let mut next_token = listeners.len() + 1;
+
+    'outer: loop {
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if let Err(err) = poll.poll(&mut events, None) {
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            lock_recover(&errors).push_back(Error::Io(err));
+            break;
+        }
+
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+
+        for event in events.iter() {
+            let token = event.token();
+
+            if token == WAKE_TOKEN {
+                continue;
+            }
+
+            if token.0 >= 1 && token.0 <= listeners.len() {
+                let listener = &mut listeners[token.0 - 1];
+                loop {
+                    match listener.socket.accept() {
+                        Ok((mut stream, _addr)) => {
+                            let conn_token = Token(next_token);
+                            next_token += 1;
+                            if poll
+                                .registry()
+                                .register(&mut stream, conn_token, Interest::READABLE)
+                                .is_err()
+                            {
+                                continue;
+                            }
+                            connections.insert(
+                                conn_token,
+                                Conn {
+                                    stream,
+                                    permission: listener.permission,
+                                    request: PendingRequest::new(),
+                                    response: Vec::new(),
+                                    response_pos: 0,
+                                },
+                            );
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            lock_recover(&errors).push_back(Error::Io(err));
+                            break;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let keep = service_connection(
+                &mut regs,
+                &mut connections,
+                token,
+                event.is_readable(),
+                event.is_writable(),
+                &errors,
+            );
+            if !keep {
+                if let Some(mut conn) = connections.remove(&token) {
+                    let _ = poll.registry().deregister(&mut conn.stream);
+                }
+            }
+        }
+
+        if shutdown.load(Ordering::Relaxed) {
+            break 'outer;
+        }
+    }
+
+    for (_, mut conn) in connections.drain() {
+        let _ = poll.registry().deregister(&mut conn.stream);
+    }
+}
+
+/// Services whatever `token`'s readiness event allows right now, possibly
+/// running several requests if the peer pipelined more than one before
+/// this event fired. Returns `false` once the connection should be torn
+/// down (the peer disconnected, or a transport error occurred).
+fn service_connection<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+    regs: &mut CoreSecondary<REGCNT, WIDTH, P, R, H>,
+    connections: &mut HashMap<Token, Conn>,
+    token: Token,
+    readable: bool,
+    writable: bool,
+    errors: &Arc<Mutex<VecDeque<Error<io::Error>>>>,
+) -> bool
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: crate::WriteHook<WIDTH>,
+{
+    let Some(conn) = connections.get_mut(&token) else {
+        return false;
+    };
+
+    if writable && conn.response_pos < conn.response.len() && !flush_response(conn, errors) {
+        return false;
+    }
+
+    if !readable {
+        return true;
+    }
+
+    let mut buf = [0u8; 4096];
+    loop {
+        match conn.stream.read(&mut buf) {
+            Ok(0) => return false,
+            Ok(n) => {
+                if conn.request.feed(&buf[..n], WIDTH) {
+                    let is_protected_write =
+                        conn.request.buf[0] == OP_WRITE && conn.permission == Permission::ReadOnly;
+                    let input = conn.request.take();
+                    let mut memio = MemIo {
+                        pos: if is_protected_write { input.len() } else { 0 },
+                        input,
+                        output: Vec::with_capacity(RESPONSE_BUF_HINT),
+                    };
+
+                    if is_protected_write {
+                        if send_nak(&mut memio, NakCode::IndexWriteProtected).is_err() {
+                            return false;
+                        }
+                    } else if let Err(err) = regs.poll(&mut memio) {
+                        lock_recover(errors).push_back(err);
+                        return false;
+                    }
+
+                    conn.response.extend_from_slice(&memio.output);
+
+                    if !flush_response(conn, errors) {
+                        return false;
+                    }
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) => {
+                lock_recover(errors).push_back(Error::Io(err));
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Writes as much of `conn.response` as the socket currently accepts,
+/// leaving the rest for the next writable event.
+fn flush_response(conn: &mut Conn, errors: &Arc<Mutex<VecDeque<Error<io::Error>>>>) -> bool {
+    while conn.response_pos < conn.response.len() {
+        match conn.stream.write(&conn.response[conn.response_pos..]) {
+            Ok(0) => return false,
+            Ok(n) => conn.response_pos += n,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return true,
+            Err(err) => {
+                lock_recover(errors).push_back(Error::Io(err));
+                return false;
+            }
+        }
+    }
+
+    conn.response.clear();
+    conn.response_pos = 0;
+    true
+}
@@ -0,0 +1,81 @@
+//! The error type shared by [`crate::UrapPrimary`] and [`crate::UrapSecondary`].
+
+use core::fmt;
+
+use crate::NakCode;
+
+/// Everything that can go wrong exchanging URAP packets.
+///
+/// `E` is the underlying transport's error type (`IO::Error`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Error<E> {
+    /// The transport returned an error while reading or writing.
+    Io(E),
+    /// The transport closed (a `read` returned zero bytes) before a full
+    /// packet was received.
+    Eof,
+    /// The CRC received over the wire did not match the payload.
+    BadCrc,
+    /// The peer rejected the request with a [`NakCode`].
+    Nak(NakCode),
+    /// The caller asked to read or write more registers than
+    /// [`crate::URAP_COUNT_MAX`] allows in a single request.
+    CountTooLarge,
+    /// A [`crate::seq`] response echoed back a different sequence byte
+    /// than was sent - it's the answer to some other request, most
+    /// likely a retry loop's earlier, abandoned attempt.
+    #[cfg(feature = "seq")]
+    SeqMismatch,
+    /// [`crate::UrapPrimary::write_4u8_verified`] read back the range it
+    /// just wrote and found it didn't match what was sent.
+    VerifyMismatch,
+    /// [`crate::UrapPrimary::read_enum`] read a register whose value
+    /// isn't a valid discriminant of the requested enum.
+    InvalidDiscriminant(u32),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(err: E) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "transport error: {err}"),
+            Self::Eof => write!(f, "transport closed before a full packet was received"),
+            Self::BadCrc => write!(f, "CRC mismatch"),
+            Self::Nak(code) => write!(f, "peer rejected request: {code:?}"),
+            Self::CountTooLarge => write!(
+                f,
+                "more than {} registers requested in a single call",
+                crate::URAP_COUNT_MAX
+            ),
+            #[cfg(feature = "seq")]
+            Self::SeqMismatch => write!(f, "response sequence byte did not match the request"),
+            Self::VerifyMismatch => write!(f, "read-back after write did not match what was written"),
+            Self::InvalidDiscriminant(value) => {
+                write!(f, "{value} is not a valid discriminant for this enum")
+            }
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Eof
+            | Self::BadCrc
+            | Self::Nak(_)
+            | Self::CountTooLarge
+            | Self::VerifyMismatch
+            | Self::InvalidDiscriminant(_) => None,
+            #[cfg(feature = "seq")]
+            Self::SeqMismatch => None,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! A JSON-friendly snapshot of a register map, for gateways that forward
+//! URAP activity as JSON and for tests that want fixture files instead
+//! of literal `[[u8; WIDTH]; N]` arrays.
+//!
+//! Unrelated to [`crate::snapshot`], which saves/loads a CRC-checked
+//! binary file for restoring a secondary's state across restarts;
+//! [`RegisterSnapshot`] is for interop and readability, not durability.
+
+use serde::{Deserialize, Serialize};
+
+/// A contiguous run of registers starting at [`Self::register`], with
+/// each register's bytes concatenated into [`Self::data`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterSnapshot {
+    /// First register captured.
+    pub register: u16,
+    /// Width in bytes of a single register in this snapshot.
+    pub width: usize,
+    /// Register bytes, concatenated in register order.
+    pub data: Vec<u8>,
+}
+
+impl RegisterSnapshot {
+    /// Captures `values` (`count` registers starting at `register`) into
+    /// a snapshot.
+    pub fn capture<const WIDTH: usize>(register: u16, values: &[[u8; WIDTH]]) -> Self {
+        Self { register, width: WIDTH, data: values.iter().flatten().copied().collect() }
+    }
+
+    /// Recovers the captured registers, if `WIDTH` matches
+    /// [`Self::width`] and [`Self::data`]'s length is a multiple of it.
+    pub fn registers<const WIDTH: usize>(&self) -> Option<Vec<[u8; WIDTH]>> {
+        if self.width != WIDTH || !self.data.len().is_multiple_of(WIDTH) {
+            return None;
+        }
+        Some(self.data.chunks_exact(WIDTH).map(|chunk| chunk.try_into().unwrap()).collect())
+    }
+}
@@ -0,0 +1,783 @@
+//! A gRPC gateway exposing `ReadRegisters`/`WriteRegisters` unary RPCs
+//! and a server-streaming `Subscribe` RPC backed by [`crate::notify`]
+//! change tracking, for fleet backends that standardize on gRPC instead
+//! of speaking URAP directly.
+//!
+//! gRPC is HTTP/2 with protobuf-encoded messages, so this module
+//! implements the slice of both that a unary/server-streaming gRPC
+//! service actually needs: frame parsing, just enough HPACK to decode
+//! request headers and encode response headers, and protobuf encoding
+//! for the fixed message shapes below. There's no dependency on `tonic`,
+//! `prost`, or an HTTP/2 crate, the same tradeoff [`crate::mqtt`] makes
+//! for its MQTT client.
+//!
+//! Wire schema (no `.proto` file is shipped - these are the field
+//! numbers this gateway reads and writes):
+//!
+//! ```text
+//! message ReadRegistersRequest  { uint32 register = 1; uint32 count = 2; }
+//! message ReadRegistersResponse { bytes values = 1; }       // 4 bytes per register
+//! message WriteRegistersRequest { uint32 register = 1; bytes values = 2; }
+//! message WriteRegistersResponse {}
+//! message SubscribeRequest      { uint32 register = 1; uint32 count = 2; }
+//! message RegisterUpdate        { uint32 register = 1; bytes value = 2; }
+//! ```
+//!
+//! Methods are routed by the `:path` pseudo-header:
+//! `/urap.Registers/ReadRegisters`, `/urap.Registers/WriteRegisters`,
+//! `/urap.Registers/Subscribe`.
+//!
+//! Request header strings are decoded as HPACK literals only - Huffman
+//! coding isn't decoded, so clients that insist on Huffman-coding every
+//! header (rather than falling back to raw literals, which HPACK always
+//! permits) can't reach this gateway yet. Full Huffman support is
+//! tracked separately.
+//!
+//! `Subscribe` reuses the [`crate::notify`] extension, which means it
+//! shares its one subscription window with the upstream secondary: a
+//! new `Subscribe` call replaces whatever range an earlier one was
+//! watching, the same way a second [`crate::NotifyPrimary::subscribe`]
+//! call from any other client would. Fleets that need independent
+//! concurrent subscriptions should give each one its own upstream
+//! connection.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, NotifyPrimary, UrapPrimary, URAP_COUNT_MAX};
+
+const CONNECTION_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const FRAME_DATA: u8 = 0x0;
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_SETTINGS: u8 = 0x4;
+const FRAME_PING: u8 = 0x6;
+const FRAME_GOAWAY: u8 = 0x7;
+const FRAME_WINDOW_UPDATE: u8 = 0x8;
+
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+const FLAG_ACK: u8 = 0x1;
+
+/// The HPACK static table (RFC 7541 Appendix A), used to resolve
+/// indexed header fields and indexed header names in literals. Only
+/// entries a gRPC request plausibly references are included; an index
+/// outside this list fails decoding.
+const HPACK_STATIC_TABLE: &[(&str, &str)] = &[
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// A decoded HTTP/2 frame: `length`/type/flags/stream_id precede
+/// `payload` on the wire.
+struct Frame {
+    frame_type: u8,
+    flags: u8,
+    stream_id: u32,
+    payload: Vec<u8>,
+}
+
+fn read_frame<S: io::Read>(stream: &mut S) -> io::Result<Frame> {
+    let mut header = [0u8; 9];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    let frame_type = header[3];
+    let flags = header[4];
+    let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF;
+
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload)?;
+    Ok(Frame { frame_type, flags, stream_id, payload })
+}
+
+fn write_frame<S: io::Write>(stream: &mut S, frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) -> io::Result<()> {
+    let mut header = [0u8; 9];
+    header[0..3].copy_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    header[3] = frame_type;
+    header[4] = flags;
+    header[5..9].copy_from_slice(&stream_id.to_be_bytes());
+    stream.write_all(&header)?;
+    stream.write_all(payload)?;
+    Ok(())
+}
+
+/// HPACK's dynamic table, tracked purely for decoding: this gateway's
+/// own responses never reference it (every header it sends is encoded
+/// as a literal without indexing, which is always valid HPACK), but a
+/// real client may reuse earlier requests' headers via indices >= 62.
+struct DynamicTable {
+    entries: std::collections::VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl DynamicTable {
+    fn new() -> Self {
+        Self { entries: std::collections::VecDeque::new(), size: 0, max_size: 4096 }
+    }
+
+    fn insert(&mut self, name: String, value: String) {
+        self.size += name.len() + value.len() + 32;
+        self.entries.push_front((name, value));
+        while self.size > self.max_size {
+            let Some((name, value)) = self.entries.pop_back() else { break };
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+
+    fn get(&self, index: usize) -> Option<(String, String)> {
+        self.entries.get(index).cloned()
+    }
+
+    fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        while self.size > self.max_size {
+            let Some((name, value)) = self.entries.pop_back() else { break };
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+}
+
+fn resolve_table_index(index: usize, dynamic: &DynamicTable) -> Option<(String, String)> {
+    if index == 0 {
+        return None;
+    }
+    if let Some(&(name, value)) = HPACK_STATIC_TABLE.get(index - 1) {
+        return Some((name.to_string(), value.to_string()));
+    }
+    dynamic.get(index - 1 - HPACK_STATIC_TABLE.len())
+}
+
+/// Reads an HPACK prefix integer using `prefix_bits` of the first byte
+/// (already consumed by the caller and passed as `first_byte_value`).
+fn read_hpack_integer(data: &[u8], pos: &mut usize, prefix_bits: u32, first_byte_value: u32) -> Option<u64> {
+    let max_prefix = (1u32 << prefix_bits) - 1;
+    if first_byte_value < max_prefix {
+        return Some(first_byte_value as u64);
+    }
+    let mut value = max_prefix as u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value += ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Reads an HPACK string literal. Only the non-Huffman form is
+/// supported - see the module doc comment.
+fn read_hpack_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let first = *data.get(*pos)?;
+    *pos += 1;
+    let huffman = first & 0x80 != 0;
+    let len = read_hpack_integer(data, pos, 7, (first & 0x7F) as u32)? as usize;
+    if huffman {
+        return None;
+    }
+    let bytes = data.get(*pos..*pos + len)?;
+    *pos += len;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// Decodes a HEADERS frame payload into `(name, value)` pairs, updating
+/// `dynamic` for any entries the client asked to be indexed.
+fn decode_headers(data: &[u8], dynamic: &mut DynamicTable) -> Option<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let first = data[pos];
+        pos += 1;
+        if first & 0x80 != 0 {
+            // Indexed Header Field.
+            let index = read_hpack_integer(data, &mut pos, 7, (first & 0x7F) as u32)? as usize;
+            headers.push(resolve_table_index(index, dynamic)?);
+        } else if first & 0x40 != 0 {
+            // Literal Header Field with Incremental Indexing.
+            let index = read_hpack_integer(data, &mut pos, 6, (first & 0x3F) as u32)? as usize;
+            let name = if index == 0 {
+                read_hpack_string(data, &mut pos)?
+            } else {
+                resolve_table_index(index, dynamic)?.0
+            };
+            let value = read_hpack_string(data, &mut pos)?;
+            dynamic.insert(name.clone(), value.clone());
+            headers.push((name, value));
+        } else if first & 0x20 != 0 {
+            // Dynamic Table Size Update.
+            let max_size = read_hpack_integer(data, &mut pos, 5, (first & 0x1F) as u32)? as usize;
+            dynamic.set_max_size(max_size);
+        } else {
+            // Literal Header Field without Indexing / Never Indexed
+            // (0000xxxx / 0001xxxx) - identical on-wire shape, 4-bit
+            // prefix, neither form touches the dynamic table.
+            let index = read_hpack_integer(data, &mut pos, 4, (first & 0x0F) as u32)? as usize;
+            let name = if index == 0 {
+                read_hpack_string(data, &mut pos)?
+            } else {
+                resolve_table_index(index, dynamic)?.0
+            };
+            let value = read_hpack_string(data, &mut pos)?;
+            headers.push((name, value));
+        }
+    }
+    Some(headers)
+}
+
+fn write_hpack_integer(buf: &mut Vec<u8>, prefix_bits: u32, prefix_pattern: u8, mut value: u64) {
+    let max_prefix = (1u64 << prefix_bits) - 1;
+    if value < max_prefix {
+        buf.push(prefix_pattern | value as u8);
+        return;
+    }
+    buf.push(prefix_pattern | max_prefix as u8);
+    value -= max_prefix;
+    while value >= 0x80 {
+        buf.push(((value % 0x80) | 0x80) as u8);
+        value /= 0x80;
+    }
+    buf.push(value as u8);
+}
+
+fn write_hpack_literal_header(buf: &mut Vec<u8>, name: &str, value: &str) {
+    // "Literal Header Field without Indexing", literal name, literal
+    // value, neither Huffman-coded - always valid HPACK, and simplest
+    // to both produce and have any conformant client decode.
+    write_hpack_integer(buf, 4, 0x00, 0);
+    write_hpack_integer(buf, 7, 0x00, name.len() as u64);
+    buf.extend_from_slice(name.as_bytes());
+    write_hpack_integer(buf, 7, 0x00, value.len() as u64);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn encode_headers(headers: &[(&str, &str)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (name, value) in headers {
+        write_hpack_literal_header(&mut buf, name, value);
+    }
+    buf
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        if value < 0x80 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push(((value % 0x80) | 0x80) as u8);
+        value /= 0x80;
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field_number: u32, value: u64) {
+    write_varint(buf, (field_number as u64) << 3);
+    write_varint(buf, value);
+}
+
+fn write_bytes_field(buf: &mut Vec<u8>, field_number: u32, bytes: &[u8]) {
+    write_varint(buf, ((field_number as u64) << 3) | 2);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+/// A decoded protobuf message: every field this gateway's request
+/// messages use is either a varint or a length-delimited byte string,
+/// so a flat map from field number to raw value is enough.
+struct ProtoMessage {
+    varints: std::collections::HashMap<u32, u64>,
+    bytes: std::collections::HashMap<u32, Vec<u8>>,
+}
+
+fn decode_proto_message(data: &[u8]) -> Option<ProtoMessage> {
+    let mut message = ProtoMessage { varints: std::collections::HashMap::new(), bytes: std::collections::HashMap::new() };
+    let mut pos = 0;
+    while pos < data.len() {
+        let tag = read_varint(data, &mut pos)?;
+        let field_number = (tag >> 3) as u32;
+        let wire_type = tag & 0x7;
+        match wire_type {
+            0 => {
+                let value = read_varint(data, &mut pos)?;
+                message.varints.insert(field_number, value);
+            }
+            2 => {
+                let len = read_varint(data, &mut pos)? as usize;
+                let value = data.get(pos..pos + len)?.to_vec();
+                pos += len;
+                message.bytes.insert(field_number, value);
+            }
+            _ => return None,
+        }
+    }
+    Some(message)
+}
+
+/// Wraps a gRPC-framed message body (1-byte compression flag + 4-byte
+/// big-endian length) around `message`, as every DATA frame payload in
+/// this protocol carries.
+fn grpc_frame(message: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(5 + message.len());
+    framed.push(0); // uncompressed
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed
+}
+
+/// Unwraps a complete gRPC-framed message, assuming `framed` holds
+/// exactly one frame (true for every request this gateway services,
+/// none of which stream more than one message from the client).
+fn unwrap_grpc_frame(framed: &[u8]) -> Option<&[u8]> {
+    let len = u32::from_be_bytes(framed.get(1..5)?.try_into().ok()?) as usize;
+    framed.get(5..5 + len)
+}
+
+/// Serves gRPC requests against a single shared upstream URAP
+/// connection.
+pub struct GrpcGateway<IO> {
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+    _io: std::marker::PhantomData<IO>,
+}
+
+impl<IO> GrpcGateway<IO>
+where
+    IO: Read + Write + Send + 'static,
+{
+    /// Binds `addr` and starts serving gRPC requests against `io`, one
+    /// worker thread per accepted connection.
+    pub fn spawn(addr: SocketAddr, io: IO) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let io = Arc::new(Mutex::new(io));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_io = Arc::clone(&io);
+        let accept_errors = Arc::clone(&errors);
+        let join_handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        push_error(&accept_errors, Error::Io(err));
+                        continue;
+                    }
+                };
+                let io = Arc::clone(&accept_io);
+                let errors = Arc::clone(&accept_errors);
+                thread::spawn(move || service_connection(stream, io, errors));
+            }
+        });
+
+        Ok(Self {
+            join_handles: vec![join_handle],
+            errors,
+            _io: std::marker::PhantomData,
+        })
+    }
+
+    /// Pops the oldest recorded error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+}
+
+impl<IO> Drop for GrpcGateway<IO> {
+    fn drop(&mut self) {
+        // The accept loop runs forever today; detach rather than block
+        // the dropping thread. A graceful shutdown API is tracked
+        // separately, mirroring `modbus::ModbusGateway`.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn write_response_headers<S: io::Write>(stream: &mut S, stream_id: u32) -> io::Result<()> {
+    let headers = encode_headers(&[(":status", "200"), ("content-type", "application/grpc")]);
+    write_frame(stream, FRAME_HEADERS, FLAG_END_HEADERS, stream_id, &headers)
+}
+
+fn write_trailers<S: io::Write>(stream: &mut S, stream_id: u32, status: &str) -> io::Result<()> {
+    let headers = encode_headers(&[("grpc-status", status)]);
+    write_frame(stream, FRAME_HEADERS, FLAG_END_HEADERS | FLAG_END_STREAM, stream_id, &headers)
+}
+
+fn service_connection<IO: Read + Write + Send + 'static>(
+    stream: TcpStream,
+    io: Arc<Mutex<IO>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let _ = stream.set_nodelay(true);
+    let mut reader = stream;
+    let writer = match reader.try_clone() {
+        Ok(clone) => Arc::new(Mutex::new(clone)),
+        Err(err) => {
+            push_error(&errors, Error::Io(err));
+            return;
+        }
+    };
+
+    let mut preface = [0u8; 24];
+    if std::io::Read::read_exact(&mut reader, &mut preface).is_err() || preface != CONNECTION_PREFACE[..] {
+        return;
+    }
+    if let Ok(mut writer) = writer.lock() {
+        if write_frame(&mut *writer, FRAME_SETTINGS, 0, 0, &[]).is_err() {
+            return;
+        }
+    }
+
+    let mut dynamic = DynamicTable::new();
+    let mut pending_headers: std::collections::HashMap<u32, (String, String)> = std::collections::HashMap::new();
+    let mut pending_body: std::collections::HashMap<u32, Vec<u8>> = std::collections::HashMap::new();
+
+    loop {
+        let frame = match read_frame(&mut reader) {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+
+        match frame.frame_type {
+            FRAME_SETTINGS if frame.flags & FLAG_ACK == 0 => {
+                if let Ok(mut writer) = writer.lock() {
+                    let _ = write_frame(&mut *writer, FRAME_SETTINGS, FLAG_ACK, 0, &[]);
+                }
+            }
+            FRAME_SETTINGS => {}
+            FRAME_PING if frame.flags & FLAG_ACK == 0 => {
+                if let Ok(mut writer) = writer.lock() {
+                    let _ = write_frame(&mut *writer, FRAME_PING, FLAG_ACK, 0, &frame.payload);
+                }
+            }
+            FRAME_PING => {}
+            FRAME_WINDOW_UPDATE | FRAME_GOAWAY => {}
+            FRAME_HEADERS => {
+                let Some(headers) = decode_headers(&frame.payload, &mut dynamic) else { return };
+                let method = headers.iter().find(|(name, _)| name == ":method").map(|(_, v)| v.clone());
+                let path = headers.iter().find(|(name, _)| name == ":path").map(|(_, v)| v.clone());
+                if let (Some(method), Some(path)) = (method, path) {
+                    pending_headers.insert(frame.stream_id, (method, path));
+                }
+                if frame.flags & FLAG_END_STREAM != 0 {
+                    dispatch(frame.stream_id, &pending_headers, &pending_body, &io, &writer, &errors);
+                }
+            }
+            FRAME_DATA => {
+                pending_body.entry(frame.stream_id).or_default().extend_from_slice(&frame.payload);
+                if frame.flags & FLAG_END_STREAM != 0 {
+                    dispatch(frame.stream_id, &pending_headers, &pending_body, &io, &writer, &errors);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn dispatch<IO: Read + Write + Send + 'static>(
+    stream_id: u32,
+    pending_headers: &std::collections::HashMap<u32, (String, String)>,
+    pending_body: &std::collections::HashMap<u32, Vec<u8>>,
+    io: &Arc<Mutex<IO>>,
+    writer: &Arc<Mutex<TcpStream>>,
+    errors: &Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let Some((_, path)) = pending_headers.get(&stream_id) else { return };
+    let body = pending_body.get(&stream_id).cloned().unwrap_or_default();
+    let Some(message) = unwrap_grpc_frame(&body).and_then(decode_proto_message) else {
+        if let Ok(mut writer) = writer.lock() {
+            let _ = write_response_headers(&mut *writer, stream_id);
+            let _ = write_trailers(&mut *writer, stream_id, "3"); // INVALID_ARGUMENT
+        }
+        return;
+    };
+
+    match path.as_str() {
+        "/urap.Registers/ReadRegisters" => handle_read_registers(stream_id, &message, io, writer, errors),
+        "/urap.Registers/WriteRegisters" => handle_write_registers(stream_id, &message, io, writer, errors),
+        "/urap.Registers/Subscribe" => handle_subscribe(stream_id, &message, io, writer, errors),
+        _ => {
+            if let Ok(mut writer) = writer.lock() {
+                let _ = write_response_headers(&mut *writer, stream_id);
+                let _ = write_trailers(&mut *writer, stream_id, "12"); // UNIMPLEMENTED
+            }
+        }
+    }
+}
+
+fn handle_read_registers<IO: Read + Write>(
+    stream_id: u32,
+    message: &ProtoMessage,
+    io: &Arc<Mutex<IO>>,
+    writer: &Arc<Mutex<TcpStream>>,
+    errors: &Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let register = message.varints.get(&1).copied().unwrap_or(0) as u16;
+    let count = message.varints.get(&2).copied().unwrap_or(1) as u16;
+    if count == 0 || count as usize > URAP_COUNT_MAX as usize {
+        respond_status(stream_id, "3", writer);
+        return;
+    }
+
+    let mut registers = vec![[0u8; 4]; count as usize];
+    let result = {
+        let mut io = match io.lock() {
+            Ok(io) => io,
+            Err(_) => return,
+        };
+        UrapPrimary::<_, 4>::new(&mut *io).read_4u8(register, &mut registers)
+    };
+    if let Err(err) = result {
+        push_error(errors, Error::Io(io::Error::other(format!("read failed: {err:?}"))));
+        respond_status(stream_id, "2", writer); // UNKNOWN
+        return;
+    }
+
+    let mut values = Vec::with_capacity(registers.len() * 4);
+    for register in &registers {
+        values.extend_from_slice(register);
+    }
+    let mut response = Vec::new();
+    write_bytes_field(&mut response, 1, &values);
+    respond_unary(stream_id, &response, writer);
+}
+
+fn handle_write_registers<IO: Read + Write>(
+    stream_id: u32,
+    message: &ProtoMessage,
+    io: &Arc<Mutex<IO>>,
+    writer: &Arc<Mutex<TcpStream>>,
+    errors: &Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let register = message.varints.get(&1).copied().unwrap_or(0) as u16;
+    let Some(values) = message.bytes.get(&2) else {
+        respond_status(stream_id, "3", writer);
+        return;
+    };
+    if values.is_empty() || !values.len().is_multiple_of(4) {
+        respond_status(stream_id, "3", writer);
+        return;
+    }
+
+    let registers: Vec<[u8; 4]> = values.chunks_exact(4).map(|chunk| [chunk[0], chunk[1], chunk[2], chunk[3]]).collect();
+    let result = {
+        let mut io = match io.lock() {
+            Ok(io) => io,
+            Err(_) => return,
+        };
+        UrapPrimary::<_, 4>::new(&mut *io).write_4u8(register, &registers)
+    };
+    if let Err(err) = result {
+        push_error(errors, Error::Io(io::Error::other(format!("write failed: {err:?}"))));
+        respond_status(stream_id, "2", writer);
+        return;
+    }
+
+    respond_unary(stream_id, &[], writer);
+}
+
+fn handle_subscribe<IO: Read + Write + Send + 'static>(
+    stream_id: u32,
+    message: &ProtoMessage,
+    io: &Arc<Mutex<IO>>,
+    writer: &Arc<Mutex<TcpStream>>,
+    errors: &Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let register = message.varints.get(&1).copied().unwrap_or(0) as u16;
+    let count = message.varints.get(&2).copied().unwrap_or(1) as u16;
+    if count == 0 || count as usize > URAP_COUNT_MAX as usize {
+        respond_status(stream_id, "3", writer);
+        return;
+    }
+
+    let subscribe_result = {
+        let mut io = match io.lock() {
+            Ok(io) => io,
+            Err(_) => return,
+        };
+        NotifyPrimary::new(&mut *io).subscribe(register, count as u8)
+    };
+    if let Err(err) = subscribe_result {
+        push_error(errors, Error::Io(io::Error::other(format!("subscribing failed: {err:?}"))));
+        respond_status(stream_id, "2", writer);
+        return;
+    }
+
+    if let Ok(mut writer) = writer.lock() {
+        if write_response_headers(&mut *writer, stream_id).is_err() {
+            return;
+        }
+    }
+
+    let io = Arc::clone(io);
+    let writer = Arc::clone(writer);
+    let errors = Arc::clone(errors);
+    thread::spawn(move || stream_updates(stream_id, io, writer, register..register + count, errors));
+}
+
+fn respond_unary(stream_id: u32, message: &[u8], writer: &Arc<Mutex<TcpStream>>) {
+    let Ok(mut writer) = writer.lock() else { return };
+    if write_response_headers(&mut *writer, stream_id).is_err() {
+        return;
+    }
+    let framed = grpc_frame(message);
+    if write_frame(&mut *writer, FRAME_DATA, 0, stream_id, &framed).is_err() {
+        return;
+    }
+    let _ = write_trailers(&mut *writer, stream_id, "0");
+}
+
+fn respond_status(stream_id: u32, status: &str, writer: &Arc<Mutex<TcpStream>>) {
+    let Ok(mut writer) = writer.lock() else { return };
+    if write_response_headers(&mut *writer, stream_id).is_err() {
+        return;
+    }
+    let _ = write_trailers(&mut *writer, stream_id, status);
+}
+
+/// Streams `RegisterUpdate` messages for `registers` on `stream_id`
+/// until a write fails, which - since this gateway doesn't yet track
+/// per-stream cancellation - is how a client closing the connection (or
+/// the whole connection being torn down) ends the subscription.
+fn stream_updates<IO: Read + Write>(
+    stream_id: u32,
+    io: Arc<Mutex<IO>>,
+    writer: Arc<Mutex<TcpStream>>,
+    registers: std::ops::Range<u16>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let mut changed = [0u16; URAP_COUNT_MAX as usize];
+    loop {
+        thread::sleep(Duration::from_millis(50));
+
+        let reported = {
+            let mut io = match io.lock() {
+                Ok(io) => io,
+                Err(_) => return,
+            };
+            NotifyPrimary::new(&mut *io).poll_notifications(&mut changed[..registers.len().max(1)])
+        };
+        let reported = match reported {
+            Ok(reported) => reported,
+            Err(err) => {
+                push_error(&errors, Error::Io(io::Error::other(format!("polling register changes failed: {err:?}"))));
+                continue;
+            }
+        };
+
+        for &register in &changed[..reported] {
+            let value = {
+                let mut io = match io.lock() {
+                    Ok(io) => io,
+                    Err(_) => return,
+                };
+                let mut value = [[0u8; 4]; 1];
+                UrapPrimary::<_, 4>::new(&mut *io).read_4u8(register, &mut value).map(|()| value[0])
+            };
+            let value = match value {
+                Ok(value) => value,
+                Err(err) => {
+                    push_error(
+                        &errors,
+                        Error::Io(io::Error::other(format!("reading changed register {register} failed: {err:?}"))),
+                    );
+                    continue;
+                }
+            };
+
+            let mut update = Vec::new();
+            write_varint_field(&mut update, 1, register as u64);
+            write_bytes_field(&mut update, 2, &value);
+            let framed = grpc_frame(&update);
+
+            let Ok(mut writer) = writer.lock() else { return };
+            if write_frame(&mut *writer, FRAME_DATA, 0, stream_id, &framed).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! A [`crate::WriteHook`] that ring-buffers the last `DEPTH` writes to a
+//! watched register range, for post-mortem diagnosis of control loop
+//! glitches. Read back by host application code via
+//! [`HistoryRecorder::entries`] — there's no wire opcode for this, it's
+//! local diagnostics, not a URAP feature.
+
+use core::ops::Range;
+
+use crate::WriteHook;
+
+/// One recorded write. `tick` is a recorder-local sequence number, not
+/// wall-clock time, incremented once per recorded write — enough to
+/// order and correlate entries across registers without pulling in a
+/// clock dependency on `no_std` targets.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryEntry<const WIDTH: usize> {
+    /// Order this entry was recorded in, relative to other entries from
+    /// the same [`HistoryRecorder`].
+    pub tick: u32,
+    /// Register that was written.
+    pub register: u16,
+    /// Value it was written to.
+    pub value: [u8; WIDTH],
+}
+
+/// Records the last `DEPTH` writes to registers inside `watch`, oldest
+/// entry overwritten first once the ring fills up.
+pub struct HistoryRecorder<const WIDTH: usize, const DEPTH: usize> {
+    watch: Range<u16>,
+    entries: [Option<HistoryEntry<WIDTH>>; DEPTH],
+    next: usize,
+    tick: u32,
+}
+
+impl<const WIDTH: usize, const DEPTH: usize> HistoryRecorder<WIDTH, DEPTH> {
+    /// Builds an empty recorder watching `watch`; writes to registers
+    /// outside it are ignored.
+    pub const fn new(watch: Range<u16>) -> Self {
+        Self { watch, entries: [None; DEPTH], next: 0, tick: 0 }
+    }
+
+    /// Recorded entries, oldest first, newest last. Shorter than `DEPTH`
+    /// until the ring has filled at least once.
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry<WIDTH>> {
+        (0..DEPTH)
+            .map(move |i| (self.next + i) % DEPTH)
+            .filter_map(move |i| self.entries[i].as_ref())
+    }
+}
+
+impl<const WIDTH: usize, const DEPTH: usize> WriteHook<WIDTH> for HistoryRecorder<WIDTH, DEPTH> {
+    fn on_write(&mut self, register: u16, values: &[[u8; WIDTH]]) {
+        for (i, value) in values.iter().enumerate() {
+            let register = register + i as u16;
+            if !self.watch.contains(&register) {
+                continue;
+            }
+
+            self.entries[self.next] = Some(HistoryEntry { tick: self.tick, register, value: *value });
+            self.next = (self.next + 1) % DEPTH;
+            self.tick = self.tick.wrapping_add(1);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Callbacks invoked by [`crate::UrapSecondary::poll`] when a write lands.
+
+/// Notified by [`crate::UrapSecondary::poll`] whenever a write is accepted
+/// and applied, before the ACK is sent back to the primary.
+///
+/// Implemented for any `FnMut(u16, &[[u8; WIDTH]])`, so most applications
+/// can hand a closure to [`crate::UrapSecondary::with_write_hook`] instead
+/// of writing a named type.
+pub trait WriteHook<const WIDTH: usize> {
+    /// `register` is the first written register; `values` holds the new
+    /// contents of `values.len()` consecutive registers starting there.
+    fn on_write(&mut self, register: u16, values: &[[u8; WIDTH]]);
+}
+
+/// No hook runs; writes are applied silently. The default for
+/// [`crate::UrapSecondary`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoWriteHook;
+
+impl<const WIDTH: usize> WriteHook<WIDTH> for NoWriteHook {
+    fn on_write(&mut self, _register: u16, _values: &[[u8; WIDTH]]) {}
+}
+
+impl<const WIDTH: usize, F: FnMut(u16, &[[u8; WIDTH]])> WriteHook<WIDTH> for F {
+    fn on_write(&mut self, register: u16, values: &[[u8; WIDTH]]) {
+        self(register, values)
+    }
+}
+
+/// Records which registers have been written since the last
+/// [`Self::take_dirty`], as a bitset — the same `BYTES`-bytes-for-`BYTES *
+/// 8`-registers tradeoff as [`crate::WriteProtectBits`].
+///
+/// Implements [`WriteHook`] for any `WIDTH`, so it plugs straight into
+/// [`crate::UrapSecondary::with_write_hook`]:
+///
+/// ```
+/// # use urap::{DirtyTracker, UrapSecondary};
+/// let secondary: UrapSecondary<4, 4, _, _, DirtyTracker<1>> =
+///     UrapSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+///         .with_write_hook(DirtyTracker::new());
+/// ```
+///
+/// This replaces diffing the whole register map every loop just to find
+/// which setpoints changed: poll as usual, then drain
+/// [`Self::take_dirty`] for the registers worth re-reading.
+#[derive(Debug, Clone, Copy)]
+pub struct DirtyTracker<const BYTES: usize> {
+    bits: [u8; BYTES],
+}
+
+impl<const BYTES: usize> DirtyTracker<BYTES> {
+    /// No register starts out dirty.
+    pub const fn new() -> Self {
+        Self { bits: [0u8; BYTES] }
+    }
+
+    /// Marks `register` dirty directly, without going through
+    /// [`WriteHook::on_write`]. Useful when application code changes a
+    /// register outside of URAP traffic (e.g. via
+    /// [`crate::UrapSecondary::regs_mut`]) and still wants it picked up
+    /// by [`Self::take_dirty`].
+    pub fn mark(&mut self, register: u16) {
+        let register = register as usize;
+        if register / 8 < BYTES {
+            self.bits[register / 8] |= 1 << (register % 8);
+        }
+    }
+
+    /// Yields every register marked dirty since the last call, clearing
+    /// each one as it's yielded.
+    pub fn take_dirty(&mut self) -> impl Iterator<Item = u16> + '_ {
+        self.take_dirty_in(0..(BYTES * 8) as u16)
+    }
+
+    /// Like [`Self::take_dirty`], but only drains registers inside
+    /// `range`; dirty registers outside it are left untouched for a
+    /// later drain. Useful for change-notification subscriptions that
+    /// poll a narrower range than the whole tracker.
+    pub fn take_dirty_in(&mut self, range: core::ops::Range<u16>) -> impl Iterator<Item = u16> + '_ {
+        range.filter(|&register| {
+            let byte = register as usize / 8;
+            if byte >= BYTES {
+                return false;
+            }
+            let bit = 1 << (register % 8);
+            if self.bits[byte] & bit == 0 {
+                return false;
+            }
+            self.bits[byte] &= !bit;
+            true
+        })
+    }
+}
+
+impl<const BYTES: usize> Default for DirtyTracker<BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BYTES: usize, const WIDTH: usize> WriteHook<WIDTH> for DirtyTracker<BYTES> {
+    fn on_write(&mut self, register: u16, values: &[[u8; WIDTH]]) {
+        for i in 0..values.len() as u16 {
+            self.mark(register + i);
+        }
+    }
+}
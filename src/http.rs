@@ -0,0 +1,364 @@
+//! An HTTP/REST gateway exposing a URAP secondary's registers as
+//! `GET`/`PUT /registers/{index}` endpoints with small JSON bodies, for
+//! web dashboards and `curl` to read and write registers without
+//! speaking URAP directly.
+//!
+//! A register's value is always a JSON array of its raw bytes
+//! (`[1,2,3,4]` for `WIDTH == 4`) rather than any particular typed
+//! interpretation - callers that want a `u32`/`f32`/etc. decode the same
+//! bytes [`crate::UrapPrimary`]'s typed accessors do. `?count=n` turns a
+//! single-register request into a range query over `n` consecutive
+//! registers:
+//!
+//! ```text
+//! GET /registers/0           -> {"register":0,"value":[1,2,3,4]}
+//! GET /registers/0?count=2   -> {"register":0,"values":[[1,2,3,4],[5,6,7,8]]}
+//! PUT /registers/0           body {"value":[1,2,3,4]}
+//! PUT /registers/0?count=2   body {"values":[[1,2,3,4],[5,6,7,8]]}
+//! ```
+//!
+//! Only this fixed, small JSON surface is supported - there's no general
+//! JSON dependency pulled in, the same tradeoff [`crate::modbus`] makes
+//! for Modbus PDU framing.
+
+use std::io::{self, Write as StdWrite};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, UrapPrimary};
+
+/// A minimal JSON value: just enough to represent a register (a number
+/// array) or a range of registers (an array of number arrays).
+enum Json {
+    Number(u32),
+    Array(Vec<Json>),
+}
+
+impl Json {
+    fn write(&self, out: &mut String) {
+        match self {
+            Json::Number(n) => out.push_str(&n.to_string()),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            }
+        }
+    }
+}
+
+/// Parses one JSON array (numbers or nested arrays, no other JSON value
+/// is accepted) starting at `input[*pos]`, advancing `pos` past it.
+fn parse_json_array(input: &[u8], pos: &mut usize) -> Option<Json> {
+    skip_whitespace(input, pos);
+    if input.get(*pos) != Some(&b'[') {
+        return None;
+    }
+    *pos += 1;
+
+    let mut items = Vec::new();
+    skip_whitespace(input, pos);
+    if input.get(*pos) == Some(&b']') {
+        *pos += 1;
+        return Some(Json::Array(items));
+    }
+
+    loop {
+        skip_whitespace(input, pos);
+        let item = if input.get(*pos) == Some(&b'[') {
+            parse_json_array(input, pos)?
+        } else {
+            Json::Number(parse_json_number(input, pos)?)
+        };
+        items.push(item);
+
+        skip_whitespace(input, pos);
+        match input.get(*pos) {
+            Some(b',') => *pos += 1,
+            Some(b']') => {
+                *pos += 1;
+                return Some(Json::Array(items));
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn skip_whitespace(input: &[u8], pos: &mut usize) {
+    while matches!(input.get(*pos), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+        *pos += 1;
+    }
+}
+
+fn parse_json_number(input: &[u8], pos: &mut usize) -> Option<u32> {
+    let start = *pos;
+    while matches!(input.get(*pos), Some(b'0'..=b'9')) {
+        *pos += 1;
+    }
+    if *pos == start {
+        return None;
+    }
+    std::str::from_utf8(&input[start..*pos]).ok()?.parse().ok()
+}
+
+/// Extracts the JSON array that follows `"key":` anywhere in `body`,
+/// tolerating the whitespace a real JSON encoder might add around the
+/// colon. There's no general object parser - this is the only shape a
+/// request body ever needs.
+fn find_json_array_field(body: &str, key: &str) -> Option<Json> {
+    let needle = format!("\"{key}\"");
+    let key_pos = body.find(&needle)?;
+    let after_key = &body.as_bytes()[key_pos + needle.len()..];
+    let mut pos = 0;
+    skip_whitespace(after_key, &mut pos);
+    if after_key.get(pos) != Some(&b':') {
+        return None;
+    }
+    pos += 1;
+    parse_json_array(after_key, &mut pos)
+}
+
+fn json_to_register(value: &Json) -> Option<[u8; 4]> {
+    let Json::Array(items) = value else { return None };
+    if items.len() != 4 {
+        return None;
+    }
+    let mut register = [0u8; 4];
+    for (byte, item) in register.iter_mut().zip(items) {
+        let Json::Number(n) = item else { return None };
+        *byte = u8::try_from(*n).ok()?;
+    }
+    Some(register)
+}
+
+fn json_to_registers(value: &Json) -> Option<Vec<[u8; 4]>> {
+    let Json::Array(items) = value else { return None };
+    items.iter().map(json_to_register).collect()
+}
+
+fn register_to_json(register: [u8; 4]) -> Json {
+    Json::Array(register.iter().map(|&b| Json::Number(b as u32)).collect())
+}
+
+fn registers_to_json(registers: &[[u8; 4]]) -> Json {
+    Json::Array(registers.iter().map(|&r| register_to_json(r)).collect())
+}
+
+/// A JSON `{"error":"..."}` body alongside its HTTP status line.
+fn error_response(status: &str, message: &str) -> String {
+    let body = format!("{{\"error\":\"{}\"}}", message.replace('"', "'"));
+    format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+fn ok_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Reads one HTTP/1.1 request off `stream`: the request line, headers up
+/// to `Content-Length`, and that many body bytes.
+fn read_request(stream: &mut TcpStream) -> io::Result<(String, String, String)> {
+    let mut reader = std::io::BufReader::new(stream);
+    let mut request_line = String::new();
+    std::io::BufRead::read_line(&mut reader, &mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0usize;
+    loop {
+        let mut line = String::new();
+        std::io::BufRead::read_line(&mut reader, &mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:").or_else(|| line.strip_prefix("content-length:")) {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+    std::io::Read::read_exact(&mut reader, &mut body)?;
+
+    Ok((method, target, String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Parses `/registers/{index}[?count=n]` into a register index and
+/// count, defaulting `count` to 1 when absent.
+fn parse_target(target: &str) -> Option<(u16, u16)> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let index = path.strip_prefix("/registers/")?.parse().ok()?;
+    let count = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("count="))
+        .map(|count| count.parse())
+        .transpose()
+        .ok()?
+        .unwrap_or(1u16);
+    Some((index, count))
+}
+
+/// Serves `GET`/`PUT /registers/{index}` against a single shared upstream
+/// URAP connection.
+pub struct HttpGateway<IO> {
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+    _io: std::marker::PhantomData<IO>,
+}
+
+impl<IO> HttpGateway<IO>
+where
+    IO: Read + Write + Send + 'static,
+{
+    /// Binds `addr` and starts serving HTTP requests against `primary`,
+    /// one worker thread per accepted connection.
+    pub fn spawn(addr: SocketAddr, primary: UrapPrimary<IO, 4>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let primary = Arc::new(Mutex::new(primary));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_primary = Arc::clone(&primary);
+        let accept_errors = Arc::clone(&errors);
+        let join_handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        push_error(&accept_errors, Error::Io(err));
+                        continue;
+                    }
+                };
+                let primary = Arc::clone(&accept_primary);
+                let errors = Arc::clone(&accept_errors);
+                thread::spawn(move || service_connection(stream, primary, errors));
+            }
+        });
+
+        Ok(Self {
+            join_handles: vec![join_handle],
+            errors,
+            _io: std::marker::PhantomData,
+        })
+    }
+
+    /// Pops the oldest recorded error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+}
+
+impl<IO> Drop for HttpGateway<IO> {
+    fn drop(&mut self) {
+        // The accept loop runs forever today; detach rather than block
+        // the dropping thread. A graceful shutdown API is tracked
+        // separately, mirroring `modbus::ModbusGateway`.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn service_connection<IO: Read + Write>(
+    mut stream: TcpStream,
+    primary: Arc<Mutex<UrapPrimary<IO, 4>>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let (method, target, body) = match read_request(&mut stream) {
+        Ok(request) => request,
+        Err(_) => return,
+    };
+    let response = match handle_request(&method, &target, &body, &primary, &errors) {
+        Ok(response) => response,
+        Err(response) => response,
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn handle_request<IO: Read + Write>(
+    method: &str,
+    target: &str,
+    body: &str,
+    primary: &Arc<Mutex<UrapPrimary<IO, 4>>>,
+    errors: &Arc<Mutex<Vec<Error<io::Error>>>>,
+) -> Result<String, String> {
+    let (register, count) = parse_target(target).ok_or_else(|| error_response("404 Not Found", "not found"))?;
+    if count == 0 || count as usize > crate::URAP_COUNT_MAX as usize {
+        return Err(error_response("400 Bad Request", "count out of range"));
+    }
+
+    let mut io = primary
+        .lock()
+        .map_err(|_| error_response("500 Internal Server Error", "register map lock was poisoned"))?;
+
+    match method {
+        "GET" => {
+            let mut registers = vec![[0u8; 4]; count as usize];
+            io.read_4u8(register, &mut registers).map_err(|err| {
+                push_error(errors, Error::Io(io::Error::other(format!("read failed: {err:?}"))));
+                error_response("502 Bad Gateway", "upstream read failed")
+            })?;
+
+            let mut body = String::new();
+            if count == 1 {
+                body.push_str(&format!("{{\"register\":{register},\"value\":"));
+                register_to_json(registers[0]).write(&mut body);
+            } else {
+                body.push_str(&format!("{{\"register\":{register},\"count\":{count},\"values\":"));
+                registers_to_json(&registers).write(&mut body);
+            }
+            body.push('}');
+            Ok(ok_response(&body))
+        }
+        "PUT" => {
+            let registers = if count == 1 {
+                let value = find_json_array_field(body, "value")
+                    .ok_or_else(|| error_response("400 Bad Request", "missing \"value\""))?;
+                vec![json_to_register(&value).ok_or_else(|| error_response("400 Bad Request", "malformed \"value\""))?]
+            } else {
+                let value = find_json_array_field(body, "values")
+                    .ok_or_else(|| error_response("400 Bad Request", "missing \"values\""))?;
+                let registers = json_to_registers(&value)
+                    .ok_or_else(|| error_response("400 Bad Request", "malformed \"values\""))?;
+                if registers.len() != count as usize {
+                    return Err(error_response("400 Bad Request", "\"values\" length does not match count"));
+                }
+                registers
+            };
+
+            io.write_4u8(register, &registers).map_err(|err| {
+                push_error(errors, Error::Io(io::Error::other(format!("write failed: {err:?}"))));
+                error_response("502 Bad Gateway", "upstream write failed")
+            })?;
+            Ok(ok_response("{}"))
+        }
+        _ => Err(error_response("405 Method Not Allowed", "unsupported method")),
+    }
+}
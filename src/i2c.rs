@@ -0,0 +1,128 @@
+//! URAP over `embedded_hal::i2c`, for sensors and small peripherals that
+//! only expose an I2C bus.
+//!
+//! I2C has no notion of a continuous byte stream: a request is one
+//! master-write transaction and a response is a separate master-read (or,
+//! as [`I2cPrimary`] does it, a single write-then-read transaction so the
+//! secondary doesn't see a stop condition in between). [`I2cPrimary`]
+//! bridges that onto [`embedded_io::Read`] + [`Write`] the same way
+//! [`crate::EncryptedIo`] bridges frames onto a byte stream: writes
+//! accumulate in a buffer until [`Write::flush`], which is what actually
+//! drives the bus; the subsequent response is read in one shot into a
+//! fixed-size buffer and served out of it a few bytes at a time to
+//! [`crate::UrapPrimary`]'s header/data/CRC reads. Secondary devices that
+//! only follow simple "shift out the response register" I2C semantics will
+//! pad the read past the real message; that's fine, [`crate::UrapPrimary`]
+//! never reads more than the response it's expecting.
+
+use core::fmt;
+
+use embedded_hal::i2c::{AddressMode, I2c, SevenBitAddress};
+use embedded_io::{ErrorType, Read, Write};
+
+/// Everything that can go wrong exchanging a URAP packet over I2C.
+#[derive(Debug)]
+pub enum I2cError<E> {
+    /// The underlying [`I2c`] implementation failed the transaction.
+    I2c(E),
+}
+
+impl<E: fmt::Debug> fmt::Display for I2cError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::I2c(err) => write!(f, "I2C bus error: {err:?}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for I2cError<E> {}
+
+impl<E: embedded_hal::i2c::Error> embedded_io::Error for I2cError<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::I2c(_) => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Bridges a byte-stream URAP exchange onto an [`I2c`] master, addressing
+/// the secondary at `address` and treating each request/response pair as
+/// one write-then-read transaction.
+///
+/// `CAP` bounds the largest request and the largest response (comfortably
+/// covers a [`crate::URAP_COUNT_MAX`]-register packet at the default width
+/// by default).
+pub struct I2cPrimary<I, A = SevenBitAddress, const CAP: usize = 512> {
+    i2c: I,
+    address: A,
+    write_buf: [u8; CAP],
+    write_len: usize,
+    read_buf: [u8; CAP],
+    read_pos: usize,
+    read_len: usize,
+}
+
+impl<I, A: AddressMode, const CAP: usize> I2cPrimary<I, A, CAP> {
+    /// Wraps `i2c`, addressing the secondary at `address`.
+    pub fn new(i2c: I, address: A) -> Self {
+        Self {
+            i2c,
+            address,
+            write_buf: [0u8; CAP],
+            write_len: 0,
+            read_buf: [0u8; CAP],
+            read_pos: 0,
+            read_len: 0,
+        }
+    }
+}
+
+impl<I, A: AddressMode, const CAP: usize> ErrorType for I2cPrimary<I, A, CAP>
+where
+    I: I2c<A>,
+{
+    type Error = I2cError<I::Error>;
+}
+
+impl<I, A: AddressMode + Copy, const CAP: usize> Read for I2cPrimary<I, A, CAP>
+where
+    I: I2c<A>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.read_pos >= self.read_len {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(self.read_len - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Ok(n)
+    }
+}
+
+impl<I, A: AddressMode + Copy, const CAP: usize> Write for I2cPrimary<I, A, CAP>
+where
+    I: I2c<A>,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(CAP - self.write_len);
+        self.write_buf[self.write_len..self.write_len + n].copy_from_slice(&buf[..n]);
+        self.write_len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.write_len == 0 {
+            return Ok(());
+        }
+
+        self.i2c
+            .write_read(self.address, &self.write_buf[..self.write_len], &mut self.read_buf)
+            .map_err(I2cError::I2c)?;
+
+        self.write_len = 0;
+        self.read_pos = 0;
+        self.read_len = CAP;
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+//! `urap` - Universal Register Access Protocol.
+//!
+//! URAP is a tiny, framed request/response protocol for exchanging
+//! fixed-width "registers" over any byte-oriented transport (UART, TCP,
+//! Unix sockets, ...). A primary issues reads and writes against a
+//! secondary's register map; every packet is guarded by a CRC-16.
+//!
+//! ```text
+//! request:  OP (1) | REGISTER (2, LE) | COUNT (1) | [DATA] | CRC (2, LE)
+//! response: OP_ACK | [DATA]           | CRC (2, LE)
+//!        or OP_NAK | NAK_CODE (1)     | CRC (2, LE)
+//! ```
+//!
+//! The register width is a const generic (`WIDTH`, default 4 bytes) so
+//! both 16-bit-native MCUs and wide telemetry devices can use their
+//! native word size without padding.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "auth")]
+mod auth;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod bitfield;
+#[cfg(feature = "std")]
+mod buffered;
+#[cfg(feature = "std")]
+mod cached;
+#[cfg(feature = "can")]
+pub mod can;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+mod crc;
+#[cfg(all(feature = "dbus", unix))]
+pub mod dbus;
+#[cfg(feature = "std")]
+pub mod dyn_primary;
+#[cfg(feature = "std")]
+pub mod dyn_secondary;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(feature = "encrypted")]
+mod encrypted;
+#[cfg(all(feature = "epoll", unix))]
+pub mod epoll;
+mod error;
+#[cfg(feature = "serde")]
+pub mod fixture;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod history;
+mod hook;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "i2c")]
+pub mod i2c;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "modbus")]
+pub mod modbus;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+mod nak;
+#[cfg(feature = "names")]
+mod names;
+#[cfg(feature = "nb")]
+pub mod nb;
+#[cfg(feature = "usockets")]
+pub mod nonblocking;
+#[cfg(feature = "notify")]
+mod notify;
+mod pipeline;
+mod primary;
+mod protect;
+mod regmap;
+mod register_id;
+#[cfg(feature = "ringbuf")]
+pub mod ringbuf;
+mod scaled;
+mod secondary;
+#[cfg(feature = "seq")]
+mod seq;
+mod shadow;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+#[cfg(feature = "spi")]
+pub mod spi;
+mod split;
+mod stats;
+#[cfg(feature = "storage")]
+pub mod storage;
+mod store;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(all(feature = "usockets", unix))]
+pub mod usockets;
+#[cfg(feature = "longpoll")]
+mod wait;
+mod watchdog;
+
+#[cfg(feature = "auth")]
+pub use auth::{AuthPrimary, AuthSecondary, AUTH_TAG_SIZE};
+#[cfg(feature = "std")]
+pub use buffered::BufferedPrimary;
+#[cfg(feature = "std")]
+pub use cached::CachedPrimary;
+pub use crc::{crc16, crc16_update};
+/// Re-exported so [`urap_registers!`] can refer to `Read`/`Write` without
+/// requiring callers to add `embedded-io` as a direct dependency.
+#[doc(hidden)]
+pub use embedded_io;
+#[cfg(feature = "encrypted")]
+pub use encrypted::{EncryptedIo, EncryptedIoError};
+pub use error::Error;
+pub use history::{HistoryEntry, HistoryRecorder};
+pub use hook::{DirtyTracker, NoWriteHook, WriteHook};
+pub use nak::NakCode;
+#[cfg(feature = "names")]
+pub use names::{NamesOutcome, NamesPrimary, NamesSecondary};
+#[cfg(feature = "notify")]
+pub use notify::{NotifyOutcome, NotifyPrimary, NotifySecondary};
+pub use pipeline::PipelinedPrimary;
+pub use primary::{
+    encode_read_request, encode_write_request, Delay, EncodeError, RetryPolicy, SelfTestReport,
+    UrapPrimary,
+};
+#[cfg(feature = "std")]
+pub use primary::{HealthReport, SharedPrimary, StdDelay};
+pub use protect::{NoWriteProtect, ReadProtect, WriteProtect, WriteProtectBits, WriteProtectRanges};
+pub use register_id::{RegisterId, RegisterValue};
+pub use scaled::ScaledRegister;
+pub use secondary::{PollOutcome, UrapSecondary};
+#[cfg(feature = "seq")]
+pub use seq::{SeqOutcome, SeqPrimary, SeqSecondary};
+pub use shadow::{ShadowedRegisters, Update};
+pub use split::{Split, SplitError};
+pub use stats::{Stats, STATS_REGISTER_COUNT};
+pub use store::RegisterStore;
+#[cfg(feature = "derive")]
+pub use urap_derive::UrapRegisters;
+#[cfg(feature = "longpoll")]
+pub use wait::{WaitOutcome, WaitPrimary, WaitSecondary};
+#[cfg(feature = "std")]
+pub use watchdog::StdClock;
+pub use watchdog::{Clock, Watchdog};
+
+/// Register width used by the historical, pre-const-generic API.
+pub const URAP_DATA_WIDTH: usize = 4;
+
+/// Largest `count` a single request may carry.
+pub const URAP_COUNT_MAX: u16 = 128;
+
+/// Request opcode: read `count` registers starting at `register`.
+pub const OP_READ: u8 = 0x01;
+/// Request opcode: write `count` registers starting at `register`.
+pub const OP_WRITE: u8 = 0x02;
+/// Response opcode: the request succeeded.
+pub const OP_ACK: u8 = 0x03;
+/// Request opcode: no-op liveness probe, always ACKed (unless the CRC is
+/// bad) without touching the register map, so a link check can't be
+/// confused with "register 0 unreadable" and doesn't contend for
+/// whatever lock guards the registers.
+pub const OP_PING: u8 = 0x04;
+/// Response opcode: the request was rejected, see [`NakCode`].
+pub const OP_NAK: u8 = 0xFF;
+/// Request opcode: ask the secondary for a fresh authentication challenge.
+#[cfg(feature = "auth")]
+pub const OP_AUTH_CHALLENGE: u8 = 0x10;
+/// Request opcode: prove knowledge of the pre-shared key by responding
+/// to a challenge.
+#[cfg(feature = "auth")]
+pub const OP_AUTH_RESPONSE: u8 = 0x11;
+/// Request opcode: subscribe to change notifications for `count`
+/// registers starting at `register`, replacing any prior subscription.
+#[cfg(feature = "notify")]
+pub const OP_SUBSCRIBE: u8 = 0x20;
+/// Request opcode: drain pending change notifications for the active
+/// subscription.
+#[cfg(feature = "notify")]
+pub const OP_NOTIFY_POLL: u8 = 0x21;
+/// Request opcode: hold the response until `count` registers starting at
+/// `register` change or a timeout elapses.
+#[cfg(feature = "longpoll")]
+pub const OP_WAIT: u8 = 0x22;
+/// Request opcode: read `count` registers starting at `register`,
+/// carrying a sequence byte the secondary echoes back in the response.
+#[cfg(feature = "seq")]
+pub const OP_READ_SEQ: u8 = 0x23;
+/// Request opcode: write `count` registers starting at `register`,
+/// carrying a sequence byte the secondary echoes back in the response.
+#[cfg(feature = "seq")]
+pub const OP_WRITE_SEQ: u8 = 0x24;
+/// Request opcode: resolve a symbolic register name (carried as the
+/// request's data) to its index.
+#[cfg(feature = "names")]
+pub const OP_NAME_LOOKUP: u8 = 0x25;
+
+/// Size in bytes of a request header (`OP | REGISTER | COUNT`).
+pub const URAP_HEADER_SIZE: usize = 4;
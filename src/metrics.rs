@@ -0,0 +1,163 @@
+//! Prometheus text-format metrics for a [`crate::usockets::UrapSecondary`]:
+//! packet and NAK counts, bytes transferred, active connections and
+//! per-request latency, served over a plain HTTP listener for a scraper
+//! to poll.
+//!
+//! [`Stats`] is the counter set; [`serve`] exposes it. Neither depends on
+//! `usockets` itself — [`crate::usockets::UrapSecondary::spawn_with_metrics`]
+//! is what wires the counters up to real traffic.
+
+use std::io::{self, Write as _};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::NakCode;
+
+const NAK_CODE_COUNT: usize = 8;
+const NAK_LABELS: [&str; NAK_CODE_COUNT] = [
+    "bad_crc",
+    "bad_op",
+    "index_out_of_bounds",
+    "count_too_large",
+    "index_write_protected",
+    "index_read_protected",
+    "auth_failed",
+    "not_subscribed",
+];
+
+/// Counters for one [`crate::usockets::UrapSecondary`], updated from
+/// every connection worker and read back by [`Self::render`].
+///
+/// All fields are atomics so connections can update them without taking
+/// the register map's lock.
+#[derive(Default)]
+pub struct Stats {
+    packets: AtomicU64,
+    naks: [AtomicU64; NAK_CODE_COUNT],
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    active_connections: AtomicUsize,
+    request_seconds_sum_nanos: AtomicU64,
+    requests: AtomicU64,
+}
+
+impl Stats {
+    /// An empty counter set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record_packet(&self) {
+        self.packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_nak(&self, code: NakCode) {
+        if let Some(counter) = self.naks.get(code as u8 as usize) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_bytes_in(&self, n: u64) {
+        self.bytes_in.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_bytes_out(&self, n: u64) {
+        self.bytes_out.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connection_opened(&self) {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connection_closed(&self) {
+        self.active_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_request(&self, elapsed: Duration) {
+        self.request_seconds_sum_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders every counter as Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP urap_packets_total Requests serviced.\n");
+        out.push_str("# TYPE urap_packets_total counter\n");
+        out.push_str(&format!(
+            "urap_packets_total {}\n",
+            self.packets.load(Ordering::Relaxed)
+        ));
+
+        out.push_str("# HELP urap_naks_total Requests rejected, by reason.\n");
+        out.push_str("# TYPE urap_naks_total counter\n");
+        for (label, counter) in NAK_LABELS.iter().zip(self.naks.iter()) {
+            out.push_str(&format!(
+                "urap_naks_total{{code=\"{label}\"}} {}\n",
+                counter.load(Ordering::Relaxed)
+            ));
+        }
+
+        out.push_str("# HELP urap_bytes_in_total Bytes read from connected peers.\n");
+        out.push_str("# TYPE urap_bytes_in_total counter\n");
+        out.push_str(&format!(
+            "urap_bytes_in_total {}\n",
+            self.bytes_in.load(Ordering::Relaxed)
+        ));
+
+        out.push_str("# HELP urap_bytes_out_total Bytes written to connected peers.\n");
+        out.push_str("# TYPE urap_bytes_out_total counter\n");
+        out.push_str(&format!(
+            "urap_bytes_out_total {}\n",
+            self.bytes_out.load(Ordering::Relaxed)
+        ));
+
+        out.push_str("# HELP urap_active_connections Connections currently open.\n");
+        out.push_str("# TYPE urap_active_connections gauge\n");
+        out.push_str(&format!(
+            "urap_active_connections {}\n",
+            self.active_connections.load(Ordering::Relaxed)
+        ));
+
+        out.push_str("# HELP urap_request_duration_seconds Time to service one request.\n");
+        out.push_str("# TYPE urap_request_duration_seconds summary\n");
+        out.push_str(&format!(
+            "urap_request_duration_seconds_sum {:.6}\n",
+            self.request_seconds_sum_nanos.load(Ordering::Relaxed) as f64 / 1e9
+        ));
+        out.push_str(&format!(
+            "urap_request_duration_seconds_count {}\n",
+            self.requests.load(Ordering::Relaxed)
+        ));
+
+        out
+    }
+}
+
+/// Serves `stats` as Prometheus text on `addr` until the process exits;
+/// every connection gets the same response regardless of the request it
+/// sent, which is enough for a scraper that just does a bare `GET`.
+pub fn serve(stats: Arc<Stats>, addr: SocketAddr) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+
+            let body = stats.render();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    }))
+}
@@ -0,0 +1,71 @@
+//! A [`crate::RegisterStore`] backed by a memory-mapped file, so a URAP
+//! secondary and a separate control process on the same host can share
+//! registers with zero copies, and the values persist across restarts
+//! with no explicit save/load step.
+//!
+//! Unlike [`crate::snapshot`], there's no framing or CRC: every write
+//! lands directly in the mapped bytes, and the kernel writes them back to
+//! disk on its own schedule (or call [`MmapRegisters::flush`] to force
+//! it). Unlike [`crate::storage`], this targets a regular OS file on
+//! `std` targets rather than a bare NOR-flash/EEPROM device.
+
+use std::fs::OpenOptions;
+use std::io;
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::RegisterStore;
+
+/// `len` consecutive `WIDTH`-byte registers mapped directly onto a file.
+pub struct MmapRegisters<const WIDTH: usize> {
+    mmap: MmapMut,
+    len: usize,
+}
+
+impl<const WIDTH: usize> MmapRegisters<WIDTH> {
+    /// Opens (creating if necessary) `path`, sizes it to hold `len`
+    /// registers, and maps it into memory.
+    ///
+    /// Existing contents are kept if the file already has the right
+    /// size; a shorter or missing file is zero-extended.
+    pub fn open(path: impl AsRef<Path>, len: usize) -> io::Result<Self> {
+        let file =
+            OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        file.set_len((len * WIDTH) as u64)?;
+
+        // SAFETY: `file` was just opened by us and sized to exactly
+        // `len * WIDTH` bytes above, and all access to the mapping goes
+        // through `MmapRegisters`'s `&mut self` methods, so nothing else
+        // in this process can race with it. A cooperating external
+        // process resizing or truncating the file while it's mapped is
+        // the documented caller hazard of `mmap` itself.
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+
+        Ok(Self { mmap, len })
+    }
+
+    /// Flushes pending writes to disk, blocking until the kernel confirms
+    /// they've landed.
+    pub fn flush(&self) -> io::Result<()> {
+        self.mmap.flush()
+    }
+}
+
+impl<const WIDTH: usize> RegisterStore<WIDTH> for MmapRegisters<WIDTH> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn read(&mut self, register: u16) -> [u8; WIDTH] {
+        let offset = register as usize * WIDTH;
+        let mut word = [0u8; WIDTH];
+        word.copy_from_slice(&self.mmap[offset..offset + WIDTH]);
+        word
+    }
+
+    fn write(&mut self, register: u16, value: [u8; WIDTH]) {
+        let offset = register as usize * WIDTH;
+        self.mmap[offset..offset + WIDTH].copy_from_slice(&value);
+    }
+}
@@ -0,0 +1,362 @@
+//! A Modbus gateway that re-serves a remote URAP secondary's registers
+//! as Modbus holding registers, for integrating URAP devices with
+//! existing SCADA/PLC tooling that only speaks Modbus.
+//!
+//! Each URAP register (4 raw bytes) maps onto two consecutive 16-bit
+//! Modbus holding registers, high word first, matching the common
+//! "Modicon" convention for 32-bit values. Modbus address `2 * n` and
+//! `2 * n + 1` are the high and low halves of URAP register `n`.
+//!
+//! Function codes 3 (read holding registers) and 16 (write multiple
+//! registers) operate on whole URAP registers and must address an even
+//! offset with an even quantity. Function code 6 (write single register)
+//! writes one half of a URAP register via read-modify-write, the same
+//! pattern [`crate::UrapPrimary::write_f16`] uses to update half of a
+//! shared register without disturbing the other half.
+//!
+//! [`ModbusGateway::spawn`] serves Modbus/TCP; [`ModbusGateway::serve_rtu`]
+//! runs a single Modbus RTU session over an already-open serial-like
+//! stream, for callers that own their own accept/connection loop.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, UrapPrimary};
+
+/// Reads `buf.len()` bytes from `io`, treating a zero-length read as an
+/// unexpected end of stream rather than blocking forever.
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+/// A Modbus exception code, returned in place of a normal response when
+/// a request can't be serviced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ModbusException {
+    /// The function code isn't one of the three this gateway implements.
+    IllegalFunction = 0x01,
+    /// The address (or address range) doesn't land on a whole URAP
+    /// register.
+    IllegalDataAddress = 0x02,
+    /// The quantity requested is odd, zero, or otherwise not a whole
+    /// number of URAP registers.
+    IllegalDataValue = 0x03,
+    /// The upstream URAP secondary rejected or failed the request.
+    SlaveDeviceFailure = 0x04,
+}
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const WRITE_SINGLE_REGISTER: u8 = 0x06;
+const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+/// Translates one Modbus PDU (`function_code` plus its data bytes)
+/// against `primary`, returning the response PDU's data bytes (the
+/// function code is echoed unchanged by the caller) or the
+/// [`ModbusException`] to report instead.
+fn handle_pdu<IO: Read + Write>(
+    primary: &mut UrapPrimary<IO, 4>,
+    function_code: u8,
+    data: &[u8],
+) -> Result<Vec<u8>, ModbusException> {
+    match function_code {
+        READ_HOLDING_REGISTERS => {
+            if data.len() != 4 {
+                return Err(ModbusException::IllegalDataValue);
+            }
+            let address = u16::from_be_bytes([data[0], data[1]]);
+            let quantity = u16::from_be_bytes([data[2], data[3]]);
+            if !address.is_multiple_of(2) || !quantity.is_multiple_of(2) || quantity == 0 {
+                return Err(ModbusException::IllegalDataValue);
+            }
+
+            let first = address / 2;
+            let count = quantity / 2;
+            let mut registers = vec![[0u8; 4]; count as usize];
+            primary
+                .read_4u8(first, &mut registers)
+                .map_err(|_| ModbusException::SlaveDeviceFailure)?;
+
+            let mut response = vec![2 * quantity as u8];
+            for register in registers {
+                response.extend_from_slice(&register);
+            }
+            Ok(response)
+        }
+        WRITE_SINGLE_REGISTER => {
+            if data.len() != 4 {
+                return Err(ModbusException::IllegalDataValue);
+            }
+            let address = u16::from_be_bytes([data[0], data[1]]);
+            let word = [data[2], data[3]];
+
+            let register = address / 2;
+            let mut current = [[0u8; 4]; 1];
+            primary
+                .read_4u8(register, &mut current)
+                .map_err(|_| ModbusException::SlaveDeviceFailure)?;
+            if address.is_multiple_of(2) {
+                current[0][0] = word[0];
+                current[0][1] = word[1];
+            } else {
+                current[0][2] = word[0];
+                current[0][3] = word[1];
+            }
+            primary
+                .write_4u8(register, &current)
+                .map_err(|_| ModbusException::SlaveDeviceFailure)?;
+
+            Ok(data.to_vec())
+        }
+        WRITE_MULTIPLE_REGISTERS => {
+            if data.len() < 5 {
+                return Err(ModbusException::IllegalDataValue);
+            }
+            let address = u16::from_be_bytes([data[0], data[1]]);
+            let quantity = u16::from_be_bytes([data[2], data[3]]);
+            let byte_count = data[4];
+            if !address.is_multiple_of(2)
+                || !quantity.is_multiple_of(2)
+                || quantity == 0
+                || byte_count as usize != data.len() - 5
+                || byte_count as u16 != 2 * quantity
+            {
+                return Err(ModbusException::IllegalDataValue);
+            }
+
+            let first = address / 2;
+            let words = &data[5..];
+            let registers: Vec<[u8; 4]> = words
+                .chunks_exact(4)
+                .map(|chunk| [chunk[0], chunk[1], chunk[2], chunk[3]])
+                .collect();
+            primary
+                .write_4u8(first, &registers)
+                .map_err(|_| ModbusException::SlaveDeviceFailure)?;
+
+            Ok(data[..4].to_vec())
+        }
+        _ => Err(ModbusException::IllegalFunction),
+    }
+}
+
+/// Serves Modbus requests over TCP (or RTU, via [`Self::serve_rtu`])
+/// against a single shared upstream URAP connection.
+pub struct ModbusGateway<IO> {
+    primary: Arc<Mutex<UrapPrimary<IO, 4>>>,
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+}
+
+impl<IO> ModbusGateway<IO>
+where
+    IO: Read + Write + Send + 'static,
+{
+    /// Binds `addr` and starts serving Modbus/TCP requests against
+    /// `primary`, one worker thread per accepted connection.
+    pub fn spawn(addr: SocketAddr, primary: UrapPrimary<IO, 4>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let primary = Arc::new(Mutex::new(primary));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_primary = Arc::clone(&primary);
+        let accept_errors = Arc::clone(&errors);
+        let join_handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        push_error(&accept_errors, Error::Io(err));
+                        continue;
+                    }
+                };
+                let primary = Arc::clone(&accept_primary);
+                let errors = Arc::clone(&accept_errors);
+                thread::spawn(move || service_tcp_connection(stream, primary, errors));
+            }
+        });
+
+        Ok(Self {
+            primary,
+            join_handles: vec![join_handle],
+            errors,
+        })
+    }
+
+    /// Pops the oldest recorded error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+
+    /// Runs a single Modbus RTU session over `io` until the peer closes
+    /// the stream or a framing error occurs, for callers that already
+    /// own a serial connection and its accept/retry loop.
+    pub fn serve_rtu<S: Read + Write>(&self, mut io: S) -> Result<(), Error<S::Error>> {
+        loop {
+            let mut address = [0u8; 1];
+            if read_exact(&mut io, &mut address).is_err() {
+                return Ok(());
+            }
+
+            let mut function_code = [0u8; 1];
+            read_exact(&mut io, &mut function_code)?;
+
+            let mut data = vec![0u8; rtu_request_len(function_code[0])];
+            read_exact(&mut io, &mut data)?;
+
+            let mut expected_crc = [0u8; 2];
+            read_exact(&mut io, &mut expected_crc)?;
+
+            let mut frame = vec![address[0], function_code[0]];
+            frame.extend_from_slice(&data);
+            if modbus_crc16(&frame).to_le_bytes() != expected_crc {
+                // A corrupted frame on a shared RS-485 bus is common and
+                // not addressed to us for certain; silently drop it and
+                // wait for the next one rather than tearing down the link.
+                continue;
+            }
+
+            let outcome = match self.primary.lock() {
+                Ok(mut primary) => handle_pdu(&mut primary, function_code[0], &data),
+                // The accept-loop thread panicked mid-transaction; there's
+                // no register state left to serve. End this session the
+                // same way a closed stream does.
+                Err(_) => return Ok(()),
+            };
+
+            let mut response = vec![address[0]];
+            match outcome {
+                Ok(payload) => {
+                    response.push(function_code[0]);
+                    response.extend_from_slice(&payload);
+                }
+                Err(exception) => {
+                    response.push(function_code[0] | 0x80);
+                    response.push(exception as u8);
+                }
+            }
+            response.extend_from_slice(&modbus_crc16(&response).to_le_bytes());
+            io.write_all(&response).map_err(Error::Io)?;
+            io.flush().map_err(Error::Io)?;
+        }
+    }
+}
+
+/// Number of data bytes a well-formed request for `function_code` always
+/// carries before its CRC, used only to size the RTU read buffer; the
+/// actual field validation happens in [`handle_pdu`].
+fn rtu_request_len(function_code: u8) -> usize {
+    match function_code {
+        READ_HOLDING_REGISTERS | WRITE_SINGLE_REGISTER => 4,
+        WRITE_MULTIPLE_REGISTERS => 5,
+        _ => 0,
+    }
+}
+
+impl<IO> Drop for ModbusGateway<IO> {
+    fn drop(&mut self) {
+        // The accept loop runs forever today; detach rather than block
+        // the dropping thread. A graceful shutdown API is tracked
+        // separately, mirroring `tcp::UrapSecondary`.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn service_tcp_connection<IO>(
+    mut stream: TcpStream,
+    primary: Arc<Mutex<UrapPrimary<IO, 4>>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) where
+    IO: Read + Write,
+{
+    let _ = stream.set_nodelay(true);
+
+    loop {
+        let mut mbap = [0u8; 7];
+        if std::io::Read::read_exact(&mut stream, &mut mbap).is_err() {
+            return;
+        }
+        let transaction_id = [mbap[0], mbap[1]];
+        let length = u16::from_be_bytes([mbap[4], mbap[5]]);
+        let unit_id = mbap[6];
+
+        if length == 0 || length > 253 {
+            return;
+        }
+        let mut pdu = vec![0u8; length as usize - 1];
+        if std::io::Read::read_exact(&mut stream, &mut pdu).is_err() {
+            return;
+        }
+        let function_code = pdu[0];
+        let data = &pdu[1..];
+
+        let outcome = match primary.lock() {
+            Ok(mut primary) => handle_pdu(&mut primary, function_code, data),
+            Err(_) => return,
+        };
+
+        let mut response_pdu = Vec::new();
+        match outcome {
+            Ok(payload) => {
+                response_pdu.push(function_code);
+                response_pdu.extend_from_slice(&payload);
+            }
+            Err(exception) => {
+                if exception == ModbusException::SlaveDeviceFailure {
+                    push_error(&errors, Error::Io(io::Error::other("upstream URAP request failed")));
+                }
+                response_pdu.push(function_code | 0x80);
+                response_pdu.push(exception as u8);
+            }
+        }
+
+        let mut response = Vec::with_capacity(7 + response_pdu.len());
+        response.extend_from_slice(&transaction_id);
+        response.extend_from_slice(&[0, 0]); // protocol id, always 0
+        response.extend_from_slice(&((response_pdu.len() + 1) as u16).to_be_bytes());
+        response.push(unit_id);
+        response.extend_from_slice(&response_pdu);
+
+        if std::io::Write::write_all(&mut stream, &response).is_err() {
+            return;
+        }
+    }
+}
+
+/// Computes the CRC-16/MODBUS checksum (poly `0x8005`, init `0xFFFF`,
+/// both input and output reflected) appended to every Modbus RTU frame.
+/// Distinct from [`crate::crc16`], which guards native URAP packets.
+fn modbus_crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            if crc & 0x0001 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
@@ -0,0 +1,335 @@
+//! An MQTT bridge that publishes URAP register changes as retained-less
+//! MQTT messages and writes incoming MQTT commands back to registers,
+//! for IoT deployments that feed a broker-based backend instead of
+//! polling URAP directly.
+//!
+//! Change detection reuses the [`crate::notify`] extension rather than
+//! diffing the whole register map: [`MqttBridge::spawn`] subscribes once
+//! to the configured register range, then a worker thread periodically
+//! drains [`NotifyPrimary::poll_notifications`] and republishes whatever
+//! came back. A register `n` is published to `{prefix}/n` as its raw
+//! 4-byte value; a second worker thread subscribes to `{prefix}/+/set`
+//! and writes the payload of any message there to the register named by
+//! the topic.
+//!
+//! Only the handful of MQTT v3.1.1 packet types this bridge needs - plain
+//! QoS 0 `CONNECT`/`CONNACK`, `SUBSCRIBE`/`SUBACK` and `PUBLISH` - are
+//! implemented here; there's no dependency on a full MQTT client crate,
+//! the same tradeoff [`crate::modbus`] makes for its PDU framing.
+
+use std::io::{self, Read as StdRead, Write as StdWrite};
+use std::net::{SocketAddr, TcpStream};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, NotifyPrimary, UrapPrimary, URAP_COUNT_MAX};
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+
+/// Reads one MQTT packet's fixed header and variable-length payload off
+/// `stream`, returning the packet type byte (the full first byte,
+/// flags included) and the payload bytes that follow the remaining-length
+/// field.
+fn read_packet(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut packet_type = [0u8; 1];
+    stream.read_exact(&mut packet_type)?;
+
+    let mut remaining_length = 0usize;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte)?;
+        remaining_length += ((byte[0] & 0x7F) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+
+    let mut payload = vec![0u8; remaining_length];
+    stream.read_exact(&mut payload)?;
+    Ok((packet_type[0], payload))
+}
+
+/// Encodes `remaining_length` as MQTT's variable-length integer.
+fn encode_remaining_length(mut remaining_length: usize, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (remaining_length % 0x80) as u8;
+        remaining_length /= 0x80;
+        if remaining_length > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if remaining_length == 0 {
+            break;
+        }
+    }
+}
+
+/// Writes a complete packet: `packet_type` (flags included), then
+/// `payload`'s length encoded as a variable-length integer, then
+/// `payload` itself.
+fn write_packet(stream: &mut TcpStream, packet_type: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![packet_type];
+    encode_remaining_length(payload.len(), &mut frame);
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
+
+/// Prefixes `bytes` with its length as a big-endian `u16`, as MQTT
+/// requires for every string and binary field.
+fn with_len_prefix(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn connect(stream: &mut TcpStream, client_id: &str) -> io::Result<()> {
+    let mut payload = Vec::new();
+    with_len_prefix(b"MQTT", &mut payload);
+    payload.push(4); // protocol level: MQTT 3.1.1
+    payload.push(0x02); // connect flags: clean session
+    payload.extend_from_slice(&60u16.to_be_bytes()); // keep-alive, seconds
+    with_len_prefix(client_id.as_bytes(), &mut payload);
+    write_packet(stream, CONNECT, &payload)?;
+
+    let (packet_type, payload) = read_packet(stream)?;
+    if packet_type != CONNACK || payload.get(1) != Some(&0) {
+        return Err(io::Error::other("broker refused the MQTT connection"));
+    }
+    Ok(())
+}
+
+fn subscribe(stream: &mut TcpStream, packet_id: u16, topic_filter: &str) -> io::Result<()> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&packet_id.to_be_bytes());
+    with_len_prefix(topic_filter.as_bytes(), &mut payload);
+    payload.push(0); // requested QoS 0
+    write_packet(stream, SUBSCRIBE, &payload)?;
+
+    let (packet_type, _) = read_packet(stream)?;
+    if packet_type != SUBACK {
+        return Err(io::Error::other("broker did not acknowledge the subscription"));
+    }
+    Ok(())
+}
+
+fn publish(stream: &mut TcpStream, topic: &str, message: &[u8]) -> io::Result<()> {
+    let mut payload = Vec::new();
+    with_len_prefix(topic.as_bytes(), &mut payload);
+    payload.extend_from_slice(message);
+    write_packet(stream, PUBLISH, &payload)
+}
+
+/// Splits a QoS 0 `PUBLISH` packet's payload into its topic and message.
+fn split_publish_payload(payload: &[u8]) -> Option<(&str, &[u8])> {
+    let topic_len = u16::from_be_bytes([*payload.first()?, *payload.get(1)?]) as usize;
+    let topic = std::str::from_utf8(payload.get(2..2 + topic_len)?).ok()?;
+    let message = payload.get(2 + topic_len..)?;
+    Some((topic, message))
+}
+
+/// Bridges a URAP upstream to an MQTT broker: register changes become
+/// `PUBLISH` messages, and messages on the command topic become
+/// register writes.
+pub struct MqttBridge {
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+}
+
+impl MqttBridge {
+    /// Connects to `broker` as `client_id`, subscribes to change
+    /// notifications for `registers` on `io`, and starts one thread
+    /// publishing `{topic_prefix}/{register}` on every change and one
+    /// thread writing `{topic_prefix}/{register}/set` messages back to
+    /// `io`. `poll_interval` paces the change-notification drain.
+    pub fn spawn<IO>(
+        broker: SocketAddr,
+        client_id: &str,
+        topic_prefix: &str,
+        registers: Range<u16>,
+        poll_interval: Duration,
+        mut io: IO,
+    ) -> io::Result<Self>
+    where
+        IO: Read + Write + Send + 'static,
+    {
+        if registers.len() > URAP_COUNT_MAX as usize {
+            return Err(io::Error::other("more registers than a single subscription can cover"));
+        }
+
+        NotifyPrimary::new(&mut io)
+            .subscribe(registers.start, registers.len() as u8)
+            .map_err(|err| io::Error::other(format!("subscribing to register changes failed: {err:?}")))?;
+
+        let publish_stream = TcpStream::connect(broker)?;
+        publish_stream.set_nodelay(true)?;
+        let mut publish_stream = publish_stream;
+        connect(&mut publish_stream, client_id)?;
+
+        let mut command_stream = publish_stream.try_clone()?;
+        subscribe(&mut command_stream, 1, &format!("{topic_prefix}/+/set"))?;
+
+        let io = Arc::new(Mutex::new(io));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let publish_io = Arc::clone(&io);
+        let publish_errors = Arc::clone(&errors);
+        let publish_prefix = topic_prefix.to_string();
+        let publish_registers = registers.clone();
+        let publish_thread = thread::spawn(move || {
+            publish_loop(
+                publish_io,
+                publish_stream,
+                publish_prefix,
+                publish_registers,
+                poll_interval,
+                publish_errors,
+            )
+        });
+
+        let command_io = Arc::clone(&io);
+        let command_errors = Arc::clone(&errors);
+        let command_prefix = topic_prefix.to_string();
+        let command_thread = thread::spawn(move || {
+            command_loop(command_io, command_stream, command_prefix, command_errors)
+        });
+
+        Ok(Self {
+            join_handles: vec![publish_thread, command_thread],
+            errors,
+        })
+    }
+
+    /// Pops the oldest recorded error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn publish_loop<IO: Read + Write>(
+    io: Arc<Mutex<IO>>,
+    mut stream: TcpStream,
+    topic_prefix: String,
+    registers: Range<u16>,
+    poll_interval: Duration,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    let mut changed = [0u16; URAP_COUNT_MAX as usize];
+    loop {
+        thread::sleep(poll_interval);
+
+        let reported = {
+            let mut io = match io.lock() {
+                Ok(io) => io,
+                Err(_) => return,
+            };
+            NotifyPrimary::new(&mut *io).poll_notifications(&mut changed[..registers.len().max(1)])
+        };
+        let reported = match reported {
+            Ok(reported) => reported,
+            Err(err) => {
+                push_error(
+                    &errors,
+                    Error::Io(io::Error::other(format!("polling register changes failed: {err:?}"))),
+                );
+                continue;
+            }
+        };
+
+        for &register in &changed[..reported] {
+            let value = {
+                let mut io = match io.lock() {
+                    Ok(io) => io,
+                    Err(_) => return,
+                };
+                let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut *io);
+                let mut value = [[0u8; 4]; 1];
+                primary.read_4u8(register, &mut value).map(|()| value[0])
+            };
+            match value {
+                Ok(value) => {
+                    if let Err(err) = publish(&mut stream, &format!("{topic_prefix}/{register}"), &value) {
+                        push_error(&errors, Error::Io(err));
+                    }
+                }
+                Err(err) => {
+                    push_error(
+                        &errors,
+                        Error::Io(io::Error::other(format!("reading changed register {register} failed: {err:?}"))),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn command_loop<IO: Read + Write>(
+    io: Arc<Mutex<IO>>,
+    mut stream: TcpStream,
+    topic_prefix: String,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) {
+    loop {
+        let (packet_type, payload) = match read_packet(&mut stream) {
+            Ok(packet) => packet,
+            Err(err) => {
+                push_error(&errors, Error::Io(err));
+                return;
+            }
+        };
+        if packet_type & 0xF0 != PUBLISH {
+            continue;
+        }
+        let Some((topic, message)) = split_publish_payload(&payload) else {
+            continue;
+        };
+        let Some(register) = topic
+            .strip_prefix(&topic_prefix)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .and_then(|rest| rest.strip_suffix("/set"))
+            .and_then(|register| register.parse::<u16>().ok())
+        else {
+            continue;
+        };
+        let Ok(value) = <[u8; 4]>::try_from(message) else {
+            continue;
+        };
+
+        let mut io = match io.lock() {
+            Ok(io) => io,
+            Err(_) => return,
+        };
+        let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut *io);
+        if let Err(err) = primary.write_4u8(register, &[value]) {
+            push_error(
+                &errors,
+                Error::Io(io::Error::other(format!("writing commanded register {register} failed: {err:?}"))),
+            );
+        }
+    }
+}
+
+impl Drop for MqttBridge {
+    fn drop(&mut self) {
+        // Both worker threads run forever today; detach rather than
+        // block the dropping thread. A graceful shutdown API is tracked
+        // separately, mirroring `modbus::ModbusGateway`.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
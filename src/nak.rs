@@ -0,0 +1,49 @@
+//! Negative-acknowledgement codes returned by a secondary.
+
+/// Reason a secondary rejected a request, carried as the single payload
+/// byte of a NAK response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum NakCode {
+    /// The CRC of the request did not match the bytes received.
+    BadCrc = 0x00,
+    /// The opcode byte was not one this secondary understands.
+    BadOp = 0x01,
+    /// `register + count` runs past the end of the register map.
+    IndexOutOfBounds = 0x02,
+    /// `count` exceeds [`crate::URAP_COUNT_MAX`].
+    CountTooLarge = 0x03,
+    /// The request would have written at least one write-protected register.
+    IndexWriteProtected = 0x04,
+    /// The request would have read at least one read-protected register.
+    IndexReadProtected = 0x05,
+    /// The session handshake or a request's authentication tag failed to
+    /// verify.
+    AuthFailed = 0x06,
+    /// A notify-poll request arrived with no active subscription.
+    NotSubscribed = 0x07,
+    /// A name-lookup request's name wasn't found in the secondary's name
+    /// table.
+    NameNotFound = 0x08,
+}
+
+impl NakCode {
+    /// Recovers a [`NakCode`] from its wire representation, if valid.
+    pub fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0x00 => Some(Self::BadCrc),
+            0x01 => Some(Self::BadOp),
+            0x02 => Some(Self::IndexOutOfBounds),
+            0x03 => Some(Self::CountTooLarge),
+            0x04 => Some(Self::IndexWriteProtected),
+            0x05 => Some(Self::IndexReadProtected),
+            0x06 => Some(Self::AuthFailed),
+            0x07 => Some(Self::NotSubscribed),
+            0x08 => Some(Self::NameNotFound),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! A symbolic name -> index table served by the secondary, so generic
+//! tooling (a dashboard, a CLI) can work against any device's register
+//! map without shipping it - [`NamesPrimary::read_by_name`]/
+//! [`NamesPrimary::write_by_name`] resolve a name to its index with an
+//! [`crate::OP_NAME_LOOKUP`] request before reading or writing it.
+//! `OP_READ`/`OP_WRITE` requests are forwarded to the wrapped secondary
+//! unchanged.
+
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::{
+    crc16, crc16_update, Error, NakCode, NoWriteHook, NoWriteProtect, PollOutcome, ReadProtect,
+    UrapPrimary, WriteHook, WriteProtect, OP_ACK, OP_NAK, OP_NAME_LOOKUP, OP_READ, OP_WRITE,
+    URAP_HEADER_SIZE,
+};
+
+/// Longest name a lookup request may carry.
+const NAME_MAX_LEN: usize = 64;
+
+/// What [`NamesSecondary::poll`] did with the request it just serviced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamesOutcome {
+    /// An `OP_READ` or `OP_WRITE`, forwarded to the wrapped secondary.
+    Forwarded(PollOutcome),
+    /// An `OP_NAME_LOOKUP` request was serviced.
+    Lookup {
+        /// Length in bytes of the requested name.
+        name_len: u8,
+        /// The resolved index, if the lookup succeeded.
+        index: Option<u16>,
+        /// Rejection reason, if the lookup was NAKed.
+        nak: Option<NakCode>,
+    },
+}
+
+struct HeaderPeek<'a, IO> {
+    header: [u8; URAP_HEADER_SIZE],
+    pos: usize,
+    inner: &'a mut IO,
+}
+
+impl<IO: ErrorType> ErrorType for HeaderPeek<'_, IO> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read> Read for HeaderPeek<'_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.pos < self.header.len() {
+            let n = buf.len().min(self.header.len() - self.pos);
+            buf[..n].copy_from_slice(&self.header[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<IO: Write> Write for HeaderPeek<'_, IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+/// Wraps a [`crate::UrapSecondary`] with a fixed name table and support
+/// for [`crate::OP_NAME_LOOKUP`] requests.
+pub struct NamesSecondary<
+    const REGCNT: usize,
+    const WIDTH: usize = 4,
+    const NAMECNT: usize = 8,
+    P = [bool; REGCNT],
+    R = NoWriteProtect,
+    H = NoWriteHook,
+> {
+    inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, H>,
+    names: [(&'static str, u16); NAMECNT],
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, const NAMECNT: usize, P, R, H>
+    NamesSecondary<REGCNT, WIDTH, NAMECNT, P, R, H>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+{
+    /// Wraps `inner`, serving lookups against `names`.
+    pub fn new(
+        inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, H>,
+        names: [(&'static str, u16); NAMECNT],
+    ) -> Self {
+        Self { inner, names }
+    }
+
+    /// Direct access to the wrapped secondary, e.g. for
+    /// [`crate::UrapSecondary::regs`].
+    pub fn inner(&self) -> &crate::UrapSecondary<REGCNT, WIDTH, P, R, H> {
+        &self.inner
+    }
+
+    /// Direct mutable access to the wrapped secondary.
+    pub fn inner_mut(&mut self) -> &mut crate::UrapSecondary<REGCNT, WIDTH, P, R, H> {
+        &mut self.inner
+    }
+
+    /// Services a single request read from `io`, writing the response
+    /// back to `io`. Blocks until a full request has been received.
+    pub fn poll<IO>(&mut self, io: &mut IO) -> Result<NamesOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        read_exact(io, &mut header)?;
+
+        let op = header[0];
+
+        match op {
+            OP_NAME_LOOKUP => self.handle_lookup(io, &header),
+            OP_READ | OP_WRITE => {
+                let mut peeked = HeaderPeek { header, pos: 0, inner: io };
+                self.inner.poll(&mut peeked).map(NamesOutcome::Forwarded)
+            }
+            _ => {
+                let mut peeked = HeaderPeek { header, pos: 0, inner: io };
+                self.inner.poll(&mut peeked).map(NamesOutcome::Forwarded)
+            }
+        }
+    }
+
+    fn handle_lookup<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+    ) -> Result<NamesOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let name_len = header[3];
+
+        if name_len as usize > NAME_MAX_LEN {
+            let mut discard = [0u8; NAME_MAX_LEN];
+            let mut remaining = name_len as usize;
+            while remaining > 0 {
+                let n = remaining.min(discard.len());
+                read_exact(io, &mut discard[..n])?;
+                remaining -= n;
+            }
+            let mut crc_bytes = [0u8; 2];
+            read_exact(io, &mut crc_bytes)?;
+            let nak = self.respond_nak(io, NakCode::CountTooLarge)?;
+            return Ok(NamesOutcome::Lookup { name_len, index: None, nak: Some(nak) });
+        }
+
+        let mut name_buf = [0u8; NAME_MAX_LEN];
+        let name_bytes = &mut name_buf[..name_len as usize];
+        read_exact(io, name_bytes)?;
+
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        let crc_state = crc16_update(crc16(header), name_bytes);
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            let nak = self.respond_nak(io, NakCode::BadCrc)?;
+            return Ok(NamesOutcome::Lookup { name_len, index: None, nak: Some(nak) });
+        }
+
+        let Some((_, index)) = self.names.iter().find(|(name, _)| name.as_bytes() == name_bytes)
+        else {
+            let nak = self.respond_nak(io, NakCode::NameNotFound)?;
+            return Ok(NamesOutcome::Lookup { name_len, index: None, nak: Some(nak) });
+        };
+
+        let index_bytes = index.to_le_bytes();
+        let mut crc_state = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        crc_state = crc16_update(crc_state, &index_bytes);
+        io.write_all(&index_bytes).map_err(Error::Io)?;
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+
+        Ok(NamesOutcome::Lookup { name_len, index: Some(*index), nak: None })
+    }
+
+    fn respond_nak<IO>(&self, io: &mut IO, code: NakCode) -> Result<NakCode, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let payload = [OP_NAK, code as u8];
+        let crc = crc16(&payload);
+        io.write_all(&payload).map_err(Error::Io)?;
+        io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(code)
+    }
+}
+
+/// The primary side of the name-lookup extension: wraps a transport the
+/// same way [`crate::UrapPrimary`] does, adding [`Self::lookup`] and the
+/// [`Self::read_by_name`]/[`Self::write_by_name`] convenience methods
+/// built on it.
+pub struct NamesPrimary<'a, IO, const WIDTH: usize = 4> {
+    io: &'a mut IO,
+}
+
+impl<'a, IO, const WIDTH: usize> NamesPrimary<'a, IO, WIDTH>
+where
+    IO: Read + Write,
+{
+    /// Wraps an existing transport. The transport is borrowed for the
+    /// lifetime of the primary.
+    pub fn new(io: &'a mut IO) -> Self {
+        Self { io }
+    }
+
+    /// Resolves `name` to its register index.
+    pub fn lookup(&mut self, name: &str) -> Result<u16, Error<IO::Error>> {
+        assert!(name.len() <= NAME_MAX_LEN);
+
+        let header = [OP_NAME_LOOKUP, 0, 0, name.len() as u8];
+        let crc = crc16_update(crc16(&header), name.as_bytes());
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(name.as_bytes()).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crc16(&op);
+                let mut index_bytes = [0u8; 2];
+                read_exact(self.io, &mut index_bytes)?;
+                crc_state = crc16_update(crc_state, &index_bytes);
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+                Ok(u16::from_le_bytes(index_bytes))
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Err(Error::Nak(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp)))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Resolves `name` and reads its register.
+    pub fn read_by_name(&mut self, name: &str) -> Result<[u8; WIDTH], Error<IO::Error>> {
+        let index = self.lookup(name)?;
+        let mut data = [[0u8; WIDTH]; 1];
+        UrapPrimary::<&mut IO, WIDTH>::new(self.io).read_4u8(index, &mut data)?;
+        Ok(data[0])
+    }
+
+    /// Resolves `name` and writes `value` to its register.
+    pub fn write_by_name(&mut self, name: &str, value: [u8; WIDTH]) -> Result<(), Error<IO::Error>> {
+        let index = self.lookup(name)?;
+        UrapPrimary::<&mut IO, WIDTH>::new(self.io).write_4u8(index, &[value])
+    }
+}
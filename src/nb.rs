@@ -0,0 +1,115 @@
+//! `nb`-based non-blocking front-end for [`UrapSecondary::poll`], for
+//! RTIC v1-style firmwares built on `embedded-hal-nb`'s byte-at-a-time
+//! serial traits rather than an async executor.
+//!
+//! [`try_poll`] only risks blocking the caller once a request has
+//! actually started arriving: it first checks for a single byte via
+//! `serial.read()`, returning `Err(nb::Error::WouldBlock)` immediately
+//! (without touching `secondary`) if the line is idle, so an RTIC idle
+//! loop calling this every tick never stalls waiting for a request. Once
+//! the first byte shows up, the rest of the packet is read out with
+//! `nb::block!`, on the assumption that a UART feeds the remaining bytes
+//! fast enough relative to one tick for that not to matter. Resuming a
+//! request that's only *partially* arrived across separate `try_poll`
+//! calls would need [`UrapSecondary::poll`] itself to carry state between
+//! calls, which it doesn't yet.
+
+use core::fmt;
+
+use embedded_hal_nb::serial::{Read as NbRead, Write as NbWrite};
+
+use crate::{
+    Error, PollOutcome, ReadProtect, RegisterStore, UrapSecondary, WriteHook, WriteProtect,
+};
+
+/// Everything that can go wrong reading or writing a byte over the
+/// underlying `embedded-hal-nb` serial peripheral.
+#[derive(Debug)]
+pub enum NbError<E> {
+    /// The serial peripheral reported an error on a byte already
+    /// committed to (i.e. not a `WouldBlock`).
+    Serial(E),
+}
+
+impl<E: fmt::Debug> fmt::Display for NbError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serial(err) => write!(f, "serial error: {err:?}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for NbError<E> {}
+
+impl<E: fmt::Debug> embedded_io::Error for NbError<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Serial(_) => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Bridges a byte-at-a-time `embedded-hal-nb` serial peripheral onto
+/// [`UrapSecondary::poll`]'s blocking `embedded_io::Read`/`Write`, with
+/// one byte already read out of the line buffered ahead of it.
+struct NbBridge<'a, S> {
+    serial: &'a mut S,
+    pending: Option<u8>,
+}
+
+impl<'a, S: embedded_hal_nb::serial::ErrorType> embedded_io::ErrorType for NbBridge<'a, S> {
+    type Error = NbError<S::Error>;
+}
+
+impl<'a, S: NbRead<u8>> embedded_io::Read for NbBridge<'a, S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = match self.pending.take() {
+            Some(byte) => byte,
+            None => nb::block!(self.serial.read()).map_err(NbError::Serial)?,
+        };
+        Ok(1)
+    }
+}
+
+impl<'a, S: NbWrite<u8>> embedded_io::Write for NbBridge<'a, S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        nb::block!(self.serial.write(buf[0])).map_err(NbError::Serial)?;
+        Ok(1)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        nb::block!(self.serial.flush()).map_err(NbError::Serial)
+    }
+}
+
+/// Services one request from `serial` without blocking if the line is
+/// currently idle; see the module documentation for exactly how much
+/// blocking the caller is still on the hook for once a request starts.
+pub fn try_poll<const REGCNT: usize, const WIDTH: usize, P, R, H, St, S>(
+    secondary: &mut UrapSecondary<REGCNT, WIDTH, P, R, H, St>,
+    serial: &mut S,
+) -> nb::Result<PollOutcome, Error<NbError<S::Error>>>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+    St: RegisterStore<WIDTH>,
+    S: NbRead<u8> + NbWrite<u8>,
+{
+    let first_byte = match serial.read() {
+        Ok(byte) => byte,
+        Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+        Err(nb::Error::Other(err)) => {
+            return Err(nb::Error::Other(Error::Io(NbError::Serial(err))))
+        }
+    };
+
+    let mut bridge = NbBridge { serial, pending: Some(first_byte) };
+    secondary.poll(&mut bridge).map_err(nb::Error::Other)
+}
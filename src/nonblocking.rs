@@ -0,0 +1,278 @@
+//! Resumable polling for [`crate::UrapSecondary`] over a non-blocking
+//! `std` transport.
+//!
+//! [`crate::UrapSecondary::poll`] assumes `io` always blocks until the
+//! bytes it asks for arrive, per `embedded_io`'s "traits are always
+//! blocking" contract. A socket put in non-blocking mode instead
+//! returns [`std::io::ErrorKind::WouldBlock`] the moment it runs out of
+//! buffered data, which `poll` has no choice but to propagate as a hard
+//! error - losing whatever header or data bytes it had already read of
+//! the in-flight packet. [`NonBlockingSecondary`] buffers those bytes
+//! itself and resumes on the next call, so a cooperative main loop (one
+//! also servicing, say, a motor control deadline) can poll a
+//! non-blocking socket every iteration without ever losing URAP framing
+//! to a short read.
+//!
+//! This is deliberately `std`-specific rather than a change to
+//! [`crate::UrapSecondary::poll`]'s generic signature:
+//! `embedded_io::ErrorKind` has no `WouldBlock` variant, so generic code
+//! can't distinguish "would block" from any other transport error in
+//! the first place. [`embedded_io_adapters::std::FromStd`] is the
+//! exception - its `Error` type is the raw, unmapped [`std::io::Error`],
+//! whose [`std::io::ErrorKind::WouldBlock`] is reliably observable.
+
+use embedded_io::Read;
+use embedded_io_adapters::std::FromStd;
+
+use crate::secondary::UrapSecondary;
+use crate::{
+    Error, NoWriteHook, NoWriteProtect, PollOutcome, ReadProtect, RegisterStore, WriteHook,
+    WriteProtect, OP_PING, OP_READ, OP_WRITE, URAP_HEADER_SIZE,
+};
+
+/// How much of the next request has arrived so far.
+enum Rx<const WIDTH: usize, const MAXCOUNT: usize> {
+    /// Nothing of the next request has arrived yet.
+    Idle,
+    /// Filling the 4-byte header.
+    Header { buf: [u8; URAP_HEADER_SIZE], filled: usize },
+    /// Header received; filling the op-specific body - the trailing CRC
+    /// alone for reads, pings, and unknown ops, or `count` data words
+    /// plus the CRC for writes.
+    Body {
+        header: [u8; URAP_HEADER_SIZE],
+        op: u8,
+        register: u16,
+        count: u8,
+        words: [[u8; WIDTH]; MAXCOUNT],
+        crc: [u8; 2],
+        filled: usize,
+    },
+}
+
+/// Wraps a [`crate::UrapSecondary`] with the buffering
+/// [`Self::poll_nonblocking`] needs to reassemble a packet across
+/// several non-blocking reads, instead of losing state on the first
+/// [`std::io::ErrorKind::WouldBlock`].
+///
+/// The buffer lives here rather than on [`crate::UrapSecondary`] itself,
+/// so secondaries that only ever use [`crate::UrapSecondary::poll`]'s
+/// blocking contract (the common case on embedded targets, where
+/// `MAXCOUNT` is deliberately kept small to bound exactly this kind of
+/// buffer) don't pay for a capability they never use.
+pub struct NonBlockingSecondary<
+    const REGCNT: usize,
+    const WIDTH: usize = 4,
+    P = [bool; REGCNT],
+    R = NoWriteProtect,
+    H = NoWriteHook,
+    S = [[u8; WIDTH]; REGCNT],
+    const MAXCOUNT: usize = { crate::URAP_COUNT_MAX as usize },
+> {
+    secondary: UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT>,
+    rx: Rx<WIDTH, MAXCOUNT>,
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, H, S, const MAXCOUNT: usize>
+    NonBlockingSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+    S: RegisterStore<WIDTH>,
+{
+    /// Wraps `secondary` with an empty receive buffer.
+    pub fn new(secondary: UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT>) -> Self {
+        Self { secondary, rx: Rx::Idle }
+    }
+
+    /// Unwraps back to the plain [`crate::UrapSecondary`], discarding
+    /// any partially-received request.
+    pub fn into_inner(self) -> UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT> {
+        self.secondary
+    }
+
+    /// Direct access to the wrapped secondary, e.g. for its `stats`.
+    pub fn inner(&self) -> &UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT> {
+        &self.secondary
+    }
+
+    /// Direct mutable access to the wrapped secondary.
+    pub fn inner_mut(&mut self) -> &mut UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT> {
+        &mut self.secondary
+    }
+
+    /// Like [`UrapSecondary::poll`], but for a non-blocking transport:
+    /// returns `Ok(None)` instead of an `Err` the moment `io` reports
+    /// [`std::io::ErrorKind::WouldBlock`], preserving whatever of the
+    /// in-flight packet had already arrived for the next call to pick
+    /// up where this one left off.
+    pub fn poll_nonblocking<T>(
+        &mut self,
+        io: &mut FromStd<T>,
+    ) -> Result<Option<PollOutcome>, Error<std::io::Error>>
+    where
+        T: std::io::Read + std::io::Write,
+    {
+        if matches!(self.rx, Rx::Idle) {
+            self.rx = Rx::Header { buf: [0u8; URAP_HEADER_SIZE], filled: 0 };
+        }
+
+        if let Rx::Header { buf, filled } = &mut self.rx {
+            if !fill(io, buf, filled)? {
+                return Ok(None);
+            }
+            let header = *buf;
+            let op = header[0];
+            let register = u16::from_le_bytes([header[1], header[2]]);
+            let count = header[3];
+            self.rx = Rx::Body {
+                header,
+                op,
+                register,
+                count,
+                words: [[0u8; WIDTH]; MAXCOUNT],
+                crc: [0u8; 2],
+                filled: 0,
+            };
+        }
+
+        let Rx::Body { header, op, register, count, words, crc, filled } = &mut self.rx else {
+            unreachable!("Idle and Header are both handled, and turned into Body, above")
+        };
+
+        // An oversize `count` is never stored into `words` - only
+        // drained - for the same reason `UrapSecondary::poll` never
+        // indexes its own scratch buffer with one: `words` only has
+        // room for `MAXCOUNT`.
+        let oversized = *op == OP_WRITE && *count as usize > MAXCOUNT;
+        let word_bytes = *count as usize * WIDTH;
+        let body_len = if *op == OP_WRITE { word_bytes + 2 } else { 2 };
+
+        while *filled < body_len {
+            let n = if oversized {
+                let mut scratch = [0u8; WIDTH];
+                let take = (body_len - *filled).min(WIDTH);
+                let n = read_once(io, &mut scratch[..take])?;
+                match n {
+                    Some(n) => n,
+                    None => return Ok(None),
+                }
+            } else if *filled < word_bytes {
+                let word = &mut words[*filled / WIDTH];
+                let byte = *filled % WIDTH;
+                match read_once(io, &mut word[byte..])? {
+                    Some(n) => n,
+                    None => return Ok(None),
+                }
+            } else {
+                let crc_idx = *filled - word_bytes;
+                match read_once(io, &mut crc[crc_idx..])? {
+                    Some(n) => n,
+                    None => return Ok(None),
+                }
+            };
+            *filled += n;
+        }
+
+        let header = *header;
+        let op = *op;
+        let register = *register;
+        let count = *count;
+        let words = *words;
+        let crc = *crc;
+        self.rx = Rx::Idle;
+
+        let outcome = match op {
+            OP_READ => self.secondary.handle_read(io, &header, register, count, crc)?,
+            OP_WRITE => self.secondary.handle_write(io, &header, register, count, &words, crc)?,
+            OP_PING => self.secondary.handle_ping(io, &header, crc)?,
+            _ => self.secondary.handle_unknown_op(io, crc)?,
+        };
+        Ok(Some(outcome))
+    }
+
+    /// Services up to `max_packets` complete requests without ever
+    /// blocking, stopping early the moment `io` has nothing left ready.
+    /// Returns every [`PollOutcome`] serviced, in the order they
+    /// completed.
+    ///
+    /// For bounding by wall-clock time instead of packet count - e.g. to
+    /// spend only what's left of a motor control deadline servicing URAP
+    /// requests - see [`Self::poll_for`].
+    pub fn poll_n<T>(
+        &mut self,
+        io: &mut FromStd<T>,
+        max_packets: usize,
+    ) -> Result<Vec<PollOutcome>, Error<std::io::Error>>
+    where
+        T: std::io::Read + std::io::Write,
+    {
+        let mut outcomes = Vec::new();
+        for _ in 0..max_packets {
+            match self.poll_nonblocking(io)? {
+                Some(outcome) => outcomes.push(outcome),
+                None => break,
+            }
+        }
+        Ok(outcomes)
+    }
+
+    /// Services complete requests without ever blocking until
+    /// `budget` elapses or `io` has nothing left ready, whichever comes
+    /// first. Returns every [`PollOutcome`] serviced, in the order they
+    /// completed.
+    ///
+    /// Stops at the first `WouldBlock` rather than spinning until
+    /// `budget` is spent, so a cooperative main loop that calls this
+    /// once per iteration doesn't busy-wait when the link is idle.
+    pub fn poll_for<T>(
+        &mut self,
+        io: &mut FromStd<T>,
+        budget: std::time::Duration,
+    ) -> Result<Vec<PollOutcome>, Error<std::io::Error>>
+    where
+        T: std::io::Read + std::io::Write,
+    {
+        let deadline = std::time::Instant::now() + budget;
+        let mut outcomes = Vec::new();
+        while std::time::Instant::now() < deadline {
+            match self.poll_nonblocking(io)? {
+                Some(outcome) => outcomes.push(outcome),
+                None => break,
+            }
+        }
+        Ok(outcomes)
+    }
+}
+
+/// Fills `buf[*filled..]` from `io`, returning `Ok(true)` once full,
+/// `Ok(false)` on `WouldBlock` (with `*filled` updated to reflect
+/// whatever arrived first), or `Err` on any other failure.
+fn fill<T: std::io::Read>(
+    io: &mut FromStd<T>,
+    buf: &mut [u8],
+    filled: &mut usize,
+) -> Result<bool, Error<std::io::Error>> {
+    while *filled < buf.len() {
+        match read_once(io, &mut buf[*filled..])? {
+            Some(n) => *filled += n,
+            None => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+/// Reads once into `buf`, translating a `WouldBlock` error into `Ok(None)`
+/// rather than propagating it.
+fn read_once<T: std::io::Read>(
+    io: &mut FromStd<T>,
+    buf: &mut [u8],
+) -> Result<Option<usize>, Error<std::io::Error>> {
+    match io.read(buf) {
+        Ok(0) => Err(Error::Eof),
+        Ok(n) => Ok(Some(n)),
+        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+        Err(err) => Err(Error::Io(err)),
+    }
+}
@@ -0,0 +1,373 @@
+//! Change-of-value notifications, so a primary doesn't have to poll every
+//! register at a fixed rate just to notice that almost nothing changed.
+//!
+//! URAP stays strictly request/response even with this extension - a
+//! secondary never writes to the wire unprompted, since that would race
+//! against the primary's own next request on a half-duplex link. Instead
+//! [`NotifyPrimary::subscribe`] asks the secondary to start tracking
+//! writes to a register range, and [`NotifyPrimary::poll_notifications`]
+//! periodically drains whatever changed since the last drain. This is
+//! "pull a push queue" rather than a true push, but it gets the primary
+//! the same win - one cheap poll instead of re-reading every register -
+//! without breaking the transport's framing.
+//!
+//! [`NotifySecondary`] wraps a [`crate::UrapSecondary`] whose write hook
+//! is a [`crate::DirtyTracker`], reusing it as the change queue: a normal
+//! write already marks the relevant bits, so servicing a notify-poll is
+//! just draining the tracker over the subscribed range.
+
+use core::ops::Range;
+
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::{
+    crc16, crc16_update, DirtyTracker, Error, NakCode, PollOutcome, ReadProtect, WriteProtect,
+    OP_ACK, OP_NAK, OP_NOTIFY_POLL, OP_READ, OP_SUBSCRIBE, OP_WRITE, URAP_COUNT_MAX,
+    URAP_HEADER_SIZE,
+};
+
+/// What [`NotifySecondary::poll`] did with the request it just serviced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyOutcome {
+    /// An `OP_READ` or `OP_WRITE`, forwarded to the wrapped secondary.
+    Forwarded(PollOutcome),
+    /// The primary (re)subscribed to change notifications.
+    Subscribed {
+        /// First subscribed register.
+        register: u16,
+        /// Number of subscribed registers.
+        count: u8,
+        /// Rejection reason, if the subscription was NAKed.
+        nak: Option<NakCode>,
+    },
+    /// The primary drained pending change notifications.
+    NotifyPoll {
+        /// Number of changed registers reported.
+        reported: u8,
+        /// Rejection reason, if there was no active subscription.
+        nak: Option<NakCode>,
+    },
+}
+
+struct HeaderPeek<'a, IO> {
+    header: [u8; URAP_HEADER_SIZE],
+    pos: usize,
+    inner: &'a mut IO,
+}
+
+impl<IO: ErrorType> ErrorType for HeaderPeek<'_, IO> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read> Read for HeaderPeek<'_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.pos < self.header.len() {
+            let n = buf.len().min(self.header.len() - self.pos);
+            buf[..n].copy_from_slice(&self.header[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<IO: Write> Write for HeaderPeek<'_, IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+fn respond_nak<IO: Read + Write>(io: &mut IO, code: NakCode) -> Result<NakCode, Error<IO::Error>> {
+    let payload = [OP_NAK, code as u8];
+    let crc = crc16(&payload);
+    io.write_all(&payload).map_err(Error::Io)?;
+    io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+    io.flush().map_err(Error::Io)?;
+    Ok(code)
+}
+
+/// Wraps a [`crate::UrapSecondary`] - whose write hook must be a
+/// [`DirtyTracker`] - with support for [`crate::OP_SUBSCRIBE`] and
+/// [`crate::OP_NOTIFY_POLL`] requests. `OP_READ`/`OP_WRITE` requests are
+/// forwarded to the inner secondary unchanged.
+pub struct NotifySecondary<const REGCNT: usize, const WIDTH: usize, P, R, const BYTES: usize> {
+    inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>>,
+    subscription: Option<Range<u16>>,
+    seq: u32,
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, const BYTES: usize>
+    NotifySecondary<REGCNT, WIDTH, P, R, BYTES>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+{
+    /// Wraps `inner`, which starts out with no active subscription.
+    pub fn new(inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>>) -> Self {
+        Self {
+            inner,
+            subscription: None,
+            seq: 0,
+        }
+    }
+
+    /// Direct access to the wrapped secondary, e.g. for
+    /// [`crate::UrapSecondary::regs`].
+    pub fn inner(&self) -> &crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>> {
+        &self.inner
+    }
+
+    /// Direct mutable access to the wrapped secondary.
+    pub fn inner_mut(
+        &mut self,
+    ) -> &mut crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>> {
+        &mut self.inner
+    }
+
+    /// Services a single request read from `io`, writing the response
+    /// back to `io`. Blocks until a full request has been received.
+    pub fn poll<IO>(&mut self, io: &mut IO) -> Result<NotifyOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        read_exact(io, &mut header)?;
+
+        let op = header[0];
+        let register = u16::from_le_bytes([header[1], header[2]]);
+        let count = header[3];
+
+        match op {
+            OP_SUBSCRIBE => self.handle_subscribe(io, &header, register, count),
+            OP_NOTIFY_POLL => self.handle_notify_poll(io, &header),
+            OP_READ | OP_WRITE => {
+                let mut peeked = HeaderPeek {
+                    header,
+                    pos: 0,
+                    inner: io,
+                };
+                self.inner.poll(&mut peeked).map(NotifyOutcome::Forwarded)
+            }
+            _ => {
+                let mut peeked = HeaderPeek {
+                    header,
+                    pos: 0,
+                    inner: io,
+                };
+                self.inner.poll(&mut peeked).map(NotifyOutcome::Forwarded)
+            }
+        }
+    }
+
+    fn handle_subscribe<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+    ) -> Result<NotifyOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        if crc16(header) != u16::from_le_bytes(crc_bytes) {
+            let nak = respond_nak(io, NakCode::BadCrc)?;
+            return Ok(NotifyOutcome::Subscribed { register, count, nak: Some(nak) });
+        }
+        if register as usize + count as usize > REGCNT {
+            let nak = respond_nak(io, NakCode::IndexOutOfBounds)?;
+            return Ok(NotifyOutcome::Subscribed { register, count, nak: Some(nak) });
+        }
+
+        self.subscription = Some(register..register + count as u16);
+
+        let payload = [OP_ACK];
+        let crc = crc16(&payload);
+        io.write_all(&payload).map_err(Error::Io)?;
+        io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(NotifyOutcome::Subscribed { register, count, nak: None })
+    }
+
+    fn handle_notify_poll<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+    ) -> Result<NotifyOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        if crc16(header) != u16::from_le_bytes(crc_bytes) {
+            let nak = respond_nak(io, NakCode::BadCrc)?;
+            return Ok(NotifyOutcome::NotifyPoll { reported: 0, nak: Some(nak) });
+        }
+
+        let Some(subscription) = self.subscription.clone() else {
+            let nak = respond_nak(io, NakCode::NotSubscribed)?;
+            return Ok(NotifyOutcome::NotifyPoll { reported: 0, nak: Some(nak) });
+        };
+
+        let mut changed = [0u16; URAP_COUNT_MAX as usize];
+        let mut reported = 0usize;
+        for register in self.inner.write_hook_mut().take_dirty_in(subscription) {
+            if reported >= URAP_COUNT_MAX as usize {
+                break;
+            }
+            changed[reported] = register;
+            reported += 1;
+        }
+
+        self.seq = self.seq.wrapping_add(1);
+        let seq_bytes = self.seq.to_le_bytes();
+        let count_byte = [reported as u8];
+
+        let mut crc_state = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        crc_state = crc16_update(crc_state, &seq_bytes);
+        io.write_all(&seq_bytes).map_err(Error::Io)?;
+        crc_state = crc16_update(crc_state, &count_byte);
+        io.write_all(&count_byte).map_err(Error::Io)?;
+        for register in &changed[..reported] {
+            let bytes = register.to_le_bytes();
+            crc_state = crc16_update(crc_state, &bytes);
+            io.write_all(&bytes).map_err(Error::Io)?;
+        }
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+
+        Ok(NotifyOutcome::NotifyPoll { reported: reported as u8, nak: None })
+    }
+}
+
+/// The primary side of the subscription extension: wraps a transport the
+/// same way [`crate::UrapPrimary`] does, adding [`Self::subscribe`] and
+/// [`Self::poll_notifications`].
+pub struct NotifyPrimary<'a, IO> {
+    io: &'a mut IO,
+    last_seq: u32,
+}
+
+impl<'a, IO> NotifyPrimary<'a, IO>
+where
+    IO: Read + Write,
+{
+    /// Wraps an existing transport. The transport is borrowed for the
+    /// lifetime of the primary.
+    pub fn new(io: &'a mut IO) -> Self {
+        Self { io, last_seq: 0 }
+    }
+
+    /// The sequence number of the most recent successful
+    /// [`Self::poll_notifications`] response.
+    pub fn last_seq(&self) -> u32 {
+        self.last_seq
+    }
+
+    /// Subscribes to change notifications for `count` registers starting
+    /// at `register`, replacing any prior subscription on this
+    /// connection.
+    pub fn subscribe(&mut self, register: u16, count: u8) -> Result<(), Error<IO::Error>> {
+        let reg = register.to_le_bytes();
+        let header = [OP_SUBSCRIBE, reg[0], reg[1], count];
+        let crc = crc16(&header);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Err(Error::Nak(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp)))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Drains pending change notifications for the active subscription,
+    /// writing up to `changed.len()` changed register indices into
+    /// `changed`. Returns how many were written; any left over (because
+    /// `changed` was too small) stay queued for the next call.
+    pub fn poll_notifications(&mut self, changed: &mut [u16]) -> Result<usize, Error<IO::Error>> {
+        assert!(changed.len() <= URAP_COUNT_MAX as usize);
+
+        let header = [OP_NOTIFY_POLL, 0, 0, 0];
+        let crc = crc16(&header);
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crc16(&op);
+                let mut seq_bytes = [0u8; 4];
+                read_exact(self.io, &mut seq_bytes)?;
+                crc_state = crc16_update(crc_state, &seq_bytes);
+                let mut count_byte = [0u8; 1];
+                read_exact(self.io, &mut count_byte)?;
+                crc_state = crc16_update(crc_state, &count_byte);
+
+                let reported = count_byte[0] as usize;
+                let mut kept = 0usize;
+                for _ in 0..reported {
+                    let mut reg_bytes = [0u8; 2];
+                    read_exact(self.io, &mut reg_bytes)?;
+                    crc_state = crc16_update(crc_state, &reg_bytes);
+                    if let Some(slot) = changed.get_mut(kept) {
+                        *slot = u16::from_le_bytes(reg_bytes);
+                        kept += 1;
+                    }
+                }
+
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+
+                self.last_seq = u32::from_le_bytes(seq_bytes);
+                Ok(kept)
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Err(Error::Nak(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp)))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+}
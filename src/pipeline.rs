@@ -0,0 +1,249 @@
+//! Pipelined requests: let a primary queue several reads/writes before
+//! reading any of their responses, instead of paying a full round trip
+//! per request.
+//!
+//! [`UrapSecondary::poll`](crate::UrapSecondary::poll) already services
+//! whatever arrives strictly in order and writes each response before
+//! reading the next request, so nothing on the secondary side needs to
+//! change - on any transport that preserves ordering (TCP, Unix
+//! sockets, a UART with no framing loss), the responses simply queue up
+//! in the same order the requests went out. [`PipelinedPrimary`] is
+//! purely a primary-side convenience: it remembers, in a small
+//! fixed-capacity queue, what it queued and in what order, so
+//! [`PipelinedPrimary::recv_read`]/[`PipelinedPrimary::recv_write`] can
+//! parse the right response shape without the caller re-deriving it.
+//!
+//! This trades latency for throughput - on a high-latency link (e.g. TCP
+//! over a cellular connection) it turns N round trips into roughly one.
+//! It does not help a link with no other outstanding traffic and no
+//! latency to hide.
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, NakCode, OP_ACK, OP_NAK, OP_READ, OP_WRITE, URAP_COUNT_MAX};
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+/// One request queued by [`PipelinedPrimary::queue_read`]/
+/// [`PipelinedPrimary::queue_write`], waiting for
+/// [`PipelinedPrimary::recv_read`]/[`PipelinedPrimary::recv_write`] to
+/// drain its response.
+#[derive(Debug, Clone, Copy)]
+enum PendingRequest {
+    Read { count: u8 },
+    Write,
+}
+
+/// Queues up to `DEPTH` reads/writes against a [`crate::UrapSecondary`]
+/// before reading any of their responses, for throughput on links where
+/// the round-trip latency, not the bandwidth, is the bottleneck.
+///
+/// Requests must be drained with [`Self::recv_read`]/[`Self::recv_write`]
+/// in the same order they were queued - the secondary answers in the
+/// order it received them, and nothing here re-orders or tags them to
+/// allow otherwise. Mixing up the order, or draining a read into a
+/// differently-sized buffer than it was queued with, desyncs the framing
+/// for every request still outstanding after it; see [`crate::seq`] if
+/// that's a real risk on your link (e.g. because requests are also
+/// retried) rather than a purely local bookkeeping matter.
+pub struct PipelinedPrimary<'a, IO, const WIDTH: usize = 4, const DEPTH: usize = 8> {
+    io: &'a mut IO,
+    pending: [Option<PendingRequest>; DEPTH],
+    head: usize,
+    len: usize,
+}
+
+impl<'a, IO, const WIDTH: usize, const DEPTH: usize> PipelinedPrimary<'a, IO, WIDTH, DEPTH>
+where
+    IO: Read + Write,
+{
+    /// Wraps an existing transport. The transport is borrowed for the
+    /// lifetime of the primary.
+    pub fn new(io: &'a mut IO) -> Self {
+        Self {
+            io,
+            pending: [None; DEPTH],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of queued requests whose responses haven't been drained
+    /// yet.
+    pub fn outstanding(&self) -> usize {
+        self.len
+    }
+
+    /// Queues a read of `count` registers starting at `register`,
+    /// without waiting for the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `DEPTH` requests are already outstanding - drain some
+    /// with [`Self::recv_read`]/[`Self::recv_write`] first.
+    pub fn queue_read(&mut self, register: u16, count: u8) -> Result<(), Error<IO::Error>> {
+        assert!(self.len < DEPTH, "pipeline is full; drain a response first");
+        if count as u16 > URAP_COUNT_MAX {
+            return Err(Error::CountTooLarge);
+        }
+
+        let reg = register.to_le_bytes();
+        let header = [OP_READ, reg[0], reg[1], count];
+        let crc = crate::crc16(&header);
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        self.push(PendingRequest::Read { count });
+        Ok(())
+    }
+
+    /// Queues a write of `data` to `data.len()` consecutive registers
+    /// starting at `register`, without waiting for the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `DEPTH` requests are already outstanding - drain some
+    /// with [`Self::recv_read`]/[`Self::recv_write`] first.
+    pub fn queue_write(&mut self, register: u16, data: &[[u8; WIDTH]]) -> Result<(), Error<IO::Error>> {
+        assert!(self.len < DEPTH, "pipeline is full; drain a response first");
+        if data.len() > URAP_COUNT_MAX as usize {
+            return Err(Error::CountTooLarge);
+        }
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_WRITE, reg[0], reg[1], count];
+        let mut crc_state = crate::crc16(&header);
+        for word in data {
+            crc_state = crate::crc16_update(crc_state, word);
+        }
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        for word in data {
+            self.io.write_all(word).map_err(Error::Io)?;
+        }
+        self.io
+            .write_all(&crc_state.to_le_bytes())
+            .map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        self.push(PendingRequest::Write);
+        Ok(())
+    }
+
+    /// Reads the response to the oldest still-outstanding request,
+    /// filling `data`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if nothing is outstanding, if the oldest outstanding
+    /// request was a [`Self::queue_write`] rather than a read, or if
+    /// `data.len()` doesn't match the count it was queued with.
+    pub fn recv_read(&mut self, data: &mut [[u8; WIDTH]]) -> Result<(), Error<IO::Error>> {
+        match self.pop() {
+            Some(PendingRequest::Read { count }) => {
+                assert_eq!(
+                    count as usize,
+                    data.len(),
+                    "recv_read's buffer doesn't match the queued read's count"
+                );
+            }
+            Some(PendingRequest::Write) => {
+                panic!("recv_read called but the oldest outstanding request was a write")
+            }
+            None => panic!("recv_read called with nothing outstanding"),
+        }
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crate::crc16(&op);
+                for word in data.iter_mut() {
+                    read_exact(self.io, word)?;
+                    crc_state = crate::crc16_update(crc_state, word);
+                }
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Err(Error::Nak(
+                    NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp),
+                ))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Reads the response to the oldest still-outstanding request,
+    /// which must have been a [`Self::queue_write`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if nothing is outstanding, or if the oldest outstanding
+    /// request was a [`Self::queue_read`] rather than a write.
+    pub fn recv_write(&mut self) -> Result<(), Error<IO::Error>> {
+        match self.pop() {
+            Some(PendingRequest::Write) => {}
+            Some(PendingRequest::Read { .. }) => {
+                panic!("recv_write called but the oldest outstanding request was a read")
+            }
+            None => panic!("recv_write called with nothing outstanding"),
+        }
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Err(Error::Nak(
+                    NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp),
+                ))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    fn push(&mut self, request: PendingRequest) {
+        let idx = (self.head + self.len) % DEPTH;
+        self.pending[idx] = Some(request);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<PendingRequest> {
+        if self.len == 0 {
+            return None;
+        }
+        let request = self.pending[self.head].take();
+        self.head = (self.head + 1) % DEPTH;
+        self.len -= 1;
+        request
+    }
+}
@@ -0,0 +1,1047 @@
+//! The primary (bus master) side of a URAP link.
+
+use core::fmt;
+use core::ops::Range;
+
+use embedded_io::{Read, Write};
+
+use crate::{
+    Error, NakCode, OP_ACK, OP_NAK, OP_PING, OP_READ, OP_WRITE, URAP_COUNT_MAX, URAP_HEADER_SIZE,
+};
+
+/// Reads `buf.len()` bytes from `io`, treating a zero-length read as an
+/// unexpected end of stream rather than blocking forever.
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+/// Everything that can go wrong encoding a request into a caller-supplied
+/// buffer: see [`encode_read_request`]/[`encode_write_request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    /// More registers were requested than [`URAP_COUNT_MAX`] allows.
+    CountTooLarge,
+    /// `buf` wasn't large enough to hold the encoded request.
+    BufferTooSmall,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CountTooLarge => write!(
+                f,
+                "more than {} registers requested in a single call",
+                URAP_COUNT_MAX
+            ),
+            Self::BufferTooSmall => write!(f, "buffer too small to hold the encoded request"),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Encodes an `OP_READ` request for `count` consecutive registers
+/// starting at `register` into `buf`, returning the number of bytes
+/// written.
+///
+/// This is the same framing [`UrapPrimary::read_4u8`] sends over the
+/// wire, but produced without touching any `IO`, so the encoded bytes can
+/// be handed straight to a DMA TX channel instead of copied out of an
+/// intermediate write.
+pub fn encode_read_request(register: u16, count: u8, buf: &mut [u8]) -> Result<usize, EncodeError> {
+    if count as u16 > URAP_COUNT_MAX {
+        return Err(EncodeError::CountTooLarge);
+    }
+    let len = URAP_HEADER_SIZE + 2;
+    if buf.len() < len {
+        return Err(EncodeError::BufferTooSmall);
+    }
+
+    let reg = register.to_le_bytes();
+    buf[0] = OP_READ;
+    buf[1] = reg[0];
+    buf[2] = reg[1];
+    buf[3] = count;
+    let crc = crate::crc16(&buf[..URAP_HEADER_SIZE]);
+    buf[URAP_HEADER_SIZE..len].copy_from_slice(&crc.to_le_bytes());
+    Ok(len)
+}
+
+/// Encodes an `OP_WRITE` request writing `data` to `data.len()`
+/// consecutive registers starting at `register` into `buf`, returning
+/// the number of bytes written; see [`encode_read_request`].
+pub fn encode_write_request<const WIDTH: usize>(
+    register: u16,
+    data: &[[u8; WIDTH]],
+    buf: &mut [u8],
+) -> Result<usize, EncodeError> {
+    if data.len() > URAP_COUNT_MAX as usize {
+        return Err(EncodeError::CountTooLarge);
+    }
+    let len = URAP_HEADER_SIZE + data.len() * WIDTH + 2;
+    if buf.len() < len {
+        return Err(EncodeError::BufferTooSmall);
+    }
+
+    let count = data.len() as u8;
+    let reg = register.to_le_bytes();
+    buf[0] = OP_WRITE;
+    buf[1] = reg[0];
+    buf[2] = reg[1];
+    buf[3] = count;
+
+    let mut crc_state = crate::crc16(&buf[..URAP_HEADER_SIZE]);
+    let mut pos = URAP_HEADER_SIZE;
+    for word in data {
+        buf[pos..pos + WIDTH].copy_from_slice(word);
+        crc_state = crate::crc16_update(crc_state, word);
+        pos += WIDTH;
+    }
+    buf[pos..pos + 2].copy_from_slice(&crc_state.to_le_bytes());
+    Ok(len)
+}
+
+/// Blocks the caller for a duration, so [`RetryPolicy`] backoff works the
+/// same on `std` targets (see [`StdDelay`]) and on bare-metal targets
+/// with their own timer.
+pub trait Delay {
+    /// Blocks for at least `ms` milliseconds.
+    fn delay_ms(&mut self, ms: u32);
+}
+
+/// A [`Delay`] backed by [`std::thread::sleep`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdDelay;
+
+#[cfg(feature = "std")]
+impl Delay for StdDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(ms.into()));
+    }
+}
+
+/// Governs [`UrapPrimary::read_4u8_with_retry`]/
+/// [`UrapPrimary::write_4u8_with_retry`]: how many attempts to make and
+/// how long to wait between them.
+///
+/// Only the transient faults a noisy link produces are retried:
+/// [`Error::BadCrc`], [`Error::Nak`]`(`[`NakCode::BadCrc`]`)`, and an
+/// [`Error::Io`] whose [`embedded_io::ErrorKind`] is
+/// [`TimedOut`](embedded_io::ErrorKind::TimedOut). Anything else (a bad
+/// register index, write protection, the link being down for good) is
+/// returned immediately, since retrying can't fix it.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts made before giving up, including the first.
+    pub attempts: u32,
+    /// How long to wait before the first retry, in milliseconds.
+    pub initial_delay_ms: u32,
+    /// Upper bound on the wait between retries; each failed attempt
+    /// doubles the previous wait, capped here.
+    pub max_delay_ms: u32,
+}
+
+impl RetryPolicy {
+    /// Makes up to `attempts` attempts, waiting `initial_delay_ms` before
+    /// the first retry and doubling up to `max_delay_ms` on each
+    /// subsequent failure.
+    pub fn new(attempts: u32, initial_delay_ms: u32, max_delay_ms: u32) -> Self {
+        Self { attempts, initial_delay_ms, max_delay_ms }
+    }
+
+    fn is_retryable<E: embedded_io::Error>(error: &Error<E>) -> bool {
+        match error {
+            Error::BadCrc | Error::Nak(NakCode::BadCrc) => true,
+            Error::Io(err) => err.kind() == embedded_io::ErrorKind::TimedOut,
+            _ => false,
+        }
+    }
+}
+
+/// The primary end of a URAP link: issues reads and writes against a
+/// secondary's register map.
+///
+/// `WIDTH` is the width in bytes of a single register (4 by default).
+/// `BIG_ENDIAN` selects the byte order the typed accessors (e.g.
+/// [`Self::read_u32`]) use to interpret register contents; it has no
+/// effect on [`Self::read_4u8`]/[`Self::write_4u8`], which move raw
+/// bytes and leave interpretation to the caller. The secondary never
+/// interprets register contents, so it has no endianness parameter.
+///
+/// `IO` is owned, not borrowed, so a primary can be stored in a
+/// long-lived struct or moved across threads; pass `&mut io` to
+/// [`Self::new`] rather than `io` to borrow a transport instead, exactly
+/// as before.
+pub struct UrapPrimary<IO, const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    io: IO,
+}
+
+/// Results of a [`UrapPrimary::self_test`] run.
+#[derive(Debug, Clone, Copy)]
+pub struct SelfTestReport {
+    /// How many registers were exercised.
+    pub registers_tested: u16,
+    /// How many of those registers failed at least one pattern.
+    pub mismatches: u16,
+    /// The first register that failed, if any.
+    pub first_mismatch: Option<u16>,
+}
+
+impl SelfTestReport {
+    /// `true` if every register in the tested range round-tripped every
+    /// pattern correctly.
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0
+    }
+}
+
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimary<IO, WIDTH, BIG_ENDIAN> {
+    /// Wraps a transport, taking ownership of it. To borrow a transport
+    /// instead (the previous, and still supported, behavior), pass
+    /// `&mut io` rather than `io`: since `&mut T` implements
+    /// [`Read`]/[`Write`] whenever `T` does, `IO` is then inferred as
+    /// `&mut T` and nothing about the call site needs to change.
+    pub fn new(io: IO) -> Self {
+        Self { io }
+    }
+
+    /// Unwraps the primary, returning the transport it was constructed
+    /// with.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimary<IO, WIDTH, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    ///
+    /// Despite the name, this works for any `WIDTH`; the `4` reflects the
+    /// original fixed-width API this generalizes.
+    ///
+    /// Returns [`Error::CountTooLarge`] rather than panicking if
+    /// `data.len()` exceeds [`URAP_COUNT_MAX`].
+    pub fn read_4u8(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        if data.len() > URAP_COUNT_MAX as usize {
+            return Err(Error::CountTooLarge);
+        }
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("urap_read", register, count = data.len()).entered();
+
+        let result = self.read_4u8_inner(register, data);
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(()) => tracing::trace!(register, count = data.len(), "read acked"),
+            Err(Error::Nak(code)) => {
+                tracing::debug!(register, count = data.len(), ?code, "read naked")
+            }
+            Err(err) => tracing::debug!(register, count = data.len(), ?err, "read failed"),
+        }
+        #[cfg(feature = "log")]
+        match &result {
+            Ok(()) => log::trace!("read {} register(s) from {register}", data.len()),
+            Err(Error::Nak(code)) => {
+                log::warn!("read from {register} naked: {code:?}")
+            }
+            Err(err) => log::debug!("read from {register} failed: {err:?}"),
+        }
+
+        result
+    }
+
+    fn read_4u8_inner(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_READ, reg[0], reg[1], count];
+        let crc = crate::crc16(&header);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(&mut self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crate::crc16(&op);
+                for word in data.iter_mut() {
+                    read_exact(&mut self.io, word)?;
+                    crc_state = crate::crc16_update(crc_state, word);
+                }
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(&mut self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Err(Error::Nak(
+                    NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp),
+                ))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    ///
+    /// Returns [`Error::CountTooLarge`] rather than panicking if
+    /// `data.len()` exceeds [`URAP_COUNT_MAX`].
+    pub fn write_4u8(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        if data.len() > URAP_COUNT_MAX as usize {
+            return Err(Error::CountTooLarge);
+        }
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("urap_write", register, count = data.len()).entered();
+
+        let result = self.write_4u8_inner(register, data);
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(()) => tracing::trace!(register, count = data.len(), "write acked"),
+            Err(Error::Nak(code)) => {
+                tracing::debug!(register, count = data.len(), ?code, "write naked")
+            }
+            Err(err) => tracing::debug!(register, count = data.len(), ?err, "write failed"),
+        }
+        #[cfg(feature = "log")]
+        match &result {
+            Ok(()) => log::trace!("wrote {} register(s) to {register}", data.len()),
+            Err(Error::Nak(code)) => {
+                log::warn!("write to {register} naked: {code:?}")
+            }
+            Err(err) => log::debug!("write to {register} failed: {err:?}"),
+        }
+
+        result
+    }
+
+    fn write_4u8_inner(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_WRITE, reg[0], reg[1], count];
+
+        let mut crc_state = crate::crc16(&header);
+        for word in data {
+            crc_state = crate::crc16_update(crc_state, word);
+        }
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        for word in data {
+            self.io.write_all(word).map_err(Error::Io)?;
+        }
+        self.io
+            .write_all(&crc_state.to_le_bytes())
+            .map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(&mut self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(&mut self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Err(Error::Nak(
+                    NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp),
+                ))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Like [`Self::read_4u8`], but retries a transient failure (see
+    /// [`RetryPolicy`]) instead of returning it to the caller.
+    pub fn read_4u8_with_retry<D: Delay>(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+        policy: &RetryPolicy,
+        delay: &mut D,
+    ) -> Result<(), Error<IO::Error>> {
+        let mut wait_ms = policy.initial_delay_ms;
+        for _ in 1..policy.attempts.max(1) {
+            match self.read_4u8(register, data) {
+                Err(err) if RetryPolicy::is_retryable(&err) => {
+                    delay.delay_ms(wait_ms);
+                    wait_ms = wait_ms.saturating_mul(2).min(policy.max_delay_ms);
+                }
+                result => return result,
+            }
+        }
+        self.read_4u8(register, data)
+    }
+
+    /// Like [`Self::write_4u8`], but retries a transient failure (see
+    /// [`RetryPolicy`]) instead of returning it to the caller.
+    pub fn write_4u8_with_retry<D: Delay>(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+        policy: &RetryPolicy,
+        delay: &mut D,
+    ) -> Result<(), Error<IO::Error>> {
+        let mut wait_ms = policy.initial_delay_ms;
+        for _ in 1..policy.attempts.max(1) {
+            match self.write_4u8(register, data) {
+                Err(err) if RetryPolicy::is_retryable(&err) => {
+                    delay.delay_ms(wait_ms);
+                    wait_ms = wait_ms.saturating_mul(2).min(policy.max_delay_ms);
+                }
+                result => return result,
+            }
+        }
+        self.write_4u8(register, data)
+    }
+
+    /// Like [`Self::write_4u8`], but re-reads `data.len()` registers
+    /// starting at `register` afterward and compares them against what
+    /// was sent, returning [`Error::VerifyMismatch`] if the secondary's
+    /// register map doesn't actually hold what was written.
+    ///
+    /// For confirming a setpoint write actually took, rather than just
+    /// that it was ACKed - a write can ACK and still not stick if, say,
+    /// the secondary clamps the value or another writer races it.
+    pub fn write_4u8_verified(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<IO::Error>> {
+        self.write_4u8(register, data)?;
+
+        let mut readback = [[0u8; WIDTH]; URAP_COUNT_MAX as usize];
+        let readback = &mut readback[..data.len()];
+        self.read_4u8(register, readback)?;
+
+        if readback == data {
+            Ok(())
+        } else {
+            Err(Error::VerifyMismatch)
+        }
+    }
+
+    /// Runs `f`, passing it this primary to issue several reads/writes
+    /// against, for naming a multi-step configuration sequence as one
+    /// unit rather than several independent calls.
+    ///
+    /// On a plain `UrapPrimary` this is just `f(self)` - `&mut self`
+    /// already rules out anything else touching this primary while `f`
+    /// runs. The grouping starts to matter once the primary is shared:
+    /// see [`SharedPrimary::transaction`], which holds the shared lock
+    /// for the whole closure instead of letting it be re-acquired (and
+    /// another clone's request interleaved) between calls.
+    ///
+    /// There's no secondary-held lock here: this only keeps `f`'s
+    /// requests from interleaving with another clone's on this side of
+    /// the link, not with a different primary talking to the same
+    /// secondary over its own connection.
+    pub fn transaction<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        f(self)
+    }
+
+    /// Sends a no-op liveness probe ([`crate::OP_PING`]) and waits for the
+    /// ACK.
+    ///
+    /// Unlike [`Self::is_healthy`], this never touches the register map,
+    /// so it can't conflate a dead link with a register that happens to
+    /// be unreadable.
+    pub fn ping(&mut self) -> Result<(), Error<IO::Error>> {
+        let header = [OP_PING, 0, 0, 0];
+        let crc = crate::crc16(&header);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(&mut self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(&mut self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Err(Error::Nak(
+                    NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp),
+                ))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Returns `true` if register `0` can be read without error.
+    ///
+    /// This is a coarse liveness check; see [`Self::read_4u8`] for reading
+    /// other registers.
+    pub fn is_healthy(&mut self) -> bool {
+        let mut scratch = [[0u8; WIDTH]; 1];
+        self.read_4u8(0, &mut scratch).is_ok()
+    }
+
+    /// Writes and reads back a walking-ones pattern (one set bit at a
+    /// time across the whole word) and an address-in-data pattern (the
+    /// register's own index) against every register in `range`, for
+    /// verifying wiring and firmware on a production line.
+    ///
+    /// `range` should cover scratch registers only — this overwrites
+    /// whatever they hold.
+    pub fn self_test(&mut self, range: Range<u16>) -> Result<SelfTestReport, Error<IO::Error>> {
+        let mut registers_tested = 0u16;
+        let mut mismatches = 0u16;
+        let mut first_mismatch = None;
+
+        for register in range {
+            registers_tested += 1;
+            let mut register_ok = true;
+
+            for bit in 0..WIDTH * 8 {
+                let mut pattern = [0u8; WIDTH];
+                pattern[bit / 8] = 1 << (bit % 8);
+                if !self.check_pattern(register, pattern)? {
+                    register_ok = false;
+                }
+            }
+
+            let addr_bytes = register.to_le_bytes();
+            let mut pattern = [0u8; WIDTH];
+            let n = WIDTH.min(addr_bytes.len());
+            pattern[..n].copy_from_slice(&addr_bytes[..n]);
+            if !self.check_pattern(register, pattern)? {
+                register_ok = false;
+            }
+
+            if !register_ok {
+                mismatches += 1;
+                first_mismatch.get_or_insert(register);
+            }
+        }
+
+        Ok(SelfTestReport { registers_tested, mismatches, first_mismatch })
+    }
+
+    /// Writes `pattern` to `register` and reads it back, returning
+    /// whether it round-tripped unchanged.
+    fn check_pattern(
+        &mut self,
+        register: u16,
+        pattern: [u8; WIDTH],
+    ) -> Result<bool, Error<IO::Error>> {
+        self.write_4u8(register, &[pattern])?;
+        let mut readback = [[0u8; WIDTH]; 1];
+        self.read_4u8(register, &mut readback)?;
+        Ok(readback[0] == pattern)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimary<IO, WIDTH, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Round-trip time of a read of register `0`, for characterizing bus
+    /// load or spotting a degraded link in production.
+    ///
+    /// There's no dedicated ping opcode; this just times a real read, the
+    /// same liveness check [`Self::is_healthy`] performs.
+    pub fn ping_latency(&mut self) -> Result<std::time::Duration, Error<IO::Error>> {
+        let mut scratch = [[0u8; WIDTH]; 1];
+        let started = std::time::Instant::now();
+        self.read_4u8(0, &mut scratch)?;
+        Ok(started.elapsed())
+    }
+
+    /// Like [`Self::is_healthy`], but probes `register` instead of
+    /// hardcoding `0` (useful when register `0` is write-only or
+    /// side-effecting) and reports the probe's latency on success rather
+    /// than discarding the error on failure.
+    pub fn health_check(&mut self, register: u16) -> Result<HealthReport, Error<IO::Error>> {
+        let mut scratch = [[0u8; WIDTH]; 1];
+        let started = std::time::Instant::now();
+        self.read_4u8(register, &mut scratch)?;
+        Ok(HealthReport {
+            register,
+            latency: started.elapsed(),
+        })
+    }
+}
+
+#[cfg(feature = "usockets")]
+impl<T, const WIDTH: usize, const BIG_ENDIAN: bool>
+    UrapPrimary<embedded_io_adapters::std::FromStd<T>, WIDTH, BIG_ENDIAN>
+where
+    T: std::io::Read + std::io::Write,
+{
+    /// Like [`Self::write_4u8`], but hands the header, each register
+    /// word, and the trailing CRC to the OS as one `writev` via
+    /// [`std::io::Write::write_vectored`] instead of one `write` per
+    /// piece - for sockets and pipes, where `write_4u8`'s per-word writes
+    /// each cost a syscall.
+    pub fn write_4u8_vectored(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<std::io::Error>> {
+        if data.len() > URAP_COUNT_MAX as usize {
+            return Err(Error::CountTooLarge);
+        }
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_WRITE, reg[0], reg[1], count];
+
+        let mut crc_state = crate::crc16(&header);
+        for word in data {
+            crc_state = crate::crc16_update(crc_state, word);
+        }
+        let crc_bytes = crc_state.to_le_bytes();
+
+        let mut slices = Vec::with_capacity(2 + data.len());
+        slices.push(std::io::IoSlice::new(&header));
+        slices.extend(data.iter().map(|word| std::io::IoSlice::new(word)));
+        slices.push(std::io::IoSlice::new(&crc_bytes));
+        write_all_vectored(self.io.inner_mut(), &mut slices)?;
+        self.io.inner_mut().flush()?;
+
+        let mut op = [0u8; 1];
+        read_exact(&mut self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Ok(())
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(&mut self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(&mut self.io, &mut crc_bytes)?;
+                Err(Error::Nak(
+                    NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp),
+                ))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+}
+
+/// Writes every byte in `slices` to `w`, advancing past whatever a
+/// partial `write_vectored` already sent - `write_vectored` itself, like
+/// `write`, is free to return short.
+#[cfg(feature = "usockets")]
+fn write_all_vectored<W: std::io::Write>(
+    w: &mut W,
+    mut slices: &mut [std::io::IoSlice<'_>],
+) -> std::io::Result<()> {
+    while !slices.is_empty() {
+        match w.write_vectored(slices) {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(n) => std::io::IoSlice::advance_slices(&mut slices, n),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Result of a successful [`UrapPrimary::health_check`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct HealthReport {
+    /// The register that was probed.
+    pub register: u16,
+    /// Round-trip time of the probe read.
+    pub latency: std::time::Duration,
+}
+
+/// Typed accessors for the common 4-byte-wide register case, honoring
+/// `BIG_ENDIAN`.
+impl<IO, const BIG_ENDIAN: bool> UrapPrimary<IO, 4, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads a single register and interprets it as a `u32`.
+    pub fn read_u32(&mut self, register: u16) -> Result<u32, Error<IO::Error>> {
+        let mut data = [[0u8; 4]; 1];
+        self.read_4u8(register, &mut data)?;
+        Ok(if BIG_ENDIAN {
+            u32::from_be_bytes(data[0])
+        } else {
+            u32::from_le_bytes(data[0])
+        })
+    }
+
+    /// Writes a single register from a `u32`.
+    pub fn write_u32(&mut self, register: u16, value: u32) -> Result<(), Error<IO::Error>> {
+        let bytes = if BIG_ENDIAN {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.write_4u8(register, &[bytes])
+    }
+
+    /// Reads a single register and interprets it as an `i32`.
+    pub fn read_i32(&mut self, register: u16) -> Result<i32, Error<IO::Error>> {
+        self.read_u32(register).map(|v| v as i32)
+    }
+
+    /// Writes a single register from an `i32`.
+    pub fn write_i32(&mut self, register: u16, value: i32) -> Result<(), Error<IO::Error>> {
+        self.write_u32(register, value as u32)
+    }
+
+    /// Reads a single register and interprets it as an `f32`.
+    pub fn read_f32(&mut self, register: u16) -> Result<f32, Error<IO::Error>> {
+        let mut data = [[0u8; 4]; 1];
+        self.read_4u8(register, &mut data)?;
+        Ok(if BIG_ENDIAN {
+            f32::from_be_bytes(data[0])
+        } else {
+            f32::from_le_bytes(data[0])
+        })
+    }
+
+    /// Writes a single register from an `f32`.
+    pub fn write_f32(&mut self, register: u16, value: f32) -> Result<(), Error<IO::Error>> {
+        let bytes = if BIG_ENDIAN {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.write_4u8(register, &[bytes])
+    }
+
+    /// Reads bits `lsb..=msb` of `register`, right-shifted down into the
+    /// low bits of the returned `u32`.
+    ///
+    /// Panics if `msb >= 32` or `lsb > msb`: like a mismatched queue
+    /// shape elsewhere in this crate, that's a caller bug, not a runtime
+    /// condition to recover from.
+    pub fn read_bits(&mut self, register: u16, msb: u8, lsb: u8) -> Result<u32, Error<IO::Error>> {
+        assert!(msb < 32 && lsb <= msb, "read_bits: invalid bit range {lsb}..={msb}");
+
+        let value = self.read_u32(register)?;
+        let width = msb - lsb + 1;
+        let mask = if width == 32 { u32::MAX } else { (1u32 << width) - 1 };
+        Ok((value >> lsb) & mask)
+    }
+
+    /// Read-modify-writes bits `lsb..=msb` of `register` from the low
+    /// bits of `value`, leaving the rest of the register untouched.
+    ///
+    /// Panics if `msb >= 32` or `lsb > msb`, or if `value` doesn't fit in
+    /// the `msb - lsb + 1` bits being written.
+    pub fn write_bits(
+        &mut self,
+        register: u16,
+        msb: u8,
+        lsb: u8,
+        value: u32,
+    ) -> Result<(), Error<IO::Error>> {
+        assert!(msb < 32 && lsb <= msb, "write_bits: invalid bit range {lsb}..={msb}");
+
+        let width = msb - lsb + 1;
+        let mask = if width == 32 { u32::MAX } else { (1u32 << width) - 1 };
+        assert!(value & !mask == 0, "write_bits: value does not fit in {width} bits");
+
+        let current = self.read_u32(register)?;
+        let updated = (current & !(mask << lsb)) | (value << lsb);
+        self.write_u32(register, updated)
+    }
+
+    /// Reads a single register as a signed `FRAC`-bit fixed-point
+    /// value, scaling it up to an `f32`.
+    ///
+    /// Panics if `FRAC >= 32`.
+    pub fn read_fixed<const FRAC: u32>(&mut self, register: u16) -> Result<f32, Error<IO::Error>> {
+        assert!(FRAC < 32, "read_fixed: FRAC must be less than 32");
+
+        let raw = self.read_i32(register)?;
+        Ok(raw as f32 / (1u32 << FRAC) as f32)
+    }
+
+    /// Writes `value` to a single register as a signed `FRAC`-bit
+    /// fixed-point value.
+    ///
+    /// Panics if `FRAC >= 32`.
+    pub fn write_fixed<const FRAC: u32>(
+        &mut self,
+        register: u16,
+        value: f32,
+    ) -> Result<(), Error<IO::Error>> {
+        assert!(FRAC < 32, "write_fixed: FRAC must be less than 32");
+
+        let shifted = value * (1u32 << FRAC) as f32;
+        // `f32::round` needs `std`; round half away from zero by hand so
+        // this works on `no_std` targets too.
+        let scaled = if shifted >= 0.0 { shifted + 0.5 } else { shifted - 0.5 } as i32;
+        self.write_i32(register, scaled)
+    }
+
+    /// Reads a single register and converts it to `T`, so a
+    /// state-machine register can be matched as an enum instead of a
+    /// raw `u32` at every call site.
+    ///
+    /// Returns [`Error::InvalidDiscriminant`] if the register's value
+    /// isn't a valid discriminant of `T`.
+    pub fn read_enum<T>(&mut self, register: u16) -> Result<T, Error<IO::Error>>
+    where
+        T: TryFrom<u32>,
+    {
+        let raw = self.read_u32(register)?;
+        T::try_from(raw).map_err(|_| Error::InvalidDiscriminant(raw))
+    }
+
+    /// Reads a single register, treating zero as `false` and any other
+    /// value as `true`.
+    pub fn read_bool(&mut self, register: u16) -> Result<bool, Error<IO::Error>> {
+        Ok(self.read_u32(register)? != 0)
+    }
+
+    /// Writes a single register as `0` or `1`.
+    pub fn write_bool(&mut self, register: u16, value: bool) -> Result<(), Error<IO::Error>> {
+        self.write_u32(register, value as u32)
+    }
+
+    /// Read-modify-writes a single flag register to its opposite,
+    /// returning the new value.
+    pub fn toggle(&mut self, register: u16) -> Result<bool, Error<IO::Error>> {
+        let flipped = !self.read_bool(register)?;
+        self.write_bool(register, flipped)?;
+        Ok(flipped)
+    }
+
+    /// Reads a single register as a Q16.16 fixed-point value.
+    pub fn read_q16_16(&mut self, register: u16) -> Result<f32, Error<IO::Error>> {
+        self.read_fixed::<16>(register)
+    }
+
+    /// Writes `value` to a single register as a Q16.16 fixed-point
+    /// value.
+    pub fn write_q16_16(&mut self, register: u16, value: f32) -> Result<(), Error<IO::Error>> {
+        self.write_fixed::<16>(register, value)
+    }
+}
+
+/// Half-precision accessors, for telemetry registers that pack two
+/// `f16`s per register to halve the bytes on the wire.
+#[cfg(feature = "half")]
+impl<IO, const BIG_ENDIAN: bool> UrapPrimary<IO, 4, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads the low half-word of a register as an `f16`.
+    pub fn read_f16(&mut self, register: u16) -> Result<half::f16, Error<IO::Error>> {
+        Ok(self.read_f16_pair(register)?.0)
+    }
+
+    /// Read-modify-writes the low half-word of a register from an
+    /// `f16`, leaving the high half-word untouched.
+    pub fn write_f16(&mut self, register: u16, value: half::f16) -> Result<(), Error<IO::Error>> {
+        let (_, high) = self.read_f16_pair(register)?;
+        self.write_f16_pair(register, value, high)
+    }
+
+    /// Reads a register as two packed `f16`s: `(low, high)`.
+    pub fn read_f16_pair(
+        &mut self,
+        register: u16,
+    ) -> Result<(half::f16, half::f16), Error<IO::Error>> {
+        let mut data = [[0u8; 4]; 1];
+        self.read_4u8(register, &mut data)?;
+        let low = [data[0][0], data[0][1]];
+        let high = [data[0][2], data[0][3]];
+        Ok(if BIG_ENDIAN {
+            (half::f16::from_be_bytes(low), half::f16::from_be_bytes(high))
+        } else {
+            (half::f16::from_le_bytes(low), half::f16::from_le_bytes(high))
+        })
+    }
+
+    /// Writes two packed `f16`s, `low` and `high`, to a single
+    /// register.
+    pub fn write_f16_pair(
+        &mut self,
+        register: u16,
+        low: half::f16,
+        high: half::f16,
+    ) -> Result<(), Error<IO::Error>> {
+        let (low_bytes, high_bytes) = if BIG_ENDIAN {
+            (low.to_be_bytes(), high.to_be_bytes())
+        } else {
+            (low.to_le_bytes(), high.to_le_bytes())
+        };
+        let bytes = [low_bytes[0], low_bytes[1], high_bytes[0], high_bytes[1]];
+        self.write_4u8(register, &[bytes])
+    }
+}
+
+/// `uom` quantity accessors, so a register's physical unit is checked
+/// by the type system instead of by convention.
+#[cfg(feature = "uom")]
+impl<IO, const BIG_ENDIAN: bool> UrapPrimary<IO, 4, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads a single register and scales it into a `uom` quantity.
+    ///
+    /// `scale` converts the raw register value to the quantity's base
+    /// SI unit, e.g. `1.0` if the device already reports kelvin, or
+    /// `1.0 / 10.0` if it reports tenths of a kelvin.
+    pub fn read_quantity<D, U>(
+        &mut self,
+        register: u16,
+        scale: f32,
+    ) -> Result<uom::si::Quantity<D, U, f32>, Error<IO::Error>>
+    where
+        D: uom::si::Dimension + ?Sized,
+        U: uom::si::Units<f32> + ?Sized,
+    {
+        let raw = self.read_f32(register)?;
+        Ok(uom::si::Quantity {
+            dimension: core::marker::PhantomData,
+            units: core::marker::PhantomData,
+            value: raw * scale,
+        })
+    }
+
+    /// Scales a `uom` quantity down by `1.0 / scale` and writes it to a
+    /// single register.
+    ///
+    /// `scale` is the same factor passed to [`Self::read_quantity`].
+    pub fn write_quantity<D, U>(
+        &mut self,
+        register: u16,
+        quantity: uom::si::Quantity<D, U, f32>,
+        scale: f32,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        D: uom::si::Dimension + ?Sized,
+        U: uom::si::Units<f32> + ?Sized,
+    {
+        self.write_f32(register, quantity.value / scale)
+    }
+}
+
+#[cfg(feature = "std")]
+fn lock_recover<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Wraps a [`UrapPrimary`] so its reads and writes take `&self`,
+/// serialized internally by a mutex, instead of `&mut self`.
+///
+/// Cloning is a cheap [`Arc`](std::sync::Arc) bump and shares the same
+/// underlying primary, so it can be stored in an `Arc` (or just cloned
+/// directly - it already is one) and handed to multiple threads or async
+/// tasks without an external `Mutex<UrapPrimary>`.
+#[cfg(feature = "std")]
+pub struct SharedPrimary<IO, const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    inner: std::sync::Arc<std::sync::Mutex<UrapPrimary<IO, WIDTH, BIG_ENDIAN>>>,
+}
+
+#[cfg(feature = "std")]
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> Clone for SharedPrimary<IO, WIDTH, BIG_ENDIAN> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: std::sync::Arc::clone(&self.inner),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> SharedPrimary<IO, WIDTH, BIG_ENDIAN> {
+    /// Wraps `primary` for sharing across threads/tasks.
+    pub fn new(primary: UrapPrimary<IO, WIDTH, BIG_ENDIAN>) -> Self {
+        Self {
+            inner: std::sync::Arc::new(std::sync::Mutex::new(primary)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO, const WIDTH: usize, const BIG_ENDIAN: bool> SharedPrimary<IO, WIDTH, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    ///
+    /// Holds the lock for the duration of the transaction, so other
+    /// clones block rather than interleaving their bytes with this one.
+    pub fn read_4u8(&self, register: u16, data: &mut [[u8; WIDTH]]) -> Result<(), Error<IO::Error>> {
+        lock_recover(&self.inner).read_4u8(register, data)
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    ///
+    /// Holds the lock for the duration of the transaction, so other
+    /// clones block rather than interleaving their bytes with this one.
+    pub fn write_4u8(&self, register: u16, data: &[[u8; WIDTH]]) -> Result<(), Error<IO::Error>> {
+        lock_recover(&self.inner).write_4u8(register, data)
+    }
+
+    /// Runs `f` against the underlying primary while holding the shared
+    /// lock for the whole call, so `f`'s reads/writes land back-to-back
+    /// on the wire with no other clone's request interleaved.
+    ///
+    /// Unlike calling [`Self::read_4u8`]/[`Self::write_4u8`] several
+    /// times in a row - each of which re-acquires the lock - nothing can
+    /// slip a request in between the operations `f` performs.
+    pub fn transaction<R>(&self, f: impl FnOnce(&mut UrapPrimary<IO, WIDTH, BIG_ENDIAN>) -> R) -> R {
+        f(&mut lock_recover(&self.inner))
+    }
+}
@@ -0,0 +1,130 @@
+//! Compact representations of which registers reject writes.
+
+use core::ops::Range;
+
+/// Decides whether a register rejects writes.
+///
+/// Letting [`crate::UrapSecondary`] take any `WriteProtect` implementation
+/// instead of a `[bool; REGCNT]` lets large register maps express
+/// protection in a few bytes (e.g. [`WriteProtectRanges`]) instead of one
+/// bool per register.
+pub trait WriteProtect {
+    /// Returns `true` if `register` must reject writes.
+    fn is_protected(&self, register: u16) -> bool;
+}
+
+/// Decides whether a register rejects reads, analogous to [`WriteProtect`].
+///
+/// Implemented for every [`WriteProtect`] storage type so the same
+/// `[bool; REGCNT]`, [`WriteProtectRanges`], or [`WriteProtectBits`] can
+/// back either a secondary's write-protect or read-protect list.
+pub trait ReadProtect {
+    /// Returns `true` if `register` must reject reads.
+    fn is_protected(&self, register: u16) -> bool;
+}
+
+impl<T: WriteProtect> ReadProtect for T {
+    fn is_protected(&self, register: u16) -> bool {
+        WriteProtect::is_protected(self, register)
+    }
+}
+
+/// No register is write- or read-protected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoWriteProtect;
+
+impl WriteProtect for NoWriteProtect {
+    fn is_protected(&self, _register: u16) -> bool {
+        false
+    }
+}
+
+impl<const N: usize> WriteProtect for [bool; N] {
+    fn is_protected(&self, register: u16) -> bool {
+        self.get(register as usize).copied().unwrap_or(false)
+    }
+}
+
+/// Like `[bool; N]`, but sized at construction rather than baked into the
+/// type, for a register count only known at runtime.
+#[cfg(feature = "alloc")]
+impl WriteProtect for alloc::boxed::Box<[bool]> {
+    fn is_protected(&self, register: u16) -> bool {
+        self.get(register as usize).copied().unwrap_or(false)
+    }
+}
+
+/// Like `[bool; N]`, but borrowed from a caller-owned slice of runtime
+/// length rather than baked into the type; pairs with
+/// [`crate::UrapSecondary::from_slices`].
+impl WriteProtect for &[bool] {
+    fn is_protected(&self, register: u16) -> bool {
+        self.get(register as usize).copied().unwrap_or(false)
+    }
+}
+
+/// A compact write-protect list expressed as a handful of register
+/// ranges, costing a few bytes instead of one bool per register.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteProtectRanges<'a>(pub &'a [Range<u16>]);
+
+impl WriteProtect for WriteProtectRanges<'_> {
+    fn is_protected(&self, register: u16) -> bool {
+        self.0.iter().any(|range| range.contains(&register))
+    }
+}
+
+impl WriteProtect for &[Range<u16>] {
+    fn is_protected(&self, register: u16) -> bool {
+        self.iter().any(|range| range.contains(&register))
+    }
+}
+
+/// A write-protect bitset backed by `BYTES` bytes (`BYTES * 8` registers),
+/// one bit per register, for an 8x smaller footprint than `[bool; N]` on
+/// large register maps. `BYTES` is a plain byte count (e.g. `8192` for a
+/// full 65,536-register map) rather than a register count, since stable
+/// Rust const generics can't compute `ceil(N / 8)` for you.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteProtectBits<const BYTES: usize> {
+    bits: [u8; BYTES],
+}
+
+impl<const BYTES: usize> WriteProtectBits<BYTES> {
+    /// All `BYTES * 8` registers start out writable.
+    pub const fn new() -> Self {
+        Self { bits: [0u8; BYTES] }
+    }
+
+    /// Marks `register` as write-protected.
+    pub fn protect(&mut self, register: u16) {
+        let register = register as usize;
+        if register / 8 < BYTES {
+            self.bits[register / 8] |= 1 << (register % 8);
+        }
+    }
+
+    /// Marks `register` as writable again.
+    pub fn unprotect(&mut self, register: u16) {
+        let register = register as usize;
+        if register / 8 < BYTES {
+            self.bits[register / 8] &= !(1 << (register % 8));
+        }
+    }
+}
+
+impl<const BYTES: usize> Default for WriteProtectBits<BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BYTES: usize> WriteProtect for WriteProtectBits<BYTES> {
+    fn is_protected(&self, register: u16) -> bool {
+        let register = register as usize;
+        if register / 8 >= BYTES {
+            return false;
+        }
+        self.bits[register / 8] & (1 << (register % 8)) != 0
+    }
+}
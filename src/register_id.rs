@@ -0,0 +1,174 @@
+//! [`RegisterId<T>`], a typed handle that pairs a register index with
+//! the Rust type it holds, so [`UrapPrimary::read`]/[`UrapPrimary::write`]
+//! can't be called with a register whose declared type doesn't match
+//! the value being read or written.
+
+use core::marker::PhantomData;
+
+use crate::{Error, UrapPrimary};
+use embedded_io::{Read, Write};
+
+/// A register index tagged with the type it holds.
+///
+/// Plain `u16` register indices carry no information about what's
+/// stored there, so nothing stops `read_f32` being called on a register
+/// that's actually a `u32` counter. A `RegisterId<f32>` can only be
+/// passed to [`UrapPrimary::read`]/[`UrapPrimary::write`] calls that
+/// expect an `f32`, catching that class of mistake at compile time.
+pub struct RegisterId<T> {
+    index: u16,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> RegisterId<T> {
+    /// Creates a handle for the register at `index`.
+    pub const fn new(index: u16) -> Self {
+        Self { index, _value: PhantomData }
+    }
+
+    /// The underlying register index.
+    pub const fn index(&self) -> u16 {
+        self.index
+    }
+}
+
+impl<T> Clone for RegisterId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RegisterId<T> {}
+
+impl<T> core::fmt::Debug for RegisterId<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RegisterId").field("index", &self.index).finish()
+    }
+}
+
+impl<T> PartialEq for RegisterId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for RegisterId<T> {}
+
+/// A value type [`RegisterId`] can be typed over, with the read/write
+/// primitives [`UrapPrimary::read`]/[`UrapPrimary::write`] dispatch to.
+///
+/// Sealed: implemented only for the types `UrapPrimary` already has
+/// typed accessors for.
+pub trait RegisterValue: sealed::Sealed + Sized {
+    /// Reads the register at `register` and interprets it as `Self`.
+    fn read_from<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+    ) -> Result<Self, Error<IO::Error>>
+    where
+        IO: Read + Write;
+
+    /// Writes `value` to the register at `register`.
+    fn write_to<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+        value: Self,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u32 {}
+    impl Sealed for i32 {}
+    impl Sealed for f32 {}
+}
+
+impl RegisterValue for u32 {
+    fn read_from<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+    ) -> Result<Self, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.read_u32(register)
+    }
+
+    fn write_to<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+        value: Self,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.write_u32(register, value)
+    }
+}
+
+impl RegisterValue for i32 {
+    fn read_from<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+    ) -> Result<Self, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.read_i32(register)
+    }
+
+    fn write_to<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+        value: Self,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.write_i32(register, value)
+    }
+}
+
+impl RegisterValue for f32 {
+    fn read_from<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+    ) -> Result<Self, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.read_f32(register)
+    }
+
+    fn write_to<IO, const BIG_ENDIAN: bool>(
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        register: u16,
+        value: Self,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.write_f32(register, value)
+    }
+}
+
+impl<IO, const BIG_ENDIAN: bool> UrapPrimary<IO, 4, BIG_ENDIAN>
+where
+    IO: Read + Write,
+{
+    /// Reads the register identified by `id`, typed by `T`.
+    pub fn read<T: RegisterValue>(&mut self, id: RegisterId<T>) -> Result<T, Error<IO::Error>> {
+        T::read_from(self, id.index())
+    }
+
+    /// Writes `value` to the register identified by `id`.
+    pub fn write<T: RegisterValue>(
+        &mut self,
+        id: RegisterId<T>,
+        value: T,
+    ) -> Result<(), Error<IO::Error>> {
+        T::write_to(self, id.index(), value)
+    }
+}
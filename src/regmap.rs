@@ -0,0 +1,183 @@
+//! [`urap_registers!`], a macro for declaring a register map once and
+//! generating everything that otherwise has to be kept in sync by hand
+//! between firmware and host code: each register's index, the
+//! `write_protect` array passed to [`crate::UrapSecondary::new`], typed
+//! read/write accessors for the primary side, and the register count.
+
+/// Declares a register map as a set of marker types, one per register.
+///
+/// ```
+/// use urap::urap_registers;
+///
+/// urap_registers! {
+///     pub mod regs {
+///         pub Setpoint: 0, f32, false;
+///         pub Status: 1, u32, true;
+///         pub Calibration: 2, raw, false;
+///     }
+/// }
+///
+/// // `regs::REGCNT` and `regs::WRITE_PROTECT` feed straight into
+/// // `UrapSecondary::new`:
+/// let secondary = urap::UrapSecondary::<{ regs::REGCNT }>::new(
+///     [[0u8; 4]; regs::REGCNT],
+///     regs::WRITE_PROTECT,
+/// );
+/// # let _ = secondary;
+///
+/// // each register name is a marker type with an `INDEX` constant and
+/// // typed `read`/`write` accessors for `UrapPrimary<IO, 4, _>`:
+/// let _ = regs::Setpoint::INDEX;
+/// let _ = regs::Calibration::INDEX;
+/// ```
+///
+/// Each entry is `Name: index, type, write_protected;`, where `type` is
+/// `u32`, `i32`, `f32`, or `raw` (a bare `[u8; 4]`, for registers with no
+/// natural numeric interpretation). Only `WIDTH == 4` is supported,
+/// matching [`crate::UrapPrimary`]'s own typed accessors
+/// (`read_u32`/`read_i32`/`read_f32`), which this macro calls into.
+#[macro_export]
+macro_rules! urap_registers {
+    ($vis:vis mod $mod_name:ident { $($reg_vis:vis $name:ident : $index:literal, $ty:ident, $protected:literal;)+ }) => {
+        $vis mod $mod_name {
+            #![allow(dead_code)]
+
+            /// Number of registers declared in this map.
+            pub const REGCNT: usize = $crate::urap_registers!(@count $($name)+);
+
+            /// Write-protect flags in declaration order, ready to pass
+            /// to [`$crate::UrapSecondary::new`].
+            pub const WRITE_PROTECT: [bool; REGCNT] = [$($protected),+];
+
+            $(
+                $crate::urap_registers!(@register $reg_vis $name, $index, $ty);
+            )+
+        }
+    };
+
+    (@count $($name:ident)+) => {
+        [$(stringify!($name)),+].len()
+    };
+
+    (@register $vis:vis $name:ident, $index:literal, u32) => {
+        $vis struct $name;
+
+        impl $name {
+            /// This register's index in the map.
+            pub const INDEX: u16 = $index;
+
+            /// Reads this register and interprets it as a `u32`.
+            pub fn read<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+            ) -> ::core::result::Result<u32, $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.read_u32(Self::INDEX)
+            }
+
+            /// Writes this register from a `u32`.
+            pub fn write<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                value: u32,
+            ) -> ::core::result::Result<(), $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.write_u32(Self::INDEX, value)
+            }
+        }
+    };
+
+    (@register $vis:vis $name:ident, $index:literal, i32) => {
+        $vis struct $name;
+
+        impl $name {
+            /// This register's index in the map.
+            pub const INDEX: u16 = $index;
+
+            /// Reads this register and interprets it as an `i32`.
+            pub fn read<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+            ) -> ::core::result::Result<i32, $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.read_i32(Self::INDEX)
+            }
+
+            /// Writes this register from an `i32`.
+            pub fn write<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                value: i32,
+            ) -> ::core::result::Result<(), $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.write_i32(Self::INDEX, value)
+            }
+        }
+    };
+
+    (@register $vis:vis $name:ident, $index:literal, f32) => {
+        $vis struct $name;
+
+        impl $name {
+            /// This register's index in the map.
+            pub const INDEX: u16 = $index;
+
+            /// Reads this register and interprets it as an `f32`.
+            pub fn read<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+            ) -> ::core::result::Result<f32, $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.read_f32(Self::INDEX)
+            }
+
+            /// Writes this register from an `f32`.
+            pub fn write<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                value: f32,
+            ) -> ::core::result::Result<(), $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.write_f32(Self::INDEX, value)
+            }
+        }
+    };
+
+    (@register $vis:vis $name:ident, $index:literal, raw) => {
+        $vis struct $name;
+
+        impl $name {
+            /// This register's index in the map.
+            pub const INDEX: u16 = $index;
+
+            /// Reads this register's raw bytes.
+            pub fn read<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+            ) -> ::core::result::Result<[u8; 4], $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                let mut data = [[0u8; 4]; 1];
+                primary.read_4u8(Self::INDEX, &mut data)?;
+                Ok(data[0])
+            }
+
+            /// Writes this register's raw bytes.
+            pub fn write<IO, const BIG_ENDIAN: bool>(
+                primary: &mut $crate::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                value: [u8; 4],
+            ) -> ::core::result::Result<(), $crate::Error<IO::Error>>
+            where
+                IO: $crate::embedded_io::Read + $crate::embedded_io::Write,
+            {
+                primary.write_4u8(Self::INDEX, &[value])
+            }
+        }
+    };
+}
@@ -0,0 +1,191 @@
+//! Lock-free single-producer/single-consumer byte queue, for feeding
+//! [`UrapSecondary::poll`] from a UART RX interrupt without ever blocking
+//! inside the handler.
+//!
+//! [`RingBuffer::split`] hands out a [`RingProducer`] (pushed from the
+//! ISR, never blocks, drops a byte and reports [`QueueFull`] on overrun
+//! instead) and a [`RingConsumer`] (drained from the main loop). The two
+//! halves only ever touch `head`/`tail` from one side each, so `push` and
+//! `pop` can run concurrently with nothing but atomics: the producer
+//! writes the byte first, then publishes it by storing `head` with
+//! [`Ordering::Release`]; the consumer loads `head` with
+//! [`Ordering::Acquire`] before reading the byte that store made visible.
+//! `tail` is published back to the producer the same way, so it never
+//! overwrites a byte the consumer hasn't read yet.
+//!
+//! [`try_poll`] bridges the consumer onto [`UrapSecondary::poll`]'s
+//! blocking `embedded_io::Read`, same as [`crate::nb::try_poll`]: it
+//! returns `None` without touching `secondary` if the queue is currently
+//! empty, but once a request has started arriving it busy-polls the
+//! queue for the rest of the packet, on the assumption that the main
+//! loop calling this isn't doing anything else worth not blocking on.
+//! Resuming a partially-arrived request across separate `try_poll` calls
+//! would need [`UrapSecondary::poll`] itself to carry state between
+//! calls, which it doesn't yet.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use embedded_io::Write as IoWrite;
+
+use crate::{
+    Error, PollOutcome, ReadProtect, RegisterStore, UrapSecondary, WriteHook, WriteProtect,
+};
+
+/// A lock-free SPSC byte queue of capacity `N - 1` (one slot is reserved
+/// so a full queue can be told apart from an empty one without a
+/// separate counter). `N` must be at least 2.
+pub struct RingBuffer<const N: usize> {
+    buf: [UnsafeCell<u8>; N],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// SAFETY: `buf` is only ever accessed at `head` by the producer and at
+// `tail` by the consumer, and the two never overlap - see the module
+// documentation for the acquire/release pairing that makes that true.
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> RingBuffer<N> {
+    /// An empty queue.
+    pub const fn new() -> Self {
+        Self {
+            buf: [const { UnsafeCell::new(0) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Splits the queue into its producer and consumer halves. Takes
+    /// `&mut self` purely so the borrow checker enforces there's only
+    /// ever one of each outstanding at a time, not because either half
+    /// needs exclusive access at runtime.
+    pub fn split(&mut self) -> (RingProducer<'_, N>, RingConsumer<'_, N>) {
+        (RingProducer { ring: self }, RingConsumer { ring: self })
+    }
+}
+
+/// The queue was full; the byte was not enqueued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+/// The ISR-safe half of a [`RingBuffer`]. `push` never blocks or spins,
+/// so it's safe to call from inside a UART RX interrupt handler.
+pub struct RingProducer<'a, const N: usize> {
+    ring: &'a RingBuffer<N>,
+}
+
+impl<const N: usize> RingProducer<'_, N> {
+    /// Enqueues `byte`, or reports [`QueueFull`] if the consumer hasn't
+    /// drained enough of the queue - the caller's job to count as an
+    /// overrun, since a ring buffer this small can't.
+    pub fn push(&self, byte: u8) -> Result<(), QueueFull> {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        let next = (head + 1) % N;
+        if next == self.ring.tail.load(Ordering::Acquire) {
+            return Err(QueueFull);
+        }
+        // SAFETY: only the producer ever writes `buf[head]`, and the
+        // consumer won't read it until the `Release` store below
+        // publishes this slot.
+        unsafe {
+            *self.ring.buf[head].get() = byte;
+        }
+        self.ring.head.store(next, Ordering::Release);
+        Ok(())
+    }
+}
+
+/// The main-loop-side half of a [`RingBuffer`].
+pub struct RingConsumer<'a, const N: usize> {
+    ring: &'a RingBuffer<N>,
+}
+
+impl<const N: usize> RingConsumer<'_, N> {
+    /// Dequeues the oldest byte, or `None` if the queue is empty.
+    pub fn pop(&self) -> Option<u8> {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        if tail == self.ring.head.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: only the consumer ever reads `buf[tail]`, and the
+        // producer won't overwrite it until the `Release` store below
+        // publishes this slot as free again.
+        let byte = unsafe { *self.ring.buf[tail].get() };
+        self.ring.tail.store((tail + 1) % N, Ordering::Release);
+        Some(byte)
+    }
+
+    /// `true` if there's nothing queued right now.
+    pub fn is_empty(&self) -> bool {
+        self.ring.tail.load(Ordering::Relaxed) == self.ring.head.load(Ordering::Acquire)
+    }
+}
+
+/// Bridges a [`RingConsumer`] (for reads) and a blocking
+/// `embedded_io::Write` transport (for the response) onto
+/// [`UrapSecondary::poll`]'s combined `Read`+`Write` IO.
+struct RingBridge<'a, 'b, W, const N: usize> {
+    consumer: &'a RingConsumer<'b, N>,
+    writer: &'a mut W,
+}
+
+impl<W: embedded_io::ErrorType, const N: usize> embedded_io::ErrorType
+    for RingBridge<'_, '_, W, N>
+{
+    type Error = W::Error;
+}
+
+impl<W: embedded_io::ErrorType, const N: usize> embedded_io::Read for RingBridge<'_, '_, W, N> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if let Some(byte) = self.consumer.pop() {
+                buf[0] = byte;
+                return Ok(1);
+            }
+        }
+    }
+}
+
+impl<W: IoWrite, const N: usize> embedded_io::Write for RingBridge<'_, '_, W, N> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush()
+    }
+}
+
+/// Services one request from `consumer`/`writer` without touching
+/// `secondary` if the queue is currently empty; see the module
+/// documentation for exactly how much blocking the caller is still on
+/// the hook for once a request starts arriving.
+pub fn try_poll<const REGCNT: usize, const WIDTH: usize, P, R, H, St, const N: usize, W>(
+    secondary: &mut UrapSecondary<REGCNT, WIDTH, P, R, H, St>,
+    consumer: &RingConsumer<'_, N>,
+    writer: &mut W,
+) -> Option<Result<PollOutcome, Error<W::Error>>>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+    St: RegisterStore<WIDTH>,
+    W: IoWrite,
+{
+    if consumer.is_empty() {
+        return None;
+    }
+
+    let mut bridge = RingBridge { consumer, writer };
+    Some(secondary.poll(&mut bridge))
+}
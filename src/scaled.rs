@@ -0,0 +1,72 @@
+//! [`ScaledRegister`], a linear `offset`/`gain` transform between raw
+//! register counts and engineering units, the way a PLC tag database
+//! scales an analog input/output channel.
+
+use embedded_io::{Read, Write};
+
+use crate::{Error, UrapPrimary};
+
+/// Converts a register's raw `u32` count to and from engineering units
+/// via `engineering = raw * gain + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaledRegister {
+    register: u16,
+    offset: f32,
+    gain: f32,
+}
+
+impl ScaledRegister {
+    /// Scales `register` by `gain`, offset by `offset`.
+    pub fn new(register: u16, offset: f32, gain: f32) -> Self {
+        Self { register, offset, gain }
+    }
+
+    /// The register this scale applies to.
+    pub fn register(&self) -> u16 {
+        self.register
+    }
+
+    /// Converts a raw count to engineering units.
+    pub fn to_engineering(&self, raw: u32) -> f32 {
+        raw as f32 * self.gain + self.offset
+    }
+
+    /// Converts an engineering-unit value back to a raw count, rounded
+    /// to the nearest integer and clamped to `u32`'s range.
+    pub fn to_raw(&self, value: f32) -> u32 {
+        let raw = (value - self.offset) / self.gain;
+        // `f32::round` needs `std`; round half away from zero by hand so
+        // this works on `no_std` targets too.
+        let rounded = if raw >= 0.0 { raw + 0.5 } else { raw - 0.5 };
+        if rounded <= 0.0 {
+            0
+        } else if rounded >= u32::MAX as f32 {
+            u32::MAX
+        } else {
+            rounded as u32
+        }
+    }
+
+    /// Reads the register and converts it to engineering units.
+    pub fn read<IO, const BIG_ENDIAN: bool>(
+        &self,
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+    ) -> Result<f32, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        Ok(self.to_engineering(primary.read_u32(self.register)?))
+    }
+
+    /// Converts `value` to a raw count and writes it to the register.
+    pub fn write<IO, const BIG_ENDIAN: bool>(
+        &self,
+        primary: &mut UrapPrimary<IO, 4, BIG_ENDIAN>,
+        value: f32,
+    ) -> Result<(), Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        primary.write_u32(self.register, self.to_raw(value))
+    }
+}
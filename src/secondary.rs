@@ -0,0 +1,576 @@
+//! The secondary (device) side of a URAP link: serves reads and writes
+//! against an in-memory register map.
+
+use embedded_io::{Read, Write};
+
+use crate::{
+    crc16, crc16_update, Error, NakCode, NoWriteHook, NoWriteProtect, ReadProtect, RegisterStore,
+    Stats, WriteHook, WriteProtect, OP_ACK, OP_NAK, OP_PING, OP_READ, OP_WRITE,
+    STATS_REGISTER_COUNT, URAP_HEADER_SIZE,
+};
+
+/// What [`UrapSecondary::poll`] did with the request it just serviced.
+///
+/// Lets callers react to a poll (e.g. trigger a side effect on a write,
+/// or count NAKs) without re-parsing the packet themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PollOutcome {
+    /// A read request for `count` registers starting at `register` was
+    /// serviced. `nak` is `Some` if it was rejected instead of ACKed.
+    Read {
+        /// First register requested.
+        register: u16,
+        /// Number of registers requested.
+        count: u8,
+        /// Rejection reason, if the read was NAKed.
+        nak: Option<NakCode>,
+    },
+    /// A write request for `count` registers starting at `register` was
+    /// serviced. `nak` is `Some` if it was rejected (and so not applied)
+    /// instead of ACKed.
+    Write {
+        /// First register targeted.
+        register: u16,
+        /// Number of registers targeted.
+        count: u8,
+        /// Rejection reason, if the write was NAKed.
+        nak: Option<NakCode>,
+    },
+    /// A liveness probe ([`crate::OP_PING`]) was serviced. `nak` is
+    /// `Some` only if the probe's CRC was bad; pinging never touches the
+    /// register map.
+    Ping {
+        /// Rejection reason, if the probe was NAKed.
+        nak: Option<NakCode>,
+    },
+    /// The request's opcode wasn't `OP_READ`, `OP_WRITE`, or `OP_PING`;
+    /// it was always rejected with `nak`.
+    UnknownOp {
+        /// Always [`NakCode::BadOp`] today; kept as a field rather than a
+        /// unit variant so this can grow without another breaking change.
+        nak: NakCode,
+    },
+}
+
+/// A register map with `REGCNT` registers of `WIDTH` bytes each, served
+/// to a [`crate::UrapPrimary`] over any [`Read`] + [`Write`] transport.
+///
+/// `P` decides which registers reject writes; it defaults to
+/// `[bool; REGCNT]` for the common case, but [`crate::WriteProtectRanges`]
+/// or a custom [`WriteProtect`] impl can be used for large maps where one
+/// bool per register would be wasteful. `R` decides which registers
+/// reject reads (e.g. secret pairing keys that may be written but never
+/// read back) and defaults to [`NoWriteProtect`], which protects nothing.
+/// `H` is run on every accepted write and defaults to [`NoWriteHook`];
+/// see [`Self::with_write_hook`]. `S` is where the register values
+/// actually live and defaults to `[[u8; WIDTH]; REGCNT]`; see
+/// [`Self::with_store`] to back registers with something other than RAM.
+/// `MAXCOUNT` caps how many registers a single request may touch and
+/// defaults to [`crate::URAP_COUNT_MAX`]; a smaller value shrinks the stack
+/// buffer `handle_write` needs to hold an in-flight write, at the cost of
+/// NAKing requests for more registers than that with
+/// [`NakCode::CountTooLarge`].
+///
+/// Packet/error counters are always maintained (see [`Self::stats`]);
+/// [`Self::with_published_stats`] additionally exposes them as a
+/// reserved block of read-only registers just past the end of the normal
+/// register range.
+pub struct UrapSecondary<
+    const REGCNT: usize,
+    const WIDTH: usize = 4,
+    P = [bool; REGCNT],
+    R = NoWriteProtect,
+    H = NoWriteHook,
+    S = [[u8; WIDTH]; REGCNT],
+    const MAXCOUNT: usize = { crate::URAP_COUNT_MAX as usize },
+> {
+    store: S,
+    write_protect: P,
+    read_protect: R,
+    write_hook: H,
+    stats: Stats,
+    publish_stats: bool,
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, const MAXCOUNT: usize>
+    UrapSecondary<REGCNT, WIDTH, P, NoWriteProtect, NoWriteHook, [[u8; WIDTH]; REGCNT], MAXCOUNT>
+where
+    P: WriteProtect,
+{
+    /// Builds a secondary over `regs`, rejecting writes to any register
+    /// for which `write_protect` reports `true`. No registers are
+    /// read-protected; use [`Self::with_read_protect`] to add some.
+    pub fn new(regs: [[u8; WIDTH]; REGCNT], write_protect: P) -> Self {
+        Self {
+            store: regs,
+            write_protect,
+            read_protect: NoWriteProtect,
+            write_hook: NoWriteHook,
+            stats: Stats::new(),
+            publish_stats: false,
+        }
+    }
+}
+
+impl<'a, const REGCNT: usize, const WIDTH: usize, const MAXCOUNT: usize>
+    UrapSecondary<REGCNT, WIDTH, &'a [bool], NoWriteProtect, NoWriteHook, &'a mut [[u8; WIDTH]], MAXCOUNT>
+{
+    /// Builds a secondary over `regs` and `write_protect` borrowed
+    /// slices instead of a `[[u8; WIDTH]; REGCNT]` array, for a register
+    /// count loaded from configuration rather than known at compile
+    /// time. `REGCNT` goes unused here (it only drives `P`/`S`'s
+    /// defaults elsewhere) — any value, including `0`, works.
+    ///
+    /// Panics if `regs` and `write_protect` have different lengths.
+    pub fn from_slices(regs: &'a mut [[u8; WIDTH]], write_protect: &'a [bool]) -> Self {
+        assert_eq!(
+            regs.len(),
+            write_protect.len(),
+            "regs.len() must match write_protect.len()"
+        );
+        Self {
+            store: regs,
+            write_protect,
+            read_protect: NoWriteProtect,
+            write_hook: NoWriteHook,
+            stats: Stats::new(),
+            publish_stats: false,
+        }
+    }
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, const MAXCOUNT: usize>
+    UrapSecondary<REGCNT, WIDTH, P, R, NoWriteHook, [[u8; WIDTH]; REGCNT], MAXCOUNT>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+{
+    /// Builds a secondary with both write- and read-protect lists, for
+    /// registers (e.g. pairing keys) that may be written but never read
+    /// back over the bus.
+    pub fn with_read_protect(
+        regs: [[u8; WIDTH]; REGCNT],
+        write_protect: P,
+        read_protect: R,
+    ) -> Self {
+        Self {
+            store: regs,
+            write_protect,
+            read_protect,
+            write_hook: NoWriteHook,
+            stats: Stats::new(),
+            publish_stats: false,
+        }
+    }
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, H, const MAXCOUNT: usize>
+    UrapSecondary<REGCNT, WIDTH, P, R, H, [[u8; WIDTH]; REGCNT], MAXCOUNT>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+{
+    /// Direct access to the backing register array. Only available when
+    /// registers are stored in RAM; not applicable to a secondary built
+    /// over a custom [`RegisterStore`] via [`Self::with_store`].
+    pub fn regs(&self) -> &[[u8; WIDTH]; REGCNT] {
+        &self.store
+    }
+
+    /// Direct mutable access to the backing register array, for
+    /// application code that updates registers outside of URAP traffic.
+    pub fn regs_mut(&mut self) -> &mut [[u8; WIDTH]; REGCNT] {
+        &mut self.store
+    }
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, H, S, const MAXCOUNT: usize>
+    UrapSecondary<REGCNT, WIDTH, P, R, H, S, MAXCOUNT>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+    S: RegisterStore<WIDTH>,
+{
+    /// Replaces the write hook, which runs on every write the secondary
+    /// accepts (after protection and CRC checks pass), just before the ACK
+    /// goes out. Useful for detecting setpoint changes without diffing the
+    /// whole register map every loop.
+    pub fn with_write_hook<H2>(
+        self,
+        write_hook: H2,
+    ) -> UrapSecondary<REGCNT, WIDTH, P, R, H2, S, MAXCOUNT>
+    where
+        H2: WriteHook<WIDTH>,
+    {
+        UrapSecondary {
+            store: self.store,
+            write_protect: self.write_protect,
+            read_protect: self.read_protect,
+            write_hook,
+            stats: self.stats,
+            publish_stats: self.publish_stats,
+        }
+    }
+
+    /// Replaces the register store, e.g. to back a range of registers
+    /// with a live peripheral reading instead of RAM. See
+    /// [`RegisterStore`].
+    pub fn with_store<S2>(self, store: S2) -> UrapSecondary<REGCNT, WIDTH, P, R, H, S2, MAXCOUNT>
+    where
+        S2: RegisterStore<WIDTH>,
+    {
+        UrapSecondary {
+            store,
+            write_protect: self.write_protect,
+            read_protect: self.read_protect,
+            write_hook: self.write_hook,
+            stats: self.stats,
+            publish_stats: self.publish_stats,
+        }
+    }
+
+    /// Exposes [`Self::stats`] as a block of `STATS_REGISTER_COUNT`
+    /// read-only registers immediately past the end of the normal
+    /// register range (i.e. starting at `self.store.len()`), so a
+    /// primary can poll them like any other register without a
+    /// host-side API.
+    pub fn with_published_stats(mut self) -> Self {
+        self.publish_stats = true;
+        self
+    }
+
+    /// Packet/error counters for every request serviced so far.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Direct mutable access to the write hook, e.g. to drain a
+    /// [`crate::DirtyTracker`] installed via [`Self::with_write_hook`].
+    pub fn write_hook_mut(&mut self) -> &mut H {
+        &mut self.write_hook
+    }
+
+    /// Direct mutable access to the register store, for reaching
+    /// functionality beyond [`RegisterStore`] itself (e.g.
+    /// [`crate::ShadowedRegisters::begin_update`]) on a custom store
+    /// installed via [`Self::with_store`].
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    pub(crate) fn is_write_protected(&self, register: u16, count: u8) -> bool {
+        (register..register + count as u16).any(|r| self.write_protect.is_protected(r))
+    }
+
+    pub(crate) fn is_read_protected(&self, register: u16, count: u8) -> bool {
+        (register..register + count as u16).any(|r| self.read_protect.is_protected(r))
+    }
+
+    /// Services a single request read from `io`, writing the response
+    /// back to `io`.
+    ///
+    /// Blocks until a full request has been received. Returns a
+    /// [`PollOutcome`] describing what the request was and whether it was
+    /// ACKed or NAKed; transport-level failures are the only `Err`s.
+    pub fn poll<IO>(&mut self, io: &mut IO) -> Result<PollOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        read_exact(io, &mut header)?;
+        self.stats.packets += 1;
+        self.stats.record_bytes_in(URAP_HEADER_SIZE as u64);
+
+        let op = header[0];
+        let register = u16::from_le_bytes([header[1], header[2]]);
+        let count = header[3];
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("urap_poll", op, register, count).entered();
+
+        let result = match op {
+            OP_READ => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                self.handle_read(io, &header, register, count, crc_bytes)
+            }
+            OP_WRITE => {
+                // Sized for the largest write this secondary can apply;
+                // an oversize `count` is drained into a disposable
+                // scratch word instead, since it's always going to be
+                // NAKed with `CountTooLarge` and never reaches
+                // `handle_write`'s apply loop.
+                let mut words = [[0u8; WIDTH]; MAXCOUNT];
+                if count as usize > MAXCOUNT {
+                    let mut scratch = [0u8; WIDTH];
+                    for _ in 0..count {
+                        read_exact(io, &mut scratch)?;
+                    }
+                } else {
+                    for word in words.iter_mut().take(count as usize) {
+                        read_exact(io, word)?;
+                    }
+                }
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                self.handle_write(io, &header, register, count, &words, crc_bytes)
+            }
+            OP_PING => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                self.handle_ping(io, &header, crc_bytes)
+            }
+            _ => {
+                let mut crc_bytes = [0u8; 2];
+                read_exact(io, &mut crc_bytes)?;
+                self.handle_unknown_op(io, crc_bytes)
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(outcome) => tracing::debug!(?outcome, "poll serviced"),
+            Err(err) => tracing::debug!(?err, "poll failed"),
+        }
+        #[cfg(feature = "log")]
+        match &result {
+            Ok(PollOutcome::Read { register, count, nak: Some(code) }) => {
+                log::warn!("read of {count} register(s) at {register} naked: {code:?}")
+            }
+            Ok(PollOutcome::Write { register, count, nak: Some(code) }) => {
+                log::warn!("write of {count} register(s) at {register} naked: {code:?}")
+            }
+            Ok(PollOutcome::UnknownOp { nak: code }) => {
+                log::warn!("unknown opcode naked: {code:?}")
+            }
+            Ok(outcome) => log::trace!("poll serviced: {outcome:?}"),
+            Err(err) => log::debug!("poll failed: {err:?}"),
+        }
+        #[cfg(feature = "defmt")]
+        match &result {
+            Ok(outcome) => defmt::trace!("poll serviced: {}", outcome),
+            Err(_err) => defmt::warn!("poll failed"),
+        }
+
+        result
+    }
+
+    /// Computes and sends the response to a read request whose header
+    /// and trailing CRC have already been read off `io` by the caller
+    /// (either [`Self::poll`]'s blocking loop, or
+    /// [`crate::nonblocking::NonBlockingSecondary`]'s resumable one).
+    pub(crate) fn handle_read<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+        crc_bytes: [u8; 2],
+    ) -> Result<PollOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        self.stats.reads += 1;
+        self.stats.record_bytes_in(2);
+        if crc16(header) != u16::from_le_bytes(crc_bytes) {
+            self.stats.crc_errors += 1;
+            let nak = self.respond_nak(io, NakCode::BadCrc)?;
+            return Ok(PollOutcome::Read { register, count, nak: Some(nak) });
+        }
+
+        if count as usize > MAXCOUNT {
+            let nak = self.respond_nak(io, NakCode::CountTooLarge)?;
+            return Ok(PollOutcome::Read { register, count, nak: Some(nak) });
+        }
+
+        let store_len = self.store.len() as u16;
+        let visible_len = if self.publish_stats {
+            store_len.saturating_add(STATS_REGISTER_COUNT)
+        } else {
+            store_len
+        };
+        if register as usize + count as usize > visible_len as usize {
+            let nak = self.respond_nak(io, NakCode::IndexOutOfBounds)?;
+            return Ok(PollOutcome::Read { register, count, nak: Some(nak) });
+        }
+        let protect_count = (count as u16).min(store_len.saturating_sub(register)) as u8;
+        if protect_count > 0 && self.is_read_protected(register, protect_count) {
+            let nak = self.respond_nak(io, NakCode::IndexReadProtected)?;
+            return Ok(PollOutcome::Read { register, count, nak: Some(nak) });
+        }
+
+        // Streamed straight from `self.store`/`self.stats` word by word,
+        // with the CRC folded in incrementally as each word goes out -
+        // there's no intermediate buffer here to hold the response, so
+        // this scales to `URAP_COUNT_MAX` without growing the stack frame.
+        let mut crc_state = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        for i in 0..count as u16 {
+            let reg = register + i;
+            let word = if reg < store_len {
+                self.store.read(reg)
+            } else {
+                self.stats.register_word::<WIDTH>((reg - store_len) as usize)
+            };
+            io.write_all(&word).map_err(Error::Io)?;
+            crc_state = crc16_update(crc_state, &word);
+        }
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        self.stats.record_bytes_out(1 + count as u64 * WIDTH as u64 + 2);
+        Ok(PollOutcome::Read { register, count, nak: None })
+    }
+
+    /// Computes and sends the response to a write request whose header,
+    /// data words, and trailing CRC have already been read off `io` by
+    /// the caller (either [`Self::poll`]'s blocking loop, or
+    /// [`crate::nonblocking::NonBlockingSecondary`]'s resumable one).
+    /// `words[..count]` holds the data; it's ignored when `count`
+    /// exceeds `MAXCOUNT`, since the caller can't have populated it
+    /// safely in that case and this rejects before ever reading it.
+    pub(crate) fn handle_write<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+        words: &[[u8; WIDTH]; MAXCOUNT],
+        crc_bytes: [u8; 2],
+    ) -> Result<PollOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        self.stats.writes += 1;
+
+        let store_len = self.store.len() as u16;
+        let visible_len = if self.publish_stats {
+            store_len.saturating_add(STATS_REGISTER_COUNT)
+        } else {
+            store_len
+        };
+
+        // Checked against the header alone, so an oversize `count` is
+        // rejected without ever touching `words`.
+        if count as usize > MAXCOUNT || register as usize + count as usize > visible_len as usize {
+            self.stats.record_bytes_in(count as u64 * WIDTH as u64 + 2);
+
+            let code = if count as usize > MAXCOUNT {
+                NakCode::CountTooLarge
+            } else {
+                NakCode::IndexOutOfBounds
+            };
+            let nak = self.respond_nak(io, code)?;
+            return Ok(PollOutcome::Write { register, count, nak: Some(nak) });
+        }
+
+        // The reserved stats block (if published) is read-only; any write
+        // that reaches into it is rejected rather than handed to
+        // `is_write_protected`, whose implementations aren't guaranteed
+        // to accept out-of-range registers.
+        let targets_stats_block = register as usize + count as usize > store_len as usize;
+        let protected = targets_stats_block || self.is_write_protected(register, count);
+
+        // Folded over `words` first, with nothing applied to the store
+        // yet, so a write that fails its CRC check never mutates live
+        // register state - only once both checks below have passed is
+        // `words` applied.
+        let mut crc_state = crc16(header);
+        for word in words.iter().take(count as usize) {
+            crc_state = crc16_update(crc_state, word);
+        }
+
+        self.stats.record_bytes_in(count as u64 * WIDTH as u64 + 2);
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            self.stats.crc_errors += 1;
+            let nak = self.respond_nak(io, NakCode::BadCrc)?;
+            return Ok(PollOutcome::Write { register, count, nak: Some(nak) });
+        }
+        if protected {
+            let nak = self.respond_nak(io, NakCode::IndexWriteProtected)?;
+            return Ok(PollOutcome::Write { register, count, nak: Some(nak) });
+        }
+
+        for (i, word) in words.iter().take(count as usize).enumerate() {
+            self.store.write(register + i as u16, *word);
+        }
+        self.write_hook.on_write(register, &words[..count as usize]);
+
+        let ack_crc = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        io.write_all(&ack_crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        self.stats.record_bytes_out(1 + 2);
+        Ok(PollOutcome::Write { register, count, nak: None })
+    }
+
+    /// Computes and sends the response to a liveness probe whose header
+    /// and trailing CRC have already been read off `io` by the caller.
+    pub(crate) fn handle_ping<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        crc_bytes: [u8; 2],
+    ) -> Result<PollOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        self.stats.record_bytes_in(2);
+        if crc16(header) != u16::from_le_bytes(crc_bytes) {
+            self.stats.crc_errors += 1;
+            let nak = self.respond_nak(io, NakCode::BadCrc)?;
+            return Ok(PollOutcome::Ping { nak: Some(nak) });
+        }
+
+        let ack_crc = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        io.write_all(&ack_crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        self.stats.record_bytes_out(1 + 2);
+        Ok(PollOutcome::Ping { nak: None })
+    }
+
+    /// Rejects a request whose opcode wasn't `OP_READ`, `OP_WRITE`, or
+    /// `OP_PING`, after the caller has already drained its trailing CRC
+    /// off `io`.
+    pub(crate) fn handle_unknown_op<IO>(
+        &mut self,
+        io: &mut IO,
+        _crc_bytes: [u8; 2],
+    ) -> Result<PollOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        self.stats.record_bytes_in(2);
+        let nak = self.respond_nak(io, NakCode::BadOp)?;
+        Ok(PollOutcome::UnknownOp { nak })
+    }
+
+    fn respond_nak<IO>(&mut self, io: &mut IO, code: NakCode) -> Result<NakCode, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        self.stats.record_nak(code);
+        let payload = [OP_NAK, code as u8];
+        let crc = crc16(&payload);
+        io.write_all(&payload).map_err(Error::Io)?;
+        io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        self.stats.record_bytes_out(4);
+        Ok(code)
+    }
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,416 @@
+//! Sequence-numbered requests, so a primary retrying over a lossy link
+//! can tell a genuine answer to the current attempt apart from a late
+//! response to an earlier, already-abandoned one.
+//!
+//! URAP stays strictly request/response - [`SeqPrimary::read_4u8_seq`]/
+//! [`SeqPrimary::write_4u8_seq`] tag the request with a sequence byte the
+//! caller picks (e.g. bumped on every retry), and [`SeqSecondary`] echoes
+//! it back verbatim in the response. If the echoed byte doesn't match
+//! what was sent, that's a response to a different request - most likely
+//! one a timed-out retry loop already gave up on - and is reported as
+//! [`Error::SeqMismatch`] rather than being mistaken for a real answer.
+//! `OP_READ`/`OP_WRITE` (unsequenced) are forwarded to the wrapped
+//! secondary unchanged.
+
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::{
+    crc16, crc16_update, Error, NakCode, NoWriteHook, NoWriteProtect, PollOutcome, ReadProtect,
+    WriteHook, WriteProtect, OP_ACK, OP_NAK, OP_READ, OP_READ_SEQ, OP_WRITE, OP_WRITE_SEQ,
+    URAP_COUNT_MAX, URAP_HEADER_SIZE,
+};
+
+/// What [`SeqSecondary::poll`] did with the request it just serviced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqOutcome {
+    /// An `OP_READ` or `OP_WRITE`, forwarded to the wrapped secondary.
+    Forwarded(PollOutcome),
+    /// An `OP_READ_SEQ` request was serviced.
+    ReadSeq {
+        /// First register requested.
+        register: u16,
+        /// Number of registers requested.
+        count: u8,
+        /// The sequence byte the request carried, echoed back unchanged.
+        seq: u8,
+        /// Rejection reason, if the read was NAKed.
+        nak: Option<NakCode>,
+    },
+    /// An `OP_WRITE_SEQ` request was serviced.
+    WriteSeq {
+        /// First register targeted.
+        register: u16,
+        /// Number of registers targeted.
+        count: u8,
+        /// The sequence byte the request carried, echoed back unchanged.
+        seq: u8,
+        /// Rejection reason, if the write was NAKed.
+        nak: Option<NakCode>,
+    },
+}
+
+struct HeaderPeek<'a, IO> {
+    header: [u8; URAP_HEADER_SIZE],
+    pos: usize,
+    inner: &'a mut IO,
+}
+
+impl<IO: ErrorType> ErrorType for HeaderPeek<'_, IO> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read> Read for HeaderPeek<'_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.pos < self.header.len() {
+            let n = buf.len().min(self.header.len() - self.pos);
+            buf[..n].copy_from_slice(&self.header[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<IO: Write> Write for HeaderPeek<'_, IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+/// Wraps a [`crate::UrapSecondary`] with support for [`crate::OP_READ_SEQ`]
+/// and [`crate::OP_WRITE_SEQ`] requests. `OP_READ`/`OP_WRITE` requests are
+/// forwarded to the inner secondary unchanged.
+pub struct SeqSecondary<const REGCNT: usize, const WIDTH: usize = 4, P = [bool; REGCNT], R = NoWriteProtect, H = NoWriteHook> {
+    inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, H>,
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, H> SeqSecondary<REGCNT, WIDTH, P, R, H>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: WriteHook<WIDTH>,
+{
+    /// Wraps `inner`.
+    pub fn new(inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, H>) -> Self {
+        Self { inner }
+    }
+
+    /// Direct access to the wrapped secondary, e.g. for
+    /// [`crate::UrapSecondary::regs`].
+    pub fn inner(&self) -> &crate::UrapSecondary<REGCNT, WIDTH, P, R, H> {
+        &self.inner
+    }
+
+    /// Direct mutable access to the wrapped secondary.
+    pub fn inner_mut(&mut self) -> &mut crate::UrapSecondary<REGCNT, WIDTH, P, R, H> {
+        &mut self.inner
+    }
+
+    /// Services a single request read from `io`, writing the response
+    /// back to `io`. Blocks until a full request has been received.
+    pub fn poll<IO>(&mut self, io: &mut IO) -> Result<SeqOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        read_exact(io, &mut header)?;
+
+        let op = header[0];
+        let register = u16::from_le_bytes([header[1], header[2]]);
+        let count = header[3];
+
+        match op {
+            OP_READ_SEQ => self.handle_read_seq(io, &header, register, count),
+            OP_WRITE_SEQ => self.handle_write_seq(io, &header, register, count),
+            OP_READ | OP_WRITE => {
+                let mut peeked = HeaderPeek { header, pos: 0, inner: io };
+                self.inner.poll(&mut peeked).map(SeqOutcome::Forwarded)
+            }
+            _ => {
+                let mut peeked = HeaderPeek { header, pos: 0, inner: io };
+                self.inner.poll(&mut peeked).map(SeqOutcome::Forwarded)
+            }
+        }
+    }
+
+    fn handle_read_seq<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+    ) -> Result<SeqOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut seq_byte = [0u8; 1];
+        read_exact(io, &mut seq_byte)?;
+        let seq = seq_byte[0];
+
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        let crc_state = crc16_update(crc16(header), &seq_byte);
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            let nak = self.respond_nak(io, NakCode::BadCrc, seq)?;
+            return Ok(SeqOutcome::ReadSeq { register, count, seq, nak: Some(nak) });
+        }
+
+        if count as u16 > URAP_COUNT_MAX {
+            let nak = self.respond_nak(io, NakCode::CountTooLarge, seq)?;
+            return Ok(SeqOutcome::ReadSeq { register, count, seq, nak: Some(nak) });
+        }
+        if register as usize + count as usize > REGCNT {
+            let nak = self.respond_nak(io, NakCode::IndexOutOfBounds, seq)?;
+            return Ok(SeqOutcome::ReadSeq { register, count, seq, nak: Some(nak) });
+        }
+        if self.inner.is_read_protected(register, count) {
+            let nak = self.respond_nak(io, NakCode::IndexReadProtected, seq)?;
+            return Ok(SeqOutcome::ReadSeq { register, count, seq, nak: Some(nak) });
+        }
+
+        let mut crc_state = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        crc_state = crc16_update(crc_state, &seq_byte);
+        io.write_all(&seq_byte).map_err(Error::Io)?;
+        for word in &self.inner.regs()[register as usize..register as usize + count as usize] {
+            io.write_all(word).map_err(Error::Io)?;
+            crc_state = crc16_update(crc_state, word);
+        }
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(SeqOutcome::ReadSeq { register, count, seq, nak: None })
+    }
+
+    fn handle_write_seq<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+    ) -> Result<SeqOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut seq_byte = [0u8; 1];
+        read_exact(io, &mut seq_byte)?;
+        let seq = seq_byte[0];
+
+        if count as u16 > URAP_COUNT_MAX || register as usize + count as usize > REGCNT {
+            let mut scratch = [0u8; WIDTH];
+            for _ in 0..count {
+                read_exact(io, &mut scratch)?;
+            }
+            let mut crc_bytes = [0u8; 2];
+            read_exact(io, &mut crc_bytes)?;
+
+            let code = if count as u16 > URAP_COUNT_MAX {
+                NakCode::CountTooLarge
+            } else {
+                NakCode::IndexOutOfBounds
+            };
+            let nak = self.respond_nak(io, code, seq)?;
+            return Ok(SeqOutcome::WriteSeq { register, count, seq, nak: Some(nak) });
+        }
+
+        let protected = self.inner.is_write_protected(register, count);
+
+        let mut written = [[0u8; WIDTH]; URAP_COUNT_MAX as usize];
+        let mut crc_state = crc16_update(crc16(header), &seq_byte);
+        for (i, slot) in written.iter_mut().enumerate().take(count as usize) {
+            let mut word = [0u8; WIDTH];
+            read_exact(io, &mut word)?;
+            crc_state = crc16_update(crc_state, &word);
+            if !protected {
+                self.inner.regs_mut()[register as usize + i] = word;
+            }
+            *slot = word;
+        }
+
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            let nak = self.respond_nak(io, NakCode::BadCrc, seq)?;
+            return Ok(SeqOutcome::WriteSeq { register, count, seq, nak: Some(nak) });
+        }
+        if protected {
+            let nak = self.respond_nak(io, NakCode::IndexWriteProtected, seq)?;
+            return Ok(SeqOutcome::WriteSeq { register, count, seq, nak: Some(nak) });
+        }
+
+        self.inner.write_hook_mut().on_write(register, &written[..count as usize]);
+
+        let ack_crc = crc16_update(crc16(&[OP_ACK]), &seq_byte);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        io.write_all(&seq_byte).map_err(Error::Io)?;
+        io.write_all(&ack_crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(SeqOutcome::WriteSeq { register, count, seq, nak: None })
+    }
+
+    fn respond_nak<IO>(&self, io: &mut IO, code: NakCode, seq: u8) -> Result<NakCode, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let payload = [OP_NAK, seq, code as u8];
+        let crc = crc16(&payload);
+        io.write_all(&payload).map_err(Error::Io)?;
+        io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+        Ok(code)
+    }
+}
+
+/// The primary side of the sequencing extension: wraps a transport the
+/// same way [`crate::UrapPrimary`] does, adding
+/// [`Self::read_4u8_seq`]/[`Self::write_4u8_seq`].
+pub struct SeqPrimary<'a, IO, const WIDTH: usize = 4> {
+    io: &'a mut IO,
+}
+
+impl<'a, IO, const WIDTH: usize> SeqPrimary<'a, IO, WIDTH>
+where
+    IO: Read + Write,
+{
+    /// Wraps an existing transport. The transport is borrowed for the
+    /// lifetime of the primary.
+    pub fn new(io: &'a mut IO) -> Self {
+        Self { io }
+    }
+
+    /// Like [`crate::UrapPrimary::read_4u8`], tagging the request with
+    /// `seq`. Returns [`Error::SeqMismatch`] instead of `Ok` if the
+    /// response echoes back a different sequence byte - it's the answer
+    /// to some other request, most likely a retry loop's earlier,
+    /// abandoned attempt.
+    pub fn read_4u8_seq(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+        seq: u8,
+    ) -> Result<(), Error<IO::Error>> {
+        assert!(data.len() <= URAP_COUNT_MAX as usize);
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_READ_SEQ, reg[0], reg[1], count];
+        let seq_byte = [seq];
+        let crc = crc16_update(crc16(&header), &seq_byte);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&seq_byte).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crc16(&op);
+                let mut echoed = [0u8; 1];
+                read_exact(self.io, &mut echoed)?;
+                crc_state = crc16_update(crc_state, &echoed);
+                for word in data.iter_mut() {
+                    read_exact(self.io, word)?;
+                    crc_state = crc16_update(crc_state, word);
+                }
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+                if echoed[0] != seq {
+                    return Err(Error::SeqMismatch);
+                }
+                Ok(())
+            }
+            OP_NAK => {
+                let mut echoed = [0u8; 1];
+                read_exact(self.io, &mut echoed)?;
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if echoed[0] != seq {
+                    return Err(Error::SeqMismatch);
+                }
+                Err(Error::Nak(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp)))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+
+    /// Like [`crate::UrapPrimary::write_4u8`], tagging the request with
+    /// `seq`. Returns [`Error::SeqMismatch`] instead of `Ok` if the
+    /// response echoes back a different sequence byte.
+    pub fn write_4u8_seq(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+        seq: u8,
+    ) -> Result<(), Error<IO::Error>> {
+        assert!(data.len() <= URAP_COUNT_MAX as usize);
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_WRITE_SEQ, reg[0], reg[1], count];
+        let seq_byte = [seq];
+
+        let mut crc_state = crc16_update(crc16(&header), &seq_byte);
+        for word in data {
+            crc_state = crc16_update(crc_state, word);
+        }
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&seq_byte).map_err(Error::Io)?;
+        for word in data {
+            self.io.write_all(word).map_err(Error::Io)?;
+        }
+        self.io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+        match op[0] {
+            OP_ACK => {
+                let mut echoed = [0u8; 1];
+                read_exact(self.io, &mut echoed)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if echoed[0] != seq {
+                    return Err(Error::SeqMismatch);
+                }
+                Ok(())
+            }
+            OP_NAK => {
+                let mut echoed = [0u8; 1];
+                read_exact(self.io, &mut echoed)?;
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if echoed[0] != seq {
+                    return Err(Error::SeqMismatch);
+                }
+                Err(Error::Nak(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp)))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+}
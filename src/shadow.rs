@@ -0,0 +1,76 @@
+//! Double-buffered register storage, so a multi-register read never
+//! observes a half-applied update — e.g. a 3-axis position where X, Y and
+//! Z should always change together from a reader's point of view.
+
+use crate::RegisterStore;
+
+/// A [`RegisterStore`] that only ever exposes a fully-committed snapshot
+/// to readers, even while an [`Update`] staging the next one is still in
+/// progress.
+///
+/// [`Self::begin_update`] hands out an owned copy of the current values
+/// to edit at leisure, with no lock held and nothing visible to readers;
+/// [`Self::commit`] swaps it in as a single assignment. Combined with
+/// [`crate::UrapSecondary::store_mut`] behind a `Mutex` (as
+/// [`crate::usockets`] does for its register map), the mutex only needs
+/// to be held for the cheap copy-in and copy-out, not for however long
+/// it takes to gather the new values.
+pub struct ShadowedRegisters<const REGCNT: usize, const WIDTH: usize> {
+    active: [[u8; WIDTH]; REGCNT],
+}
+
+/// A batch of staged register writes, seeded from a [`ShadowedRegisters`]
+/// snapshot via [`ShadowedRegisters::begin_update`].
+pub struct Update<const REGCNT: usize, const WIDTH: usize> {
+    staged: [[u8; WIDTH]; REGCNT],
+}
+
+impl<const REGCNT: usize, const WIDTH: usize> ShadowedRegisters<REGCNT, WIDTH> {
+    /// Builds a shadowed register store with `regs` as the initial,
+    /// already-committed snapshot.
+    pub const fn new(regs: [[u8; WIDTH]; REGCNT]) -> Self {
+        Self { active: regs }
+    }
+
+    /// Starts a batch of edits seeded with the currently active values.
+    /// Nothing staged here is visible to readers until it's passed to
+    /// [`Self::commit`].
+    pub fn begin_update(&self) -> Update<REGCNT, WIDTH> {
+        Update { staged: self.active }
+    }
+
+    /// Atomically replaces the active snapshot with `update`'s staged
+    /// values. A read that started before this call sees the old
+    /// snapshot in full; one that starts after sees the new one in full.
+    pub fn commit(&mut self, update: Update<REGCNT, WIDTH>) {
+        self.active = update.staged;
+    }
+}
+
+impl<const REGCNT: usize, const WIDTH: usize> Update<REGCNT, WIDTH> {
+    /// Stages `value` for `register`, without affecting what readers see
+    /// until this update is passed to [`ShadowedRegisters::commit`].
+    pub fn write(&mut self, register: u16, value: [u8; WIDTH]) {
+        self.staged[register as usize] = value;
+    }
+
+    /// Reads back a value already staged in this update, which may
+    /// differ from what's currently active.
+    pub fn read(&self, register: u16) -> [u8; WIDTH] {
+        self.staged[register as usize]
+    }
+}
+
+impl<const REGCNT: usize, const WIDTH: usize> RegisterStore<WIDTH> for ShadowedRegisters<REGCNT, WIDTH> {
+    fn len(&self) -> usize {
+        REGCNT
+    }
+
+    fn read(&mut self, register: u16) -> [u8; WIDTH] {
+        self.active[register as usize]
+    }
+
+    fn write(&mut self, register: u16, value: [u8; WIDTH]) {
+        self.active[register as usize] = value;
+    }
+}
@@ -0,0 +1,193 @@
+//! Saving and loading a register map to disk, so a [`crate::usockets`] or
+//! [`crate::tcp`] service can restore its configuration across restarts
+//! instead of coming back up zeroed.
+//!
+//! A snapshot file is a small header followed by the raw register bytes:
+//!
+//! ```text
+//! MAGIC (4) | WIDTH (1) | REGISTER (2, LE) | COUNT (2, LE) | DATA | CRC (2, LE)
+//! ```
+//!
+//! [`save`]/[`load`] cover the whole register map; [`save_range`]/
+//! [`load_range`] cover a sub-range, for services that only want to
+//! persist a handful of configuration registers rather than the full
+//! map. Saving is atomic: the file is written to a temporary path next
+//! to the destination, then renamed into place, so a crash or power loss
+//! mid-write can never leave a corrupt or partial snapshot where the
+//! real one used to be.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read as _, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::{crc16, crc16_update};
+
+const MAGIC: [u8; 4] = *b"URAP";
+const HEADER_SIZE: usize = MAGIC.len() + 1 + 2 + 2;
+
+/// Everything that can go wrong saving or loading a register snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// A filesystem operation failed.
+    Io(io::Error),
+    /// The file didn't start with the expected magic bytes.
+    BadMagic,
+    /// The CRC stored in the file didn't match its contents.
+    BadCrc,
+    /// The file's register width doesn't match the caller's `WIDTH`.
+    WidthMismatch {
+        /// Width the caller asked to load.
+        expected: usize,
+        /// Width recorded in the file.
+        found: usize,
+    },
+    /// The file's starting register doesn't match the caller's
+    /// `register`.
+    RegisterMismatch {
+        /// Register the caller asked to load into.
+        expected: u16,
+        /// Starting register recorded in the file.
+        found: u16,
+    },
+    /// The file holds a different number of registers than the caller
+    /// asked to load.
+    CountMismatch {
+        /// Number of registers the caller asked to load.
+        expected: usize,
+        /// Number of registers recorded in the file.
+        found: usize,
+    },
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "snapshot I/O error: {err}"),
+            Self::BadMagic => write!(f, "not a urap register snapshot"),
+            Self::BadCrc => write!(f, "snapshot CRC mismatch"),
+            Self::WidthMismatch { expected, found } => {
+                write!(f, "snapshot register width {found} does not match expected {expected}")
+            }
+            Self::RegisterMismatch { expected, found } => {
+                write!(f, "snapshot starts at register {found}, expected {expected}")
+            }
+            Self::CountMismatch { expected, found } => {
+                write!(f, "snapshot holds {found} registers, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Atomically saves the full register map to `path`.
+pub fn save<const REGCNT: usize, const WIDTH: usize>(
+    path: impl AsRef<Path>,
+    regs: &[[u8; WIDTH]; REGCNT],
+) -> Result<(), SnapshotError> {
+    save_range(path, 0, regs)
+}
+
+/// Atomically saves `data.len()` consecutive registers, starting at
+/// `register`, to `path`.
+pub fn save_range<const WIDTH: usize>(
+    path: impl AsRef<Path>,
+    register: u16,
+    data: &[[u8; WIDTH]],
+) -> Result<(), SnapshotError> {
+    let path = path.as_ref();
+    let tmp_path = tmp_path_for(path);
+
+    let mut crc_state = crc16(&MAGIC);
+    let width_byte = [WIDTH as u8];
+    crc_state = crc16_update(crc_state, &width_byte);
+    let reg_bytes = register.to_le_bytes();
+    crc_state = crc16_update(crc_state, &reg_bytes);
+    let count_bytes = (data.len() as u16).to_le_bytes();
+    crc_state = crc16_update(crc_state, &count_bytes);
+    for word in data {
+        crc_state = crc16_update(crc_state, word);
+    }
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&MAGIC)?;
+    file.write_all(&width_byte)?;
+    file.write_all(&reg_bytes)?;
+    file.write_all(&count_bytes)?;
+    for word in data {
+        file.write_all(word)?;
+    }
+    file.write_all(&crc_state.to_le_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Loads the full register map from `path`, overwriting `regs` only if
+/// the whole file is read and verified successfully.
+pub fn load<const REGCNT: usize, const WIDTH: usize>(
+    path: impl AsRef<Path>,
+    regs: &mut [[u8; WIDTH]; REGCNT],
+) -> Result<(), SnapshotError> {
+    load_range(path, 0, regs)
+}
+
+/// Loads `data.len()` consecutive registers, starting at `register`,
+/// from `path`, overwriting `data` only if the whole file is read and
+/// verified successfully.
+pub fn load_range<const WIDTH: usize>(
+    path: impl AsRef<Path>,
+    register: u16,
+    data: &mut [[u8; WIDTH]],
+) -> Result<(), SnapshotError> {
+    let mut file = File::open(path)?;
+
+    let mut header = [0u8; HEADER_SIZE];
+    file.read_exact(&mut header)?;
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+    let width = header[MAGIC.len()] as usize;
+    let file_register = u16::from_le_bytes([header[MAGIC.len() + 1], header[MAGIC.len() + 2]]);
+    let count = u16::from_le_bytes([header[MAGIC.len() + 3], header[MAGIC.len() + 4]]) as usize;
+
+    if width != WIDTH {
+        return Err(SnapshotError::WidthMismatch { expected: WIDTH, found: width });
+    }
+    if file_register != register {
+        return Err(SnapshotError::RegisterMismatch { expected: register, found: file_register });
+    }
+    if count != data.len() {
+        return Err(SnapshotError::CountMismatch { expected: data.len(), found: count });
+    }
+
+    let mut crc_state = crc16(&header);
+    let mut loaded = vec![[0u8; WIDTH]; count];
+    for word in &mut loaded {
+        file.read_exact(word)?;
+        crc_state = crc16_update(crc_state, word);
+    }
+    let mut crc_bytes = [0u8; 2];
+    file.read_exact(&mut crc_bytes)?;
+    if crc_state != u16::from_le_bytes(crc_bytes) {
+        return Err(SnapshotError::BadCrc);
+    }
+
+    data.copy_from_slice(&loaded);
+    Ok(())
+}
+
+fn tmp_path_for(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
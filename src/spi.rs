@@ -0,0 +1,168 @@
+//! URAP over `embedded_hal::spi`, for co-processors and MCUs whose only
+//! shared link is an SPI bus.
+//!
+//! Like I2C, SPI has no notion of a continuous byte stream: [`SpiPrimary`]
+//! buffers writes and, on [`Write::flush`], shifts the whole buffered
+//! request out over the wire, then shifts the response in. Unlike I2C
+//! there's no combined write-then-read transaction, so by default the read
+//! starts immediately after the write with no gap - fine for secondaries
+//! fast enough to have a response ready before the first clock edge, but
+//! many aren't. [`ReadyLine`] fills that gap: [`GpioReady`] polls a "data
+//! ready" GPIO between the write and the read, so the secondary can signal
+//! "give me a moment" without the primary guessing at a fixed delay. The
+//! default, `()`, never waits.
+//!
+//! The response is shifted in as a full-duplex transfer (dummy `0x00`
+//! bytes out, response bytes in), the common pattern for SPI secondaries
+//! that can't otherwise signal "nothing to send yet".
+
+use core::fmt;
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::spi::SpiDevice;
+use embedded_io::{ErrorType, Read, Write};
+
+/// Signals when a secondary's SPI response is ready to be shifted in.
+///
+/// Implemented for `()` (the default: never wait) and [`GpioReady`]
+/// (poll an [`InputPin`]).
+pub trait ReadyLine {
+    /// What can go wrong polling the ready signal.
+    type Error: fmt::Debug;
+
+    /// Blocks until the secondary reports its response is ready.
+    fn wait_ready(&mut self) -> Result<(), Self::Error>;
+}
+
+impl ReadyLine for () {
+    type Error = core::convert::Infallible;
+
+    fn wait_ready(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Polls `pin` until it reads high, for secondaries that drive their "data
+/// ready" line active-high.
+pub struct GpioReady<P>(pub P);
+
+impl<P: InputPin> ReadyLine for GpioReady<P> {
+    type Error = P::Error;
+
+    fn wait_ready(&mut self) -> Result<(), Self::Error> {
+        while !self.0.is_high()? {}
+        Ok(())
+    }
+}
+
+/// Everything that can go wrong exchanging a URAP packet over SPI.
+#[derive(Debug)]
+pub enum SpiError<E, RE> {
+    /// The underlying [`SpiDevice`] failed the transaction.
+    Spi(E),
+    /// The [`ReadyLine`] failed while polling for the response.
+    Ready(RE),
+}
+
+impl<E: fmt::Debug, RE: fmt::Debug> fmt::Display for SpiError<E, RE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spi(err) => write!(f, "SPI bus error: {err:?}"),
+            Self::Ready(err) => write!(f, "ready-line error: {err:?}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug, RE: fmt::Debug> core::error::Error for SpiError<E, RE> {}
+
+impl<E: embedded_hal::spi::Error, RE: fmt::Debug> embedded_io::Error for SpiError<E, RE> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Spi(_) | Self::Ready(_) => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Bridges a byte-stream URAP exchange onto an [`SpiDevice`], writing the
+/// buffered request out, then (after [`ready`](ReadyLine::wait_ready))
+/// shifting the response in as a full-duplex transfer.
+///
+/// `CAP` bounds the largest request and the largest response (comfortably
+/// covers a [`crate::URAP_COUNT_MAX`]-register packet at the default width
+/// by default).
+pub struct SpiPrimary<SPI, RDY = (), const CAP: usize = 512> {
+    spi: SPI,
+    ready: RDY,
+    write_buf: [u8; CAP],
+    write_len: usize,
+    read_buf: [u8; CAP],
+    read_pos: usize,
+    read_len: usize,
+}
+
+impl<SPI, const CAP: usize> SpiPrimary<SPI, (), CAP> {
+    /// Wraps `spi` with no ready-line handshake: the response is shifted
+    /// in immediately after the request is written.
+    pub fn new(spi: SPI) -> Self {
+        Self::with_ready_line(spi, ())
+    }
+}
+
+impl<SPI, RDY, const CAP: usize> SpiPrimary<SPI, RDY, CAP> {
+    /// Wraps `spi`, polling `ready` between writing the request and
+    /// reading the response.
+    pub fn with_ready_line(spi: SPI, ready: RDY) -> Self {
+        Self {
+            spi,
+            ready,
+            write_buf: [0u8; CAP],
+            write_len: 0,
+            read_buf: [0u8; CAP],
+            read_pos: 0,
+            read_len: 0,
+        }
+    }
+}
+
+impl<SPI: SpiDevice, RDY: ReadyLine, const CAP: usize> ErrorType for SpiPrimary<SPI, RDY, CAP> {
+    type Error = SpiError<SPI::Error, RDY::Error>;
+}
+
+impl<SPI: SpiDevice, RDY: ReadyLine, const CAP: usize> Read for SpiPrimary<SPI, RDY, CAP> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.read_pos >= self.read_len {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(self.read_len - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Ok(n)
+    }
+}
+
+impl<SPI: SpiDevice, RDY: ReadyLine, const CAP: usize> Write for SpiPrimary<SPI, RDY, CAP> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(CAP - self.write_len);
+        self.write_buf[self.write_len..self.write_len + n].copy_from_slice(&buf[..n]);
+        self.write_len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.write_len == 0 {
+            return Ok(());
+        }
+
+        self.spi.write(&self.write_buf[..self.write_len]).map_err(SpiError::Spi)?;
+        self.write_len = 0;
+
+        self.ready.wait_ready().map_err(SpiError::Ready)?;
+
+        self.read_buf.fill(0);
+        self.spi.transfer_in_place(&mut self.read_buf).map_err(SpiError::Spi)?;
+        self.read_pos = 0;
+        self.read_len = CAP;
+        Ok(())
+    }
+}
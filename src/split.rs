@@ -0,0 +1,80 @@
+//! [`Split`] joins an independent reader and writer into a single
+//! [`Read`] + [`Write`] transport, for hardware that hands back separate
+//! RX/TX halves (e.g. a UART with DMA TX and interrupt-driven RX)
+//! instead of one bidirectional object. It drops in underneath
+//! [`crate::UrapPrimary`]/[`crate::UrapSecondary`] unchanged.
+
+use core::fmt;
+use embedded_io::{ErrorType, Read, Write};
+
+/// What went wrong reading or writing through a [`Split`]; which side
+/// failed is preserved rather than collapsed into one error type.
+#[derive(Debug)]
+pub enum SplitError<RE, WE> {
+    /// The reader half returned an error.
+    Read(RE),
+    /// The writer half returned an error.
+    Write(WE),
+}
+
+impl<RE: fmt::Display, WE: fmt::Display> fmt::Display for SplitError<RE, WE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(err) => write!(f, "reader error: {err}"),
+            Self::Write(err) => write!(f, "writer error: {err}"),
+        }
+    }
+}
+
+impl<RE: fmt::Debug + fmt::Display, WE: fmt::Debug + fmt::Display> core::error::Error
+    for SplitError<RE, WE>
+{
+}
+
+impl<RE: embedded_io::Error, WE: embedded_io::Error> embedded_io::Error for SplitError<RE, WE> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Read(err) => err.kind(),
+            Self::Write(err) => err.kind(),
+        }
+    }
+}
+
+/// A [`Read`] + [`Write`] transport backed by independent reader and
+/// writer halves.
+pub struct Split<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R, W> Split<R, W> {
+    /// Joins `reader` and `writer` into a single transport.
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    /// Splits back into the original reader and writer halves.
+    pub fn into_halves(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R: ErrorType, W: ErrorType> ErrorType for Split<R, W> {
+    type Error = SplitError<R::Error, W::Error>;
+}
+
+impl<R: Read, W: ErrorType> Read for Split<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.reader.read(buf).map_err(SplitError::Read)
+    }
+}
+
+impl<R: ErrorType, W: Write> Write for Split<R, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.writer.write(buf).map_err(SplitError::Write)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush().map_err(SplitError::Write)
+    }
+}
@@ -0,0 +1,94 @@
+//! Plain packet/error counters for a [`crate::UrapSecondary`], for
+//! lightweight fleet visibility without pulling in the `metrics`
+//! feature's threaded HTTP exporter. `no_std`-safe, single set of
+//! non-atomic counters — fine for the common case of one secondary
+//! serviced from one thread; [`crate::metrics::Stats`] covers the
+//! multi-threaded `usockets` case instead.
+
+use crate::NakCode;
+
+const NAK_CODE_COUNT: usize = 8;
+
+/// Registers reserved by [`crate::UrapSecondary::with_published_stats`],
+/// in order: `packets`, `reads`, `writes`, `crc_errors`, one per
+/// [`NakCode`] (by discriminant), `bytes_in`, `bytes_out`.
+pub const STATS_REGISTER_COUNT: u16 = 4 + NAK_CODE_COUNT as u16 + 2;
+
+/// Running counters for requests a [`crate::UrapSecondary`] has
+/// serviced. Read back by reference via
+/// [`crate::UrapSecondary::stats`], or as live registers once
+/// [`crate::UrapSecondary::with_published_stats`] is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Total requests serviced, ACKed or NAKed.
+    pub packets: u32,
+    /// Read requests serviced.
+    pub reads: u32,
+    /// Write requests serviced.
+    pub writes: u32,
+    /// Requests rejected for a CRC mismatch, on either the header or the
+    /// data that followed it.
+    pub crc_errors: u32,
+    naks_by_code: [u32; NAK_CODE_COUNT],
+    /// Bytes read from the transport.
+    pub bytes_in: u64,
+    /// Bytes written to the transport.
+    pub bytes_out: u64,
+}
+
+impl Stats {
+    /// An empty counter set.
+    pub const fn new() -> Self {
+        Self {
+            packets: 0,
+            reads: 0,
+            writes: 0,
+            crc_errors: 0,
+            naks_by_code: [0; NAK_CODE_COUNT],
+            bytes_in: 0,
+            bytes_out: 0,
+        }
+    }
+
+    /// How many requests have been rejected with `code`. Always zero for
+    /// a `code` this crate didn't know about when it shipped.
+    pub fn nak_count(&self, code: NakCode) -> u32 {
+        self.naks_by_code.get(code as u8 as usize).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn record_nak(&mut self, code: NakCode) {
+        if let Some(counter) = self.naks_by_code.get_mut(code as u8 as usize) {
+            *counter += 1;
+        }
+    }
+
+    pub(crate) fn record_bytes_in(&mut self, n: u64) {
+        self.bytes_in += n;
+    }
+
+    pub(crate) fn record_bytes_out(&mut self, n: u64) {
+        self.bytes_out += n;
+    }
+
+    /// Encodes the counter at `index` (per [`STATS_REGISTER_COUNT`]'s
+    /// layout) as a little-endian register word, truncated or
+    /// zero-padded to fit `WIDTH`.
+    pub(crate) fn register_word<const WIDTH: usize>(&self, index: usize) -> [u8; WIDTH] {
+        let value: u64 = match index {
+            0 => self.packets as u64,
+            1 => self.reads as u64,
+            2 => self.writes as u64,
+            3 => self.crc_errors as u64,
+            4..=11 => self.naks_by_code[index - 4] as u64,
+            12 => self.bytes_in,
+            13 => self.bytes_out,
+            _ => 0,
+        };
+
+        let mut word = [0u8; WIDTH];
+        let le = value.to_le_bytes();
+        let n = WIDTH.min(le.len());
+        word[..n].copy_from_slice(&le[..n]);
+        word
+    }
+}
@@ -0,0 +1,123 @@
+//! Persisting a range of registers to non-volatile storage (NOR flash,
+//! EEPROM, ...) via [`embedded_storage`], so calibration registers written
+//! over URAP survive a power cycle without bespoke flash glue in every
+//! firmware.
+//!
+//! Unlike [`crate::snapshot`], this is `no_std` and works against any
+//! [`Storage`] implementation, transparent `embedded-storage` gives you RMW
+//! and page erase for free, so [`FlashBackedRegisters`] only has to frame
+//! the data with a CRC. Flash wear is kept in check by [`Self::commit`]
+//! being a deliberate, caller-triggered operation rather than something
+//! run on every write: pair it with a [`crate::DirtyTracker`] and call
+//! [`Self::commit_if_dirty`] from your own poll loop so an idle register
+//! map never touches the flash at all.
+//!
+//! ```text
+//! offset: DATA (len * WIDTH) | CRC (2, LE)
+//! ```
+
+use embedded_storage::Storage;
+
+use crate::{crc16, crc16_update, DirtyTracker};
+
+/// Everything that can go wrong committing or loading a flash-backed
+/// register range.
+#[derive(Debug)]
+pub enum StorageError<E> {
+    /// The underlying [`Storage`]/[`ReadStorage`] implementation failed.
+    Storage(E),
+    /// The CRC stored in flash didn't match its contents, e.g. because the
+    /// region was never written or was interrupted mid-commit.
+    BadCrc,
+}
+
+/// Maps `len` consecutive registers onto a byte range of an
+/// [`embedded_storage`] [`Storage`] device, starting at `offset`.
+pub struct FlashBackedRegisters<S, const WIDTH: usize> {
+    storage: S,
+    offset: u32,
+    len: u16,
+}
+
+impl<S, const WIDTH: usize> FlashBackedRegisters<S, WIDTH>
+where
+    S: Storage,
+{
+    /// Wraps `storage`, managing `len` registers starting at byte `offset`.
+    ///
+    /// The region actually occupied is `len * WIDTH + 2` bytes (the extra
+    /// 2 bytes are the trailing CRC), starting at `offset`.
+    pub const fn new(storage: S, offset: u32, len: u16) -> Self {
+        Self { storage, offset, len }
+    }
+
+    /// Loads the managed registers from flash into `regs`, overwriting
+    /// `regs` only if the whole region is read and its CRC verified.
+    ///
+    /// Returns [`StorageError::BadCrc`] for a blank or never-committed
+    /// region, since erased flash reads back as all-`0xFF`/`0x00` and
+    /// won't match any valid CRC.
+    pub fn load(&mut self, regs: &mut [[u8; WIDTH]]) -> Result<(), StorageError<S::Error>> {
+        assert_eq!(regs.len(), self.len as usize, "regs.len() must match the region's len");
+
+        let mut crc_state = crc16(&[]);
+        for (i, word) in regs.iter_mut().enumerate() {
+            self.storage
+                .read(self.offset + (i * WIDTH) as u32, word)
+                .map_err(StorageError::Storage)?;
+            crc_state = crc16_update(crc_state, word);
+        }
+
+        let mut crc_bytes = [0u8; 2];
+        self.storage
+            .read(self.offset + (self.len as usize * WIDTH) as u32, &mut crc_bytes)
+            .map_err(StorageError::Storage)?;
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            return Err(StorageError::BadCrc);
+        }
+
+        Ok(())
+    }
+
+    /// Commits `data` to flash unconditionally, along with a CRC covering
+    /// it. Prefer [`Self::commit_if_dirty`] in a poll loop so idle
+    /// registers don't wear the flash down for no reason.
+    pub fn commit(&mut self, data: &[[u8; WIDTH]]) -> Result<(), StorageError<S::Error>> {
+        assert_eq!(data.len(), self.len as usize, "data.len() must match the region's len");
+
+        let mut crc_state = crc16(&[]);
+        for (i, word) in data.iter().enumerate() {
+            self.storage
+                .write(self.offset + (i * WIDTH) as u32, word)
+                .map_err(StorageError::Storage)?;
+            crc_state = crc16_update(crc_state, word);
+        }
+
+        self.storage
+            .write(self.offset + (self.len as usize * WIDTH) as u32, &crc_state.to_le_bytes())
+            .map_err(StorageError::Storage)?;
+
+        Ok(())
+    }
+
+    /// Commits `data` only if [`DirtyTracker::take_dirty_in`] reports at
+    /// least one of the managed registers changed since the last commit,
+    /// returning whether a commit happened.
+    ///
+    /// `dirty` is expected to track the same register range as `data`
+    /// (register `0` in `dirty` is `data[0]`, not the caller's absolute
+    /// register numbering), matching how it's fed writes via
+    /// [`crate::WriteHook::on_write`].
+    pub fn commit_if_dirty<const BYTES: usize>(
+        &mut self,
+        data: &[[u8; WIDTH]],
+        dirty: &mut DirtyTracker<BYTES>,
+    ) -> Result<bool, StorageError<S::Error>> {
+        if dirty.take_dirty_in(0..data.len() as u16).count() == 0 {
+            return Ok(false);
+        }
+
+        self.commit(data)?;
+        Ok(true)
+    }
+}
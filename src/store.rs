@@ -0,0 +1,81 @@
+//! Backing storage for a [`crate::UrapSecondary`]'s registers.
+
+/// Where a [`crate::UrapSecondary`] actually keeps its register values.
+///
+/// The default, [`[[u8; WIDTH]; REGCNT]`][array], just mirrors everything
+/// into RAM. Implement this trait yourself for computed or virtual
+/// registers — a live ADC reading, a peripheral register, a value backed
+/// by [`crate::storage`] — that don't need (or can't afford) a RAM copy.
+/// Plug a custom store in via [`crate::UrapSecondary::with_store`].
+///
+/// [array]: https://doc.rust-lang.org/std/primitive.array.html
+pub trait RegisterStore<const WIDTH: usize> {
+    /// Number of registers this store exposes.
+    fn len(&self) -> usize;
+
+    /// Whether this store exposes no registers at all.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the current value of `register`, which [`crate::UrapSecondary`]
+    /// guarantees is always `< self.len()`. Takes `&mut self`, like
+    /// `embedded_storage::ReadStorage::read`, since reading a live
+    /// peripheral register often requires it (e.g. triggering an ADC
+    /// conversion).
+    fn read(&mut self, register: u16) -> [u8; WIDTH];
+
+    /// Writes `value` to `register`, which [`crate::UrapSecondary`]
+    /// guarantees is always `< self.len()`.
+    fn write(&mut self, register: u16, value: [u8; WIDTH]);
+}
+
+impl<const REGCNT: usize, const WIDTH: usize> RegisterStore<WIDTH> for [[u8; WIDTH]; REGCNT] {
+    fn len(&self) -> usize {
+        REGCNT
+    }
+
+    fn read(&mut self, register: u16) -> [u8; WIDTH] {
+        self[register as usize]
+    }
+
+    fn write(&mut self, register: u16, value: [u8; WIDTH]) {
+        self[register as usize] = value;
+    }
+}
+
+/// For a register count known only at runtime but with no heap to put a
+/// `Box` on: the caller owns a slice of whatever length it likes (static
+/// `mut`, stack array, borrowed from elsewhere) and hands a borrow of it
+/// to [`crate::UrapSecondary::from_slices`].
+impl<const WIDTH: usize> RegisterStore<WIDTH> for &mut [[u8; WIDTH]] {
+    fn len(&self) -> usize {
+        <[[u8; WIDTH]]>::len(self)
+    }
+
+    fn read(&mut self, register: u16) -> [u8; WIDTH] {
+        self[register as usize]
+    }
+
+    fn write(&mut self, register: u16, value: [u8; WIDTH]) {
+        self[register as usize] = value;
+    }
+}
+
+/// For `no_std` targets with a heap but no `std` (so a fixed-size array
+/// would have to be sized for the worst case at compile time), a
+/// register count chosen at construction instead of baked into the type.
+#[cfg(feature = "alloc")]
+impl<const WIDTH: usize> RegisterStore<WIDTH> for alloc::boxed::Box<[[u8; WIDTH]]> {
+    fn len(&self) -> usize {
+        <[[u8; WIDTH]]>::len(self)
+    }
+
+    fn read(&mut self, register: u16) -> [u8; WIDTH] {
+        self[register as usize]
+    }
+
+    fn write(&mut self, register: u16, value: [u8; WIDTH]) {
+        self[register as usize] = value;
+    }
+}
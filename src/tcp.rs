@@ -0,0 +1,288 @@
+//! URAP over plain TCP: a threaded secondary server and a primary
+//! client, for talking to devices reachable over a network rather than a
+//! local socket.
+//!
+//! See [`crate::usockets`] for the Unix-domain-socket equivalent; the
+//! shapes are deliberately identical so swapping transports is a type
+//! change, not a rewrite. [`crate::tls`] layers certificate-based
+//! encryption on top of this module's [`Listener`]/accept-loop design.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use embedded_io::{ErrorType, Read, Write};
+use embedded_io_adapters::std::FromStd;
+
+use crate::{Error, NakCode, ReadProtect, WriteProtect, UrapSecondary as CoreSecondary};
+use crate::{OP_NAK, OP_WRITE, URAP_HEADER_SIZE};
+
+/// What a connection on a given address is allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Reads and writes are both serviced normally.
+    ReadWrite,
+    /// Writes are rejected with [`NakCode::IndexWriteProtected`] before
+    /// they reach the register map; reads pass through.
+    ReadOnly,
+}
+
+/// One address to bind, and the [`Permission`] profile every connection
+/// accepted on it gets.
+pub struct Listener {
+    /// Address to bind, e.g. `"0.0.0.0:7878"`.
+    pub addr: SocketAddr,
+    /// Access level granted to clients connecting on `addr`.
+    pub permission: Permission,
+}
+
+impl Listener {
+    /// A listener that grants full read/write access.
+    pub fn read_write(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            permission: Permission::ReadWrite,
+        }
+    }
+
+    /// A listener that only ever allows reads.
+    pub fn read_only(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            permission: Permission::ReadOnly,
+        }
+    }
+}
+
+/// The first [`URAP_HEADER_SIZE`] bytes of a request, already consumed
+/// from the stream while deciding whether to service it, replayed ahead
+/// of the live stream so [`CoreSecondary::poll`] can read the request
+/// normally.
+pub(crate) struct HeaderPeek<'a, IO> {
+    pub(crate) header: [u8; URAP_HEADER_SIZE],
+    pub(crate) pos: usize,
+    pub(crate) inner: &'a mut IO,
+}
+
+impl<IO: ErrorType> ErrorType for HeaderPeek<'_, IO> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read> Read for HeaderPeek<'_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.pos < self.header.len() {
+            let n = buf.len().min(self.header.len() - self.pos);
+            buf[..n].copy_from_slice(&self.header[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<IO: Write> Write for HeaderPeek<'_, IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+pub(crate) fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(n) => filled += n,
+            Err(_) => return Err(io::Error::from(io::ErrorKind::Other)),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn send_nak<IO: Write>(io: &mut IO, code: NakCode) -> io::Result<()> {
+    let payload = [OP_NAK, code as u8];
+    let crc = crate::crc16(&payload);
+    io.write_all(&payload)
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+    io.write_all(&crc.to_le_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+    io.flush().map_err(|_| io::Error::from(io::ErrorKind::Other))
+}
+
+/// Services connections on one or more TCP listeners against a single
+/// shared register map, each listener granting its connections a
+/// [`Permission`] profile.
+///
+/// Runs one accept thread plus one worker thread per active connection;
+/// all threads share the register map behind a [`Mutex`].
+pub struct UrapSecondary {
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+}
+
+impl UrapSecondary {
+    /// Binds every [`Listener`] in `listeners` and starts servicing
+    /// connections against `regs`, which is shared (behind a [`Mutex`])
+    /// across every listener and connection.
+    pub fn spawn<const REGCNT: usize, const WIDTH: usize, P, R>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R>,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let mut join_handles = Vec::new();
+        for listener in listeners {
+            let listener_sock = TcpListener::bind(listener.addr)?;
+            let regs = Arc::clone(&regs);
+            let errors = Arc::clone(&errors);
+            let permission = listener.permission;
+
+            join_handles.push(thread::spawn(move || {
+                for stream in listener_sock.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            push_error(&errors, Error::Io(err));
+                            continue;
+                        }
+                    };
+                    let regs = Arc::clone(&regs);
+                    let errors = Arc::clone(&errors);
+                    thread::spawn(move || service_connection(stream, regs, permission, errors));
+                }
+            }));
+        }
+
+        Ok(Self {
+            join_handles,
+            errors,
+        })
+    }
+
+    /// Pops the oldest recorded transport error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+}
+
+impl Drop for UrapSecondary {
+    fn drop(&mut self) {
+        // Accept loops run forever today; detach rather than block the
+        // dropping thread. A graceful shutdown API is tracked separately.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn service_connection<const REGCNT: usize, const WIDTH: usize, P, R>(
+    stream: TcpStream,
+    regs: Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R>>>,
+    permission: Permission,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) where
+    P: WriteProtect,
+    R: ReadProtect,
+{
+    let _ = stream.set_nodelay(true);
+    let mut io = FromStd::new(stream);
+
+    loop {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        if read_exact(&mut io, &mut header).is_err() {
+            // Peer disconnected; nothing more to service on this stream.
+            return;
+        }
+
+        let count = header[3];
+        if header[0] == OP_WRITE && permission == Permission::ReadOnly {
+            let mut scratch = [0u8; WIDTH];
+            let mut drain_ok = true;
+            for _ in 0..count {
+                if read_exact(&mut io, &mut scratch).is_err() {
+                    drain_ok = false;
+                    break;
+                }
+            }
+            let mut crc_bytes = [0u8; 2];
+            if drain_ok && read_exact(&mut io, &mut crc_bytes).is_err() {
+                drain_ok = false;
+            }
+            if !drain_ok || send_nak(&mut io, NakCode::IndexWriteProtected).is_err() {
+                return;
+            }
+            continue;
+        }
+
+        let mut peeked = HeaderPeek {
+            header,
+            pos: 0,
+            inner: &mut io,
+        };
+
+        let result = match regs.lock() {
+            Ok(mut regs) => regs.poll(&mut peeked),
+            Err(_) => return,
+        };
+
+        if let Err(err) = result {
+            push_error(&errors, err);
+            return;
+        }
+    }
+}
+
+/// A URAP primary connected to a secondary over TCP.
+pub struct UrapPrimary<const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    io: FromStd<TcpStream>,
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimary<WIDTH, BIG_ENDIAN> {
+    /// Connects to a secondary listening at `addr`.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            io: FromStd::new(stream),
+        })
+    }
+
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    pub fn read_4u8(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut self.io);
+        primary.read_4u8(register, data)
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    pub fn write_4u8(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut self.io);
+        primary.write_4u8(register, data)
+    }
+}
@@ -0,0 +1,218 @@
+//! TLS-secured variant of [`crate::tcp`]: the same threaded
+//! secondary/primary shapes, but every connection is a `rustls` session
+//! instead of a bare socket, so the primary verifies the secondary's
+//! certificate and traffic is encrypted end to end.
+//!
+//! Certificate and key material is entirely the caller's concern - this
+//! module only wires an already-built [`rustls::ServerConfig`] or
+//! [`rustls::ClientConfig`] into the accept loop / connect call.
+
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use embedded_io_adapters::std::FromStd;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, ServerConfig, ServerConnection, StreamOwned};
+
+use crate::tcp::{read_exact, send_nak, HeaderPeek, Listener, Permission};
+use crate::{Error, NakCode, ReadProtect, WriteProtect, UrapSecondary as CoreSecondary};
+use crate::{OP_WRITE, URAP_HEADER_SIZE};
+
+type TlsServerStream = StreamOwned<ServerConnection, TcpStream>;
+type TlsClientStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Services TLS connections on one or more TCP listeners against a
+/// single shared register map, under one server identity.
+///
+/// Otherwise identical to [`crate::tcp::UrapSecondary`]: one accept
+/// thread per listener, one worker thread per connection, all sharing
+/// the register map behind a [`Mutex`].
+pub struct UrapSecondary {
+    join_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+}
+
+impl UrapSecondary {
+    /// Binds every [`Listener`] in `listeners`, terminating TLS on each
+    /// accepted connection with `config`, and starts servicing
+    /// connections against `regs`.
+    pub fn spawn<const REGCNT: usize, const WIDTH: usize, P, R>(
+        listeners: Vec<Listener>,
+        config: Arc<ServerConfig>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R>,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<Vec<Error<io::Error>>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let mut join_handles = Vec::new();
+        for listener in listeners {
+            let listener_sock = TcpListener::bind(listener.addr)?;
+            let regs = Arc::clone(&regs);
+            let errors = Arc::clone(&errors);
+            let permission = listener.permission;
+            let config = Arc::clone(&config);
+
+            join_handles.push(thread::spawn(move || {
+                for stream in listener_sock.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            push_error(&errors, Error::Io(err));
+                            continue;
+                        }
+                    };
+                    let regs = Arc::clone(&regs);
+                    let errors = Arc::clone(&errors);
+                    let config = Arc::clone(&config);
+                    thread::spawn(move || {
+                        service_connection(stream, config, regs, permission, errors)
+                    });
+                }
+            }));
+        }
+
+        Ok(Self {
+            join_handles,
+            errors,
+        })
+    }
+
+    /// Pops the oldest recorded transport error, if any.
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        self.errors.lock().ok()?.pop()
+    }
+}
+
+impl Drop for UrapSecondary {
+    fn drop(&mut self) {
+        // Accept loops run forever today; detach rather than block the
+        // dropping thread. A graceful shutdown API is tracked separately.
+        for handle in self.join_handles.drain(..) {
+            drop(handle);
+        }
+    }
+}
+
+fn push_error(errors: &Arc<Mutex<Vec<Error<io::Error>>>>, err: Error<io::Error>) {
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(err);
+    }
+}
+
+fn service_connection<const REGCNT: usize, const WIDTH: usize, P, R>(
+    stream: TcpStream,
+    config: Arc<ServerConfig>,
+    regs: Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R>>>,
+    permission: Permission,
+    errors: Arc<Mutex<Vec<Error<io::Error>>>>,
+) where
+    P: WriteProtect,
+    R: ReadProtect,
+{
+    let _ = stream.set_nodelay(true);
+    let conn = match ServerConnection::new(config) {
+        Ok(conn) => conn,
+        Err(err) => {
+            push_error(&errors, Error::Io(io::Error::other(err)));
+            return;
+        }
+    };
+    let tls: TlsServerStream = StreamOwned::new(conn, stream);
+    let mut io = FromStd::new(tls);
+
+    loop {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        if read_exact(&mut io, &mut header).is_err() {
+            // Peer disconnected; nothing more to service on this stream.
+            return;
+        }
+
+        let count = header[3];
+        if header[0] == OP_WRITE && permission == Permission::ReadOnly {
+            let mut scratch = [0u8; WIDTH];
+            let mut drain_ok = true;
+            for _ in 0..count {
+                if read_exact(&mut io, &mut scratch).is_err() {
+                    drain_ok = false;
+                    break;
+                }
+            }
+            let mut crc_bytes = [0u8; 2];
+            if drain_ok && read_exact(&mut io, &mut crc_bytes).is_err() {
+                drain_ok = false;
+            }
+            if !drain_ok || send_nak(&mut io, NakCode::IndexWriteProtected).is_err() {
+                return;
+            }
+            continue;
+        }
+
+        let mut peeked = HeaderPeek {
+            header,
+            pos: 0,
+            inner: &mut io,
+        };
+
+        let result = match regs.lock() {
+            Ok(mut regs) => regs.poll(&mut peeked),
+            Err(_) => return,
+        };
+
+        if let Err(err) = result {
+            push_error(&errors, err);
+            return;
+        }
+    }
+}
+
+/// A URAP primary connected to a secondary over TLS-secured TCP.
+pub struct UrapPrimary<const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    io: FromStd<TlsClientStream>,
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimary<WIDTH, BIG_ENDIAN> {
+    /// Connects to a secondary listening at `addr`, verifying its
+    /// certificate against `config` for `server_name`.
+    pub fn connect(
+        addr: impl ToSocketAddrs,
+        config: Arc<ClientConfig>,
+        server_name: ServerName<'static>,
+    ) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let conn = ClientConnection::new(config, server_name).map_err(io::Error::other)?;
+        let tls: TlsClientStream = StreamOwned::new(conn, stream);
+        Ok(Self {
+            io: FromStd::new(tls),
+        })
+    }
+
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    pub fn read_4u8(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut self.io);
+        primary.read_4u8(register, data)
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    pub fn write_4u8(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut self.io);
+        primary.write_4u8(register, data)
+    }
+}
@@ -0,0 +1,1812 @@
+//! URAP over Unix domain sockets: a threaded secondary server and a
+//! primary client, for talking to local daemons and simulated devices.
+
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use embedded_io::{ErrorType, Read, Write};
+use embedded_io_adapters::std::FromStd;
+
+use crate::{
+    crc16, ReadProtect, WriteProtect, {Error, NakCode, UrapSecondary as CoreSecondary},
+    {OP_ACK, OP_NAK, OP_PING, OP_WRITE, URAP_HEADER_SIZE},
+};
+
+/// What a connection on a given socket path is allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Reads and writes are both serviced normally.
+    ReadWrite,
+    /// Writes are rejected with [`NakCode::IndexWriteProtected`] before
+    /// they reach the register map; reads pass through.
+    ReadOnly,
+}
+
+/// The identity of a process connected to a Unix socket, as reported by
+/// the kernel (`SO_PEERCRED`) rather than anything the peer could forge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// Effective UID of the connecting process.
+    pub uid: u32,
+    /// Effective GID of the connecting process.
+    pub gid: u32,
+    /// PID of the connecting process.
+    pub pid: i32,
+}
+
+/// A transport error recorded by a running [`UrapSecondary`], along with
+/// the context needed to track it back to the connection that caused it.
+#[derive(Debug)]
+pub struct ErrorEvent {
+    /// When the error was recorded.
+    pub at: SystemTime,
+    /// The connection this error came from, if it came from one rather
+    /// than e.g. the accept loop or the autosave thread.
+    pub connection_id: Option<u64>,
+    /// The connecting process's identity, if it was known at the time of
+    /// the error (an auth callback ran, or the peer had already been
+    /// looked up for some other reason).
+    pub peer: Option<PeerCredentials>,
+    /// The underlying error.
+    pub error: Error<io::Error>,
+}
+
+/// How many [`ErrorEvent`]s a server keeps around before discarding the
+/// oldest one, so a busy server's error log can't grow without bound.
+const MAX_RECORDED_ERRORS: usize = 256;
+
+/// Clones an [`Error`], reconstructing its [`io::Error`] payload (if any)
+/// from just its [`io::ErrorKind`] since `io::Error` itself isn't `Clone`.
+fn clone_error(error: &Error<io::Error>) -> Error<io::Error> {
+    match error {
+        Error::Io(err) => Error::Io(io::Error::from(err.kind())),
+        Error::Eof => Error::Eof,
+        Error::BadCrc => Error::BadCrc,
+        Error::Nak(code) => Error::Nak(*code),
+        Error::CountTooLarge => Error::CountTooLarge,
+        #[cfg(feature = "seq")]
+        Error::SeqMismatch => Error::SeqMismatch,
+        Error::VerifyMismatch => Error::VerifyMismatch,
+        Error::InvalidDiscriminant(value) => Error::InvalidDiscriminant(*value),
+    }
+}
+
+/// Something a running [`UrapSecondary`] can report to a
+/// [`UrapSecondary::spawn_with_events`] callback as it happens, rather
+/// than callers having to poll [`UrapSecondary::pop_error`].
+#[derive(Debug)]
+pub enum ServerEvent {
+    /// A connection was accepted (and passed auth, if any).
+    ConnectionOpened {
+        /// The connection's id, stable for the rest of its lifetime.
+        connection_id: u64,
+        /// The connecting process's identity, if known.
+        peer: Option<PeerCredentials>,
+    },
+    /// A connection ended, whether cleanly or due to a transport error.
+    ConnectionClosed {
+        /// The id of the connection that closed.
+        connection_id: u64,
+        /// The connecting process's identity, if known.
+        peer: Option<PeerCredentials>,
+    },
+    /// A request was rejected with a [`NakCode`].
+    Nak {
+        /// The connection the request came in on.
+        connection_id: u64,
+        /// The connecting process's identity, if known.
+        peer: Option<PeerCredentials>,
+        /// Why the request was rejected.
+        code: NakCode,
+    },
+    /// A transport error occurred; see [`ErrorEvent`].
+    Error(ErrorEvent),
+}
+
+/// Invoked for every [`ServerEvent`] a server's connections and accept
+/// loops produce.
+type EventCallback = Arc<dyn Fn(ServerEvent) + Send + Sync>;
+
+/// Binds `listener.path`, reclaiming a stale socket file left behind by a
+/// crashed previous run when `listener.reclaim_stale` is set.
+///
+/// A bind that fails with [`io::ErrorKind::AddrInUse`] means the path
+/// already exists; that's only safe to clear out once we've confirmed
+/// nothing is actually listening on it, which a failed connect attempt
+/// tells us. If a live server answers, the original bind error is kept.
+pub(crate) fn bind_reclaiming(listener: &Listener) -> io::Result<UnixListener> {
+    if let Some(fd) = listener.systemd_fd {
+        // Safety: `fd` came from `systemd_listen_fd`, which only resolves
+        // to a file descriptor systemd told us (via `LISTEN_FDS`) it
+        // handed this process, starting at `SD_LISTEN_FDS_START`.
+        return Ok(unsafe { UnixListener::from_raw_fd(fd) });
+    }
+
+    match UnixListener::bind(&listener.path) {
+        Ok(sock) => Ok(sock),
+        Err(err)
+            if listener.reclaim_stale
+                && err.kind() == io::ErrorKind::AddrInUse
+                && UnixStream::connect(&listener.path).is_err() =>
+        {
+            std::fs::remove_file(&listener.path)?;
+            UnixListener::bind(&listener.path)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn peer_credentials(stream: &UnixStream) -> io::Result<PeerCredentials> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut cred: libc::ucred = unsafe { core::mem::zeroed() };
+    let mut len = core::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(PeerCredentials {
+        uid: cred.uid,
+        gid: cred.gid,
+        pid: cred.pid,
+    })
+}
+
+/// What a [`Listener`]'s `auth` callback decided for an incoming
+/// connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthDecision {
+    /// Accept the connection, granting the given permission.
+    Accept(Permission),
+    /// Refuse the connection; it is closed immediately.
+    Reject,
+}
+
+/// One socket path to bind, the [`Permission`] profile every connection
+/// accepted on it gets by default, and an optional callback that can
+/// override that profile (or refuse the connection outright) based on
+/// the connecting process's [`PeerCredentials`].
+pub struct Listener {
+    /// Filesystem path of the Unix socket to bind.
+    pub path: PathBuf,
+    /// Access level granted to clients connecting on `path` when no
+    /// `auth` callback is set, or when `auth` is set but this value is
+    /// otherwise used as a base permission.
+    pub permission: Permission,
+    /// Consulted for every new connection before any URAP traffic is
+    /// serviced. Anyone who can open the socket path has `permission`
+    /// access unless this rejects or restricts them.
+    pub auth: Option<Arc<dyn Fn(PeerCredentials) -> AuthDecision + Send + Sync>>,
+    /// If binding `path` fails because it already exists, and nothing is
+    /// actually listening on it (a stale file left behind by a crashed
+    /// previous run), unlink it and retry the bind once. See
+    /// [`Self::reclaim_stale`].
+    pub reclaim_stale: bool,
+    /// If set, adopt this already-bound, already-listening file
+    /// descriptor instead of binding `path` ourselves. See
+    /// [`Self::from_systemd`]. `path` is then purely informational (used
+    /// in logs) and is never unlinked on shutdown, since systemd owns
+    /// the socket's lifecycle.
+    systemd_fd: Option<RawFd>,
+}
+
+impl Listener {
+    /// A listener that grants full read/write access.
+    pub fn read_write(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            permission: Permission::ReadWrite,
+            auth: None,
+            reclaim_stale: false,
+            systemd_fd: None,
+        }
+    }
+
+    /// A listener that only ever allows reads.
+    pub fn read_only(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            permission: Permission::ReadOnly,
+            auth: None,
+            reclaim_stale: false,
+            systemd_fd: None,
+        }
+    }
+
+    /// Adopts the `index`th socket systemd passed this process via
+    /// socket activation (`LISTEN_PID`/`LISTEN_FDS`; see `sd_listen_fds(3)`)
+    /// instead of binding a path ourselves, so the service can be started
+    /// on first client connection rather than at boot.
+    ///
+    /// Fails if this process wasn't started under socket activation, or
+    /// systemd didn't pass at least `index + 1` sockets.
+    pub fn from_systemd(index: usize, permission: Permission) -> io::Result<Self> {
+        let fd = systemd_listen_fd(index)?;
+        Ok(Self {
+            path: PathBuf::from(format!("systemd-fd-{fd}")),
+            permission,
+            auth: None,
+            reclaim_stale: false,
+            systemd_fd: Some(fd),
+        })
+    }
+
+    /// Attaches a peer-credential callback deciding, per connection,
+    /// whether to accept it and with what permission.
+    pub fn with_auth(
+        mut self,
+        auth: impl Fn(PeerCredentials) -> AuthDecision + Send + Sync + 'static,
+    ) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    /// Opts into reclaiming a stale socket file left behind by a crashed
+    /// previous run: if binding `path` fails because it already exists,
+    /// this connects to it first to make sure no live server is actually
+    /// holding it, and only unlinks and retries the bind once that
+    /// check fails to connect.
+    pub fn reclaim_stale(mut self) -> Self {
+        self.reclaim_stale = true;
+        self
+    }
+}
+
+/// First file descriptor systemd hands a socket-activated service; see
+/// `sd_listen_fds(3)`.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Resolves the `index`th socket-activation file descriptor systemd
+/// passed this process, validating `LISTEN_PID` names us and `LISTEN_FDS`
+/// covers `index`.
+fn systemd_listen_fd(index: usize) -> io::Result<RawFd> {
+    let pid: u32 = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "not started under systemd socket activation (LISTEN_PID unset)",
+            )
+        })?;
+
+    if pid != std::process::id() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "LISTEN_PID does not name this process",
+        ));
+    }
+
+    let count: usize = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "LISTEN_FDS unset"))?;
+
+    if index >= count {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("systemd only passed {count} socket(s); index {index} is out of range"),
+        ));
+    }
+
+    Ok(SD_LISTEN_FDS_START + index as RawFd)
+}
+
+/// The first [`URAP_HEADER_SIZE`] bytes of a request, already consumed
+/// from the stream while deciding whether to service it, replayed ahead
+/// of the live stream so [`CoreSecondary::poll`] can read the request
+/// normally.
+struct HeaderPeek<'a, IO> {
+    header: [u8; URAP_HEADER_SIZE],
+    pos: usize,
+    inner: &'a mut IO,
+}
+
+impl<IO: ErrorType> ErrorType for HeaderPeek<'_, IO> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read> Read for HeaderPeek<'_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.pos < self.header.len() {
+            let n = buf.len().min(self.header.len() - self.pos);
+            buf[..n].copy_from_slice(&self.header[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<IO: Write> Write for HeaderPeek<'_, IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Locks `mutex`, recovering from poison instead of propagating it.
+///
+/// A panic inside one connection's locked section (e.g. from a custom
+/// [`crate::WriteHook`]) must not permanently wedge every other
+/// connection and the autosave thread behind a poisoned [`Mutex`].
+fn lock_recover<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(n) => filled += n,
+            Err(_) => return Err(io::Error::from(io::ErrorKind::Other)),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn send_nak<IO: Write>(io: &mut IO, code: NakCode) -> io::Result<()> {
+    let payload = [OP_NAK, code as u8];
+    let crc = crc16(&payload);
+    io.write_all(&payload)
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+    io.write_all(&crc.to_le_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+    io.flush().map_err(|_| io::Error::from(io::ErrorKind::Other))
+}
+
+fn send_ack<IO: Write>(io: &mut IO) -> io::Result<()> {
+    let crc = crc16(&[OP_ACK]);
+    io.write_all(&[OP_ACK])
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+    io.write_all(&crc.to_le_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+    io.flush().map_err(|_| io::Error::from(io::ErrorKind::Other))
+}
+
+/// Answers an `OP_PING` liveness probe directly, without locking the
+/// register map. Returns `Ok(true)` if it was ACKed, `Ok(false)` if it
+/// was NAKed for a bad CRC.
+fn handle_ping<IO: Read + Write>(io: &mut IO, header: &[u8; URAP_HEADER_SIZE]) -> io::Result<bool> {
+    let mut crc_bytes = [0u8; 2];
+    read_exact(io, &mut crc_bytes)?;
+    if crc16(header) != u16::from_le_bytes(crc_bytes) {
+        send_nak(io, NakCode::BadCrc)?;
+        return Ok(false);
+    }
+    send_ack(io)?;
+    Ok(true)
+}
+
+/// Services connections on one or more Unix sockets against a single
+/// shared register map, each socket granting its connections a
+/// [`Permission`] profile.
+///
+/// Runs one accept thread per [`Listener`] plus a bounded pool of worker
+/// threads (see [`SecondaryConfig`]) that service accepted connections;
+/// all threads share the register map behind a [`Mutex`]. Dropping this
+/// (or calling [`Self::shutdown`] directly) stops accepting new
+/// connections, closes the ones still open, and unlinks every bound
+/// socket path.
+pub struct UrapSecondary {
+    join_handles: Vec<JoinHandle<()>>,
+    worker_handles: Vec<JoinHandle<()>>,
+    detached_handles: Vec<JoinHandle<()>>,
+    errors: Arc<Mutex<VecDeque<ErrorEvent>>>,
+    shutdown: Arc<AtomicBool>,
+    sockets: Vec<PathBuf>,
+    connections: Arc<Mutex<Vec<(u64, UnixStream)>>>,
+}
+
+/// Assigns every accepted connection a process-wide unique id so
+/// [`ConnectionGuard`] can remove exactly its own entry from a server's
+/// connection registry, even if several servers are running at once.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Keeps an accepted connection's stream registered (so
+/// [`UrapSecondary::shutdown`] can force it closed) for as long as its
+/// worker thread is alive, removing the entry on drop — including if the
+/// worker thread panics mid-connection.
+struct ConnectionGuard {
+    id: u64,
+    connections: Arc<Mutex<Vec<(u64, UnixStream)>>>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        lock_recover(&self.connections).retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Registers `stream` in `connections` and returns the guard that
+/// unregisters it once the connection ends.
+fn track_connection(
+    connections: &Arc<Mutex<Vec<(u64, UnixStream)>>>,
+    stream: &UnixStream,
+) -> io::Result<ConnectionGuard> {
+    let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+    lock_recover(connections).push((id, stream.try_clone()?));
+    Ok(ConnectionGuard {
+        id,
+        connections: Arc::clone(connections),
+    })
+}
+
+/// A per-connection token-bucket limit on how many requests a client may
+/// send in a burst, passed via [`SecondaryConfig::rate_limit`].
+///
+/// A connection starts with `burst` tokens and regains one every
+/// `interval`; a request beyond the available tokens is serviced once one
+/// refills instead of being dropped, so one aggressive poller is slowed
+/// down rather than disconnected, and can't starve other connections of
+/// the register mutex.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    /// Requests allowed in a single burst before throttling kicks in.
+    pub burst: u32,
+    /// How often one more request is permitted once the burst is spent.
+    pub interval: Duration,
+}
+
+impl RateLimit {
+    /// Allows bursts of up to `burst` requests, regaining one token every
+    /// `interval`.
+    pub fn new(burst: u32, interval: Duration) -> Self {
+        Self { burst, interval }
+    }
+}
+
+/// Tracks one connection's remaining burst allowance for a [`RateLimit`],
+/// sleeping in [`Self::throttle`] once it's spent.
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: u32,
+    last_refill: std::time::Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst,
+            last_refill: std::time::Instant::now(),
+        }
+    }
+
+    /// Blocks until a token is available, then spends it.
+    fn throttle(&mut self) {
+        loop {
+            let elapsed = self.last_refill.elapsed();
+            let refilled = (elapsed.as_secs_f64() / self.limit.interval.as_secs_f64()) as u32;
+            if refilled > 0 {
+                self.tokens = self.tokens.saturating_add(refilled).min(self.limit.burst);
+                self.last_refill += self.limit.interval * refilled;
+            }
+
+            if self.tokens > 0 {
+                self.tokens -= 1;
+                return;
+            }
+
+            thread::sleep(self.limit.interval.saturating_sub(self.last_refill.elapsed()));
+        }
+    }
+}
+
+/// Configuration for [`UrapSecondary::spawn_with_autosave`]: how often
+/// the in-memory register map gets flushed to disk.
+#[cfg(feature = "autosave")]
+#[derive(Debug, Clone)]
+pub struct Autosave {
+    /// File the full register map is saved to.
+    pub path: PathBuf,
+    /// Save at least this often, even if few registers have changed.
+    pub interval: std::time::Duration,
+    /// Save sooner than `interval` once this many registers have gone
+    /// dirty since the last save, so a burst of writes doesn't sit
+    /// unsaved for the whole interval.
+    pub max_dirty: u32,
+}
+
+#[cfg(feature = "autosave")]
+impl Autosave {
+    /// Saves to `path` every `interval`, or as soon as `max_dirty`
+    /// registers have gone dirty, whichever comes first.
+    pub fn new(path: impl Into<PathBuf>, interval: std::time::Duration, max_dirty: u32) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+            max_dirty,
+        }
+    }
+}
+
+/// How often the autosave thread wakes to check whether `interval` or
+/// `max_dirty` has been reached; also the floor on how often a save can
+/// actually happen, protecting flash/SSD wear from a burst of writes.
+#[cfg(feature = "autosave")]
+const AUTOSAVE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How often an accept loop wakes from a non-blocking `accept()` to check
+/// whether [`UrapSecondary::shutdown`] has been requested; the bound on
+/// how long shutdown waits for a listener thread to notice.
+const ACCEPT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// A single accepted connection's worker, boxed so the pool threads don't
+/// need to know the register map's generic parameters.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// How many worker threads service connections when a server is spawned
+/// without an explicit [`SecondaryConfig`]: one per available core, same
+/// as most thread-pool defaults elsewhere, falling back to 4 if the host
+/// doesn't report a core count.
+fn default_worker_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Configuration for how a [`UrapSecondary`] services accepted
+/// connections; currently just the size of the worker pool. See
+/// [`UrapSecondary::spawn_with_config`].
+#[derive(Debug, Clone, Copy)]
+pub struct SecondaryConfig {
+    /// Number of worker threads servicing accepted connections. A
+    /// connection queues (bounded by the same count) once every worker is
+    /// busy, rather than spawning a new OS thread per connection, so a
+    /// burst of short-lived clients can't run the process out of threads.
+    pub worker_threads: usize,
+    /// How long a connection may go without sending a byte before it's
+    /// closed. `None` (the default) waits forever, matching prior
+    /// behavior; set this so a crashed or wedged client's connection
+    /// eventually frees its worker thread and file descriptor instead of
+    /// blocking on a read forever.
+    pub idle_timeout: Option<Duration>,
+    /// Maximum number of connections accepted at once, across all
+    /// listeners. `None` (the default) leaves it unbounded, matching prior
+    /// behavior; set this so a reconnect-looping client can't exhaust file
+    /// descriptors. Connections beyond the cap are refused immediately
+    /// rather than queued.
+    pub max_connections: Option<usize>,
+    /// Caps how fast any single connection's requests are serviced. `None`
+    /// (the default) applies no limit, matching prior behavior; set this
+    /// so one aggressive poller can't starve other clients of the
+    /// register mutex. See [`RateLimit`].
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for SecondaryConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: default_worker_threads(),
+            idle_timeout: None,
+            max_connections: None,
+            rate_limit: None,
+        }
+    }
+}
+
+/// Starts `worker_threads` workers sharing one job queue, returning the
+/// sending half (clone it into every accept thread) and the workers'
+/// join handles. A worker exits once the queue's last sender is dropped,
+/// so shutdown only has to make sure of that before joining the handles.
+///
+/// A job that panics (e.g. a [`crate::WriteHook`] blowing up while
+/// servicing a connection) is caught rather than left to unwind the
+/// worker: the old thread-per-connection design only ever lost the one
+/// throwaway thread to a panic, and a long-lived worker must keep that
+/// same guarantee for every connection queued after it.
+fn spawn_worker_pool(worker_threads: usize) -> (mpsc::SyncSender<Job>, Vec<JoinHandle<()>>) {
+    let worker_threads = worker_threads.max(1);
+    let (sender, receiver) = mpsc::sync_channel::<Job>(worker_threads);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let handles = (0..worker_threads)
+        .map(|_| {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let job = lock_recover(&receiver).recv();
+                match job {
+                    Ok(job) => {
+                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+                    }
+                    Err(_) => return,
+                }
+            })
+        })
+        .collect();
+
+    (sender, handles)
+}
+
+/// Queues `job` for the worker pool, retrying while the queue is full
+/// instead of blocking indefinitely so the caller (an accept loop) keeps
+/// noticing `shutdown`. Drops `job` if shutdown is requested before room
+/// frees up, or if every worker has already gone away.
+fn submit_job(sender: &mpsc::SyncSender<Job>, shutdown: &Arc<AtomicBool>, mut job: Job) {
+    loop {
+        match sender.try_send(job) {
+            Ok(()) => return,
+            Err(mpsc::TrySendError::Full(returned)) => {
+                if shutdown.load(Ordering::Relaxed) {
+                    return;
+                }
+                job = returned;
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => return,
+        }
+    }
+}
+
+impl UrapSecondary {
+    /// Binds every [`Listener`] in `listeners` and starts servicing
+    /// connections against `regs`, which is shared (behind a [`Mutex`])
+    /// across every socket and connection.
+    pub fn spawn<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+        H: crate::WriteHook<WIDTH> + Send + 'static,
+    {
+        Self::spawn_with_config(listeners, regs, SecondaryConfig::default())
+    }
+
+    /// Like [`Self::spawn`], but services connections with a worker pool
+    /// sized by `config` instead of [`SecondaryConfig::default`].
+    pub fn spawn_with_config<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+        config: SecondaryConfig,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+        H: crate::WriteHook<WIDTH> + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<VecDeque<ErrorEvent>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let sockets = listeners.iter().map(|l| l.path.clone()).collect();
+
+        let (sender, worker_handles) = spawn_worker_pool(config.worker_threads);
+        let join_handles = bind_listeners(
+            listeners, &regs, &errors, &None, &shutdown, &connections, &sender,
+            config.idle_timeout, config.max_connections, config.rate_limit,
+        )?;
+
+        Ok(Self {
+            join_handles,
+            worker_handles,
+            detached_handles: Vec::new(),
+            errors,
+            shutdown,
+            sockets,
+            connections,
+        })
+    }
+
+    /// Like [`Self::spawn`], but also calls `on_event` for every
+    /// [`ServerEvent`] a connection produces (opened, closed, NAKed, or
+    /// hit a transport error), so callers can log or alert immediately
+    /// instead of polling [`Self::pop_error`].
+    pub fn spawn_with_events<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+        on_event: impl Fn(ServerEvent) + Send + Sync + 'static,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+        H: crate::WriteHook<WIDTH> + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<VecDeque<ErrorEvent>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let on_event: Option<EventCallback> = Some(Arc::new(on_event));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let sockets = listeners.iter().map(|l| l.path.clone()).collect();
+
+        let (sender, worker_handles) = spawn_worker_pool(SecondaryConfig::default().worker_threads);
+        let join_handles = bind_listeners(
+            listeners, &regs, &errors, &on_event, &shutdown, &connections, &sender,
+            SecondaryConfig::default().idle_timeout,
+            SecondaryConfig::default().max_connections,
+            SecondaryConfig::default().rate_limit,
+        )?;
+
+        Ok(Self {
+            join_handles,
+            worker_handles,
+            detached_handles: Vec::new(),
+            errors,
+            shutdown,
+            sockets,
+            connections,
+        })
+    }
+
+    /// Pops the oldest recorded transport error, if any, discarding the
+    /// connection ID, peer, and timestamp recorded alongside it.
+    ///
+    /// Kept for callers that only care about the error itself; see
+    /// [`Self::drain_errors`] for the full [`ErrorEvent`].
+    pub fn pop_error(&self) -> Option<Error<io::Error>> {
+        lock_recover(&self.errors).pop_front().map(|event| event.error)
+    }
+
+    /// Drains every recorded [`ErrorEvent`], oldest first, clearing the
+    /// log. Unlike [`Self::pop_error`], this keeps the connection ID,
+    /// peer, and timestamp each error was recorded with.
+    pub fn drain_errors(&self) -> impl Iterator<Item = ErrorEvent> {
+        lock_recover(&self.errors).drain(..).collect::<Vec<_>>().into_iter()
+    }
+
+    /// Stops accepting new connections, forcibly closes every connection
+    /// still open, waits for the accept and connection-handling threads
+    /// to finish, then unlinks every socket path this server bound.
+    ///
+    /// Idempotent: calling this more than once (or letting [`Drop`] call
+    /// it after an explicit call) does nothing past the first time.
+    ///
+    /// A metrics endpoint started via [`Self::spawn_with_metrics`] has no
+    /// shutdown signal of its own and keeps serving scrapes for the rest
+    /// of the process's life; only the URAP listeners and connections are
+    /// torn down here.
+    pub fn shutdown(&mut self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        for (_, stream) in lock_recover(&self.connections).drain(..) {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+
+        // Accept threads are in `join_handles`; once they've all returned,
+        // every clone of the worker pool's job sender has been dropped, so
+        // the pool's queue closes and the workers below are free to exit.
+        for handle in self.join_handles.drain(..) {
+            let _ = handle.join();
+        }
+
+        for handle in self.worker_handles.drain(..) {
+            let _ = handle.join();
+        }
+
+        for handle in self.detached_handles.drain(..) {
+            drop(handle);
+        }
+
+        for path in &self.sockets {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Like [`Self::spawn`], but also runs a background thread that
+    /// periodically persists `regs` to disk per `autosave`, via
+    /// [`crate::snapshot`]. `regs`'s write hook must be a
+    /// [`crate::DirtyTracker`], which this drains on every save to find
+    /// out how many registers have changed since the last one.
+    #[cfg(feature = "autosave")]
+    pub fn spawn_with_autosave<const REGCNT: usize, const WIDTH: usize, P, R, const BYTES: usize>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, crate::DirtyTracker<BYTES>>,
+        autosave: Autosave,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<VecDeque<ErrorEvent>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let sockets = listeners.iter().map(|l| l.path.clone()).collect();
+
+        let (sender, worker_handles) = spawn_worker_pool(SecondaryConfig::default().worker_threads);
+        let mut join_handles = bind_listeners(
+            listeners, &regs, &errors, &None, &shutdown, &connections, &sender,
+            SecondaryConfig::default().idle_timeout,
+            SecondaryConfig::default().max_connections,
+            SecondaryConfig::default().rate_limit,
+        )?;
+        join_handles.push(spawn_autosave_thread(
+            regs,
+            Arc::clone(&errors),
+            autosave,
+            Arc::clone(&shutdown),
+        ));
+
+        Ok(Self {
+            join_handles,
+            worker_handles,
+            detached_handles: Vec::new(),
+            errors,
+            shutdown,
+            sockets,
+            connections,
+        })
+    }
+
+    /// Like [`Self::spawn`], but also serves Prometheus text metrics for
+    /// the traffic `listeners` see (packet and NAK counts, bytes
+    /// transferred, active connections, per-request latency) on
+    /// `metrics_addr`. See [`crate::metrics`].
+    #[cfg(feature = "metrics")]
+    pub fn spawn_with_metrics<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+        listeners: Vec<Listener>,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+        metrics_addr: std::net::SocketAddr,
+    ) -> io::Result<Self>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+        H: crate::WriteHook<WIDTH> + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<VecDeque<ErrorEvent>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let stats = Arc::new(crate::metrics::Stats::new());
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let sockets = listeners.iter().map(|l| l.path.clone()).collect();
+
+        let (sender, worker_handles) = spawn_worker_pool(SecondaryConfig::default().worker_threads);
+        let join_handles = bind_listeners_with_metrics(
+            listeners, &regs, &errors, &stats, &shutdown, &connections, &sender,
+            SecondaryConfig::default().idle_timeout,
+            SecondaryConfig::default().max_connections,
+            SecondaryConfig::default().rate_limit,
+        )?;
+        let detached_handles = vec![crate::metrics::serve(Arc::clone(&stats), metrics_addr)?];
+
+        Ok(Self {
+            join_handles,
+            worker_handles,
+            detached_handles,
+            errors,
+            shutdown,
+            sockets,
+            connections,
+        })
+    }
+}
+
+/// Builder for [`UrapSecondary`], for collecting the worker pool size,
+/// idle timeout, connection cap, rate limit, and event callback in one
+/// place instead of picking a `spawn_with_*` constructor per combination.
+///
+/// Autosave ([`UrapSecondary::spawn_with_autosave`]) and metrics
+/// ([`UrapSecondary::spawn_with_metrics`]) aren't covered here; reach for
+/// those constructors directly when you need them.
+pub struct UrapSecondaryBuilder {
+    listeners: Vec<Listener>,
+    config: SecondaryConfig,
+    on_event: Option<EventCallback>,
+}
+
+impl UrapSecondaryBuilder {
+    /// Starts a builder that will bind `listeners`.
+    pub fn new(listeners: Vec<Listener>) -> Self {
+        Self {
+            listeners,
+            config: SecondaryConfig::default(),
+            on_event: None,
+        }
+    }
+
+    /// Sets the worker pool size; see [`SecondaryConfig::worker_threads`].
+    pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+        self.config.worker_threads = worker_threads;
+        self
+    }
+
+    /// Sets the idle timeout; see [`SecondaryConfig::idle_timeout`].
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.config.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Closes a connection once it's gone `missed_allowed` keepalive
+    /// `interval`s without a byte of traffic, pairing with
+    /// [`UrapPrimary::spawn_keepalive`] on the other end. Built on
+    /// [`SecondaryConfig::idle_timeout`] (ordinary register traffic
+    /// resets the clock too, so this isn't limited to pings).
+    pub fn keepalive(mut self, interval: Duration, missed_allowed: u32) -> Self {
+        self.config.idle_timeout = Some(interval * missed_allowed.max(1));
+        self
+    }
+
+    /// Sets the connection cap; see [`SecondaryConfig::max_connections`].
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.config.max_connections = Some(max_connections);
+        self
+    }
+
+    /// Sets the per-connection rate limit; see [`SecondaryConfig::rate_limit`].
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.config.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Calls `on_event` for every [`ServerEvent`] a connection produces;
+    /// see [`UrapSecondary::spawn_with_events`].
+    pub fn on_event(mut self, on_event: impl Fn(ServerEvent) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(Arc::new(on_event));
+        self
+    }
+
+    /// Binds every listener and starts servicing `regs` per whichever
+    /// options were set.
+    pub fn spawn<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+        self,
+        regs: CoreSecondary<REGCNT, WIDTH, P, R, H>,
+    ) -> io::Result<UrapSecondary>
+    where
+        P: WriteProtect + Send + 'static,
+        R: ReadProtect + Send + 'static,
+        H: crate::WriteHook<WIDTH> + Send + 'static,
+    {
+        let regs = Arc::new(Mutex::new(regs));
+        let errors: Arc<Mutex<VecDeque<ErrorEvent>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let sockets = self.listeners.iter().map(|l| l.path.clone()).collect();
+
+        let (sender, worker_handles) = spawn_worker_pool(self.config.worker_threads);
+        let join_handles = bind_listeners(
+            self.listeners, &regs, &errors, &self.on_event, &shutdown, &connections, &sender,
+            self.config.idle_timeout, self.config.max_connections, self.config.rate_limit,
+        )?;
+
+        Ok(UrapSecondary {
+            join_handles,
+            worker_handles,
+            detached_handles: Vec::new(),
+            errors,
+            shutdown,
+            sockets,
+            connections,
+        })
+    }
+}
+
+#[cfg(feature = "autosave")]
+fn spawn_autosave_thread<const REGCNT: usize, const WIDTH: usize, P, R, const BYTES: usize>(
+    regs: Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R, crate::DirtyTracker<BYTES>>>>,
+    errors: Arc<Mutex<VecDeque<ErrorEvent>>>,
+    autosave: Autosave,
+    shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()>
+where
+    P: WriteProtect + Send + 'static,
+    R: ReadProtect + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut last_saved = std::time::Instant::now();
+        let mut dirty_since_save: u32 = 0;
+
+        loop {
+            thread::sleep(AUTOSAVE_CHECK_INTERVAL);
+
+            if shutdown.load(Ordering::Relaxed) {
+                return;
+            }
+
+            dirty_since_save += lock_recover(&regs).write_hook_mut().take_dirty().count() as u32;
+
+            if dirty_since_save == 0 {
+                continue;
+            }
+            if dirty_since_save < autosave.max_dirty && last_saved.elapsed() < autosave.interval {
+                continue;
+            }
+
+            let result = crate::snapshot::save(&autosave.path, lock_recover(&regs).regs());
+            if let Err(crate::snapshot::SnapshotError::Io(err)) = result {
+                push_error(&errors, &None, None, None, Error::Io(err));
+            }
+
+            last_saved = std::time::Instant::now();
+            dirty_since_save = 0;
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn bind_listeners<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+    listeners: Vec<Listener>,
+    regs: &Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R, H>>>,
+    errors: &Arc<Mutex<VecDeque<ErrorEvent>>>,
+    on_event: &Option<EventCallback>,
+    shutdown: &Arc<AtomicBool>,
+    connections: &Arc<Mutex<Vec<(u64, UnixStream)>>>,
+    sender: &mpsc::SyncSender<Job>,
+    idle_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+    rate_limit: Option<RateLimit>,
+) -> io::Result<Vec<JoinHandle<()>>>
+where
+    P: WriteProtect + Send + 'static,
+    R: ReadProtect + Send + 'static,
+    H: crate::WriteHook<WIDTH> + Send + 'static,
+{
+    let mut join_handles = Vec::new();
+    for listener in listeners {
+        let listener_sock = bind_reclaiming(&listener)?;
+        listener_sock.set_nonblocking(true)?;
+        let regs = Arc::clone(regs);
+        let errors = Arc::clone(errors);
+        let on_event = on_event.clone();
+        let shutdown = Arc::clone(shutdown);
+        let connections = Arc::clone(connections);
+        let sender = sender.clone();
+        let permission = listener.permission;
+        let auth = listener.auth.clone();
+
+        join_handles.push(thread::spawn(move || loop {
+            if shutdown.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let stream = match listener_sock.accept() {
+                Ok((stream, _addr)) => stream,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(err) => {
+                    #[cfg(feature = "log")]
+                    log::warn!("failed to accept connection: {err}");
+                    push_error(&errors, &on_event, None, None, Error::Io(err));
+                    continue;
+                }
+            };
+            if let Err(err) = stream.set_read_timeout(idle_timeout) {
+                push_error(&errors, &on_event, None, None, Error::Io(err));
+                continue;
+            }
+
+            let mut peer = None;
+            let permission = match &auth {
+                None => permission,
+                Some(auth) => match peer_credentials(&stream) {
+                    Ok(creds) => {
+                        peer = Some(creds);
+                        match auth(creds) {
+                            AuthDecision::Accept(permission) => permission,
+                            AuthDecision::Reject => continue,
+                        }
+                    }
+                    Err(err) => {
+                        push_error(&errors, &on_event, None, None, Error::Io(err));
+                        continue;
+                    }
+                },
+            };
+            if peer.is_none() {
+                peer = peer_credentials(&stream).ok();
+            }
+
+            if let Some(max) = max_connections {
+                if lock_recover(&connections).len() >= max {
+                    #[cfg(feature = "log")]
+                    log::debug!("rejecting connection on {:?}: at connection limit", listener.path);
+                    continue;
+                }
+            }
+
+            #[cfg(feature = "log")]
+            log::debug!("accepted connection on {:?}", listener.path);
+
+            let guard = match track_connection(&connections, &stream) {
+                Ok(guard) => guard,
+                Err(err) => {
+                    push_error(&errors, &on_event, None, peer, Error::Io(err));
+                    continue;
+                }
+            };
+            let connection_id = guard.id;
+            if let Some(on_event) = &on_event {
+                on_event(ServerEvent::ConnectionOpened { connection_id, peer });
+            }
+            let regs = Arc::clone(&regs);
+            let errors = Arc::clone(&errors);
+            let on_event = on_event.clone();
+            let bucket = rate_limit.map(TokenBucket::new);
+            let job: Job = Box::new(move || {
+                let _guard = guard;
+                service_connection(
+                    stream,
+                    regs,
+                    permission,
+                    errors,
+                    on_event,
+                    connection_id,
+                    peer,
+                    bucket,
+                );
+            });
+            submit_job(&sender, &shutdown, job);
+        }));
+    }
+
+    Ok(join_handles)
+}
+
+#[cfg(feature = "metrics")]
+#[allow(clippy::too_many_arguments)]
+fn bind_listeners_with_metrics<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+    listeners: Vec<Listener>,
+    regs: &Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R, H>>>,
+    errors: &Arc<Mutex<VecDeque<ErrorEvent>>>,
+    stats: &Arc<crate::metrics::Stats>,
+    shutdown: &Arc<AtomicBool>,
+    connections: &Arc<Mutex<Vec<(u64, UnixStream)>>>,
+    sender: &mpsc::SyncSender<Job>,
+    idle_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+    rate_limit: Option<RateLimit>,
+) -> io::Result<Vec<JoinHandle<()>>>
+where
+    P: WriteProtect + Send + 'static,
+    R: ReadProtect + Send + 'static,
+    H: crate::WriteHook<WIDTH> + Send + 'static,
+{
+    let mut join_handles = Vec::new();
+    for listener in listeners {
+        let listener_sock = bind_reclaiming(&listener)?;
+        listener_sock.set_nonblocking(true)?;
+        let regs = Arc::clone(regs);
+        let errors = Arc::clone(errors);
+        let stats = Arc::clone(stats);
+        let shutdown = Arc::clone(shutdown);
+        let connections = Arc::clone(connections);
+        let sender = sender.clone();
+        let permission = listener.permission;
+        let auth = listener.auth.clone();
+
+        join_handles.push(thread::spawn(move || loop {
+            if shutdown.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let stream = match listener_sock.accept() {
+                Ok((stream, _addr)) => stream,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(err) => {
+                    push_error(&errors, &None, None, None, Error::Io(err));
+                    continue;
+                }
+            };
+            if let Err(err) = stream.set_read_timeout(idle_timeout) {
+                push_error(&errors, &None, None, None, Error::Io(err));
+                continue;
+            }
+
+            let mut peer = None;
+            let permission = match &auth {
+                None => permission,
+                Some(auth) => match peer_credentials(&stream) {
+                    Ok(creds) => {
+                        peer = Some(creds);
+                        match auth(creds) {
+                            AuthDecision::Accept(permission) => permission,
+                            AuthDecision::Reject => continue,
+                        }
+                    }
+                    Err(err) => {
+                        push_error(&errors, &None, None, None, Error::Io(err));
+                        continue;
+                    }
+                },
+            };
+            if peer.is_none() {
+                peer = peer_credentials(&stream).ok();
+            }
+
+            if let Some(max) = max_connections {
+                if lock_recover(&connections).len() >= max {
+                    continue;
+                }
+            }
+
+            let guard = match track_connection(&connections, &stream) {
+                Ok(guard) => guard,
+                Err(err) => {
+                    push_error(&errors, &None, None, peer, Error::Io(err));
+                    continue;
+                }
+            };
+            let connection_id = guard.id;
+            let regs = Arc::clone(&regs);
+            let errors = Arc::clone(&errors);
+            let stats = Arc::clone(&stats);
+            let bucket = rate_limit.map(TokenBucket::new);
+            let job: Job = Box::new(move || {
+                let _guard = guard;
+                service_connection_with_metrics(
+                    stream,
+                    regs,
+                    permission,
+                    errors,
+                    stats,
+                    connection_id,
+                    peer,
+                    bucket,
+                )
+            });
+            submit_job(&sender, &shutdown, job);
+        }));
+    }
+
+    Ok(join_handles)
+}
+
+/// Wraps a transport, counting bytes read and written into `stats`.
+#[cfg(feature = "metrics")]
+struct CountingIo<IO> {
+    inner: IO,
+    stats: Arc<crate::metrics::Stats>,
+}
+
+#[cfg(feature = "metrics")]
+impl<IO: ErrorType> ErrorType for CountingIo<IO> {
+    type Error = IO::Error;
+}
+
+#[cfg(feature = "metrics")]
+impl<IO: Read> Read for CountingIo<IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.read(buf)?;
+        self.stats.record_bytes_in(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<IO: Write> Write for CountingIo<IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let n = self.inner.write(buf)?;
+        self.stats.record_bytes_out(n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+fn outcome_nak(outcome: crate::PollOutcome) -> Option<NakCode> {
+    match outcome {
+        crate::PollOutcome::Read { nak, .. } => nak,
+        crate::PollOutcome::Write { nak, .. } => nak,
+        crate::PollOutcome::Ping { nak } => nak,
+        crate::PollOutcome::UnknownOp { nak } => Some(nak),
+    }
+}
+
+#[cfg(feature = "metrics")]
+#[allow(clippy::too_many_arguments)]
+fn service_connection_with_metrics<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+    stream: UnixStream,
+    regs: Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R, H>>>,
+    permission: Permission,
+    errors: Arc<Mutex<VecDeque<ErrorEvent>>>,
+    stats: Arc<crate::metrics::Stats>,
+    connection_id: u64,
+    peer: Option<PeerCredentials>,
+    mut bucket: Option<TokenBucket>,
+) where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: crate::WriteHook<WIDTH>,
+{
+    stats.connection_opened();
+    let mut io = CountingIo {
+        inner: FromStd::new(stream),
+        stats: Arc::clone(&stats),
+    };
+
+    loop {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        if read_exact(&mut io, &mut header).is_err() {
+            break;
+        }
+        if let Some(bucket) = &mut bucket {
+            bucket.throttle();
+        }
+
+        let count = header[3];
+        if header[0] == OP_WRITE && permission == Permission::ReadOnly {
+            let mut scratch = [0u8; WIDTH];
+            let mut drain_ok = true;
+            for _ in 0..count {
+                if read_exact(&mut io, &mut scratch).is_err() {
+                    drain_ok = false;
+                    break;
+                }
+            }
+            let mut crc_bytes = [0u8; 2];
+            if drain_ok && read_exact(&mut io, &mut crc_bytes).is_err() {
+                drain_ok = false;
+            }
+            if !drain_ok || send_nak(&mut io, NakCode::IndexWriteProtected).is_err() {
+                break;
+            }
+            stats.record_packet();
+            stats.record_nak(NakCode::IndexWriteProtected);
+            continue;
+        }
+
+        if header[0] == OP_PING {
+            let started = std::time::Instant::now();
+            let acked = match handle_ping(&mut io, &header) {
+                Ok(acked) => acked,
+                Err(_) => break,
+            };
+            stats.record_request(started.elapsed());
+            stats.record_packet();
+            if !acked {
+                stats.record_nak(NakCode::BadCrc);
+            }
+            continue;
+        }
+
+        let mut peeked = HeaderPeek {
+            header,
+            pos: 0,
+            inner: &mut io,
+        };
+
+        let started = std::time::Instant::now();
+        let result = lock_recover(&regs).poll(&mut peeked);
+        stats.record_request(started.elapsed());
+
+        match result {
+            Ok(outcome) => {
+                stats.record_packet();
+                if let Some(nak) = outcome_nak(outcome) {
+                    stats.record_nak(nak);
+                }
+            }
+            Err(err) => {
+                push_error(&errors, &None, Some(connection_id), peer, err);
+                break;
+            }
+        }
+    }
+
+    stats.connection_closed();
+}
+
+impl Drop for UrapSecondary {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn push_error(
+    errors: &Arc<Mutex<VecDeque<ErrorEvent>>>,
+    on_event: &Option<EventCallback>,
+    connection_id: Option<u64>,
+    peer: Option<PeerCredentials>,
+    error: Error<io::Error>,
+) {
+    if let Some(on_event) = on_event {
+        on_event(ServerEvent::Error(ErrorEvent {
+            at: SystemTime::now(),
+            connection_id,
+            peer,
+            error: clone_error(&error),
+        }));
+    }
+
+    let mut errors = lock_recover(errors);
+    if errors.len() >= MAX_RECORDED_ERRORS {
+        errors.pop_front();
+    }
+    errors.push_back(ErrorEvent {
+        at: SystemTime::now(),
+        connection_id,
+        peer,
+        error,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn service_connection<const REGCNT: usize, const WIDTH: usize, P, R, H>(
+    stream: UnixStream,
+    regs: Arc<Mutex<CoreSecondary<REGCNT, WIDTH, P, R, H>>>,
+    permission: Permission,
+    errors: Arc<Mutex<VecDeque<ErrorEvent>>>,
+    on_event: Option<EventCallback>,
+    connection_id: u64,
+    peer: Option<PeerCredentials>,
+    mut bucket: Option<TokenBucket>,
+) where
+    P: WriteProtect,
+    R: ReadProtect,
+    H: crate::WriteHook<WIDTH>,
+{
+    let mut io = FromStd::new(stream);
+
+    loop {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        if read_exact(&mut io, &mut header).is_err() {
+            // Peer disconnected; nothing more to service on this stream.
+            #[cfg(feature = "log")]
+            log::debug!("connection closed");
+            break;
+        }
+        if let Some(bucket) = &mut bucket {
+            bucket.throttle();
+        }
+
+        let count = header[3];
+        if header[0] == OP_WRITE && permission == Permission::ReadOnly {
+            let mut scratch = [0u8; WIDTH];
+            let mut drain_ok = true;
+            for _ in 0..count {
+                if read_exact(&mut io, &mut scratch).is_err() {
+                    drain_ok = false;
+                    break;
+                }
+            }
+            let mut crc_bytes = [0u8; 2];
+            if drain_ok && read_exact(&mut io, &mut crc_bytes).is_err() {
+                drain_ok = false;
+            }
+            if !drain_ok || send_nak(&mut io, NakCode::IndexWriteProtected).is_err() {
+                break;
+            }
+            if let Some(on_event) = &on_event {
+                on_event(ServerEvent::Nak {
+                    connection_id,
+                    peer,
+                    code: NakCode::IndexWriteProtected,
+                });
+            }
+            continue;
+        }
+
+        if header[0] == OP_PING {
+            let acked = match handle_ping(&mut io, &header) {
+                Ok(acked) => acked,
+                Err(_) => break,
+            };
+            if !acked {
+                if let Some(on_event) = &on_event {
+                    on_event(ServerEvent::Nak {
+                        connection_id,
+                        peer,
+                        code: NakCode::BadCrc,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let mut peeked = HeaderPeek {
+            header,
+            pos: 0,
+            inner: &mut io,
+        };
+
+        let result = lock_recover(&regs).poll(&mut peeked);
+
+        match result {
+            Ok(outcome) => {
+                if let (Some(nak), Some(on_event)) = (outcome_nak(outcome), &on_event) {
+                    on_event(ServerEvent::Nak { connection_id, peer, code: nak });
+                }
+            }
+            Err(err) => {
+                #[cfg(feature = "log")]
+                log::warn!("connection dropped after transport error: {err:?}");
+                push_error(&errors, &on_event, Some(connection_id), peer, err);
+                break;
+            }
+        }
+    }
+
+    if let Some(on_event) = &on_event {
+        on_event(ServerEvent::ConnectionClosed { connection_id, peer });
+    }
+}
+
+/// A URAP primary connected to a secondary over a Unix socket.
+///
+/// Cloning shares the same underlying stream (behind an internal mutex
+/// rather than the caller having to hold one), so it's cheap to hand a
+/// clone to each worker thread in a daemon and issue reads/writes from
+/// any of them concurrently without an external `Mutex<UrapPrimary>`.
+pub struct UrapPrimary<const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    io: Arc<Mutex<FromStd<UnixStream>>>,
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> Clone for UrapPrimary<WIDTH, BIG_ENDIAN> {
+    fn clone(&self) -> Self {
+        Self {
+            io: Arc::clone(&self.io),
+        }
+    }
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimary<WIDTH, BIG_ENDIAN> {
+    /// Connects to a secondary listening on the Unix socket at `path`.
+    ///
+    /// The connection blocks indefinitely on reads and writes; use
+    /// [`Self::connect_with_timeout`] to bound how long a hung secondary
+    /// can stall the caller.
+    pub fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::connect_with_timeout(path, None, None)
+    }
+
+    /// Connects to a secondary listening on the Unix socket at `path`,
+    /// setting `SO_RCVTIMEO`/`SO_SNDTIMEO` on the underlying socket.
+    ///
+    /// A read or write that doesn't complete within its timeout fails
+    /// with an [`Error::Io`] of [`io::ErrorKind::WouldBlock`] (or
+    /// [`io::ErrorKind::TimedOut`], depending on platform) rather than
+    /// blocking forever.
+    pub fn connect_with_timeout(
+        path: impl AsRef<Path>,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+    ) -> io::Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(read_timeout)?;
+        stream.set_write_timeout(write_timeout)?;
+        Ok(Self {
+            io: Arc::new(Mutex::new(FromStd::new(stream))),
+        })
+    }
+
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    ///
+    /// Holds the shared stream's lock for the duration of the
+    /// transaction, so clones on other threads block rather than
+    /// interleaving their bytes with this one.
+    pub fn read_4u8(
+        &self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        let mut io = lock_recover(&self.io);
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut *io);
+        primary.read_4u8(register, data)
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`.
+    ///
+    /// Holds the shared stream's lock for the duration of the
+    /// transaction, so clones on other threads block rather than
+    /// interleaving their bytes with this one.
+    pub fn write_4u8(
+        &self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        let mut io = lock_recover(&self.io);
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut *io);
+        primary.write_4u8(register, data)
+    }
+
+    /// Sends a no-op liveness probe; see [`crate::UrapPrimary::ping`].
+    ///
+    /// On the secondary side this is answered without locking the
+    /// register map, so it stays responsive even while every worker is
+    /// busy servicing register traffic.
+    pub fn ping(&self) -> Result<(), Error<io::Error>> {
+        let mut io = lock_recover(&self.io);
+        let mut primary: crate::UrapPrimary<_, WIDTH, BIG_ENDIAN> =
+            crate::UrapPrimary::new(&mut *io);
+        primary.ping()
+    }
+
+    /// Spawns a background thread that calls [`Self::ping`] every
+    /// `interval` until the returned [`KeepaliveHandle`] is dropped, so a
+    /// secondary configured with [`UrapSecondaryBuilder::keepalive`] sees
+    /// steady traffic and can tell a dead peer from one that's merely
+    /// quiet.
+    pub fn spawn_keepalive(&self, interval: Duration) -> KeepaliveHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let primary = self.clone();
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                if thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let _ = primary.ping();
+            }
+        });
+        KeepaliveHandle {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+/// Stops the background thread started by [`UrapPrimary::spawn_keepalive`]
+/// when dropped.
+pub struct KeepaliveHandle {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for KeepaliveHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Builder for [`UrapPrimary`], for collecting the read/write timeouts
+/// (and any options added later) rather than growing [`UrapPrimary::connect_with_timeout`]'s
+/// parameter list further. [`UrapPrimary::connect`] remains the direct way
+/// to reach the simple, untimed case.
+pub struct UrapPrimaryBuilder<const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    path: PathBuf,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> UrapPrimaryBuilder<WIDTH, BIG_ENDIAN> {
+    /// Starts a builder that will connect to the Unix socket at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Sets `SO_RCVTIMEO` on the underlying socket.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets `SO_SNDTIMEO` on the underlying socket.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Connects with whichever timeouts were set; see
+    /// [`UrapPrimary::connect_with_timeout`].
+    pub fn connect(self) -> io::Result<UrapPrimary<WIDTH, BIG_ENDIAN>> {
+        UrapPrimary::connect_with_timeout(self.path, self.read_timeout, self.write_timeout)
+    }
+}
+
+/// Configuration for [`ReconnectingPrimary`]'s retries: how long to wait
+/// between reconnect attempts, and how many to make before giving up.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    /// How long to wait before the first reconnect attempt.
+    pub initial: Duration,
+    /// Upper bound on the wait between attempts; each failed attempt
+    /// doubles the previous wait, capped here.
+    pub max: Duration,
+    /// Give up after this many attempts. `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Backoff {
+    /// Waits `initial` before the first retry, doubling up to `max` on
+    /// each subsequent failure, giving up after `max_attempts` (or never,
+    /// if `None`).
+    pub fn new(initial: Duration, max: Duration, max_attempts: Option<u32>) -> Self {
+        Self { initial, max, max_attempts }
+    }
+}
+
+fn reconnect<const WIDTH: usize, const BIG_ENDIAN: bool>(
+    path: &Path,
+    backoff: &Backoff,
+) -> io::Result<UrapPrimary<WIDTH, BIG_ENDIAN>> {
+    let mut wait = backoff.initial;
+    let mut attempt = 0u32;
+    loop {
+        match UrapPrimary::connect(path) {
+            Ok(primary) => return Ok(primary),
+            Err(err) => {
+                attempt += 1;
+                if backoff.max_attempts.is_some_and(|max| attempt >= max) {
+                    return Err(err);
+                }
+                thread::sleep(wait);
+                wait = wait.saturating_mul(2).min(backoff.max);
+            }
+        }
+    }
+}
+
+/// A [`UrapPrimary`] that transparently reconnects and retries a failed
+/// transaction once when its connection to the secondary has dropped
+/// (e.g. the secondary restarted), instead of returning [`Error::Io`]
+/// until the caller rebuilds it.
+pub struct ReconnectingPrimary<const WIDTH: usize = 4, const BIG_ENDIAN: bool = false> {
+    path: PathBuf,
+    inner: UrapPrimary<WIDTH, BIG_ENDIAN>,
+    backoff: Backoff,
+}
+
+impl<const WIDTH: usize, const BIG_ENDIAN: bool> ReconnectingPrimary<WIDTH, BIG_ENDIAN> {
+    /// Connects to a secondary listening on the Unix socket at `path`,
+    /// retrying per `backoff` if it isn't up yet.
+    pub fn connect(path: impl Into<PathBuf>, backoff: Backoff) -> io::Result<Self> {
+        let path = path.into();
+        let inner = reconnect(&path, &backoff)?;
+        Ok(Self { path, inner, backoff })
+    }
+
+    /// Reads `data.len()` consecutive registers starting at `register`.
+    /// If the connection had dropped, reconnects per `backoff` and
+    /// retries the read once before giving up.
+    pub fn read_4u8(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        match self.inner.read_4u8(register, data) {
+            Err(Error::Io(_)) => {
+                self.inner = reconnect(&self.path, &self.backoff).map_err(Error::Io)?;
+                self.inner.read_4u8(register, data)
+            }
+            result => result,
+        }
+    }
+
+    /// Writes `data` to `data.len()` consecutive registers starting at
+    /// `register`. If the connection had dropped, reconnects per
+    /// `backoff` and retries the write once before giving up.
+    pub fn write_4u8(
+        &mut self,
+        register: u16,
+        data: &[[u8; WIDTH]],
+    ) -> Result<(), Error<io::Error>> {
+        match self.inner.write_4u8(register, data) {
+            Err(Error::Io(_)) => {
+                self.inner = reconnect(&self.path, &self.backoff).map_err(Error::Io)?;
+                self.inner.write_4u8(register, data)
+            }
+            result => result,
+        }
+    }
+}
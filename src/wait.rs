@@ -0,0 +1,300 @@
+//! Primary-initiated long-poll: "reply when register X changes, or after
+//! timeout T" instead of a tight read loop.
+//!
+//! Built on the same dirty-bit tracking [`crate::DirtyTracker`] provides
+//! for [`crate::notify`]: the secondary blocks inside the request
+//! handler, periodically checking the watched range for a pending write,
+//! until either one lands or the deadline passes - then replies with the
+//! current register contents and whether they actually changed.
+
+use std::time::{Duration, Instant};
+
+use embedded_io::{Read, Write};
+
+use crate::{
+    crc16, crc16_update, DirtyTracker, Error, NakCode, PollOutcome, ReadProtect, WriteProtect,
+    OP_ACK, OP_NAK, OP_WAIT, URAP_COUNT_MAX, URAP_HEADER_SIZE,
+};
+
+/// Time between dirty-bit checks while [`WaitSecondary::poll`] blocks on
+/// an [`crate::OP_WAIT`] request. Short enough to keep latency low
+/// without spinning the CPU.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// What [`WaitSecondary::poll`] did with the request it just serviced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitOutcome {
+    /// Any request other than `OP_WAIT`, forwarded to the wrapped
+    /// secondary.
+    Forwarded(PollOutcome),
+    /// An `OP_WAIT` request was serviced.
+    Wait {
+        /// First watched register.
+        register: u16,
+        /// Number of watched registers.
+        count: u8,
+        /// `true` if a write landed before the timeout elapsed; `false`
+        /// if the deadline passed with no change.
+        changed: bool,
+        /// Rejection reason, if the request was NAKed.
+        nak: Option<NakCode>,
+    },
+}
+
+struct HeaderPeek<'a, IO> {
+    header: [u8; URAP_HEADER_SIZE],
+    pos: usize,
+    inner: &'a mut IO,
+}
+
+impl<IO: embedded_io::ErrorType> embedded_io::ErrorType for HeaderPeek<'_, IO> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read> Read for HeaderPeek<'_, IO> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.pos < self.header.len() {
+            let n = buf.len().min(self.header.len() - self.pos);
+            buf[..n].copy_from_slice(&self.header[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+impl<IO: Write> Write for HeaderPeek<'_, IO> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+fn read_exact<IO: Read>(io: &mut IO, buf: &mut [u8]) -> Result<(), Error<IO::Error>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match io.read(&mut buf[filled..]) {
+            Ok(0) => return Err(Error::Eof),
+            Ok(n) => filled += n,
+            Err(err) => return Err(Error::Io(err)),
+        }
+    }
+    Ok(())
+}
+
+fn respond_nak<IO: Read + Write>(io: &mut IO, code: NakCode) -> Result<NakCode, Error<IO::Error>> {
+    let payload = [OP_NAK, code as u8];
+    let crc = crc16(&payload);
+    io.write_all(&payload).map_err(Error::Io)?;
+    io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+    io.flush().map_err(Error::Io)?;
+    Ok(code)
+}
+
+/// Wraps a [`crate::UrapSecondary`] - whose write hook must be a
+/// [`DirtyTracker`] - with support for [`crate::OP_WAIT`] requests.
+/// Every other opcode is forwarded to the inner secondary unchanged.
+pub struct WaitSecondary<const REGCNT: usize, const WIDTH: usize, P, R, const BYTES: usize> {
+    inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>>,
+}
+
+impl<const REGCNT: usize, const WIDTH: usize, P, R, const BYTES: usize>
+    WaitSecondary<REGCNT, WIDTH, P, R, BYTES>
+where
+    P: WriteProtect,
+    R: ReadProtect,
+{
+    /// Wraps `inner`.
+    pub fn new(inner: crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>>) -> Self {
+        Self { inner }
+    }
+
+    /// Direct access to the wrapped secondary, e.g. for
+    /// [`crate::UrapSecondary::regs`].
+    pub fn inner(&self) -> &crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>> {
+        &self.inner
+    }
+
+    /// Direct mutable access to the wrapped secondary.
+    pub fn inner_mut(
+        &mut self,
+    ) -> &mut crate::UrapSecondary<REGCNT, WIDTH, P, R, DirtyTracker<BYTES>> {
+        &mut self.inner
+    }
+
+    /// Services a single request read from `io`, writing the response
+    /// back to `io`. Blocks until a full request has been received, and,
+    /// for `OP_WAIT`, until the watched range changes or times out.
+    pub fn poll<IO>(&mut self, io: &mut IO) -> Result<WaitOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut header = [0u8; URAP_HEADER_SIZE];
+        read_exact(io, &mut header)?;
+
+        let op = header[0];
+        let register = u16::from_le_bytes([header[1], header[2]]);
+        let count = header[3];
+
+        if op == OP_WAIT {
+            self.handle_wait(io, &header, register, count)
+        } else {
+            let mut peeked = HeaderPeek {
+                header,
+                pos: 0,
+                inner: io,
+            };
+            self.inner.poll(&mut peeked).map(WaitOutcome::Forwarded)
+        }
+    }
+
+    fn handle_wait<IO>(
+        &mut self,
+        io: &mut IO,
+        header: &[u8; URAP_HEADER_SIZE],
+        register: u16,
+        count: u8,
+    ) -> Result<WaitOutcome, Error<IO::Error>>
+    where
+        IO: Read + Write,
+    {
+        let mut timeout_bytes = [0u8; 4];
+        read_exact(io, &mut timeout_bytes)?;
+        let timeout_ms = u32::from_le_bytes(timeout_bytes);
+
+        let mut crc_bytes = [0u8; 2];
+        read_exact(io, &mut crc_bytes)?;
+        let crc_state = crc16_update(crc16(header), &timeout_bytes);
+        if crc_state != u16::from_le_bytes(crc_bytes) {
+            let nak = respond_nak(io, NakCode::BadCrc)?;
+            return Ok(WaitOutcome::Wait { register, count, changed: false, nak: Some(nak) });
+        }
+
+        if count as u16 > URAP_COUNT_MAX {
+            let nak = respond_nak(io, NakCode::CountTooLarge)?;
+            return Ok(WaitOutcome::Wait { register, count, changed: false, nak: Some(nak) });
+        }
+        if register as usize + count as usize > REGCNT {
+            let nak = respond_nak(io, NakCode::IndexOutOfBounds)?;
+            return Ok(WaitOutcome::Wait { register, count, changed: false, nak: Some(nak) });
+        }
+        if self.inner.is_read_protected(register, count) {
+            let nak = respond_nak(io, NakCode::IndexReadProtected)?;
+            return Ok(WaitOutcome::Wait { register, count, changed: false, nak: Some(nak) });
+        }
+
+        let watched = register..register + count as u16;
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+        let mut changed = false;
+        loop {
+            if self
+                .inner
+                .write_hook_mut()
+                .take_dirty_in(watched.clone())
+                .count()
+                > 0
+            {
+                changed = true;
+                break;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+
+        let mut crc_state = crc16(&[OP_ACK]);
+        io.write_all(&[OP_ACK]).map_err(Error::Io)?;
+        for word in &self.inner.regs()[register as usize..register as usize + count as usize] {
+            io.write_all(word).map_err(Error::Io)?;
+            crc_state = crc16_update(crc_state, word);
+        }
+        let changed_byte = [changed as u8];
+        io.write_all(&changed_byte).map_err(Error::Io)?;
+        crc_state = crc16_update(crc_state, &changed_byte);
+        io.write_all(&crc_state.to_le_bytes()).map_err(Error::Io)?;
+        io.flush().map_err(Error::Io)?;
+
+        Ok(WaitOutcome::Wait { register, count, changed, nak: None })
+    }
+}
+
+/// The primary side of the long-poll extension: wraps a transport the
+/// same way [`crate::UrapPrimary`] does, adding [`Self::wait_for_change`].
+pub struct WaitPrimary<'a, IO, const WIDTH: usize = 4> {
+    io: &'a mut IO,
+}
+
+impl<'a, IO, const WIDTH: usize> WaitPrimary<'a, IO, WIDTH>
+where
+    IO: Read + Write,
+{
+    /// Wraps an existing transport. The transport is borrowed for the
+    /// lifetime of the primary.
+    pub fn new(io: &'a mut IO) -> Self {
+        Self { io }
+    }
+
+    /// Asks the secondary to hold the response until one of the
+    /// `data.len()` consecutive registers starting at `register` changes,
+    /// or until `timeout_ms` elapses, whichever comes first.
+    ///
+    /// `data` is filled with the current register contents either way.
+    /// Returns `true` if a write landed before the timeout, `false` if it
+    /// simply timed out.
+    pub fn wait_for_change(
+        &mut self,
+        register: u16,
+        data: &mut [[u8; WIDTH]],
+        timeout_ms: u32,
+    ) -> Result<bool, Error<IO::Error>> {
+        assert!(data.len() <= URAP_COUNT_MAX as usize);
+
+        let count = data.len() as u8;
+        let reg = register.to_le_bytes();
+        let header = [OP_WAIT, reg[0], reg[1], count];
+        let timeout_bytes = timeout_ms.to_le_bytes();
+        let crc = crc16_update(crc16(&header), &timeout_bytes);
+
+        self.io.write_all(&header).map_err(Error::Io)?;
+        self.io.write_all(&timeout_bytes).map_err(Error::Io)?;
+        self.io.write_all(&crc.to_le_bytes()).map_err(Error::Io)?;
+        self.io.flush().map_err(Error::Io)?;
+
+        let mut op = [0u8; 1];
+        read_exact(self.io, &mut op)?;
+
+        match op[0] {
+            OP_ACK => {
+                let mut crc_state = crc16(&op);
+                for word in data.iter_mut() {
+                    read_exact(self.io, word)?;
+                    crc_state = crc16_update(crc_state, word);
+                }
+                let mut changed_byte = [0u8; 1];
+                read_exact(self.io, &mut changed_byte)?;
+                crc_state = crc16_update(crc_state, &changed_byte);
+
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                if crc_state != u16::from_le_bytes(crc_bytes) {
+                    return Err(Error::BadCrc);
+                }
+                Ok(changed_byte[0] != 0)
+            }
+            OP_NAK => {
+                let mut nak = [0u8; 1];
+                read_exact(self.io, &mut nak)?;
+                let mut crc_bytes = [0u8; 2];
+                read_exact(self.io, &mut crc_bytes)?;
+                Err(Error::Nak(NakCode::from_u8(nak[0]).unwrap_or(NakCode::BadOp)))
+            }
+            _ => Err(Error::BadCrc),
+        }
+    }
+}
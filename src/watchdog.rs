@@ -0,0 +1,112 @@
+//! A communication watchdog for [`crate::UrapSecondary`]: tracks how long
+//! it's been since a byte last arrived from the primary, so firmware can
+//! fall back to a safe state once the link goes quiet. Pluggable via
+//! [`Clock`] so it works on `no_std` targets with their own timer.
+//!
+//! [`Watchdog`] is a transport wrapper, not a hook into [`crate::UrapSecondary::poll`]
+//! itself: wrap the transport passed to `poll` in one, then poll
+//! [`Watchdog::elapsed_ms`]/[`Watchdog::is_expired`] from wherever the
+//! application already runs its safe-state logic.
+
+use embedded_io::{ErrorType, Read, Write};
+
+/// A monotonic time source, pluggable so [`Watchdog`] works on `no_std`
+/// targets with their own timer.
+pub trait Clock {
+    /// Milliseconds since some arbitrary fixed point; must never go
+    /// backwards.
+    fn now_ms(&self) -> u64;
+}
+
+/// A [`Clock`] backed by [`std::time::Instant`], ticking from the moment
+/// it's constructed.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StdClock(std::time::Instant);
+
+#[cfg(feature = "std")]
+impl StdClock {
+    /// Starts a new clock, ticking from now.
+    pub fn new() -> Self {
+        Self(std::time::Instant::now())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now_ms(&self) -> u64 {
+        self.0.elapsed().as_millis() as u64
+    }
+}
+
+/// Wraps a transport, recording the [`Clock`] time a byte was last
+/// successfully read from it. Drop-in underneath
+/// [`crate::UrapSecondary::poll`]: every request the secondary services
+/// reads at least its header, which keeps this touched.
+pub struct Watchdog<IO, C> {
+    io: IO,
+    clock: C,
+    last_seen_ms: u64,
+}
+
+impl<IO, C: Clock> Watchdog<IO, C> {
+    /// Wraps `io`, tracking activity against `clock`. Starts as if a byte
+    /// had just arrived, so [`Self::elapsed_ms`] doesn't report a long
+    /// gap before the first request is even serviced.
+    pub fn new(io: IO, clock: C) -> Self {
+        let last_seen_ms = clock.now_ms();
+        Self {
+            io,
+            clock,
+            last_seen_ms,
+        }
+    }
+
+    /// Milliseconds since a byte was last read from the transport.
+    pub fn elapsed_ms(&self) -> u64 {
+        self.clock.now_ms().saturating_sub(self.last_seen_ms)
+    }
+
+    /// `true` if [`Self::elapsed_ms`] has reached `timeout_ms`, i.e. the
+    /// primary has gone quiet for at least that long.
+    pub fn is_expired(&self, timeout_ms: u64) -> bool {
+        self.elapsed_ms() >= timeout_ms
+    }
+
+    /// Unwraps the watchdog, returning the transport it was constructed
+    /// with.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO: ErrorType, C> ErrorType for Watchdog<IO, C> {
+    type Error = IO::Error;
+}
+
+impl<IO: Read, C: Clock> Read for Watchdog<IO, C> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = self.io.read(buf)?;
+        if n > 0 {
+            self.last_seen_ms = self.clock.now_ms();
+        }
+        Ok(n)
+    }
+}
+
+impl<IO: Write, C> Write for Watchdog<IO, C> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.io.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.io.flush()
+    }
+}
@@ -0,0 +1,45 @@
+//! Exercises `Box<[_]>`-backed register storage and write-protection, for
+//! `no_std + alloc` targets with a heap but no `std`.
+
+#![cfg(feature = "alloc")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{RegisterStore, UrapPrimary, UrapSecondary};
+
+#[test]
+fn a_boxed_slice_store_and_protect_list_serve_writes_and_reads() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let store: Box<[[u8; 4]]> = vec![[0u8; 4]; 4].into_boxed_slice();
+    let write_protect: Box<[bool]> = vec![false, false, true, false].into_boxed_slice();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4, 4, Box<[bool]>, _, _, Box<[[u8; 4]]>> =
+            UrapSecondary::new([[0u8; 4]; 4], write_protect).with_store(store);
+        for _ in 0..3 {
+            secondary.poll(&mut io).unwrap();
+        }
+        secondary
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    let result = primary.write_4u8(2, &[[9, 9, 9, 9]]);
+    assert!(matches!(
+        result,
+        Err(urap::Error::Nak(urap::NakCode::IndexWriteProtected))
+    ));
+
+    let mut secondary = secondary_thread.join().unwrap();
+    assert_eq!(RegisterStore::len(secondary.store_mut()), 4);
+}
@@ -0,0 +1,57 @@
+//! End-to-end exercise of the HMAC pre-shared-key handshake and
+//! per-packet authentication.
+
+#![cfg(feature = "auth")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{AuthPrimary, AuthSecondary};
+
+const PSK: &[u8] = b"correct horse battery staple";
+
+#[test]
+fn handshake_then_authenticated_round_trip() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: AuthSecondary<4> = AuthSecondary::new(PSK, [[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io, [7u8; 16]).unwrap();
+        secondary.poll(&mut io, [0u8; 16]).unwrap();
+        assert!(secondary.is_authenticated());
+        secondary.poll(&mut io, [0u8; 16]).unwrap();
+        secondary.poll(&mut io, [0u8; 16]).unwrap();
+        secondary
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: AuthPrimary<_, 4> = AuthPrimary::connect(&mut io, PSK).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn wrong_psk_is_rejected() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: AuthSecondary<4> = AuthSecondary::new(PSK, [[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io, [3u8; 16]).unwrap();
+        secondary.poll(&mut io, [0u8; 16]).unwrap();
+        assert!(!secondary.is_authenticated());
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let result = AuthPrimary::<_, 4>::connect(&mut io, b"wrong password entirely");
+    assert!(result.is_err());
+
+    secondary_thread.join().unwrap();
+}
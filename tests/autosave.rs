@@ -0,0 +1,47 @@
+//! End-to-end exercise of periodic autosave for a `usockets` service.
+
+#![cfg(all(feature = "autosave", unix))]
+
+use std::thread;
+use std::time::Duration;
+
+use urap::usockets::{Autosave, Listener, UrapPrimary, UrapSecondary};
+use urap::{DirtyTracker, UrapSecondary as CoreSecondary};
+
+fn socket_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("urap-autosave-test-{name}-{}.sock", std::process::id()))
+}
+
+fn snapshot_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("urap-autosave-test-{name}-{}.bin", std::process::id()))
+}
+
+#[test]
+fn a_write_is_flushed_to_disk_once_max_dirty_is_reached() {
+    let sock_path = socket_path("max-dirty");
+    let snap_path = snapshot_path("max-dirty");
+    let _ = std::fs::remove_file(&sock_path);
+    let _ = std::fs::remove_file(&snap_path);
+
+    let secondary: CoreSecondary<4, 4, _, _, DirtyTracker<1>> =
+        CoreSecondary::new([[0u8; 4]; 4], [false; 4]).with_write_hook(DirtyTracker::new());
+    let autosave = Autosave::new(&snap_path, Duration::from_secs(3600), 1);
+    let _server =
+        UrapSecondary::spawn_with_autosave(vec![Listener::read_write(&sock_path)], secondary, autosave)
+            .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&sock_path).unwrap();
+    primary.write_4u8(2, &[[7, 7, 7, 7]]).unwrap();
+
+    // The autosave thread wakes every 500ms; give it a couple of ticks.
+    thread::sleep(Duration::from_millis(1500));
+
+    let mut loaded = [[0u8; 4]; 4];
+    urap::snapshot::load(&snap_path, &mut loaded).unwrap();
+    assert_eq!(loaded[2], [7, 7, 7, 7]);
+
+    let _ = std::fs::remove_file(&sock_path);
+    let _ = std::fs::remove_file(&snap_path);
+}
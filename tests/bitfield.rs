@@ -0,0 +1,98 @@
+//! Exercises `read_bits`/`write_bits` and [`urap::urap_bitfields!`] over
+//! a real Unix socket pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{urap_bitfields, UrapPrimary, UrapSecondary};
+
+urap_bitfields! {
+    pub mod status_bits {
+        register: 0;
+        pub Enabled: 0, 0;
+        pub Mode: 3, 1;
+        pub ErrorCode: 15, 8;
+    }
+}
+
+#[test]
+fn read_bits_extracts_a_field_without_disturbing_its_siblings() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_u32(0, 0b1010_1101_0000_0111).unwrap();
+    assert_eq!(primary.read_bits(0, 15, 8).unwrap(), 0b1010_1101);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn write_bits_leaves_untouched_bits_alone() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..4 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_u32(0, 0b1111_0000).unwrap();
+    primary.write_bits(0, 3, 1, 0b101).unwrap();
+    assert_eq!(primary.read_u32(0).unwrap(), 0b1111_1010);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+#[should_panic(expected = "write_bits")]
+fn write_bits_panics_when_the_value_does_not_fit() {
+    let (primary_sock, _secondary_sock) = UnixStream::pair().unwrap();
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    let _ = primary.write_bits(0, 3, 1, 0b1000);
+}
+
+#[test]
+fn bitfield_macro_fields_read_and_write_through_the_named_register() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..9 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    assert_eq!(status_bits::REGISTER, 0);
+
+    status_bits::Enabled::write(&mut primary, 1).unwrap();
+    status_bits::Mode::write(&mut primary, 0b101).unwrap();
+    status_bits::ErrorCode::write(&mut primary, 0x2a).unwrap();
+
+    assert_eq!(status_bits::Enabled::read(&mut primary).unwrap(), 1);
+    assert_eq!(status_bits::Mode::read(&mut primary).unwrap(), 0b101);
+    assert_eq!(status_bits::ErrorCode::read(&mut primary).unwrap(), 0x2a);
+
+    secondary_thread.join().unwrap();
+}
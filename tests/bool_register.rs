@@ -0,0 +1,71 @@
+//! Exercises `read_bool`/`write_bool`/`toggle` over a real Unix socket
+//! pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{UrapPrimary, UrapSecondary};
+
+#[test]
+fn read_bool_treats_any_nonzero_value_as_true() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_u32(0, 42).unwrap();
+    assert!(primary.read_bool(0).unwrap());
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn write_bool_round_trips_through_read_bool() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_bool(0, true).unwrap();
+    assert!(primary.read_bool(0).unwrap());
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn toggle_flips_a_flag_register_and_returns_the_new_value() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..4 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    assert!(primary.toggle(0).unwrap());
+    assert!(!primary.toggle(0).unwrap());
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,93 @@
+//! End-to-end exercise of [`BufferedPrimary`] over a real Unix socket pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{BufferedPrimary, PollOutcome, UrapPrimary, UrapSecondary};
+
+#[test]
+fn adjacent_queued_writes_flush_as_a_single_multi_register_packet() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        // A single poll would hang if the flush sent more than one
+        // packet, proving the three adjacent writes were coalesced.
+        let outcome = secondary.poll(&mut io).unwrap();
+        assert!(matches!(
+            outcome,
+            PollOutcome::Write { register: 0, count: 3, nak: None }
+        ));
+        secondary
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut buffered: BufferedPrimary<_, 4> = BufferedPrimary::new(primary, None);
+
+    buffered.queue_write(0, &[[1, 0, 0, 0]]).unwrap();
+    buffered.queue_write(1, &[[2, 0, 0, 0]]).unwrap();
+    buffered.queue_write(2, &[[3, 0, 0, 0]]).unwrap();
+    assert_eq!(buffered.pending_len(), 3);
+    buffered.flush().unwrap();
+    assert_eq!(buffered.pending_len(), 0);
+
+    let secondary = secondary_thread.join().unwrap();
+    assert_eq!(secondary.regs()[0..3], [[1, 0, 0, 0], [2, 0, 0, 0], [3, 0, 0, 0]]);
+}
+
+#[test]
+fn requeuing_the_same_register_replaces_its_buffered_value() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io).unwrap();
+        secondary
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut buffered: BufferedPrimary<_, 4> = BufferedPrimary::new(primary, None);
+
+    buffered.queue_write(0, &[[1, 1, 1, 1]]).unwrap();
+    buffered.queue_write(0, &[[2, 2, 2, 2]]).unwrap();
+    assert_eq!(buffered.pending_len(), 1);
+    buffered.flush().unwrap();
+
+    let secondary = secondary_thread.join().unwrap();
+    assert_eq!(secondary.regs()[0], [2, 2, 2, 2]);
+}
+
+#[test]
+fn a_stale_buffer_is_flushed_before_the_next_queue_write() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io).unwrap();
+        secondary.poll(&mut io).unwrap();
+        secondary
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut buffered: BufferedPrimary<_, 4> =
+        BufferedPrimary::new(primary, Some(Duration::from_millis(10)));
+
+    buffered.queue_write(0, &[[1, 2, 3, 4]]).unwrap();
+    thread::sleep(Duration::from_millis(30));
+    // Stale, so this flushes register 0 first, then queues register 2.
+    buffered.queue_write(2, &[[5, 6, 7, 8]]).unwrap();
+    assert_eq!(buffered.pending_len(), 1);
+    buffered.flush().unwrap();
+
+    let secondary = secondary_thread.join().unwrap();
+    assert_eq!(secondary.regs()[0], [1, 2, 3, 4]);
+    assert_eq!(secondary.regs()[2], [5, 6, 7, 8]);
+}
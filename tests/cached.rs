@@ -0,0 +1,86 @@
+//! End-to-end exercise of [`CachedPrimary`] over a real Unix socket pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{CachedPrimary, UrapPrimary, UrapSecondary};
+
+#[test]
+fn a_repeated_read_within_the_ttl_does_not_touch_the_wire() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        // Only one poll: a second request on the wire would hang this
+        // thread, proving the second read was served from the cache.
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut cached = CachedPrimary::new(primary, Duration::from_secs(60));
+
+    let mut first = [[0u8; 4]; 1];
+    cached.read_4u8(0, &mut first).unwrap();
+
+    let mut second = [[0u8; 4]; 1];
+    cached.read_4u8(0, &mut second).unwrap();
+    assert_eq!(first, second);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn a_write_invalidates_the_cached_entry_for_that_register() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        for _ in 0..3 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut cached = CachedPrimary::new(primary, Duration::from_secs(60));
+
+    let mut readback = [[0u8; 4]; 1];
+    cached.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [0, 0, 0, 0]);
+
+    cached.write_4u8(0, &[[9, 9, 9, 9]]).unwrap();
+
+    cached.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [9, 9, 9, 9]);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn an_expired_entry_is_re_read_from_the_wire() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io).unwrap();
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut cached = CachedPrimary::new(primary, Duration::from_millis(10));
+
+    let mut readback = [[0u8; 4]; 1];
+    cached.read_4u8(0, &mut readback).unwrap();
+
+    thread::sleep(Duration::from_millis(30));
+    cached.read_4u8(0, &mut readback).unwrap();
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,69 @@
+//! Exercises generating a register module from a TOML register-map
+//! file.
+#![cfg(feature = "codegen")]
+
+use urap::codegen::{generate_module, CodegenError};
+
+const SOURCE: &str = r#"
+[registers.setpoint]
+index = 0
+type = "f32"
+protected = false
+
+[registers.status]
+index = 1
+type = "u32"
+protected = true
+"#;
+
+#[test]
+fn a_register_map_generates_a_urap_registers_invocation_in_index_order() {
+    let code = generate_module(SOURCE, "regs").unwrap();
+    assert!(code.contains("urap::urap_registers! {"));
+    assert!(code.contains("pub mod regs {"));
+
+    let setpoint_at = code.find("pub Setpoint: 0, f32, false;").unwrap();
+    let status_at = code.find("pub Status: 1, u32, true;").unwrap();
+    assert!(setpoint_at < status_at);
+}
+
+#[test]
+fn register_names_are_converted_to_pascal_case() {
+    let source = r#"
+        [registers.motor_current]
+        index = 0
+        type = "f32"
+        protected = false
+    "#;
+    let code = generate_module(source, "regs").unwrap();
+    assert!(code.contains("pub MotorCurrent: 0, f32, false;"));
+}
+
+#[test]
+fn a_table_missing_the_registers_key_is_rejected() {
+    let err = generate_module("other_key = 1", "regs").unwrap_err();
+    assert!(matches!(err, CodegenError::MissingRegistersTable));
+}
+
+#[test]
+fn an_unrecognized_register_type_is_rejected() {
+    let source = r#"
+        [registers.setpoint]
+        index = 0
+        type = "f64"
+        protected = false
+    "#;
+    let err = generate_module(source, "regs").unwrap_err();
+    assert!(matches!(err, CodegenError::UnknownType { .. }));
+}
+
+#[test]
+fn a_register_missing_a_required_field_is_rejected() {
+    let source = r#"
+        [registers.setpoint]
+        type = "f32"
+        protected = false
+    "#;
+    let err = generate_module(source, "regs").unwrap_err();
+    assert!(matches!(err, CodegenError::BadRegister { .. }));
+}
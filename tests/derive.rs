@@ -0,0 +1,54 @@
+//! End-to-end exercise of `#[derive(UrapRegisters)]`, over a real Unix
+//! socket pair.
+#![cfg(feature = "derive")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{UrapPrimary, UrapRegisters, UrapSecondary};
+
+#[derive(UrapRegisters)]
+#[repr(C)]
+struct Controller {
+    setpoint: f32,
+    #[urap(read_only)]
+    status: u32,
+    trim: i32,
+}
+
+#[test]
+fn typed_accessors_generated_by_the_derive_round_trip_over_the_wire() {
+    assert_eq!(Controller::REGCNT, 3);
+    assert_eq!(Controller::write_protect(), [false, true, false]);
+    assert_eq!(Controller::SETPOINT, 0);
+    assert_eq!(Controller::STATUS, 1);
+    assert_eq!(Controller::TRIM, 2);
+
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<{ Controller::REGCNT }> =
+            UrapSecondary::new([[0u8; 4]; Controller::REGCNT], Controller::write_protect());
+        for _ in 0..5 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    Controller::write_setpoint(&mut primary, 12.5).unwrap();
+    assert_eq!(Controller::read_setpoint(&mut primary).unwrap(), 12.5);
+
+    Controller::write_trim(&mut primary, -3).unwrap();
+    assert_eq!(Controller::read_trim(&mut primary).unwrap(), -3);
+
+    // `status` is read-only per `#[urap(read_only)]`; a primary-side
+    // write attempt is rejected by the secondary rather than accepted.
+    let err = primary.write_u32(Controller::STATUS, 1).unwrap_err();
+    assert!(matches!(err, urap::Error::Nak(urap::NakCode::IndexWriteProtected)));
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,58 @@
+//! End-to-end exercise of the ChaCha20-Poly1305 encrypted transport
+//! wrapper underneath an ordinary primary/secondary exchange.
+
+#![cfg(feature = "encrypted")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{EncryptedIo, UrapPrimary, UrapSecondary};
+
+const KEY: [u8; 32] = [7u8; 32];
+
+#[test]
+fn write_then_read_round_trip_over_encrypted_transport() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let raw = FromStd::new(secondary_sock);
+        let mut io: EncryptedIo<_> = EncryptedIo::new(raw, KEY, [1, 0, 0, 0], [0, 0, 0, 1]);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io).unwrap();
+        secondary.poll(&mut io).unwrap();
+        secondary
+    });
+
+    let raw = FromStd::new(primary_sock);
+    let mut io: EncryptedIo<_> = EncryptedIo::new(raw, KEY, [0, 0, 0, 1], [1, 0, 0, 0]);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn mismatched_keys_fail_closed() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let raw = FromStd::new(secondary_sock);
+        let mut io: EncryptedIo<_> =
+            EncryptedIo::new(raw, [9u8; 32], [1, 0, 0, 0], [0, 0, 0, 1]);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        let _ = secondary.poll(&mut io);
+    });
+
+    let raw = FromStd::new(primary_sock);
+    let mut io: EncryptedIo<_> = EncryptedIo::new(raw, KEY, [0, 0, 0, 1], [1, 0, 0, 0]);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    let mut readback = [[0u8; 4]; 1];
+    assert!(primary.read_4u8(0, &mut readback).is_err());
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,70 @@
+//! Exercises `read_enum` over a real Unix socket pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{Error, UrapPrimary, UrapSecondary};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Idle,
+    Running,
+    Faulted,
+}
+
+impl TryFrom<u32> for Mode {
+    type Error = ();
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Idle),
+            1 => Ok(Self::Running),
+            2 => Ok(Self::Faulted),
+            _ => Err(()),
+        }
+    }
+}
+
+#[test]
+fn read_enum_converts_a_valid_discriminant() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_u32(0, 1).unwrap();
+    assert_eq!(primary.read_enum::<Mode>(0).unwrap(), Mode::Running);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn read_enum_reports_an_out_of_range_discriminant() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_u32(0, 99).unwrap();
+    let err = primary.read_enum::<Mode>(0).unwrap_err();
+    assert!(matches!(err, Error::InvalidDiscriminant(99)));
+
+    secondary_thread.join().unwrap();
+}
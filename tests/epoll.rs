@@ -0,0 +1,104 @@
+//! End-to-end exercise of the single-threaded, `mio`-based Unix-socket
+//! secondary against the same [`UrapPrimary`] client `tests/usockets.rs`
+//! uses.
+
+#![cfg(all(feature = "epoll", unix))]
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use urap::epoll::EpollSecondary;
+use urap::usockets::{Listener, UrapPrimary};
+use urap::UrapSecondary as CoreSecondary;
+
+fn socket_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("urap-epoll-test-{name}-{}.sock", std::process::id()))
+}
+
+#[test]
+fn read_write_round_trip_over_unix_socket() {
+    let path = socket_path("rw");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = EpollSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn read_only_listener_rejects_writes() {
+    let path = socket_path("ro");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let _server = EpollSecondary::spawn(vec![Listener::read_only(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let result = primary.write_4u8(0, &[[9, 9, 9, 9]]);
+    assert!(result.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn one_event_loop_services_many_concurrent_clients() {
+    let path = socket_path("burst");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<16> = CoreSecondary::new([[0u8; 4]; 16], [false; 16]);
+    let server = EpollSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let clients: Vec<_> = (0..10u8)
+        .map(|n| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+                primary.write_4u8(n as u16, &[[n, n, n, n]]).unwrap();
+                let mut readback = [[0u8; 4]; 1];
+                primary.read_4u8(n as u16, &mut readback).unwrap();
+                readback[0]
+            })
+        })
+        .collect();
+
+    for (n, client) in clients.into_iter().enumerate() {
+        let readback = client.join().unwrap();
+        assert_eq!(readback, [n as u8; 4]);
+    }
+
+    assert!(server.pop_error().is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn shutdown_closes_connections_and_unlinks_socket() {
+    let path = socket_path("shutdown");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let mut server = EpollSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    server.shutdown();
+
+    assert!(!path.exists());
+    let result: io::Result<UrapPrimary> = UrapPrimary::connect(&path);
+    assert!(result.is_err());
+}
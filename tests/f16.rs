@@ -0,0 +1,58 @@
+//! Exercises the half-precision float accessors over a real Unix
+//! socket pair.
+#![cfg(feature = "half")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use half::f16;
+use urap::{UrapPrimary, UrapSecondary};
+
+#[test]
+fn write_f16_preserves_the_untouched_high_half() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..4 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_f16_pair(0, f16::from_f32(1.0), f16::from_f32(2.0)).unwrap();
+    primary.write_f16(0, f16::from_f32(3.5)).unwrap();
+
+    let (low, high) = primary.read_f16_pair(0).unwrap();
+    assert_eq!(low, f16::from_f32(3.5));
+    assert_eq!(high, f16::from_f32(2.0));
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn f16_pair_round_trips_both_halves_of_a_register() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_f16_pair(0, f16::from_f32(-1.5), f16::from_f32(42.0)).unwrap();
+    let (low, high) = primary.read_f16_pair(0).unwrap();
+    assert_eq!(low, f16::from_f32(-1.5));
+    assert_eq!(high, f16::from_f32(42.0));
+
+    secondary_thread.join().unwrap();
+}
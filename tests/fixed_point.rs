@@ -0,0 +1,50 @@
+//! Exercises the Q-format fixed-point accessors over a real Unix
+//! socket pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{UrapPrimary, UrapSecondary};
+
+#[test]
+fn q16_16_round_trips_a_negative_fractional_value() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_q16_16(0, -12.5).unwrap();
+    assert_eq!(primary.read_q16_16(0).unwrap(), -12.5);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn read_fixed_and_write_fixed_honor_an_arbitrary_frac_width() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_fixed::<8>(0, 3.25).unwrap();
+    assert_eq!(primary.read_fixed::<8>(0).unwrap(), 3.25);
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,343 @@
+//! End-to-end exercise of [`GrpcGateway`] against a real URAP secondary,
+//! driven with a hand-rolled HTTP/2+HPACK(non-Huffman)+protobuf client -
+//! there's no real gRPC client crate in this dependency tree, the same
+//! tradeoff the other gateway tests make for their own protocols.
+
+#![cfg(feature = "grpc")]
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use embedded_io_adapters::std::FromStd;
+use urap::grpc::GrpcGateway;
+use urap::{DirtyTracker, NotifySecondary, UrapSecondary};
+
+const CONNECTION_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_DATA: u8 = 0x0;
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_SETTINGS: u8 = 0x4;
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+const FLAG_ACK: u8 = 0x1;
+
+fn loopback_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)
+}
+
+struct Frame {
+    frame_type: u8,
+    flags: u8,
+    stream_id: u32,
+    payload: Vec<u8>,
+}
+
+fn read_frame(stream: &mut TcpStream) -> Frame {
+    let mut header = [0u8; 9];
+    stream.read_exact(&mut header).unwrap();
+    let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload).unwrap();
+    Frame {
+        frame_type: header[3],
+        flags: header[4],
+        stream_id: u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF,
+        payload,
+    }
+}
+
+fn write_frame(stream: &mut TcpStream, frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) {
+    let mut header = [0u8; 9];
+    header[0..3].copy_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    header[3] = frame_type;
+    header[4] = flags;
+    header[5..9].copy_from_slice(&stream_id.to_be_bytes());
+    stream.write_all(&header).unwrap();
+    stream.write_all(payload).unwrap();
+}
+
+/// Encodes headers the same way [`GrpcGateway`] does - HPACK "Literal
+/// Header Field without Indexing", literal name, literal value, never
+/// Huffman-coded - since that's the one representation the gateway's
+/// decoder supports.
+fn encode_headers(headers: &[(&str, &str)]) -> Vec<u8> {
+    fn write_integer(buf: &mut Vec<u8>, value: usize) {
+        buf.push(value as u8); // every length used here fits the 7-bit prefix
+    }
+    let mut buf = Vec::new();
+    for (name, value) in headers {
+        buf.push(0x00);
+        write_integer(&mut buf, name.len());
+        buf.extend_from_slice(name.as_bytes());
+        write_integer(&mut buf, value.len());
+        buf.extend_from_slice(value.as_bytes());
+    }
+    buf
+}
+
+/// Decodes HPACK-literal-without-indexing headers, mirroring
+/// [`encode_headers`]. Good enough for this gateway's own responses,
+/// which never index or Huffman-code anything.
+fn decode_headers(data: &[u8]) -> Vec<(String, String)> {
+    let mut headers = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        assert_eq!(data[pos] & 0xF0, 0x00, "unexpected HPACK representation in gateway response");
+        pos += 1;
+        let name_len = data[pos] as usize;
+        pos += 1;
+        let name = String::from_utf8(data[pos..pos + name_len].to_vec()).unwrap();
+        pos += name_len;
+        let value_len = data[pos] as usize;
+        pos += 1;
+        let value = String::from_utf8(data[pos..pos + value_len].to_vec()).unwrap();
+        pos += value_len;
+        headers.push((name, value));
+    }
+    headers
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        if value < 0x80 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push(((value % 0x80) | 0x80) as u8);
+        value /= 0x80;
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field_number: u32, value: u64) {
+    write_varint(buf, (field_number as u64) << 3);
+    write_varint(buf, value);
+}
+
+fn write_bytes_field(buf: &mut Vec<u8>, field_number: u32, bytes: &[u8]) {
+    write_varint(buf, ((field_number as u64) << 3) | 2);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn grpc_frame(message: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(5 + message.len());
+    framed.push(0);
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed
+}
+
+fn unwrap_grpc_frame(framed: &[u8]) -> &[u8] {
+    let len = u32::from_be_bytes(framed[1..5].try_into().unwrap()) as usize;
+    &framed[5..5 + len]
+}
+
+fn connect_and_handshake(addr: SocketAddr) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    stream.write_all(CONNECTION_PREFACE).unwrap();
+    write_frame(&mut stream, FRAME_SETTINGS, 0, 0, &[]);
+    stream
+}
+
+/// Sends one unary RPC on a fresh stream id and returns the decoded
+/// response message bytes alongside the `grpc-status` the trailer
+/// reported.
+fn unary_call(stream: &mut TcpStream, stream_id: u32, path: &str, message: &[u8]) -> (Vec<u8>, String) {
+    let headers = encode_headers(&[(":method", "POST"), (":path", path), ("content-type", "application/grpc")]);
+    write_frame(stream, FRAME_HEADERS, FLAG_END_HEADERS, stream_id, &headers);
+    write_frame(stream, FRAME_DATA, FLAG_END_STREAM, stream_id, &grpc_frame(message));
+
+    let mut body = Vec::new();
+    let status;
+    loop {
+        let frame = read_frame(stream);
+        if frame.stream_id != stream_id {
+            if frame.frame_type == FRAME_SETTINGS && frame.flags & FLAG_ACK == 0 {
+                write_frame(stream, FRAME_SETTINGS, FLAG_ACK, 0, &[]);
+            }
+            continue;
+        }
+        match frame.frame_type {
+            FRAME_HEADERS if frame.flags & FLAG_END_STREAM != 0 => {
+                let trailers = decode_headers(&frame.payload);
+                status = trailers.into_iter().find(|(name, _)| name == "grpc-status").map(|(_, v)| v).unwrap();
+                break;
+            }
+            FRAME_HEADERS => {}
+            FRAME_DATA => body.extend_from_slice(unwrap_grpc_frame(&frame.payload)),
+            _ => {}
+        }
+    }
+    (body, status)
+}
+
+/// Reads `count` streamed `RegisterUpdate` messages off a `Subscribe`
+/// response stream, ignoring the initial response headers.
+fn read_streamed_updates(stream: &mut TcpStream, stream_id: u32, count: usize) -> Vec<(u32, Vec<u8>)> {
+    let mut updates = Vec::new();
+    while updates.len() < count {
+        let frame = read_frame(stream);
+        if frame.stream_id != stream_id {
+            if frame.frame_type == FRAME_SETTINGS && frame.flags & FLAG_ACK == 0 {
+                write_frame(stream, FRAME_SETTINGS, FLAG_ACK, 0, &[]);
+            }
+            continue;
+        }
+        if frame.frame_type != FRAME_DATA {
+            continue;
+        }
+        let message = unwrap_grpc_frame(&frame.payload);
+        let mut pos = 0;
+        let mut register = 0u32;
+        let mut value = Vec::new();
+        while pos < message.len() {
+            let tag = read_varint(message, &mut pos);
+            match tag >> 3 {
+                1 => register = read_varint(message, &mut pos) as u32,
+                2 => {
+                    let len = read_varint(message, &mut pos) as usize;
+                    value = message[pos..pos + len].to_vec();
+                    pos += len;
+                }
+                _ => unreachable!("unexpected field in RegisterUpdate"),
+            }
+        }
+        updates.push((register, value));
+    }
+    updates
+}
+
+fn spawn_upstream_secondary() -> UnixStream {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+    thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = NotifySecondary::new(
+            UrapSecondary::<4>::new([[1, 2, 3, 4], [5, 6, 7, 8], [0; 4], [0; 4]], [false; 4])
+                .with_write_hook(DirtyTracker::<1>::new()),
+        );
+        loop {
+            if secondary.poll(&mut io).is_err() {
+                return;
+            }
+        }
+    });
+    primary_sock
+}
+
+fn spawn_gateway_at() -> SocketAddr {
+    let upstream = spawn_upstream_secondary();
+
+    let probe = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = probe.local_addr().unwrap();
+    drop(probe);
+
+    let gateway = GrpcGateway::spawn(addr, FromStd::new(upstream)).unwrap();
+    std::mem::forget(gateway);
+
+    thread::sleep(Duration::from_millis(20));
+    addr
+}
+
+#[test]
+fn read_registers_returns_raw_register_bytes() {
+    let addr = spawn_gateway_at();
+    let mut stream = connect_and_handshake(addr);
+
+    let mut request = Vec::new();
+    write_varint_field(&mut request, 1, 0);
+    write_varint_field(&mut request, 2, 2);
+
+    let (body, status) = unary_call(&mut stream, 1, "/urap.Registers/ReadRegisters", &request);
+    assert_eq!(status, "0");
+
+    let mut pos = 0;
+    let tag = read_varint(&body, &mut pos);
+    assert_eq!(tag >> 3, 1);
+    let len = read_varint(&body, &mut pos) as usize;
+    let values = &body[pos..pos + len];
+    assert_eq!(values, &[1, 2, 3, 4, 5, 6, 7, 8]);
+}
+
+#[test]
+fn write_registers_writes_the_register_and_a_following_read_sees_it() {
+    let addr = spawn_gateway_at();
+    let mut stream = connect_and_handshake(addr);
+
+    let mut write_request = Vec::new();
+    write_varint_field(&mut write_request, 1, 2);
+    write_bytes_field(&mut write_request, 2, &[9, 9, 9, 9]);
+    let (_, status) = unary_call(&mut stream, 1, "/urap.Registers/WriteRegisters", &write_request);
+    assert_eq!(status, "0");
+
+    let mut read_request = Vec::new();
+    write_varint_field(&mut read_request, 1, 2);
+    write_varint_field(&mut read_request, 2, 1);
+    let (body, status) = unary_call(&mut stream, 3, "/urap.Registers/ReadRegisters", &read_request);
+    assert_eq!(status, "0");
+
+    let mut pos = 0;
+    read_varint(&body, &mut pos);
+    let len = read_varint(&body, &mut pos) as usize;
+    assert_eq!(&body[pos..pos + len], &[9, 9, 9, 9]);
+}
+
+#[test]
+fn an_unknown_method_is_reported_as_unimplemented() {
+    let addr = spawn_gateway_at();
+    let mut stream = connect_and_handshake(addr);
+    let (_, status) = unary_call(&mut stream, 1, "/urap.Registers/Explode", &[]);
+    assert_eq!(status, "12");
+}
+
+#[test]
+fn subscribe_streams_register_updates_as_they_change() {
+    let addr = spawn_gateway_at();
+    let mut subscribe_stream = connect_and_handshake(addr);
+
+    let mut request = Vec::new();
+    write_varint_field(&mut request, 1, 0);
+    write_varint_field(&mut request, 2, 2);
+    let headers = encode_headers(&[(":method", "POST"), (":path", "/urap.Registers/Subscribe"), ("content-type", "application/grpc")]);
+    write_frame(&mut subscribe_stream, FRAME_HEADERS, FLAG_END_HEADERS, 1, &headers);
+    write_frame(&mut subscribe_stream, FRAME_DATA, FLAG_END_STREAM, 1, &grpc_frame(&request));
+
+    // Drain the initial response headers before the streamed updates.
+    loop {
+        let frame = read_frame(&mut subscribe_stream);
+        if frame.stream_id == 1 && frame.frame_type == FRAME_HEADERS {
+            break;
+        }
+        if frame.frame_type == FRAME_SETTINGS && frame.flags & FLAG_ACK == 0 {
+            write_frame(&mut subscribe_stream, FRAME_SETTINGS, FLAG_ACK, 0, &[]);
+        }
+    }
+
+    // Write register 0 through a second, independent unary connection
+    // to trigger a change notification on the subscribed stream.
+    let mut write_stream = connect_and_handshake(addr);
+    let mut write_request = Vec::new();
+    write_varint_field(&mut write_request, 1, 0);
+    write_bytes_field(&mut write_request, 2, &[7, 7, 7, 7]);
+    let (_, status) = unary_call(&mut write_stream, 1, "/urap.Registers/WriteRegisters", &write_request);
+    assert_eq!(status, "0");
+
+    let updates = read_streamed_updates(&mut subscribe_stream, 1, 1);
+    assert_eq!(updates[0], (0, vec![7, 7, 7, 7]));
+}
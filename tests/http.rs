@@ -0,0 +1,117 @@
+//! End-to-end exercise of the HTTP gateway against a real URAP secondary,
+//! driven with raw `TcpStream` requests rather than an HTTP client crate.
+
+#![cfg(feature = "http")]
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use embedded_io_adapters::std::FromStd;
+use urap::http::HttpGateway;
+use urap::{UrapPrimary, UrapSecondary};
+
+fn loopback_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)
+}
+
+fn spawn_upstream_secondary() -> UnixStream {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+    thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = UrapSecondary::<4>::new([[1, 2, 3, 4], [5, 6, 7, 8], [0; 4], [0; 4]], [false; 4]);
+        loop {
+            if secondary.poll(&mut io).is_err() {
+                return;
+            }
+        }
+    });
+    primary_sock
+}
+
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u32, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = if body.is_empty() {
+        format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\n\r\n")
+    } else {
+        format!(
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+    };
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let response = String::from_utf8_lossy(&response).into_owned();
+
+    let status_line = response.lines().next().unwrap();
+    let status: u32 = status_line.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or("").to_string();
+    (status, body)
+}
+
+fn spawn_gateway_at() -> SocketAddr {
+    let upstream = spawn_upstream_secondary();
+    let primary: UrapPrimary<_, 4> = UrapPrimary::new(FromStd::new(upstream));
+
+    // Bind an ephemeral port ourselves so the test knows the address
+    // before the gateway's own listener comes up, then hand it to
+    // `HttpGateway::spawn`.
+    let probe = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = probe.local_addr().unwrap();
+    drop(probe);
+
+    let gateway = HttpGateway::spawn(addr, primary).unwrap();
+    // Keep the gateway alive for the rest of the test process; tests are
+    // single-shot processes, so leaking it here is fine.
+    std::mem::forget(gateway);
+
+    // The listener may not have finished binding inside the spawned
+    // thread yet; give it a moment before the first request.
+    thread::sleep(Duration::from_millis(20));
+    addr
+}
+
+#[test]
+fn get_a_single_register_returns_its_raw_bytes() {
+    let addr = spawn_gateway_at();
+    let (status, body) = request(addr, "GET", "/registers/0", "");
+    assert_eq!(status, 200);
+    assert_eq!(body, "{\"register\":0,\"value\":[1,2,3,4]}");
+}
+
+#[test]
+fn get_a_range_of_registers_returns_every_value() {
+    let addr = spawn_gateway_at();
+    let (status, body) = request(addr, "GET", "/registers/0?count=2", "");
+    assert_eq!(status, 200);
+    assert_eq!(body, "{\"register\":0,\"count\":2,\"values\":[[1,2,3,4],[5,6,7,8]]}");
+}
+
+#[test]
+fn put_a_single_register_writes_it_and_a_following_get_sees_it() {
+    let addr = spawn_gateway_at();
+    let (status, _) = request(addr, "PUT", "/registers/2", "{\"value\":[9,9,9,9]}");
+    assert_eq!(status, 200);
+
+    let (status, body) = request(addr, "GET", "/registers/2", "");
+    assert_eq!(status, 200);
+    assert_eq!(body, "{\"register\":2,\"value\":[9,9,9,9]}");
+}
+
+#[test]
+fn put_with_malformed_json_is_rejected() {
+    let addr = spawn_gateway_at();
+    let (status, _) = request(addr, "PUT", "/registers/0", "not json");
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn an_unknown_path_is_not_found() {
+    let addr = spawn_gateway_at();
+    let (status, _) = request(addr, "GET", "/nope", "");
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,116 @@
+//! Exercises [`urap::i2c`] against a small in-memory mock I2C device, since
+//! `embedded-hal` itself ships no test utilities.
+
+#![cfg(feature = "i2c")]
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+use urap::i2c::I2cPrimary;
+use urap::{UrapPrimary, UrapSecondary};
+
+const ADDRESS: u8 = 0x42;
+
+/// A secondary that answers a single write-then-read transaction by
+/// feeding the request through a [`UrapSecondary`] and capturing whatever
+/// it wrote back, the way a simple I2C register device would.
+struct MockI2cDevice {
+    secondary: UrapSecondary<4>,
+}
+
+/// A `Read`+`Write` pair backed by plain byte buffers, so [`UrapSecondary`]
+/// can run against the request/response bytes of one transaction.
+struct Buffers<'a> {
+    request: &'a [u8],
+    pos: usize,
+    response: heapless_vec::Vec,
+}
+
+mod heapless_vec {
+    pub struct Vec {
+        bytes: [u8; 512],
+        len: usize,
+    }
+
+    impl Vec {
+        pub fn new() -> Self {
+            Self { bytes: [0u8; 512], len: 0 }
+        }
+
+        pub fn extend(&mut self, data: &[u8]) {
+            self.bytes[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            &self.bytes[..self.len]
+        }
+    }
+}
+
+impl<'a> embedded_io::ErrorType for Buffers<'a> {
+    type Error = core::convert::Infallible;
+}
+
+impl<'a> embedded_io::Read for Buffers<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(self.request.len() - self.pos);
+        buf[..n].copy_from_slice(&self.request[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<'a> embedded_io::Write for Buffers<'a> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.response.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl ErrorType for MockI2cDevice {
+    type Error = core::convert::Infallible;
+}
+
+impl I2c<SevenBitAddress> for MockI2cDevice {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        assert_eq!(address, ADDRESS);
+
+        let [Operation::Write(request), Operation::Read(read)] = operations else {
+            panic!("expected a single write-then-read transaction");
+        };
+
+        let mut buffers = Buffers { request, pos: 0, response: heapless_vec::Vec::new() };
+        self.secondary.poll(&mut buffers).unwrap();
+
+        let response = buffers.response.as_slice();
+        let n = read.len().min(response.len());
+        read[..n].copy_from_slice(&response[..n]);
+        Ok(())
+    }
+}
+
+#[test]
+fn write_then_read_round_trip_over_i2c() {
+    let device = MockI2cDevice { secondary: UrapSecondary::new([[0u8; 4]; 4], [false; 4]) };
+    let mut io: I2cPrimary<_, u8> = I2cPrimary::new(device, ADDRESS);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+}
+
+#[test]
+fn a_nak_is_surfaced_as_an_error() {
+    let device = MockI2cDevice { secondary: UrapSecondary::new([[0u8; 4]; 4], [false; 4]) };
+    let mut io: I2cPrimary<_, u8> = I2cPrimary::new(device, ADDRESS);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    let mut readback = [[0u8; 4]; 1];
+    assert!(primary.read_4u8(10, &mut readback).is_err());
+}
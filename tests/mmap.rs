@@ -0,0 +1,57 @@
+//! Exercises a register map backed by a real memory-mapped file.
+
+#![cfg(feature = "mmap")]
+
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::mmap::MmapRegisters;
+use urap::{RegisterStore, UrapPrimary, UrapSecondary};
+
+fn tmp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("urap-mmap-test-{name}-{}.bin", std::process::id()))
+}
+
+#[test]
+fn write_then_read_round_trips_through_the_mapped_file() {
+    let path = tmp_path("round-trip");
+    let _ = std::fs::remove_file(&path);
+
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+    let store = MmapRegisters::<4>::open(&path, 4).unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = UrapSecondary::<4>::new([[0u8; 4]; 4], [false; 4]).with_store(store);
+        secondary.poll(&mut io).unwrap();
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    primary.write_4u8(1, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(1, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    secondary_thread.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn writes_are_visible_to_a_second_mapping_of_the_same_file() {
+    let path = tmp_path("shared");
+    let _ = std::fs::remove_file(&path);
+
+    let mut writer = MmapRegisters::<4>::open(&path, 2).unwrap();
+    writer.write(0, [9, 9, 9, 9]);
+    writer.flush().unwrap();
+
+    let mut reader = MmapRegisters::<4>::open(&path, 2).unwrap();
+    assert_eq!(reader.read(0), [9, 9, 9, 9]);
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,178 @@
+//! End-to-end exercise of the Modbus/TCP and Modbus RTU gateway against
+//! a real URAP secondary.
+
+#![cfg(all(feature = "modbus", feature = "tcp"))]
+
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use embedded_io_adapters::std::FromStd;
+use urap::modbus::ModbusGateway;
+use urap::tcp::{Listener, UrapSecondary};
+use urap::{UrapPrimary, UrapSecondary as CoreSecondary};
+
+fn loopback_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)
+}
+
+fn spawn_upstream_secondary() -> SocketAddr {
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    let _server = UrapSecondary::spawn(vec![Listener::read_write(addr)], secondary).unwrap();
+    thread::sleep(Duration::from_millis(20));
+    addr
+}
+
+fn connect_upstream_primary(addr: SocketAddr) -> UrapPrimary<FromStd<TcpStream>, 4> {
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    UrapPrimary::new(FromStd::new(stream))
+}
+
+fn modbus_crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            if crc & 0x0001 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
+
+fn read_response_pdu(stream: &mut TcpStream) -> Vec<u8> {
+    use std::io::Read;
+    let mut mbap = [0u8; 7];
+    stream.read_exact(&mut mbap).unwrap();
+    let length = u16::from_be_bytes([mbap[4], mbap[5]]);
+    let mut pdu = vec![0u8; length as usize - 1];
+    stream.read_exact(&mut pdu).unwrap();
+    pdu
+}
+
+fn send_request(stream: &mut TcpStream, pdu: &[u8]) {
+    use std::io::Write;
+    let mut frame = vec![0, 1, 0, 0];
+    frame.extend_from_slice(&((pdu.len() + 1) as u16).to_be_bytes());
+    frame.push(1);
+    frame.extend_from_slice(pdu);
+    stream.write_all(&frame).unwrap();
+}
+
+#[test]
+fn write_then_read_holding_registers_over_modbus_tcp() {
+    let upstream_addr = spawn_upstream_secondary();
+    let primary = connect_upstream_primary(upstream_addr);
+
+    let modbus_listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let modbus_addr = modbus_listener.local_addr().unwrap();
+    drop(modbus_listener);
+    let gateway = ModbusGateway::spawn(modbus_addr, primary).unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let mut client = TcpStream::connect(modbus_addr).unwrap();
+    client.set_nodelay(true).unwrap();
+
+    // Write URAP register 0 (Modbus addresses 0 and 1) via function code 16.
+    let mut write_pdu = vec![0x10, 0, 0, 0, 2, 4];
+    write_pdu.extend_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+    send_request(&mut client, &write_pdu);
+    let write_response = read_response_pdu(&mut client);
+    assert_eq!(write_response, [0x10, 0, 0, 0, 2]);
+
+    // Read it back via function code 3.
+    send_request(&mut client, &[0x03, 0, 0, 0, 2]);
+    let read_response = read_response_pdu(&mut client);
+    assert_eq!(read_response, [0x03, 4, 0x01, 0x02, 0x03, 0x04]);
+
+    assert!(gateway.pop_error().is_none());
+}
+
+#[test]
+fn write_single_register_is_a_read_modify_write() {
+    let upstream_addr = spawn_upstream_secondary();
+    let primary = connect_upstream_primary(upstream_addr);
+
+    let modbus_listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let modbus_addr = modbus_listener.local_addr().unwrap();
+    drop(modbus_listener);
+    let _gateway = ModbusGateway::spawn(modbus_addr, primary).unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let mut client = TcpStream::connect(modbus_addr).unwrap();
+    client.set_nodelay(true).unwrap();
+
+    // Set the high word (Modbus address 0) without touching the low word.
+    send_request(&mut client, &[0x06, 0, 0, 0xAA, 0xBB]);
+    let response = read_response_pdu(&mut client);
+    assert_eq!(response, [0x06, 0, 0, 0xAA, 0xBB]);
+
+    // Set the low word (Modbus address 1).
+    send_request(&mut client, &[0x06, 0, 1, 0xCC, 0xDD]);
+    let response = read_response_pdu(&mut client);
+    assert_eq!(response, [0x06, 0, 1, 0xCC, 0xDD]);
+
+    send_request(&mut client, &[0x03, 0, 0, 0, 2]);
+    let read_response = read_response_pdu(&mut client);
+    assert_eq!(read_response, [0x03, 4, 0xAA, 0xBB, 0xCC, 0xDD]);
+}
+
+#[test]
+fn illegal_address_is_reported_as_a_modbus_exception() {
+    let upstream_addr = spawn_upstream_secondary();
+    let primary = connect_upstream_primary(upstream_addr);
+
+    let modbus_listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let modbus_addr = modbus_listener.local_addr().unwrap();
+    drop(modbus_listener);
+    let _gateway = ModbusGateway::spawn(modbus_addr, primary).unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let mut client = TcpStream::connect(modbus_addr).unwrap();
+    client.set_nodelay(true).unwrap();
+
+    // Odd address, not a whole URAP register.
+    send_request(&mut client, &[0x03, 0, 1, 0, 2]);
+    let response = read_response_pdu(&mut client);
+    assert_eq!(response, [0x03 | 0x80, 0x03]);
+}
+
+#[test]
+fn read_holding_registers_round_trip_over_modbus_rtu() {
+    let upstream_addr = spawn_upstream_secondary();
+    let primary = connect_upstream_primary(upstream_addr);
+    let gateway = ModbusGateway::spawn(loopback_addr(), primary).unwrap();
+
+    let (master_end, slave_end) = UnixStream::pair().unwrap();
+    let server_thread = thread::spawn(move || {
+        gateway.serve_rtu(FromStd::new(slave_end)).unwrap();
+    });
+
+    use std::io::{Read, Write};
+    let mut master = master_end;
+
+    let mut frame = vec![0x01, 0x03, 0, 0, 0, 2];
+    frame.extend_from_slice(&modbus_crc16(&frame).to_le_bytes());
+    master.write_all(&frame).unwrap();
+
+    let mut response = [0u8; 1 + 1 + 1 + 4 + 2];
+    master.read_exact(&mut response).unwrap();
+    assert_eq!(&response[..3], [0x01, 0x03, 4]);
+    let (payload, crc_bytes) = response[3..].split_at(4);
+    assert_eq!(payload, [0, 0, 0, 0]);
+    assert_eq!(
+        u16::from_le_bytes([crc_bytes[0], crc_bytes[1]]),
+        modbus_crc16(&response[..response.len() - 2])
+    );
+
+    drop(master);
+    server_thread.join().unwrap();
+}
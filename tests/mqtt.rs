@@ -0,0 +1,180 @@
+//! End-to-end exercise of the MQTT bridge against a real URAP secondary
+//! and a minimal fake broker that speaks just enough MQTT v3.1.1 to
+//! drive [`MqttBridge`].
+
+#![cfg(feature = "mqtt")]
+
+use std::io::{Read as StdRead, Write as StdWrite};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use embedded_io_adapters::std::FromStd;
+use urap::mqtt::MqttBridge;
+use urap::{DirtyTracker, NotifySecondary, UrapSecondary};
+
+fn loopback_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)
+}
+
+fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut packet_type = [0u8; 1];
+    stream.read_exact(&mut packet_type).unwrap();
+
+    let mut remaining_length = 0usize;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).unwrap();
+        remaining_length += ((byte[0] & 0x7F) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+
+    let mut payload = vec![0u8; remaining_length];
+    stream.read_exact(&mut payload).unwrap();
+    (packet_type[0], payload)
+}
+
+fn write_packet(stream: &mut TcpStream, packet_type: u8, payload: &[u8]) {
+    let mut remaining_length = payload.len();
+    let mut frame = vec![packet_type];
+    loop {
+        let mut byte = (remaining_length % 0x80) as u8;
+        remaining_length /= 0x80;
+        if remaining_length > 0 {
+            byte |= 0x80;
+        }
+        frame.push(byte);
+        if remaining_length == 0 {
+            break;
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame).unwrap();
+}
+
+fn publish(stream: &mut TcpStream, topic: &str, message: &[u8]) {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&(topic.len() as u16).to_be_bytes());
+    payload.extend_from_slice(topic.as_bytes());
+    payload.extend_from_slice(message);
+    write_packet(stream, 0x30, &payload);
+}
+
+fn split_publish_payload(payload: &[u8]) -> (&str, &[u8]) {
+    let topic_len = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+    let topic = std::str::from_utf8(&payload[2..2 + topic_len]).unwrap();
+    (topic, &payload[2 + topic_len..])
+}
+
+/// Accepts one connection and completes the `CONNECT`/`SUBSCRIBE`
+/// handshake a broker would, returning the accepted stream for the test
+/// to drive directly.
+fn accept_and_handshake(listener: &TcpListener) -> TcpStream {
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let (packet_type, _) = read_packet(&mut stream);
+    assert_eq!(packet_type, 0x10); // CONNECT
+    write_packet(&mut stream, 0x20, &[0, 0]); // CONNACK, session present = 0, return code = 0
+
+    let (packet_type, payload) = read_packet(&mut stream);
+    assert_eq!(packet_type, 0x82); // SUBSCRIBE
+    write_packet(&mut stream, 0x90, &[payload[0], payload[1], 0]); // SUBACK, granted QoS 0
+
+    stream
+}
+
+fn spawn_upstream_secondary() -> UnixStream {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+    thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let inner = UrapSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+            .with_write_hook(DirtyTracker::<1>::new());
+        let mut secondary = NotifySecondary::new(inner);
+        loop {
+            if secondary.poll(&mut io).is_err() {
+                return;
+            }
+        }
+    });
+    primary_sock
+}
+
+#[test]
+fn a_command_message_writes_the_register_and_the_write_is_published_back() {
+    let upstream = spawn_upstream_secondary();
+
+    let broker_listener = TcpListener::bind(loopback_addr()).unwrap();
+    let broker_addr = broker_listener.local_addr().unwrap();
+    let accept_thread = thread::spawn(move || accept_and_handshake(&broker_listener));
+
+    let _bridge = MqttBridge::spawn(
+        broker_addr,
+        "urap-bridge-test",
+        "urap/test",
+        0..4,
+        Duration::from_millis(10),
+        FromStd::new(upstream),
+    )
+    .unwrap();
+
+    let mut broker = accept_thread.join().unwrap();
+
+    publish(&mut broker, "urap/test/0/set", &[9, 9, 9, 9]);
+
+    loop {
+        let (packet_type, payload) = read_packet(&mut broker);
+        if packet_type & 0xF0 != 0x30 {
+            continue;
+        }
+        let (topic, message) = split_publish_payload(&payload);
+        if topic == "urap/test/0" {
+            assert_eq!(message, [9, 9, 9, 9]);
+            break;
+        }
+    }
+}
+
+#[test]
+fn a_message_on_an_unrelated_topic_is_ignored() {
+    let upstream = spawn_upstream_secondary();
+
+    let broker_listener = TcpListener::bind(loopback_addr()).unwrap();
+    let broker_addr = broker_listener.local_addr().unwrap();
+    let accept_thread = thread::spawn(move || accept_and_handshake(&broker_listener));
+
+    let bridge = MqttBridge::spawn(
+        broker_addr,
+        "urap-bridge-test",
+        "urap/test",
+        0..4,
+        Duration::from_millis(10),
+        FromStd::new(upstream),
+    )
+    .unwrap();
+
+    let mut broker = accept_thread.join().unwrap();
+
+    publish(&mut broker, "some/other/topic", &[1, 2, 3, 4]);
+    // Followed by a real command, so we can confirm the bridge kept
+    // reading past the message it ignored.
+    publish(&mut broker, "urap/test/1/set", &[5, 6, 7, 8]);
+
+    loop {
+        let (packet_type, payload) = read_packet(&mut broker);
+        if packet_type & 0xF0 != 0x30 {
+            continue;
+        }
+        let (topic, message) = split_publish_payload(&payload);
+        if topic == "urap/test/1" {
+            assert_eq!(message, [5, 6, 7, 8]);
+            break;
+        }
+    }
+
+    assert!(bridge.pop_error().is_none());
+}
@@ -0,0 +1,96 @@
+//! Exercises symbolic name lookup over a real Unix socket pair.
+#![cfg(feature = "names")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{Error, NakCode, NamesOutcome, NamesPrimary, NamesSecondary, PollOutcome, UrapSecondary};
+
+fn new_secondary() -> NamesSecondary<3, 4, 3> {
+    NamesSecondary::new(
+        UrapSecondary::new([[0u8; 4]; 3], [false, false, false]),
+        [("motor.speed", 0), ("motor.current", 1), ("status", 2)],
+    )
+}
+
+#[test]
+fn a_looked_up_name_resolves_to_its_declared_index() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = new_secondary();
+        let outcome = secondary.poll(&mut io).unwrap();
+        assert!(matches!(
+            outcome,
+            NamesOutcome::Lookup { index: Some(1), nak: None, .. }
+        ));
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut names = NamesPrimary::<_, 4>::new(&mut io);
+    assert_eq!(names.lookup("motor.current").unwrap(), 1);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn an_unknown_name_is_rejected_with_name_not_found() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = new_secondary();
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut names = NamesPrimary::<_, 4>::new(&mut io);
+    let err = names.lookup("no.such.register").unwrap_err();
+    assert!(matches!(err, Error::Nak(NakCode::NameNotFound)));
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn read_by_name_and_write_by_name_round_trip_through_the_resolved_register() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = new_secondary();
+        for _ in 0..4 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut names = NamesPrimary::<_, 4>::new(&mut io);
+
+    names.write_by_name("motor.speed", [1, 2, 3, 4]).unwrap();
+    assert_eq!(names.read_by_name("motor.speed").unwrap(), [1, 2, 3, 4]);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn plain_reads_and_writes_are_forwarded_unchanged() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary = new_secondary();
+        let outcome = secondary.poll(&mut io).unwrap();
+        assert!(matches!(
+            outcome,
+            NamesOutcome::Forwarded(PollOutcome::Write { register: 0, count: 1, nak: None })
+        ));
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: urap::UrapPrimary<_, 4> = urap::UrapPrimary::new(&mut io);
+    primary.write_4u8(0, &[[9, 9, 9, 9]]).unwrap();
+
+    secondary_thread.join().unwrap();
+}
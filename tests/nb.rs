@@ -0,0 +1,72 @@
+//! Exercises [`urap::nb::try_poll`] against a small in-memory mock serial
+//! peripheral, since `embedded-hal-nb` itself ships no test utilities.
+
+#![cfg(feature = "nb")]
+
+use std::collections::VecDeque;
+
+use embedded_hal_nb::serial::{ErrorType, Read, Write};
+use urap::nb::try_poll;
+use urap::{crc16, UrapSecondary, OP_WRITE};
+
+/// A half-duplex serial line backed by byte queues, with no buffering
+/// beyond what's already queued: `read()` reports `WouldBlock` once the
+/// queue runs dry, exactly like a real UART with an empty RX FIFO.
+#[derive(Default)]
+struct MockSerial {
+    rx: VecDeque<u8>,
+    tx: VecDeque<u8>,
+}
+
+impl ErrorType for MockSerial {
+    type Error = core::convert::Infallible;
+}
+
+impl Read<u8> for MockSerial {
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.rx.pop_front().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl Write<u8> for MockSerial {
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.tx.push_back(word);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Hand-assembles the wire bytes of a single-register write request, the
+/// way they'd arrive one at a time from the line.
+fn write_request_bytes(register: u16, data: [u8; 4]) -> VecDeque<u8> {
+    let mut header = [OP_WRITE, 0, 0, 1];
+    header[1..3].copy_from_slice(&register.to_le_bytes());
+    let mut packet = Vec::from(header);
+    packet.extend_from_slice(&data);
+    packet.extend_from_slice(&crc16(&packet).to_le_bytes());
+    packet.into()
+}
+
+#[test]
+fn an_idle_line_reports_would_block_without_touching_the_secondary() {
+    let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let mut serial = MockSerial::default();
+
+    assert!(matches!(try_poll(&mut secondary, &mut serial), Err(nb::Error::WouldBlock)));
+    assert_eq!(secondary.stats().packets, 0);
+}
+
+#[test]
+fn a_request_that_has_started_arriving_is_serviced() {
+    let mut secondary: UrapSecondary<4> = UrapSecondary::new([[9u8; 4]; 4], [false; 4]);
+    let mut serial = MockSerial { rx: write_request_bytes(0, [1, 2, 3, 4]), ..Default::default() };
+
+    try_poll(&mut secondary, &mut serial).unwrap();
+    assert_eq!(secondary.regs()[0], [1, 2, 3, 4]);
+
+    let response: Vec<u8> = serial.tx.into_iter().collect();
+    assert_eq!(response[0], urap::OP_ACK);
+}
@@ -0,0 +1,62 @@
+//! End-to-end change-notification subscription exchange over a real Unix
+//! socket pair.
+
+#![cfg(feature = "notify")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{DirtyTracker, NakCode, NotifyPrimary, NotifySecondary, UrapPrimary, UrapSecondary};
+
+#[test]
+fn poll_after_subscribe_reports_changed_registers_in_range() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let inner = UrapSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+            .with_write_hook(DirtyTracker::<1>::new());
+        let mut secondary = NotifySecondary::new(inner);
+        for _ in 0..4 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    NotifyPrimary::new(&mut io).subscribe(0, 2).unwrap();
+
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    primary.write_4u8(3, &[[5, 6, 7, 8]]).unwrap();
+
+    let mut changed = [0u16; 8];
+    let reported = NotifyPrimary::new(&mut io)
+        .poll_notifications(&mut changed)
+        .unwrap();
+    assert_eq!(&changed[..reported], &[0]);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn poll_without_a_subscription_is_rejected() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let inner = UrapSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+            .with_write_hook(DirtyTracker::<1>::new());
+        let mut secondary = NotifySecondary::new(inner);
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut notify = NotifyPrimary::new(&mut io);
+
+    let mut changed = [0u16; 8];
+    let err = notify.poll_notifications(&mut changed).unwrap_err();
+    assert!(matches!(err, urap::Error::Nak(NakCode::NotSubscribed)));
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,67 @@
+//! End-to-end pipelined exchange over a real Unix socket pair: several
+//! requests queued before any response is read.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{Error, NakCode, PipelinedPrimary, UrapSecondary};
+
+#[test]
+fn queued_requests_are_answered_in_order() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        for _ in 0..3 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: PipelinedPrimary<_, 4> = PipelinedPrimary::new(&mut io);
+
+    primary.queue_write(0, &[[1, 2, 3, 4]]).unwrap();
+    primary.queue_read(0, 1).unwrap();
+    primary.queue_read(2, 2).unwrap();
+    assert_eq!(primary.outstanding(), 3);
+
+    primary.recv_write().unwrap();
+    let mut first = [[0u8; 4]; 1];
+    primary.recv_read(&mut first).unwrap();
+    assert_eq!(first[0], [1, 2, 3, 4]);
+    let mut rest = [[0u8; 4]; 2];
+    primary.recv_read(&mut rest).unwrap();
+    assert_eq!(primary.outstanding(), 0);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn a_nak_on_one_queued_request_does_not_desync_the_next() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+        secondary.poll(&mut io).unwrap();
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: PipelinedPrimary<_, 4> = PipelinedPrimary::new(&mut io);
+
+    // Out of bounds: 4 registers in the store, this reads past the end.
+    primary.queue_read(10, 1).unwrap();
+    primary.queue_read(0, 4).unwrap();
+
+    let mut oob = [[0u8; 4]; 1];
+    let err = primary.recv_read(&mut oob).unwrap_err();
+    assert!(matches!(err, Error::Nak(NakCode::IndexOutOfBounds)));
+
+    let mut readback = [[0u8; 4]; 4];
+    primary.recv_read(&mut readback).unwrap();
+
+    secondary_thread.join().unwrap();
+}
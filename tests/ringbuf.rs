@@ -0,0 +1,109 @@
+//! Exercises [`urap::ringbuf`] both as a plain SPSC queue and as a front
+//! end for [`urap::UrapSecondary::poll`].
+
+#![cfg(feature = "ringbuf")]
+
+use std::thread;
+use std::time::Duration;
+
+use urap::ringbuf::{try_poll, RingBuffer};
+use urap::{crc16, UrapSecondary, OP_WRITE};
+
+#[test]
+fn a_consumer_reads_back_exactly_what_the_producer_pushed() {
+    let mut ring: RingBuffer<4> = RingBuffer::new();
+    let (producer, consumer) = ring.split();
+
+    assert!(consumer.is_empty());
+    producer.push(1).unwrap();
+    producer.push(2).unwrap();
+    producer.push(3).unwrap();
+
+    // Capacity is `N - 1`: one slot is reserved to tell full from empty.
+    assert!(producer.push(4).is_err());
+
+    assert_eq!(consumer.pop(), Some(1));
+    assert_eq!(consumer.pop(), Some(2));
+    assert_eq!(consumer.pop(), Some(3));
+    assert_eq!(consumer.pop(), None);
+    assert!(consumer.is_empty());
+}
+
+#[test]
+fn a_producer_and_consumer_on_separate_threads_agree_on_every_byte() {
+    let mut ring: RingBuffer<8> = RingBuffer::new();
+    let (producer, consumer) = ring.split();
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for byte in 0..200u16 {
+                while producer.push(byte as u8).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut received = Vec::with_capacity(200);
+        while received.len() < 200 {
+            match consumer.pop() {
+                Some(byte) => received.push(byte),
+                None => thread::yield_now(),
+            }
+        }
+
+        let expected: Vec<u8> = (0..200u16).map(|byte| byte as u8).collect();
+        assert_eq!(received, expected);
+    });
+}
+
+fn write_request_bytes(register: u16, data: [u8; 4]) -> Vec<u8> {
+    let mut header = [OP_WRITE, 0, 0, 1];
+    header[1..3].copy_from_slice(&register.to_le_bytes());
+    let mut packet = Vec::from(header);
+    packet.extend_from_slice(&data);
+    packet.extend_from_slice(&crc16(&packet).to_le_bytes());
+    packet
+}
+
+#[test]
+fn an_idle_queue_reports_none_without_touching_the_secondary() {
+    let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let mut ring: RingBuffer<32> = RingBuffer::new();
+    let (_producer, consumer) = ring.split();
+    let mut response = Vec::new();
+
+    assert!(try_poll(&mut secondary, &consumer, &mut response).is_none());
+    assert_eq!(secondary.stats().packets, 0);
+}
+
+#[test]
+fn a_request_fed_byte_by_byte_from_another_thread_is_serviced() {
+    let mut secondary: UrapSecondary<4> = UrapSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let mut ring: RingBuffer<32> = RingBuffer::new();
+    let (producer, consumer) = ring.split();
+    let mut response = Vec::new();
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for byte in write_request_bytes(1, [5, 6, 7, 8]) {
+                thread::sleep(Duration::from_micros(50));
+                while producer.push(byte).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        // A real main loop calls `try_poll` on every tick regardless of
+        // whether a byte has shown up yet; keep retrying until it has.
+        loop {
+            if let Some(result) = try_poll(&mut secondary, &consumer, &mut response) {
+                result.expect("the fully-received request should be serviced");
+                break;
+            }
+            thread::yield_now();
+        }
+    });
+
+    assert_eq!(secondary.regs()[1], [5, 6, 7, 8]);
+    assert_eq!(response[0], urap::OP_ACK);
+}
@@ -0,0 +1,60 @@
+//! Exercises [`ScaledRegister`] over a real Unix socket pair.
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{ScaledRegister, UrapPrimary, UrapSecondary};
+
+#[test]
+fn read_converts_a_raw_count_to_engineering_units() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    // 4-20mA over a 0..4095 raw count, offset 4.0, gain 16.0/4095.
+    let scale = ScaledRegister::new(0, 4.0, 16.0 / 4095.0);
+
+    primary.write_u32(0, 4095).unwrap();
+    assert!((scale.read(&mut primary).unwrap() - 20.0).abs() < 1e-3);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn write_converts_engineering_units_to_a_rounded_raw_count() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    let scale = ScaledRegister::new(0, 4.0, 16.0 / 4095.0);
+    scale.write(&mut primary, 12.0).unwrap();
+    assert!((primary.read_u32(0).unwrap() as i64 - 2048).abs() <= 1);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn to_raw_clamps_out_of_range_engineering_values() {
+    let scale = ScaledRegister::new(0, 0.0, 1.0);
+    assert_eq!(scale.to_raw(-5.0), 0);
+    assert_eq!(scale.to_raw(1e12), u32::MAX);
+}
@@ -0,0 +1,71 @@
+//! Exercises saving and loading register snapshots against real files.
+
+#![cfg(feature = "snapshot")]
+
+use std::path::PathBuf;
+
+use urap::snapshot::{self, SnapshotError};
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("urap-snapshot-test-{name}-{}.bin", std::process::id()));
+    path
+}
+
+#[test]
+fn save_then_load_round_trips_the_full_register_map() {
+    let path = tmp_path("round-trip");
+    let regs: [[u8; 4]; 3] = [[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]];
+
+    snapshot::save(&path, &regs).unwrap();
+
+    let mut loaded = [[0u8; 4]; 3];
+    snapshot::load(&path, &mut loaded).unwrap();
+    assert_eq!(loaded, regs);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn save_then_load_round_trips_a_sub_range() {
+    let path = tmp_path("range");
+    let data: [[u8; 4]; 2] = [[42, 0, 0, 0], [43, 0, 0, 0]];
+
+    snapshot::save_range(&path, 5, &data).unwrap();
+
+    let mut loaded = [[0u8; 4]; 2];
+    snapshot::load_range(&path, 5, &mut loaded).unwrap();
+    assert_eq!(loaded, data);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn load_rejects_a_register_offset_mismatch() {
+    let path = tmp_path("register-mismatch");
+    let data: [[u8; 4]; 1] = [[1, 2, 3, 4]];
+    snapshot::save_range(&path, 5, &data).unwrap();
+
+    let mut loaded = [[0u8; 4]; 1];
+    let err = snapshot::load_range(&path, 6, &mut loaded).unwrap_err();
+    assert!(matches!(err, SnapshotError::RegisterMismatch { expected: 6, found: 5 }));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn load_rejects_a_corrupted_file() {
+    let path = tmp_path("corrupt");
+    let regs: [[u8; 4]; 2] = [[1, 2, 3, 4], [5, 6, 7, 8]];
+    snapshot::save(&path, &regs).unwrap();
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 0xFF;
+    std::fs::write(&path, &bytes).unwrap();
+
+    let mut loaded = [[0u8; 4]; 2];
+    let err = snapshot::load(&path, &mut loaded).unwrap_err();
+    assert!(matches!(err, SnapshotError::BadCrc));
+
+    std::fs::remove_file(&path).unwrap();
+}
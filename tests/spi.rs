@@ -0,0 +1,165 @@
+//! Exercises [`urap::spi`] against a small in-memory mock SPI device, since
+//! `embedded-hal` itself ships no test utilities.
+
+#![cfg(feature = "spi")]
+
+use embedded_hal::digital::{ErrorType as PinErrorType, InputPin};
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use urap::spi::{GpioReady, SpiPrimary};
+use urap::{UrapPrimary, UrapSecondary};
+
+/// A secondary that answers each write/read SPI transaction by feeding the
+/// request through a [`UrapSecondary`] and capturing whatever it wrote
+/// back, the way a simple SPI register device would.
+struct MockSpiDevice {
+    secondary: UrapSecondary<4>,
+    request: heapless_vec::Vec,
+}
+
+/// A `Read`+`Write` pair backed by plain byte buffers, so [`UrapSecondary`]
+/// can run against the request/response bytes of one transaction.
+struct Buffers<'a> {
+    request: &'a [u8],
+    pos: usize,
+    response: heapless_vec::Vec,
+}
+
+mod heapless_vec {
+    pub struct Vec {
+        bytes: [u8; 512],
+        len: usize,
+    }
+
+    impl Vec {
+        pub fn new() -> Self {
+            Self { bytes: [0u8; 512], len: 0 }
+        }
+
+        pub fn extend(&mut self, data: &[u8]) {
+            self.bytes[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            &self.bytes[..self.len]
+        }
+    }
+}
+
+impl<'a> embedded_io::ErrorType for Buffers<'a> {
+    type Error = core::convert::Infallible;
+}
+
+impl<'a> embedded_io::Read for Buffers<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let n = buf.len().min(self.request.len() - self.pos);
+        buf[..n].copy_from_slice(&self.request[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<'a> embedded_io::Write for Buffers<'a> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.response.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl ErrorType for MockSpiDevice {
+    type Error = core::convert::Infallible;
+}
+
+impl SpiDevice<u8> for MockSpiDevice {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        match operations {
+            [Operation::Write(data)] => {
+                self.request = heapless_vec::Vec::new();
+                self.request.extend(data);
+                Ok(())
+            }
+            [Operation::TransferInPlace(buf)] => {
+                let mut buffers = Buffers { request: self.request.as_slice(), pos: 0, response: heapless_vec::Vec::new() };
+                self.secondary.poll(&mut buffers).unwrap();
+
+                let response = buffers.response.as_slice();
+                let n = buf.len().min(response.len());
+                buf[..n].copy_from_slice(&response[..n]);
+                Ok(())
+            }
+            other => panic!("unexpected SPI transaction: {other:?}"),
+        }
+    }
+}
+
+/// A ready pin that reports not-ready for the first `delay` polls, then
+/// ready, so a test can confirm [`GpioReady`] actually waits.
+struct CountdownPin {
+    delay: u32,
+}
+
+impl PinErrorType for CountdownPin {
+    type Error = core::convert::Infallible;
+}
+
+impl InputPin for CountdownPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        if self.delay == 0 {
+            Ok(true)
+        } else {
+            self.delay -= 1;
+            Ok(false)
+        }
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
+
+#[test]
+fn write_then_read_round_trip_over_spi() {
+    let device = MockSpiDevice {
+        secondary: UrapSecondary::new([[0u8; 4]; 4], [false; 4]),
+        request: heapless_vec::Vec::new(),
+    };
+    let mut io: SpiPrimary<_> = SpiPrimary::new(device);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+}
+
+#[test]
+fn a_ready_line_is_waited_on_before_the_response_is_read() {
+    let device = MockSpiDevice {
+        secondary: UrapSecondary::new([[7u8; 4]; 4], [false; 4]),
+        request: heapless_vec::Vec::new(),
+    };
+    let mut io: SpiPrimary<_, _> = SpiPrimary::with_ready_line(device, GpioReady(CountdownPin { delay: 3 }));
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [7, 7, 7, 7]);
+}
+
+#[test]
+fn a_nak_is_surfaced_as_an_error() {
+    let device = MockSpiDevice {
+        secondary: UrapSecondary::new([[0u8; 4]; 4], [false; 4]),
+        request: heapless_vec::Vec::new(),
+    };
+    let mut io: SpiPrimary<_> = SpiPrimary::new(device);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    let mut readback = [[0u8; 4]; 1];
+    assert!(primary.read_4u8(10, &mut readback).is_err());
+}
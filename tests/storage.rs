@@ -0,0 +1,84 @@
+//! Exercises [`urap::storage`] against a small in-memory mock flash, since
+//! `embedded-storage` itself ships no test utilities.
+
+#![cfg(feature = "storage")]
+
+use embedded_storage::{ReadStorage, Storage};
+use urap::storage::{FlashBackedRegisters, StorageError};
+use urap::DirtyTracker;
+
+/// A flash that's just a byte array; `write` overwrites in place rather
+/// than modelling erase blocks, which is all [`FlashBackedRegisters`]
+/// needs to exercise.
+struct MockFlash {
+    bytes: [u8; 64],
+}
+
+impl MockFlash {
+    fn new() -> Self {
+        Self { bytes: [0xFFu8; 64] }
+    }
+}
+
+impl ReadStorage for MockFlash {
+    type Error = core::convert::Infallible;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.bytes[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl Storage for MockFlash {
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        self.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[test]
+fn commit_then_load_round_trips() {
+    let mut flash: FlashBackedRegisters<MockFlash, 4> = FlashBackedRegisters::new(MockFlash::new(), 0, 3);
+    let regs: [[u8; 4]; 3] = [[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]];
+
+    flash.commit(&regs).unwrap();
+
+    let mut loaded = [[0u8; 4]; 3];
+    flash.load(&mut loaded).unwrap();
+    assert_eq!(loaded, regs);
+}
+
+#[test]
+fn load_rejects_a_blank_region() {
+    let mut flash: FlashBackedRegisters<MockFlash, 4> = FlashBackedRegisters::new(MockFlash::new(), 0, 2);
+
+    let mut loaded = [[0u8; 4]; 2];
+    let err = flash.load(&mut loaded).unwrap_err();
+    assert!(matches!(err, StorageError::BadCrc));
+}
+
+#[test]
+fn commit_if_dirty_skips_an_unchanged_range() {
+    let mut flash: FlashBackedRegisters<MockFlash, 4> = FlashBackedRegisters::new(MockFlash::new(), 0, 2);
+    let mut dirty: DirtyTracker<1> = DirtyTracker::new();
+    let regs: [[u8; 4]; 2] = [[1, 0, 0, 0], [2, 0, 0, 0]];
+
+    let committed = flash.commit_if_dirty(&regs, &mut dirty).unwrap();
+    assert!(!committed);
+
+    let mut loaded = [[0u8; 4]; 2];
+    assert!(matches!(flash.load(&mut loaded).unwrap_err(), StorageError::BadCrc));
+
+    dirty.mark(0);
+    let committed = flash.commit_if_dirty(&regs, &mut dirty).unwrap();
+    assert!(committed);
+
+    flash.load(&mut loaded).unwrap();
+    assert_eq!(loaded, regs);
+}
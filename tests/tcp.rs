@@ -0,0 +1,51 @@
+//! End-to-end exercise of the threaded TCP primary/secondary.
+
+#![cfg(feature = "tcp")]
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::thread;
+use std::time::Duration;
+
+use urap::tcp::{Listener, UrapPrimary, UrapSecondary};
+use urap::UrapSecondary as CoreSecondary;
+
+fn loopback_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)
+}
+
+#[test]
+fn read_write_round_trip_over_tcp() {
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let server = UrapSecondary::spawn(vec![Listener::read_write(addr)], secondary).unwrap();
+
+    // Give the accept thread a moment to bind before connecting.
+    thread::sleep(Duration::from_millis(20));
+
+    let mut primary: UrapPrimary = UrapPrimary::connect(addr).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+    assert!(server.pop_error().is_none());
+}
+
+#[test]
+fn read_only_listener_rejects_writes_over_tcp() {
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let _server = UrapSecondary::spawn(vec![Listener::read_only(addr)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let mut primary: UrapPrimary = UrapPrimary::connect(addr).unwrap();
+    let result = primary.write_4u8(0, &[[9, 9, 9, 9]]);
+    assert!(result.is_err());
+}
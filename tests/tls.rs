@@ -0,0 +1,90 @@
+//! End-to-end exercise of the threaded TLS-secured TCP primary/secondary.
+
+#![cfg(feature = "tls")]
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rcgen::{generate_simple_self_signed, CertifiedKey};
+use rustls::pki_types::{PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+use urap::tcp::Listener;
+use urap::tls::{UrapPrimary, UrapSecondary};
+use urap::UrapSecondary as CoreSecondary;
+
+fn loopback_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)
+}
+
+/// Builds a self-signed "localhost" certificate and the matching
+/// server/client TLS configs, with the client trusting only that
+/// certificate.
+fn test_configs() -> (Arc<ServerConfig>, Arc<ClientConfig>) {
+    let CertifiedKey { cert, signing_key } =
+        generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert_der = cert.der().clone();
+    let key_der: PrivateKeyDer<'static> = signing_key.into();
+
+    let server_config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert_der.clone()], key_der)
+        .unwrap();
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert_der).unwrap();
+    let client_config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    (Arc::new(server_config), Arc::new(client_config))
+}
+
+#[test]
+fn read_write_round_trip_over_tls() {
+    let (server_config, client_config) = test_configs();
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+
+    let listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let server =
+        UrapSecondary::spawn(vec![Listener::read_write(addr)], server_config, secondary).unwrap();
+
+    // Give the accept thread a moment to bind before connecting.
+    thread::sleep(Duration::from_millis(20));
+
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut primary: UrapPrimary = UrapPrimary::connect(addr, client_config, server_name).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+    assert!(server.pop_error().is_none());
+}
+
+#[test]
+fn connect_fails_when_client_does_not_trust_the_certificate() {
+    let (server_config, _) = test_configs();
+    let (_, untrusting_client_config) = test_configs();
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+
+    let listener = std::net::TcpListener::bind(loopback_addr()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let _server =
+        UrapSecondary::spawn(vec![Listener::read_write(addr)], server_config, secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut primary: UrapPrimary =
+        UrapPrimary::connect(addr, untrusting_client_config, server_name).unwrap();
+    let result = primary.read_4u8(0, &mut [[0u8; 4]; 1]);
+    assert!(result.is_err());
+}
@@ -0,0 +1,56 @@
+//! Exercises `read_quantity`/`write_quantity` over a real Unix socket
+//! pair.
+#![cfg(feature = "uom")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use uom::si::f32::ThermodynamicTemperature;
+use uom::si::thermodynamic_temperature::kelvin;
+use urap::{UrapPrimary, UrapSecondary};
+
+#[test]
+fn read_quantity_scales_a_raw_register_into_kelvin() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    // Device reports tenths of a kelvin.
+    primary.write_f32(0, 3153.0).unwrap();
+    let temperature: ThermodynamicTemperature = primary.read_quantity(0, 0.1).unwrap();
+    assert!((temperature.get::<kelvin>() - 315.3).abs() < 1e-3);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn write_quantity_scales_kelvin_down_to_the_raw_register() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let mut secondary: UrapSecondary<1> = UrapSecondary::new([[0u8; 4]; 1], [false]);
+        for _ in 0..2 {
+            secondary.poll(&mut io).unwrap();
+        }
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(&mut io);
+
+    let temperature = ThermodynamicTemperature::new::<kelvin>(315.3);
+    primary.write_quantity(0, temperature, 0.1).unwrap();
+    assert!((primary.read_f32(0).unwrap() - 3153.0).abs() < 1e-1);
+
+    secondary_thread.join().unwrap();
+}
@@ -0,0 +1,820 @@
+//! End-to-end exercise of the threaded Unix-socket primary/secondary.
+
+#![cfg(all(feature = "usockets", unix))]
+
+use std::thread;
+use std::time::Duration;
+
+use urap::usockets::{
+    AuthDecision, Listener, Permission, SecondaryConfig, UrapPrimary, UrapPrimaryBuilder,
+    UrapSecondary, UrapSecondaryBuilder,
+};
+use urap::UrapSecondary as CoreSecondary;
+
+fn socket_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("urap-test-{name}-{}.sock", std::process::id()))
+}
+
+#[test]
+fn read_write_round_trip_over_unix_socket() {
+    let path = socket_path("rw");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    // Give the accept thread a moment to bind before connecting.
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn auth_callback_sees_own_process_and_can_restrict() {
+    let path = socket_path("auth-restrict");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let our_uid = unsafe { libc::getuid() };
+    let listener = Listener::read_write(&path).with_auth(move |creds| {
+        assert_eq!(creds.uid, our_uid);
+        AuthDecision::Accept(Permission::ReadOnly)
+    });
+    let _server = UrapSecondary::spawn(vec![listener], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let result = primary.write_4u8(0, &[[9, 9, 9, 9]]);
+    assert!(result.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn auth_callback_can_reject_connection() {
+    let path = socket_path("auth-reject");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let listener = Listener::read_write(&path).with_auth(|_creds| AuthDecision::Reject);
+    let _server = UrapSecondary::spawn(vec![listener], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    let result = primary.read_4u8(0, &mut readback);
+    assert!(result.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_endpoint_reports_serviced_traffic() {
+    use std::io::Read;
+    use std::net::TcpStream;
+
+    let path = socket_path("metrics");
+    let _ = std::fs::remove_file(&path);
+    let metrics_addr: std::net::SocketAddr =
+        format!("127.0.0.1:{}", 20_000 + (std::process::id() % 10_000) as u16)
+            .parse()
+            .unwrap();
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let _server =
+        UrapSecondary::spawn_with_metrics(vec![Listener::read_write(&path)], secondary, metrics_addr)
+            .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    // Rejected: out of bounds.
+    let mut readback = [[0u8; 4]; 1];
+    let _ = primary.read_4u8(10, &mut readback);
+
+    // The server replies unconditionally without reading the request, so
+    // just read the response straight back.
+    let mut scrape = TcpStream::connect(metrics_addr).unwrap();
+    let mut body = String::new();
+    scrape.read_to_string(&mut body).unwrap();
+
+    assert!(body.contains("urap_packets_total 2"));
+    assert!(body.contains("urap_naks_total{code=\"index_out_of_bounds\"} 1"));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn read_only_listener_rejects_writes() {
+    let path = socket_path("ro");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let _server = UrapSecondary::spawn(vec![Listener::read_only(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let result = primary.write_4u8(0, &[[9, 9, 9, 9]]);
+    assert!(result.is_err());
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn poisoned_register_lock_recovers_instead_of_wedging_the_server() {
+    let path = socket_path("poison");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary = CoreSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+        .with_write_hook(|_register: u16, values: &[[u8; 4]]| {
+            if values[0] == [0xDE, 0xAD, 0xBE, 0xEF] {
+                panic!("write hook blew up");
+            }
+        });
+    let server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    {
+        let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+        // The write hook panics while the register lock is held, poisoning
+        // it; the connection drops but the server must stay up.
+        let _ = primary.write_4u8(0, &[[0xDE, 0xAD, 0xBE, 0xEF]]);
+    }
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    let _ = server.pop_error();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn shutdown_closes_connections_unlinks_socket_and_rejects_new_ones() {
+    let path = socket_path("shutdown");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let mut server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    server.shutdown();
+
+    // The still-open connection was forced closed.
+    let mut readback = [[0u8; 4]; 1];
+    assert!(primary.read_4u8(0, &mut readback).is_err());
+
+    // The socket path was unlinked, so a fresh connect must fail.
+    assert!(UrapPrimary::<4>::connect(&path).is_err());
+
+    // Calling shutdown again is a no-op, not a hang or a panic.
+    server.shutdown();
+}
+
+#[test]
+fn reclaim_stale_rebinds_a_leftover_socket_file_from_a_crashed_run() {
+    let path = socket_path("stale");
+    let _ = std::fs::remove_file(&path);
+
+    // Simulate a crashed previous run: a plain Unix listener bound to the
+    // path, then dropped without unlinking it.
+    {
+        let leftover = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        drop(leftover);
+    }
+    assert!(path.exists());
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server =
+        UrapSecondary::spawn(vec![Listener::read_write(&path).reclaim_stale()], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    let _ = server.pop_error();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn recorded_errors_carry_connection_id_and_peer_and_can_be_drained() {
+    use std::io::Write as _;
+    use std::os::unix::net::UnixStream;
+
+    let path = socket_path("error-events");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let our_uid = unsafe { libc::getuid() };
+    let listener = Listener::read_write(&path).with_auth(move |creds| {
+        assert_eq!(creds.uid, our_uid);
+        AuthDecision::Accept(Permission::ReadWrite)
+    });
+    let server = UrapSecondary::spawn(vec![listener], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    {
+        // A well-formed request header followed by a dropped connection:
+        // the secondary's CRC read hits EOF partway through the request,
+        // which surfaces as a transport error on this connection.
+        let mut raw = UnixStream::connect(&path).unwrap();
+        raw.write_all(&[urap::OP_READ, 0, 0, 1]).unwrap();
+    }
+
+    thread::sleep(Duration::from_millis(50));
+
+    let events: Vec<_> = server.drain_errors().collect();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].connection_id.is_some());
+    assert_eq!(events[0].peer.unwrap().uid, our_uid);
+
+    // The log was cleared by the drain above.
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn event_callback_sees_connection_lifecycle_and_naks() {
+    use std::sync::{Arc, Mutex};
+    use urap::usockets::ServerEvent;
+
+    let path = socket_path("events");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&events);
+    let server = UrapSecondary::spawn_with_events(
+        vec![Listener::read_only(&path)],
+        secondary,
+        move |event| {
+            let label = match event {
+                ServerEvent::ConnectionOpened { .. } => "opened",
+                ServerEvent::ConnectionClosed { .. } => "closed",
+                ServerEvent::Nak { .. } => "nak",
+                ServerEvent::Error(_) => "error",
+            };
+            recorded.lock().unwrap().push(label.to_string());
+        },
+    )
+    .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    {
+        let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+        let result = primary.write_4u8(0, &[[9, 9, 9, 9]]);
+        assert!(result.is_err());
+    }
+
+    thread::sleep(Duration::from_millis(50));
+
+    let seen = events.lock().unwrap().clone();
+    assert_eq!(seen.first(), Some(&"opened".to_string()));
+    assert!(seen.contains(&"nak".to_string()));
+    assert_eq!(seen.last(), Some(&"closed".to_string()));
+
+    let _ = server.pop_error();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn nak_event_carries_the_offending_peers_credentials() {
+    use std::sync::{Arc, Mutex};
+    use urap::usockets::ServerEvent;
+
+    let path = socket_path("nak-peer");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let our_uid = unsafe { libc::getuid() };
+    let naks: Arc<Mutex<Vec<u32>>> = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&naks);
+    let server = UrapSecondary::spawn_with_events(
+        vec![Listener::read_only(&path)],
+        secondary,
+        move |event| {
+            if let ServerEvent::Nak {
+                peer: Some(peer), ..
+            } = event
+            {
+                recorded.lock().unwrap().push(peer.uid);
+            }
+        },
+    )
+    .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let _ = primary.write_4u8(0, &[[9, 9, 9, 9]]);
+
+    thread::sleep(Duration::from_millis(50));
+
+    assert_eq!(*naks.lock().unwrap(), vec![our_uid]);
+
+    let _ = server.pop_error();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_small_worker_pool_still_services_more_concurrent_clients_than_it_has_threads() {
+    let path = socket_path("pool");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondary::spawn_with_config(
+        vec![Listener::read_write(&path)],
+        secondary,
+        SecondaryConfig {
+            worker_threads: 2,
+            ..SecondaryConfig::default()
+        },
+    )
+    .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let clients: Vec<_> = (0..8u8)
+        .map(|n| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+                primary.write_4u8(0, &[[n, n, n, n]]).unwrap();
+                let mut readback = [[0u8; 4]; 1];
+                primary.read_4u8(0, &mut readback).unwrap();
+                readback[0]
+            })
+        })
+        .collect();
+
+    for client in clients {
+        let readback = client.join().unwrap();
+        assert_eq!(readback[0], readback[1]);
+    }
+
+    assert!(server.pop_error().is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn an_idle_connection_is_closed_after_its_timeout_elapses() {
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+
+    let path = socket_path("idle");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondary::spawn_with_config(
+        vec![Listener::read_write(&path)],
+        secondary,
+        SecondaryConfig {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..SecondaryConfig::default()
+        },
+    )
+    .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let mut idle_client = UnixStream::connect(&path).unwrap();
+    idle_client
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    let mut buf = [0u8; 1];
+    let read = idle_client.read(&mut buf).unwrap();
+    assert_eq!(read, 0, "server should have closed the idle connection");
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    let _ = server.pop_error();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_connection_beyond_the_cap_is_refused() {
+    use std::io::Read;
+
+    let path = socket_path("capped");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let _server = UrapSecondary::spawn_with_config(
+        vec![Listener::read_write(&path)],
+        secondary,
+        SecondaryConfig {
+            max_connections: Some(1),
+            ..SecondaryConfig::default()
+        },
+    )
+    .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let first: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+
+    let mut second = std::os::unix::net::UnixStream::connect(&path).unwrap();
+    second
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    let mut buf = [0u8; 1];
+    let read = second.read(&mut buf).unwrap();
+    assert_eq!(read, 0, "connection beyond the cap should be refused");
+
+    first.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    first.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_request_beyond_the_rate_limit_is_delayed_rather_than_dropped() {
+    use urap::usockets::RateLimit;
+
+    let path = socket_path("ratelimited");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondary::spawn_with_config(
+        vec![Listener::read_write(&path)],
+        secondary,
+        SecondaryConfig {
+            rate_limit: Some(RateLimit::new(1, Duration::from_millis(150))),
+            ..SecondaryConfig::default()
+        },
+    )
+    .unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+
+    let started = std::time::Instant::now();
+    primary.write_4u8(0, &[[1, 1, 1, 1]]).unwrap();
+    let first_request = started.elapsed();
+    primary.write_4u8(0, &[[2, 2, 2, 2]]).unwrap();
+    let second_request = started.elapsed();
+
+    assert!(
+        first_request < Duration::from_millis(100),
+        "first request should have spent the burst token immediately, took {first_request:?}"
+    );
+    assert!(
+        second_request >= Duration::from_millis(100),
+        "second request should have waited for a token to refill, took {second_request:?}"
+    );
+
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [2, 2, 2, 2]);
+
+    assert!(server.pop_error().is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_reconnecting_primary_survives_the_secondary_restarting() {
+    use urap::usockets::{Backoff, ReconnectingPrimary};
+
+    let path = socket_path("reconnect");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let mut server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    let mut primary: ReconnectingPrimary = ReconnectingPrimary::connect(
+        &path,
+        Backoff::new(Duration::from_millis(10), Duration::from_millis(100), Some(50)),
+    )
+    .unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+
+    server.shutdown();
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let _server2 = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    thread::sleep(Duration::from_millis(20));
+
+    primary.write_4u8(0, &[[5, 6, 7, 8]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [5, 6, 7, 8]);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn from_systemd_rejects_a_process_not_started_under_socket_activation() {
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+
+    let result = Listener::from_systemd(0, Permission::ReadWrite);
+    assert!(result.is_err());
+}
+
+#[test]
+fn from_systemd_adopts_an_inherited_listening_socket() {
+    use std::os::unix::io::AsRawFd;
+
+    const SD_LISTEN_FDS_START: libc::c_int = 3;
+
+    let path = socket_path("systemd-activated");
+    let _ = std::fs::remove_file(&path);
+
+    let bound = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let bound_fd = bound.as_raw_fd();
+    assert_eq!(
+        unsafe { libc::dup2(bound_fd, SD_LISTEN_FDS_START) },
+        SD_LISTEN_FDS_START
+    );
+    // `bound` and the eventual adopted `Listener` must not both think they
+    // own a fd and close it on drop, so forget `bound`'s Rust wrapper and
+    // (if `dup2` didn't land on the same fd by coincidence) close its
+    // original fd directly, leaving exactly one owner of fd 3.
+    std::mem::forget(bound);
+    if bound_fd != SD_LISTEN_FDS_START {
+        unsafe {
+            libc::close(bound_fd);
+        }
+    }
+
+    std::env::set_var("LISTEN_PID", std::process::id().to_string());
+    std::env::set_var("LISTEN_FDS", "1");
+
+    let listener = Listener::from_systemd(0, Permission::ReadWrite).unwrap();
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondary::spawn(vec![listener], secondary).unwrap();
+
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[5, 6, 7, 8]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [5, 6, 7, 8]);
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn without_reclaim_stale_a_leftover_socket_file_fails_the_bind() {
+    let path = socket_path("stale-rejected");
+    let _ = std::fs::remove_file(&path);
+
+    let leftover = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let result = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary);
+    assert!(result.is_err());
+
+    drop(leftover);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_read_timeout_fails_a_request_instead_of_blocking_forever_on_a_hung_secondary() {
+    let path = socket_path("primary-read-timeout");
+    let _ = std::fs::remove_file(&path);
+
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let hang_thread = thread::spawn(move || {
+        // Accept the connection and read the request, but never respond,
+        // simulating a secondary that's hung.
+        let (stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4];
+        let _ = std::io::Read::read(&mut &stream, &mut buf);
+        thread::sleep(Duration::from_secs(1));
+    });
+
+    let primary: UrapPrimary =
+        UrapPrimary::connect_with_timeout(&path, Some(Duration::from_millis(50)), None).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    let result = primary.read_4u8(0, &mut readback);
+    assert!(matches!(result, Err(urap::Error::Io(_))));
+
+    drop(hang_thread);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_cloned_primary_can_be_shared_across_threads_without_an_external_mutex() {
+    let path = socket_path("primary-clone");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<8> = CoreSecondary::new([[0u8; 4]; 8], [false; 8]);
+    let server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+
+    let handles: Vec<_> = (0..8u16)
+        .map(|register| {
+            let primary = primary.clone();
+            thread::spawn(move || {
+                let value = [register as u8; 4];
+                primary.write_4u8(register, &[value]).unwrap();
+                let mut readback = [[0u8; 4]; 1];
+                primary.read_4u8(register, &mut readback).unwrap();
+                assert_eq!(readback[0], value);
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_keepalived_connection_survives_past_its_idle_timeout() {
+    let path = socket_path("keepalive-alive");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary = CoreSecondary::<4>::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondaryBuilder::new(vec![Listener::read_write(&path)])
+        .keepalive(Duration::from_millis(20), 3)
+        .spawn(secondary)
+        .unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let keepalive = primary.spawn_keepalive(Duration::from_millis(20));
+
+    thread::sleep(Duration::from_millis(200));
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    drop(keepalive);
+    assert!(server.pop_error().is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_connection_without_keepalives_is_closed_after_the_idle_timeout() {
+    let path = socket_path("keepalive-dead");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary = CoreSecondary::<4>::new([[0u8; 4]; 4], [false; 4]);
+    let _server = UrapSecondaryBuilder::new(vec![Listener::read_write(&path)])
+        .keepalive(Duration::from_millis(20), 3)
+        .spawn(secondary)
+        .unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    let mut readback = [[0u8; 4]; 1];
+    let result = primary.read_4u8(0, &mut readback);
+    assert!(matches!(result, Err(urap::Error::Io(_))));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn a_ping_is_answered_promptly_even_while_a_slow_write_holds_the_register_lock() {
+    let path = socket_path("ping");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary = CoreSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+        .with_write_hook(|_register: u16, _values: &[[u8; 4]]| {
+            thread::sleep(Duration::from_millis(200));
+        });
+    let server = UrapSecondary::spawn_with_config(
+        vec![Listener::read_write(&path)],
+        secondary,
+        SecondaryConfig {
+            worker_threads: 2,
+            ..SecondaryConfig::default()
+        },
+    )
+    .unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let writer: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let write_thread = thread::spawn(move || writer.write_4u8(0, &[[1, 2, 3, 4]]).unwrap());
+    thread::sleep(Duration::from_millis(50));
+
+    let pinger: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    let started = std::time::Instant::now();
+    pinger.ping().unwrap();
+    assert!(started.elapsed() < Duration::from_millis(150));
+
+    write_thread.join().unwrap();
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn the_secondary_builder_wires_up_worker_threads_and_events_together() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use urap::usockets::ServerEvent;
+
+    let path = socket_path("secondary-builder");
+    let _ = std::fs::remove_file(&path);
+
+    let opened = Arc::new(AtomicUsize::new(0));
+    let opened_for_callback = Arc::clone(&opened);
+
+    let secondary: CoreSecondary<4> = CoreSecondary::new([[0u8; 4]; 4], [false; 4]);
+    let server = UrapSecondaryBuilder::new(vec![Listener::read_write(&path)])
+        .worker_threads(2)
+        .on_event(move |event| {
+            if let ServerEvent::ConnectionOpened { .. } = event {
+                opened_for_callback.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .spawn(secondary)
+        .unwrap();
+    thread::sleep(Duration::from_millis(20));
+
+    let primary: UrapPrimary = UrapPrimary::connect(&path).unwrap();
+    primary.write_4u8(0, &[[1, 2, 3, 4]]).unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    primary.read_4u8(0, &mut readback).unwrap();
+    assert_eq!(readback[0], [1, 2, 3, 4]);
+
+    assert_eq!(opened.load(Ordering::SeqCst), 1);
+    assert!(server.pop_error().is_none());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn the_primary_builder_applies_its_configured_timeout() {
+    let path = socket_path("primary-builder");
+    let _ = std::fs::remove_file(&path);
+
+    let hang_listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let hang_thread = thread::spawn(move || {
+        let (mut stream, _) = hang_listener.accept().unwrap();
+        let mut header = [0u8; 4];
+        let _ = std::io::Read::read_exact(&mut stream, &mut header);
+        thread::sleep(Duration::from_secs(1));
+    });
+
+    let primary: UrapPrimary = UrapPrimaryBuilder::new(&path)
+        .read_timeout(Duration::from_millis(50))
+        .connect()
+        .unwrap();
+    let mut readback = [[0u8; 4]; 1];
+    let result = primary.read_4u8(0, &mut readback);
+    assert!(matches!(result, Err(urap::Error::Io(_))));
+
+    drop(hang_thread);
+    let _ = std::fs::remove_file(&path);
+}
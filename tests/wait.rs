@@ -0,0 +1,55 @@
+//! End-to-end long-poll exchange over a real Unix socket pair.
+
+#![cfg(feature = "longpoll")]
+
+use std::os::unix::net::UnixStream;
+use std::thread;
+
+use embedded_io_adapters::std::FromStd;
+use urap::{DirtyTracker, UrapSecondary, WaitPrimary, WaitSecondary};
+
+#[test]
+fn wait_returns_immediately_for_an_already_dirty_register() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let inner = UrapSecondary::<4>::new([[1, 2, 3, 4], [0u8; 4], [0u8; 4], [0u8; 4]], [false; 4])
+            .with_write_hook(DirtyTracker::<1>::new());
+        let mut secondary = WaitSecondary::new(inner);
+        secondary.inner_mut().write_hook_mut().mark(0);
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut wait = WaitPrimary::<_, 4>::new(&mut io);
+    let mut data = [[0u8; 4]; 1];
+    let changed = wait.wait_for_change(0, &mut data, 5_000).unwrap();
+
+    assert!(changed);
+    assert_eq!(data[0], [1, 2, 3, 4]);
+
+    secondary_thread.join().unwrap();
+}
+
+#[test]
+fn wait_times_out_when_nothing_changes() {
+    let (primary_sock, secondary_sock) = UnixStream::pair().unwrap();
+
+    let secondary_thread = thread::spawn(move || {
+        let mut io = FromStd::new(secondary_sock);
+        let inner = UrapSecondary::<4>::new([[0u8; 4]; 4], [false; 4])
+            .with_write_hook(DirtyTracker::<1>::new());
+        let mut secondary = WaitSecondary::new(inner);
+        secondary.poll(&mut io).unwrap();
+    });
+
+    let mut io = FromStd::new(primary_sock);
+    let mut wait = WaitPrimary::<_, 4>::new(&mut io);
+    let mut data = [[0u8; 4]; 1];
+    let changed = wait.wait_for_change(0, &mut data, 20).unwrap();
+
+    assert!(!changed);
+
+    secondary_thread.join().unwrap();
+}
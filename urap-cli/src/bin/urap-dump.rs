@@ -0,0 +1,82 @@
+//! `urap-dump` - polls a range of registers at a fixed rate and prints
+//! their values, for logging sensor data during bring-up.
+//!
+//! Addresses are given as `tcp://host:port`, `unix:///path/to.sock`, or
+//! (with the `serial` feature) `serial:///dev/ttyUSB0`.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use urap::UrapPrimary;
+use urap_cli::{connect, format_value, parse_register, DataType};
+
+#[derive(Parser)]
+#[command(name = "urap-dump", about = "Poll a register range and print its values")]
+struct Cli {
+    /// `tcp://host:port`, `unix:///path`, or `serial:///dev/ttyUSB0`.
+    address: String,
+    /// First register to poll, decimal or `0x`-prefixed hex.
+    register: String,
+    /// How many consecutive registers to poll.
+    #[arg(long, default_value_t = 1)]
+    count: u16,
+    /// How to interpret each register's bytes.
+    #[arg(long = "as", value_enum, default_value_t = DataType::U32)]
+    data_type: DataType,
+    /// Milliseconds between polls.
+    #[arg(long, default_value_t = 1000)]
+    interval_ms: u64,
+    /// Number of polls to run before exiting (runs forever if unset).
+    #[arg(long)]
+    samples: Option<u64>,
+    /// Print one CSV row per poll instead of one line per register.
+    #[arg(long)]
+    csv: bool,
+}
+
+fn run() -> Result<(), String> {
+    let cli = Cli::parse();
+
+    let io = connect(&cli.address)?;
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(io);
+    let first = parse_register(&cli.register)?;
+    let interval = Duration::from_millis(cli.interval_ms);
+    let started = Instant::now();
+
+    if cli.csv {
+        let header: Vec<String> = (0..cli.count).map(|offset| (first + offset).to_string()).collect();
+        println!("elapsed_ms,{}", header.join(","));
+    }
+
+    let mut sample = 0u64;
+    loop {
+        if cli.samples.is_some_and(|limit| sample >= limit) {
+            break;
+        }
+
+        let values: Vec<String> = (0..cli.count)
+            .map(|offset| format_value(cli.data_type, &mut primary, first + offset))
+            .collect::<Result<_, _>>()?;
+
+        if cli.csv {
+            println!("{},{}", started.elapsed().as_millis(), values.join(","));
+        } else {
+            for (offset, value) in values.iter().enumerate() {
+                println!("{}: {value}", first + offset as u16);
+            }
+        }
+
+        sample += 1;
+        thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("urap-dump: {err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,149 @@
+//! `urap-tui` - a live, scrolling-table register viewer with inline
+//! edits of writable registers, driven by any supported transport.
+//!
+//! Addresses are given as `tcp://host:port`, `unix:///path/to.sock`, or
+//! (with the `serial` feature) `serial:///dev/ttyUSB0`.
+
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyCode};
+use ratatui::layout::Constraint;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Row, Table, TableState};
+use ratatui::Frame;
+use urap::UrapPrimary;
+use urap_cli::{connect, format_value, parse_register, write_value, DataType};
+
+#[derive(Parser)]
+#[command(name = "urap-tui", about = "Live table view of a register range")]
+struct Cli {
+    /// `tcp://host:port`, `unix:///path`, or `serial:///dev/ttyUSB0`.
+    address: String,
+    /// First register to watch, decimal or `0x`-prefixed hex.
+    register: String,
+    /// How many consecutive registers to watch.
+    #[arg(long, default_value_t = 8)]
+    count: u16,
+    /// How to interpret each register's bytes.
+    #[arg(long = "as", value_enum, default_value_t = DataType::U32)]
+    data_type: DataType,
+    /// Milliseconds between refreshes.
+    #[arg(long, default_value_t = 200)]
+    interval_ms: u64,
+}
+
+struct App {
+    first: u16,
+    values: Vec<String>,
+    changed: Vec<bool>,
+    selected: usize,
+    editing: Option<String>,
+    status: String,
+}
+
+fn run() -> Result<(), String> {
+    let cli = Cli::parse();
+    let io = connect(&cli.address)?;
+    let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(io);
+
+    let mut app = App {
+        first: parse_register(&cli.register)?,
+        values: vec![String::new(); cli.count as usize],
+        changed: vec![false; cli.count as usize],
+        selected: 0,
+        editing: None,
+        status: "↑/↓ select · e edit · Enter apply · Esc cancel · q quit".into(),
+    };
+
+    let mut terminal = ratatui::init();
+    let interval = Duration::from_millis(cli.interval_ms);
+    let mut last_poll = Instant::now() - interval;
+
+    loop {
+        if app.editing.is_none() && last_poll.elapsed() >= interval {
+            for (offset, (value, changed)) in app.values.iter_mut().zip(app.changed.iter_mut()).enumerate() {
+                match format_value(cli.data_type, &mut primary, app.first + offset as u16) {
+                    Ok(fresh) => {
+                        *changed = fresh != *value;
+                        *value = fresh;
+                    }
+                    Err(err) => app.status = err,
+                }
+            }
+            last_poll = Instant::now();
+        }
+
+        terminal.draw(|frame| draw(frame, &app)).map_err(|err| err.to_string())?;
+
+        if event::poll(Duration::from_millis(50)).map_err(|err| err.to_string())? {
+            if let Event::Key(key) = event::read().map_err(|err| err.to_string())? {
+                if let Some(text) = app.editing.as_mut() {
+                    match key.code {
+                        KeyCode::Enter => {
+                            let register = app.first + app.selected as u16;
+                            match write_value(cli.data_type, &mut primary, register, text) {
+                                Ok(()) => app.status = format!("wrote register {register}"),
+                                Err(err) => app.status = err,
+                            }
+                            app.editing = None;
+                            last_poll = Instant::now() - interval;
+                        }
+                        KeyCode::Esc => app.editing = None,
+                        KeyCode::Backspace => {
+                            text.pop();
+                        }
+                        KeyCode::Char(c) => text.push(c),
+                        _ => {}
+                    }
+                } else {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => break,
+                        KeyCode::Down => app.selected = (app.selected + 1).min(app.values.len().saturating_sub(1)),
+                        KeyCode::Up => app.selected = app.selected.saturating_sub(1),
+                        KeyCode::Char('e') => app.editing = Some(String::new()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    ratatui::restore();
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let rows = app.values.iter().zip(app.changed.iter()).enumerate().map(|(offset, (value, changed))| {
+        let register = app.first + offset as u16;
+        let style = if *changed {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        Row::new(vec![register.to_string(), value.clone()]).style(style)
+    });
+
+    let widths = [Constraint::Length(10), Constraint::Min(20)];
+    let title = if let Some(text) = &app.editing {
+        format!("register {} = {text}_", app.first + app.selected as u16)
+    } else {
+        app.status.clone()
+    };
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["register", "value"]))
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(table, frame.area(), &mut state);
+}
+
+fn main() {
+    if let Err(err) = run() {
+        ratatui::restore();
+        eprintln!("urap-tui: {err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,83 @@
+//! `urap` - a command-line client for poking registers on a running
+//! URAP secondary, so a technician can check or set a register without
+//! writing a Rust program.
+//!
+//! Addresses are given as `tcp://host:port`, `unix:///path/to.sock`, or
+//! (with the `serial` feature) `serial:///dev/ttyUSB0`.
+
+use clap::{Parser, Subcommand};
+use urap::UrapPrimary;
+use urap_cli::{connect, format_value, parse_register, write_value, DataType};
+
+#[derive(Parser)]
+#[command(name = "urap", about = "Read and write registers on a URAP secondary")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Read one or more registers.
+    Read {
+        /// `tcp://host:port`, `unix:///path`, or `serial:///dev/ttyUSB0`.
+        address: String,
+        /// Register index, decimal or `0x`-prefixed hex.
+        register: String,
+        /// How many consecutive registers to read.
+        #[arg(long, default_value_t = 1)]
+        count: u16,
+        /// How to interpret each register's bytes.
+        #[arg(long = "as", value_enum, default_value_t = DataType::U32)]
+        data_type: DataType,
+    },
+    /// Write one or more registers.
+    Write {
+        /// `tcp://host:port`, `unix:///path`, or `serial:///dev/ttyUSB0`.
+        address: String,
+        /// Register index, decimal or `0x`-prefixed hex.
+        register: String,
+        /// How to interpret each value.
+        #[arg(long = "as", value_enum, default_value_t = DataType::U32)]
+        data_type: DataType,
+        /// One value per register being written.
+        values: Vec<String>,
+    },
+}
+
+fn run() -> Result<(), String> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Read { address, register, count, data_type } => {
+            let io = connect(&address)?;
+            let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(io);
+            let first = parse_register(&register)?;
+
+            for offset in 0..count {
+                let register = first + offset;
+                let value = format_value(data_type, &mut primary, register)?;
+                println!("{register}: {value}");
+            }
+        }
+        Command::Write { address, register, data_type, values } => {
+            let io = connect(&address)?;
+            let mut primary: UrapPrimary<_, 4> = UrapPrimary::new(io);
+            let first = parse_register(&register)?;
+
+            for (offset, text) in values.iter().enumerate() {
+                let register = first + offset as u16;
+                write_value(data_type, &mut primary, register, text)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("urap: {err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,119 @@
+//! Shared transport and register plumbing for the `urap` and `urap-dump`
+//! binaries, so both tools connect and format values the same way.
+
+use std::io;
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use embedded_io_adapters::std::FromStd;
+use urap::UrapPrimary;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DataType {
+    U32,
+    I32,
+    F32,
+}
+
+/// Any transport the tools connected to, so the rest of the program can
+/// talk to it through a single `UrapPrimary`.
+trait Transport: io::Read + io::Write {}
+impl<T: io::Read + io::Write> Transport for T {}
+
+pub struct DynIo(Box<dyn Transport>);
+
+impl io::Read for DynIo {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl io::Write for DynIo {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Connects to `tcp://host:port`, `unix:///path`, or (with the `serial`
+/// feature) `serial:///dev/ttyUSB0`.
+pub fn connect(address: &str) -> Result<FromStd<DynIo>, String> {
+    if let Some(rest) = address.strip_prefix("tcp://") {
+        let stream = TcpStream::connect(rest).map_err(|err| format!("connecting to {rest}: {err}"))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
+        return Ok(FromStd::new(DynIo(Box::new(stream))));
+    }
+
+    if let Some(rest) = address.strip_prefix("unix://") {
+        let stream = UnixStream::connect(rest).map_err(|err| format!("connecting to {rest}: {err}"))?;
+        return Ok(FromStd::new(DynIo(Box::new(stream))));
+    }
+
+    if let Some(rest) = address.strip_prefix("serial://") {
+        return connect_serial(rest);
+    }
+
+    Err(format!(
+        "unrecognized address {address:?}: expected a tcp://, unix://, or serial:// URL"
+    ))
+}
+
+#[cfg(feature = "serial")]
+fn connect_serial(path: &str) -> Result<FromStd<DynIo>, String> {
+    let port = serialport::new(path, 115_200)
+        .timeout(Duration::from_secs(5))
+        .open()
+        .map_err(|err| format!("opening {path}: {err}"))?;
+    Ok(FromStd::new(DynIo(Box::new(port))))
+}
+
+#[cfg(not(feature = "serial"))]
+fn connect_serial(_path: &str) -> Result<FromStd<DynIo>, String> {
+    Err("serial transport support was not compiled in (rebuild with --features serial)".into())
+}
+
+/// Parses a register index, decimal or `0x`-prefixed hex.
+pub fn parse_register(text: &str) -> Result<u16, String> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16).map_err(|err| format!("{text:?} is not a valid register: {err}"))
+    } else {
+        text.parse().map_err(|err| format!("{text:?} is not a valid register: {err}"))
+    }
+}
+
+pub fn format_value(data_type: DataType, primary: &mut UrapPrimary<FromStd<DynIo>, 4>, register: u16) -> Result<String, String> {
+    match data_type {
+        DataType::U32 => primary.read_u32(register).map(|v| v.to_string()),
+        DataType::I32 => primary.read_i32(register).map(|v| v.to_string()),
+        DataType::F32 => primary.read_f32(register).map(|v| v.to_string()),
+    }
+    .map_err(|err| format!("reading register {register}: {err:?}"))
+}
+
+pub fn write_value(
+    data_type: DataType,
+    primary: &mut UrapPrimary<FromStd<DynIo>, 4>,
+    register: u16,
+    text: &str,
+) -> Result<(), String> {
+    match data_type {
+        DataType::U32 => {
+            let value: u32 = text.parse().map_err(|err| format!("{text:?} is not a valid u32: {err}"))?;
+            primary.write_u32(register, value)
+        }
+        DataType::I32 => {
+            let value: i32 = text.parse().map_err(|err| format!("{text:?} is not a valid i32: {err}"))?;
+            primary.write_i32(register, value)
+        }
+        DataType::F32 => {
+            let value: f32 = text.parse().map_err(|err| format!("{text:?} is not a valid f32: {err}"))?;
+            primary.write_f32(register, value)
+        }
+    }
+    .map_err(|err| format!("writing register {register}: {err:?}"))
+}
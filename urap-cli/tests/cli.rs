@@ -0,0 +1,53 @@
+//! End-to-end exercise of the built `urap` binary against a real
+//! Unix-socket secondary.
+
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use urap::usockets::{Listener, UrapSecondary};
+use urap::UrapSecondary as CoreSecondary;
+
+fn socket_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("urap-cli-test-{name}-{}.sock", std::process::id()))
+}
+
+#[test]
+fn write_then_read_round_trips_a_register_through_the_binary() {
+    let path = socket_path("rw");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<1> = CoreSecondary::new([[0u8; 4]; 1], [false]);
+    let server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    // Give the accept thread a moment to bind before connecting.
+    thread::sleep(Duration::from_millis(20));
+
+    let address = format!("unix://{}", path.display());
+
+    let write = Command::new(env!("CARGO_BIN_EXE_urap"))
+        .args(["write", &address, "0", "--as", "f32", "4.5"])
+        .output()
+        .unwrap();
+    assert!(write.status.success(), "{:?}", write);
+
+    let read = Command::new(env!("CARGO_BIN_EXE_urap"))
+        .args(["read", &address, "0", "--as", "f32"])
+        .output()
+        .unwrap();
+    assert!(read.status.success(), "{:?}", read);
+    assert_eq!(String::from_utf8_lossy(&read.stdout).trim(), "0: 4.5");
+
+    assert!(server.pop_error().is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn an_unrecognized_address_scheme_fails_with_a_clear_error() {
+    let read = Command::new(env!("CARGO_BIN_EXE_urap"))
+        .args(["read", "ftp://nowhere", "0"])
+        .output()
+        .unwrap();
+    assert!(!read.status.success());
+    assert!(String::from_utf8_lossy(&read.stderr).contains("unrecognized address"));
+}
@@ -0,0 +1,47 @@
+//! End-to-end exercise of the `urap-dump` binary against a real
+//! Unix-socket secondary.
+
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use urap::usockets::{Listener, UrapSecondary};
+use urap::UrapSecondary as CoreSecondary;
+
+fn socket_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("urap-dump-test-{name}-{}.sock", std::process::id()))
+}
+
+#[test]
+fn csv_mode_prints_a_header_and_one_row_per_sample() {
+    let path = socket_path("csv");
+    let _ = std::fs::remove_file(&path);
+
+    let secondary: CoreSecondary<2> = CoreSecondary::new([[0u8; 4]; 2], [false; 2]);
+    let server = UrapSecondary::spawn(vec![Listener::read_write(&path)], secondary).unwrap();
+
+    // Give the accept thread a moment to bind before connecting.
+    thread::sleep(Duration::from_millis(20));
+
+    let address = format!("unix://{}", path.display());
+
+    let dump = Command::new(env!("CARGO_BIN_EXE_urap-dump"))
+        .args([&address, "0", "--count", "2", "--samples", "3", "--interval-ms", "0", "--csv"])
+        .output()
+        .unwrap();
+    assert!(dump.status.success(), "{:?}", dump);
+
+    let stdout = String::from_utf8_lossy(&dump.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "expected a header plus 3 samples: {stdout}");
+    assert_eq!(lines[0].split(',').skip(1).collect::<Vec<_>>(), ["0", "1"]);
+    for row in &lines[1..] {
+        let fields: Vec<&str> = row.split(',').collect();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[1], "0");
+        assert_eq!(fields[2], "0");
+    }
+
+    assert!(server.pop_error().is_none());
+    let _ = std::fs::remove_file(&path);
+}
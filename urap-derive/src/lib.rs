@@ -0,0 +1,192 @@
+//! `#[derive(UrapRegisters)]`: maps a `#[repr(C)]` struct of `u32`/`i32`/
+//! `f32` fields onto a urap register block, generating each field's
+//! register index, typed `read_<field>`/`write_<field>` accessors for
+//! [`urap::UrapPrimary`], and a `write_protect` function built from
+//! `#[urap(read_only)]` field attributes.
+//!
+//! See `urap::urap_registers!` for the declarative-macro equivalent of
+//! this, which doesn't require a struct to mirror the register layout.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, parse_macro_input};
+
+#[proc_macro_derive(UrapRegisters, attributes(urap))]
+pub fn derive_urap_registers(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct RegisterField {
+    name: syn::Ident,
+    const_name: syn::Ident,
+    ty: RegisterType,
+    read_only: bool,
+}
+
+#[derive(Clone, Copy)]
+enum RegisterType {
+    U32,
+    I32,
+    F32,
+}
+
+impl RegisterType {
+    fn from_type(ty: &syn::Type) -> Option<Self> {
+        let syn::Type::Path(path) = ty else { return None };
+        match path.path.segments.last()?.ident.to_string().as_str() {
+            "u32" => Some(Self::U32),
+            "i32" => Some(Self::I32),
+            "f32" => Some(Self::F32),
+            _ => None,
+        }
+    }
+
+    fn rust_type(&self) -> syn::Ident {
+        match self {
+            Self::U32 => format_ident!("u32"),
+            Self::I32 => format_ident!("i32"),
+            Self::F32 => format_ident!("f32"),
+        }
+    }
+
+    fn read_method(&self) -> syn::Ident {
+        match self {
+            Self::U32 => format_ident!("read_u32"),
+            Self::I32 => format_ident!("read_i32"),
+            Self::F32 => format_ident!("read_f32"),
+        }
+    }
+
+    fn write_method(&self) -> syn::Ident {
+        match self {
+            Self::U32 => format_ident!("write_u32"),
+            Self::I32 => format_ident!("write_i32"),
+            Self::F32 => format_ident!("write_f32"),
+        }
+    }
+}
+
+fn is_read_only(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    for attr in attrs {
+        if !attr.path().is_ident("urap") {
+            continue;
+        }
+
+        let mut read_only = false;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("read_only") {
+                read_only = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `urap` attribute, expected `read_only`"))
+            }
+        })?;
+        return Ok(read_only);
+    }
+
+    Ok(false)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let struct_name = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "UrapRegisters can only be derived for a struct",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "UrapRegisters requires named fields",
+        ));
+    };
+
+    let mut registers = Vec::with_capacity(fields.named.len());
+    for field in &fields.named {
+        let name = field.ident.clone().expect("named field");
+        let ty = RegisterType::from_type(&field.ty).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &field.ty,
+                "UrapRegisters fields must be u32, i32, or f32",
+            )
+        })?;
+        let read_only = is_read_only(&field.attrs)?;
+        let const_name = format_ident!("{}", name.to_string().to_uppercase());
+
+        registers.push(RegisterField { name, const_name, ty, read_only });
+    }
+
+    let regcnt = registers.len();
+    let index_consts = registers.iter().enumerate().map(|(index, reg)| {
+        let const_name = &reg.const_name;
+        let index = index as u16;
+        quote! {
+            pub const #const_name: u16 = #index;
+        }
+    });
+
+    let protect_flags = registers.iter().map(|reg| reg.read_only);
+
+    let accessors = registers.iter().map(|reg| {
+        let read_fn = format_ident!("read_{}", reg.name);
+        let write_fn = format_ident!("write_{}", reg.name);
+        let const_name = &reg.const_name;
+        let rust_ty = reg.ty.rust_type();
+        let read_method = reg.ty.read_method();
+        let write_method = reg.ty.write_method();
+
+        let setter = if reg.read_only {
+            quote! {}
+        } else {
+            quote! {
+                pub fn #write_fn<IO, const BIG_ENDIAN: bool>(
+                    primary: &mut ::urap::UrapPrimary<IO, 4, BIG_ENDIAN>,
+                    value: #rust_ty,
+                ) -> ::core::result::Result<(), ::urap::Error<IO::Error>>
+                where
+                    IO: ::urap::embedded_io::Read + ::urap::embedded_io::Write,
+                {
+                    primary.#write_method(Self::#const_name, value)
+                }
+            }
+        };
+
+        quote! {
+            pub fn #read_fn<IO, const BIG_ENDIAN: bool>(
+                primary: &mut ::urap::UrapPrimary<IO, 4, BIG_ENDIAN>,
+            ) -> ::core::result::Result<#rust_ty, ::urap::Error<IO::Error>>
+            where
+                IO: ::urap::embedded_io::Read + ::urap::embedded_io::Write,
+            {
+                primary.#read_method(Self::#const_name)
+            }
+
+            #setter
+        }
+    });
+
+    Ok(quote! {
+        impl #struct_name {
+            /// Number of registers this struct maps onto.
+            pub const REGCNT: usize = #regcnt;
+
+            #(#index_consts)*
+
+            /// Write-protect flags in field declaration order, ready to
+            /// pass to `UrapSecondary::new`.
+            pub fn write_protect() -> [bool; #regcnt] {
+                [#(#protect_flags),*]
+            }
+
+            #(#accessors)*
+        }
+    })
+}